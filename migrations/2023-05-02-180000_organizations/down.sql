@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE todos DROP COLUMN organization_id;
+DROP TABLE invitations;
+DROP TABLE memberships;
+DROP TABLE organizations;
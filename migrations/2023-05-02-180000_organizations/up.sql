@@ -0,0 +1,31 @@
+-- Your SQL goes here
+
+CREATE TABLE ORGANIZATIONS (
+  id SERIAL PRIMARY KEY,
+  name VARCHAR(255) NOT NULL
+);
+
+CREATE TABLE MEMBERSHIPS (
+  id SERIAL PRIMARY KEY,
+  organization_id INTEGER NOT NULL,
+  user_id INTEGER NOT NULL,
+  role VARCHAR(16) NOT NULL CHECK (role IN ('owner', 'admin', 'member', 'viewer')),
+  UNIQUE (organization_id, user_id),
+  FOREIGN KEY (organization_id) REFERENCES organizations(id) ON DELETE CASCADE,
+  FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+CREATE TABLE INVITATIONS (
+  id SERIAL PRIMARY KEY,
+  organization_id INTEGER NOT NULL,
+  invited_by INTEGER NOT NULL,
+  email VARCHAR(255) NOT NULL,
+  role VARCHAR(16) NOT NULL CHECK (role IN ('admin', 'member', 'viewer')),
+  token VARCHAR(64) NOT NULL UNIQUE,
+  expires_at TIMESTAMP NOT NULL,
+  accepted_at TIMESTAMP,
+  FOREIGN KEY (organization_id) REFERENCES organizations(id) ON DELETE CASCADE,
+  FOREIGN KEY (invited_by) REFERENCES users(id)
+);
+
+ALTER TABLE TODOS ADD COLUMN organization_id INTEGER REFERENCES organizations(id) ON DELETE CASCADE;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE todos DROP COLUMN list_id;
+DROP TABLE lists;
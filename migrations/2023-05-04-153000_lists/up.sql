@@ -0,0 +1,17 @@
+-- Your SQL goes here
+
+CREATE TABLE LISTS (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL,
+  organization_id INTEGER,
+  name VARCHAR(255) NOT NULL,
+  color VARCHAR(7) NOT NULL DEFAULT '#808080',
+  position INTEGER NOT NULL DEFAULT 0,
+  archived BOOLEAN NOT NULL DEFAULT FALSE,
+  FOREIGN KEY (user_id) REFERENCES users(id),
+  FOREIGN KEY (organization_id) REFERENCES organizations(id) ON DELETE CASCADE
+);
+
+ALTER TABLE TODOS ADD COLUMN list_id INTEGER REFERENCES lists(id) ON DELETE SET NULL;
+
+CREATE INDEX todos_list_id_idx ON todos (list_id);
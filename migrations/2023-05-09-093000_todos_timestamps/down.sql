@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER set_updated_at ON todos;
+ALTER TABLE todos DROP COLUMN created_at, DROP COLUMN updated_at, DROP COLUMN due_at, DROP COLUMN completed_at, DROP COLUMN priority;
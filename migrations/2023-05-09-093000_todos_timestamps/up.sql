@@ -0,0 +1,14 @@
+-- Your SQL goes here
+
+ALTER TABLE TODOS
+  ADD COLUMN created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  ADD COLUMN updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  ADD COLUMN due_at TIMESTAMP,
+  ADD COLUMN completed_at TIMESTAMP,
+  ADD COLUMN priority VARCHAR(8) NOT NULL DEFAULT 'normal' CHECK (priority IN ('low', 'normal', 'high', 'urgent'));
+
+UPDATE TODOS SET completed_at = NOW() WHERE completed;
+
+CREATE INDEX todos_due_at_idx ON todos (due_at);
+
+SELECT diesel_manage_updated_at('todos');
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE todos DROP COLUMN recurrence_rule, DROP COLUMN recurrence_tz, DROP COLUMN series_id, DROP COLUMN occurrence;
@@ -0,0 +1,9 @@
+-- Your SQL goes here
+
+ALTER TABLE TODOS
+  ADD COLUMN recurrence_rule TEXT,
+  ADD COLUMN recurrence_tz VARCHAR(64),
+  ADD COLUMN series_id INTEGER REFERENCES todos(id) ON DELETE SET NULL,
+  ADD COLUMN occurrence INTEGER NOT NULL DEFAULT 1;
+
+CREATE INDEX todos_series_id_idx ON todos (series_id);
@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+ALTER TABLE TODOS ADD COLUMN parent_id INTEGER REFERENCES todos(id) ON DELETE RESTRICT;
+
+CREATE INDEX todos_parent_id_idx ON todos (parent_id);
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+CREATE TABLE TODO_DEPENDENCIES (
+  blocker_id INTEGER NOT NULL,
+  blocked_id INTEGER NOT NULL,
+  PRIMARY KEY (blocker_id, blocked_id),
+  CHECK (blocker_id <> blocked_id),
+  FOREIGN KEY (blocker_id) REFERENCES todos(id) ON DELETE CASCADE,
+  FOREIGN KEY (blocked_id) REFERENCES todos(id) ON DELETE CASCADE
+);
+
+CREATE INDEX todo_dependencies_blocked_id_idx ON todo_dependencies (blocked_id);
@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+ALTER TABLE TODOS ADD COLUMN position VARCHAR(64) COLLATE "C" NOT NULL DEFAULT '';
+
+-- Existing todos keep their creation order, odd values never end with the lowest digit
+UPDATE TODOS SET position = ranked.position
+FROM (SELECT id, LPAD(TO_HEX(ROW_NUMBER() OVER (ORDER BY id) * 2 - 1), 8, '0') AS position FROM TODOS) AS ranked
+WHERE TODOS.id = ranked.id;
+
+ALTER TABLE TODOS ALTER COLUMN position DROP DEFAULT;
+
+CREATE INDEX todos_position_idx ON todos (position);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE todos DROP COLUMN status_id;
+DROP TABLE status_transitions;
+DROP TABLE statuses;
@@ -0,0 +1,22 @@
+-- Your SQL goes here
+
+CREATE TABLE STATUSES (
+  id SERIAL PRIMARY KEY,
+  list_id INTEGER NOT NULL REFERENCES lists(id) ON DELETE CASCADE,
+  name VARCHAR(64) NOT NULL,
+  position INTEGER NOT NULL DEFAULT 0,
+  done BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE UNIQUE INDEX statuses_list_id_name_idx ON statuses (list_id, LOWER(name));
+
+CREATE TABLE STATUS_TRANSITIONS (
+  from_status_id INTEGER NOT NULL REFERENCES statuses(id) ON DELETE CASCADE,
+  to_status_id INTEGER NOT NULL REFERENCES statuses(id) ON DELETE CASCADE,
+  PRIMARY KEY (from_status_id, to_status_id),
+  CHECK (from_status_id <> to_status_id)
+);
+
+ALTER TABLE TODOS ADD COLUMN status_id INTEGER REFERENCES statuses(id) ON DELETE SET NULL;
+
+CREATE INDEX todos_status_id_idx ON todos (status_id);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE todo_watchers;
+ALTER TABLE todos DROP COLUMN assignee_id;
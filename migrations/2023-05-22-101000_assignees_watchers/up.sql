@@ -0,0 +1,13 @@
+-- Your SQL goes here
+
+ALTER TABLE TODOS ADD COLUMN assignee_id INTEGER REFERENCES users(id) ON DELETE SET NULL;
+
+CREATE INDEX todos_assignee_id_idx ON todos (assignee_id);
+
+CREATE TABLE TODO_WATCHERS (
+  todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  PRIMARY KEY (todo_id, user_id)
+);
+
+CREATE INDEX todo_watchers_user_id_idx ON todo_watchers (user_id);
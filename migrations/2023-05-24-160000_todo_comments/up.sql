@@ -0,0 +1,37 @@
+-- Your SQL goes here
+
+CREATE TABLE TODO_COMMENTS (
+  id SERIAL PRIMARY KEY,
+  todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  body TEXT NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX todo_comments_todo_id_idx ON todo_comments (todo_id);
+
+SELECT diesel_manage_updated_at('todo_comments');
+
+CREATE TABLE TODO_COMMENT_REVISIONS (
+  id SERIAL PRIMARY KEY,
+  comment_id INTEGER NOT NULL REFERENCES todo_comments(id) ON DELETE CASCADE,
+  body TEXT NOT NULL,
+  edited_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  edited_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX todo_comment_revisions_comment_id_idx ON todo_comment_revisions (comment_id);
+
+CREATE TABLE NOTIFICATIONS (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  actor_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  todo_id INTEGER REFERENCES todos(id) ON DELETE CASCADE,
+  kind VARCHAR(32) NOT NULL CHECK (kind IN ('mention')),
+  message TEXT NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  read_at TIMESTAMP
+);
+
+CREATE INDEX notifications_user_id_idx ON notifications (user_id, created_at);
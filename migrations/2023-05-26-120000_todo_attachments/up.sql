@@ -0,0 +1,14 @@
+-- Your SQL goes here
+
+CREATE TABLE TODO_ATTACHMENTS (
+  id SERIAL PRIMARY KEY,
+  todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+  user_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  file_name VARCHAR(255) NOT NULL,
+  content_type VARCHAR(127) NOT NULL,
+  size BIGINT NOT NULL CHECK (size > 0),
+  storage_key VARCHAR(255) NOT NULL UNIQUE,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX todo_attachments_todo_id_idx ON todo_attachments (todo_id);
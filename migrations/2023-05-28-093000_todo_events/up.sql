@@ -0,0 +1,19 @@
+-- Your SQL goes here
+
+-- Events are kept after their todo is deleted, so todo_id is not a foreign key
+-- The owner and organization of the todo are copied to check who can read the event
+CREATE TABLE TODO_EVENTS (
+  id SERIAL PRIMARY KEY,
+  todo_id INTEGER NOT NULL,
+  version INTEGER NOT NULL,
+  actor_id INTEGER REFERENCES users(id) ON DELETE SET NULL,
+  action VARCHAR(16) NOT NULL CHECK (action IN ('create', 'update', 'delete')),
+  changes JSONB NOT NULL,
+  owner_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  organization_id INTEGER REFERENCES organizations(id) ON DELETE CASCADE,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  UNIQUE (todo_id, version)
+);
+
+CREATE INDEX todo_events_owner_id_idx ON todo_events (owner_id, id);
+CREATE INDEX todo_events_organization_id_idx ON todo_events (organization_id, id);
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+
+-- Events recorded by the same transaction share the id of that transaction, so a change
+-- spanning several todos, e.g. deleting a todo along with its subtasks, can be undone at once
+ALTER TABLE TODO_EVENTS ADD COLUMN mutation_id BIGINT NOT NULL DEFAULT txid_current();
+
+CREATE INDEX todo_events_actor_id_idx ON todo_events (actor_id, id);
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+
+-- Incremented on every change, todos carry on from the version of their last recorded event
+ALTER TABLE TODOS ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+
+UPDATE todos SET version = events.version
+FROM (SELECT todo_id, MAX(version) AS version FROM todo_events GROUP BY todo_id) AS events
+WHERE todos.id = events.todo_id;
@@ -0,0 +1,19 @@
+-- Your SQL goes here
+
+-- Responses of requests sent with an Idempotency-Key header, replayed when the request is retried
+-- A row without status is a request still being handled
+CREATE TABLE IDEMPOTENCY_KEYS (
+  id SERIAL PRIMARY KEY,
+  user_id INTEGER REFERENCES users (id) ON DELETE CASCADE,
+  idempotency_key VARCHAR(255) NOT NULL,
+  fingerprint VARCHAR(64) NOT NULL,
+  status SMALLINT,
+  response JSONB,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  expires_at TIMESTAMP NOT NULL
+);
+
+-- Keys sent without a token, e.g. on signup, share the scope of user 0
+CREATE UNIQUE INDEX idempotency_keys_scope_idx ON idempotency_keys (COALESCE(user_id, 0), idempotency_key);
+
+CREATE INDEX idempotency_keys_expires_at_idx ON idempotency_keys (expires_at);
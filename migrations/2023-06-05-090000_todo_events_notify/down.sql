@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TRIGGER notify_todo_event ON todo_events;
+DROP FUNCTION notify_todo_event();
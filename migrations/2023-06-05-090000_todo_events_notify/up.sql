@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+-- Every recorded event is announced on the todo_events channel once committed, the payload is its id
+CREATE OR REPLACE FUNCTION notify_todo_event() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('todo_events', NEW.id::text);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER notify_todo_event AFTER INSERT ON TODO_EVENTS
+FOR EACH ROW EXECUTE PROCEDURE notify_todo_event();
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+CREATE INDEX webhook_deliveries_due_idx ON webhook_deliveries (next_attempt_at) WHERE status = 'pending';
+DROP TABLE job_schedules;
+DROP TABLE jobs;
@@ -0,0 +1,49 @@
+-- Your SQL goes here
+
+-- Work run in the background by the job workers
+-- A queued job runs once run_at is reached, a running job whose lock expired is picked up again
+-- A job failing on every attempt is left dead until an admin retries it
+CREATE TABLE JOBS (
+  id SERIAL PRIMARY KEY,
+  kind VARCHAR(64) NOT NULL,
+  payload JSONB NOT NULL DEFAULT '{}',
+  status VARCHAR(16) NOT NULL DEFAULT 'queued' CHECK (status IN ('queued', 'running', 'succeeded', 'dead')),
+  attempts INTEGER NOT NULL DEFAULT 0,
+  max_attempts INTEGER NOT NULL DEFAULT 5,
+  unique_key VARCHAR(255),
+  run_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  locked_until TIMESTAMP,
+  last_error TEXT,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  finished_at TIMESTAMP
+);
+
+CREATE INDEX jobs_due_idx ON jobs (run_at) WHERE status = 'queued';
+
+CREATE INDEX jobs_locked_idx ON jobs (locked_until) WHERE status = 'running';
+
+CREATE INDEX jobs_status_idx ON jobs (status, id DESC);
+
+-- A job with a unique key is not queued again while the same one is waiting or running
+CREATE UNIQUE INDEX jobs_unique_key_idx ON jobs (unique_key) WHERE status IN ('queued', 'running');
+
+-- Jobs queued on a cron schedule
+CREATE TABLE JOB_SCHEDULES (
+  name VARCHAR(64) PRIMARY KEY,
+  kind VARCHAR(64) NOT NULL,
+  payload JSONB NOT NULL DEFAULT '{}',
+  cron VARCHAR(128) NOT NULL,
+  next_run_at TIMESTAMP NOT NULL,
+  last_run_at TIMESTAMP
+);
+
+-- Webhook deliveries are now sent by jobs, the pending ones get theirs
+DROP INDEX webhook_deliveries_due_idx;
+
+INSERT INTO jobs (kind, payload, unique_key, run_at)
+SELECT 'webhook.deliver',
+  json_build_object('deliveryId', id, 'attempt', attempts),
+  'webhook.deliver:' || id || ':' || attempts,
+  next_attempt_at
+FROM webhook_deliveries
+WHERE status = 'pending';
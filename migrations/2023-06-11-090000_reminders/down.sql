@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX notifications_unread_idx;
+DELETE FROM notifications WHERE kind = 'reminder';
+ALTER TABLE notifications DROP CONSTRAINT notifications_kind_check;
+ALTER TABLE notifications ADD CONSTRAINT notifications_kind_check CHECK (kind IN ('mention'));
+DROP TABLE notification_settings;
+DROP TABLE todo_reminders;
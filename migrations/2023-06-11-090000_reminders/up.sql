@@ -0,0 +1,31 @@
+-- Your SQL goes here
+
+-- Reminders a user set on a todo, sent offset_minutes before the todo is due
+-- fired_for holds the due date the reminder was last sent for, so moving the due date sends it again
+CREATE TABLE TODO_REMINDERS (
+  id SERIAL PRIMARY KEY,
+  todo_id INTEGER NOT NULL REFERENCES todos (id) ON DELETE CASCADE,
+  user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+  offset_minutes INTEGER NOT NULL CHECK (offset_minutes >= 0),
+  fired_for TIMESTAMP,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  UNIQUE (todo_id, user_id, offset_minutes)
+);
+
+CREATE INDEX todo_reminders_user_id_idx ON todo_reminders (user_id);
+
+-- How a user is notified, quiet hours are local times in the time zone of the user
+-- Users without settings get in-app notifications only
+CREATE TABLE NOTIFICATION_SETTINGS (
+  user_id INTEGER PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+  time_zone VARCHAR(64) NOT NULL DEFAULT 'UTC',
+  quiet_hours_start TIME,
+  quiet_hours_end TIME,
+  channels TEXT[] NOT NULL DEFAULT '{in_app}'
+);
+
+ALTER TABLE notifications DROP CONSTRAINT notifications_kind_check;
+
+ALTER TABLE notifications ADD CONSTRAINT notifications_kind_check CHECK (kind IN ('mention', 'reminder'));
+
+CREATE INDEX notifications_unread_idx ON notifications (user_id) WHERE read_at IS NULL;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX todos_series_occurrence_key;
+
+CREATE INDEX todos_series_id_idx ON todos (series_id);
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+
+-- Completing a recurring todo twice could create the same occurrence twice, the later copies leave the series
+UPDATE todos duplicate
+  SET series_id = NULL
+  FROM todos original
+  WHERE duplicate.series_id = original.series_id
+    AND duplicate.occurrence = original.occurrence
+    AND duplicate.id > original.id;
+
+DROP INDEX todos_series_id_idx;
+
+-- An occurrence exists once per series
+CREATE UNIQUE INDEX todos_series_occurrence_key ON todos (series_id, occurrence);
@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE idempotency_keys DROP COLUMN headers;
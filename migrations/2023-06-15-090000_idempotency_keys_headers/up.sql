@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+-- Headers of the stored responses sent again when they are replayed, e.g. the Location of a created resource
+ALTER TABLE idempotency_keys ADD COLUMN headers JSONB;
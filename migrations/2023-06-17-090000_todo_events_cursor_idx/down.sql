@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX todo_events_cursor_idx;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+-- Sync and event streams read the events in the order of their transaction, then of their id
+CREATE INDEX todo_events_cursor_idx ON todo_events (mutation_id, id);
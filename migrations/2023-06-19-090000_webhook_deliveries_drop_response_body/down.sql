@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE webhook_deliveries ADD COLUMN response_body TEXT;
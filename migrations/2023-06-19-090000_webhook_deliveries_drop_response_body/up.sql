@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+-- Endpoints can answer with anything, the delivery log only keeps the status of the response
+ALTER TABLE webhook_deliveries DROP COLUMN response_body;
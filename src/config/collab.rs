@@ -0,0 +1,23 @@
+use std::env;
+
+use rocket::fairing::AdHoc;
+
+use crate::utils::collab::CollabServer;
+
+/// Address the live editing WebSocket listens on by default
+static DEFAULT_ADDRESS: &str = "127.0.0.1:8001";
+
+/// Function to create the fairing serving the live editing of todos over WebSocket
+///
+/// The address is read from the `COLLAB_ADDRESS` environment variable, `127.0.0.1:8001` by default,
+/// so the server is only reachable through a reverse proxy unless told otherwise. Clients connect to
+/// `ws://<address>/collab` with their token in the `Authorization` header
+///
+/// # Returns
+///
+/// * The fairing starting the server once Rocket is up
+pub fn establish_collab() -> AdHoc {
+    let address = env::var("COLLAB_ADDRESS").unwrap_or_else(|_| DEFAULT_ADDRESS.to_string());
+
+    CollabServer::new(address).fairing()
+}
@@ -0,0 +1,16 @@
+use crate::config::db::PoolConnection;
+use crate::utils::event_bus::EventBus;
+
+/// Function to start the event bus streaming the changes made to todos
+///
+/// # Arguments
+///
+/// * `db_url` - A string slice containing the database connection string
+/// * `pool` - A pool of database connections
+///
+/// # Returns
+///
+/// * The event bus
+pub fn establish_event_bus(db_url: &str, pool: PoolConnection) -> EventBus {
+    EventBus::listen(db_url, pool)
+}
@@ -0,0 +1,29 @@
+use std::env;
+
+/// Number of seconds a response is kept for retries by default, one day
+static DEFAULT_TTL: i64 = 60 * 60 * 24;
+
+/// Settings of the handling of the `Idempotency-Key` header
+#[derive(Debug, Clone, Copy)]
+pub struct IdempotencyConfig {
+    /// Number of seconds the response of a request is replayed for
+    pub ttl: i64,
+}
+
+/// Function to read the settings of the handling of the `Idempotency-Key` header
+///
+/// The number of seconds responses are kept is read from the `IDEMPOTENCY_TTL` environment variable,
+/// one day by default
+///
+/// # Returns
+///
+/// * The settings
+pub fn establish_idempotency() -> IdempotencyConfig {
+    let ttl = env::var("IDEMPOTENCY_TTL")
+        .ok()
+        .and_then(|ttl| ttl.parse::<i64>().ok())
+        .filter(|ttl| *ttl > 0)
+        .unwrap_or(DEFAULT_TTL);
+
+    IdempotencyConfig { ttl }
+}
@@ -0,0 +1,126 @@
+use std::env;
+
+use diesel::PgConnection;
+use rocket::fairing::AdHoc;
+
+use crate::config::email::establish_mailer;
+use crate::config::storage::Storage;
+use crate::config::webhooks::establish_webhook_sender;
+use crate::models::organization::{Membership, Role};
+use crate::utils::idempotency::PurgeIdempotencyKeys;
+use crate::utils::invitations::SendInvitations;
+use crate::utils::jobs::{JobWorker, PurgeJobs, Schedule};
+use crate::utils::notifications::{
+    EmailChannel, InAppChannel, NotificationSender, SendReminders, WebhookChannel,
+};
+use crate::utils::storage::PurgeBlobs;
+
+/// Number of threads running jobs by default
+static DEFAULT_WORKERS: usize = 4;
+
+/// Number of seconds a succeeded job is kept for, a week
+static SUCCEEDED_JOB_AGE: i64 = 60 * 60 * 24 * 7;
+
+/// Settings of the background jobs
+#[derive(Debug, Clone)]
+pub struct JobsConfig {
+    /// Id of the organization whose admins and owner can inspect and retry jobs, None if nobody can
+    pub organization: Option<i32>,
+}
+
+impl JobsConfig {
+    /// Checks that a user can inspect and retry jobs
+    ///
+    /// # Arguments
+    ///
+    /// * `user` - Id of the user
+    /// * `conn` - The database connection
+    ///
+    /// # Returns
+    ///
+    /// * Nothing, or an error message if the user is not an admin or the owner of the organization
+    pub fn require_admin(&self, user: i32, conn: &mut PgConnection) -> Result<(), String> {
+        let organization = match self.organization {
+            Some(organization) => organization,
+            None => return Err("Only admins can manage jobs".to_string()),
+        };
+
+        match Membership::require_role(organization, user, Role::Admin, conn) {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Only admins can manage jobs".to_string()),
+        }
+    }
+}
+
+/// Function to read the settings of the background jobs
+///
+/// Jobs are managed by the admins and the owner of the organization whose id is read from the
+/// `JOB_ADMIN_ORGANIZATION` environment variable, so they are granted and revoked like any other
+/// role. Nobody can manage jobs by default
+///
+/// # Returns
+///
+/// * The settings
+pub fn establish_jobs() -> JobsConfig {
+    let organization = env::var("JOB_ADMIN_ORGANIZATION")
+        .ok()
+        .and_then(|organization| organization.trim().parse::<i32>().ok());
+
+    JobsConfig { organization }
+}
+
+/// Function to create the fairing running the background jobs
+///
+/// The number of workers is read from the `JOB_WORKERS` environment variable, 4 by default. Setting
+/// it to 0 leaves the jobs to the other server instances
+///
+/// # Arguments
+///
+/// * `storage` - The blob store the files of deleted attachments are removed from
+///
+/// # Returns
+///
+/// * The fairing registering the schedules and starting the workers once Rocket is up
+pub fn establish_job_workers(storage: Storage) -> AdHoc {
+    let workers = env::var("JOB_WORKERS")
+        .ok()
+        .and_then(|workers| workers.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_WORKERS);
+    let mailer = establish_mailer();
+
+    JobWorker::fairing(
+        workers,
+        vec![
+            Box::new(establish_webhook_sender()),
+            Box::new(PurgeIdempotencyKeys),
+            Box::new(PurgeJobs {
+                age: SUCCEEDED_JOB_AGE,
+            }),
+            Box::new(NotificationSender::new(vec![
+                Box::new(InAppChannel),
+                Box::new(EmailChannel::new(mailer.clone())),
+                Box::new(WebhookChannel),
+            ])),
+            Box::new(SendReminders),
+            Box::new(PurgeBlobs::new(storage)),
+            Box::new(SendInvitations::new(mailer)),
+        ],
+        vec![
+            Schedule {
+                name: "purge-idempotency-keys",
+                kind: "idempotency.purge",
+                cron: "@hourly",
+            },
+            Schedule {
+                name: "purge-jobs",
+                kind: "jobs.purge",
+                cron: "0 3 * * *",
+            },
+            Schedule {
+                name: "send-reminders",
+                kind: "reminders.send",
+                cron: "* * * * *",
+            },
+        ],
+    )
+}
@@ -1 +1,8 @@
+pub mod collab;
 pub mod db;
+pub mod email;
+pub mod event_bus;
+pub mod idempotency;
+pub mod jobs;
+pub mod storage;
+pub mod webhooks;
@@ -0,0 +1,44 @@
+use std::env;
+use std::sync::Arc;
+
+use crate::utils::s3::S3Store;
+use crate::utils::storage::{BlobStore, LocalStore};
+
+/// Blob store type shared by the routes, cloned into the blocking tasks that use it
+pub type Storage = Arc<dyn BlobStore>;
+
+/// Function to create the blob store attachments are kept in
+///
+/// The store is picked with the `STORAGE_BACKEND` environment variable:
+///
+/// * `local` (default) - files are kept under `STORAGE_PATH`, `uploads` by default
+/// * `s3` - files are kept in the `S3_BUCKET` bucket of `S3_ENDPOINT` (e.g. `http://localhost:9000` for MinIO),
+///   using `S3_ACCESS_KEY`, `S3_SECRET_KEY` and `S3_REGION` (`us-east-1` by default)
+///
+/// # Returns
+///
+/// * The blob store
+pub fn establish_storage() -> Storage {
+    let backend = env::var("STORAGE_BACKEND").unwrap_or_else(|_| "local".to_string());
+
+    match backend.as_str() {
+        "local" => {
+            let path = env::var("STORAGE_PATH").unwrap_or_else(|_| "uploads".to_string());
+
+            Arc::new(LocalStore::new(path))
+        }
+        "s3" => {
+            let endpoint = env::var("S3_ENDPOINT").expect("S3_ENDPOINT must be set");
+            let bucket = env::var("S3_BUCKET").expect("S3_BUCKET must be set");
+            let region = env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+            let access_key = env::var("S3_ACCESS_KEY").expect("S3_ACCESS_KEY must be set");
+            let secret_key = env::var("S3_SECRET_KEY").expect("S3_SECRET_KEY must be set");
+
+            Arc::new(
+                S3Store::new(&endpoint, &bucket, &region, &access_key, &secret_key)
+                    .expect("Failed to create S3 storage."),
+            )
+        }
+        other => panic!("Unknown STORAGE_BACKEND {}, expected local or s3", other),
+    }
+}
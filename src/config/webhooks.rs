@@ -0,0 +1,23 @@
+use std::env;
+
+use crate::utils::webhooks::WebhookSender;
+
+/// Function to create the sender of webhook deliveries
+///
+/// Deliveries are only sent to public addresses. The hosts read from the `WEBHOOK_ALLOWED_HOSTS`
+/// environment variable, separated by commas, e.g. `localhost,127.0.0.1`, are reached whatever
+/// they resolve to, for a stub endpoint running next to the server. No host is allowed by default
+///
+/// # Returns
+///
+/// * The sender
+pub fn establish_webhook_sender() -> WebhookSender {
+    let allowed_hosts = env::var("WEBHOOK_ALLOWED_HOSTS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|host| host.trim().to_string())
+        .filter(|host| !host.is_empty())
+        .collect();
+
+    WebhookSender::new(allowed_hosts)
+}
@@ -0,0 +1,436 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::{prelude::*, Identifiable, Insertable, PgConnection, Queryable, RunQueryDsl};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use rocket::form::{self, DataField, FromForm, Options, ValueField};
+use rocket::fs::TempFile;
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::models::job::{Job, NewJob};
+use crate::models::todos::Todo;
+use crate::schema::todo_attachments::{self, dsl::*};
+use crate::utils::signature;
+use crate::utils::storage::BlobStore;
+
+/// Maximum size of an attachment in bytes
+pub static MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+/// MIME types attachments can have
+static ALLOWED_TYPES: [&str; 6] = [
+    "application/pdf",
+    "image/gif",
+    "image/jpeg",
+    "image/png",
+    "image/webp",
+    "text/plain",
+];
+
+/// Number of seconds a download URL stays valid
+static URL_TTL: i64 = 15 * 60;
+
+/// Kind of the jobs removing the files of deleted attachments from the blob store
+pub static PURGE: &str = "attachments.purge";
+
+/// Attachment struct representing a row in the todo_attachments table in the database
+#[derive(Identifiable, Queryable, Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "todo_attachments"]
+pub struct Attachment {
+    /// Unique id of the attachment
+    /// This is the primary key of the todo_attachments table
+    /// This is auto generated by the database
+    pub id: i32,
+    /// Id of the todo the file is attached to
+    pub todo_id: i32,
+    /// Id of the user who uploaded the file, empty if the user was deleted
+    pub user_id: Option<i32>,
+    /// Name of the file as uploaded
+    pub file_name: String,
+    /// MIME type of the file
+    pub content_type: String,
+    /// Size of the file in bytes
+    pub size: i64,
+    /// Key of the file in the blob store
+    #[serde(skip)]
+    pub storage_key: String,
+    /// Time the file was uploaded in UTC
+    pub created_at: NaiveDateTime,
+}
+
+/// NewAttachment struct representing the data to be sent to the database to create an attachment
+#[derive(Insertable, Debug)]
+#[table_name = "todo_attachments"]
+struct NewAttachment {
+    todo_id: i32,
+    user_id: Option<i32>,
+    file_name: String,
+    content_type: String,
+    size: i64,
+    storage_key: String,
+}
+
+/// AttachmentUpload struct representing the multipart form a file is uploaded with
+#[derive(Debug, JsonSchema)]
+pub struct AttachmentUpload<'r> {
+    /// The file to attach, its MIME type is taken from the Content-Type of the form field
+    #[schemars(with = "String")]
+    pub file: TempFile<'r>,
+}
+
+/// Reads the `file` field of the form, other fields are ignored
+/// Written by hand, the derive of this version of Rocket allows the removed `private_in_public` lint
+#[rocket::async_trait]
+impl<'r> FromForm<'r> for AttachmentUpload<'r> {
+    type Context = <TempFile<'r> as FromForm<'r>>::Context;
+
+    fn init(opts: Options) -> Self::Context {
+        <TempFile<'r> as FromForm<'r>>::init(opts)
+    }
+
+    fn push_value(ctxt: &mut Self::Context, field: ValueField<'r>) {
+        if field.name.key_lossy().as_str() == "file" {
+            <TempFile<'r> as FromForm<'r>>::push_value(ctxt, field.shift());
+        }
+    }
+
+    async fn push_data(ctxt: &mut Self::Context, field: DataField<'r, '_>) {
+        if field.name.key_lossy().as_str() == "file" {
+            <TempFile<'r> as FromForm<'r>>::push_data(ctxt, field.shift()).await;
+        }
+    }
+
+    fn finalize(ctxt: Self::Context) -> form::Result<'r, Self> {
+        match <TempFile<'r> as FromForm<'r>>::finalize(ctxt) {
+            Ok(file) => Ok(AttachmentUpload { file }),
+            Err(errors) => Err(errors.with_name("file")),
+        }
+    }
+}
+
+/// Upload struct representing a file received from a client
+#[derive(Debug)]
+pub struct Upload {
+    /// Name of the file given by the client
+    pub file_name: Option<String>,
+    /// MIME type of the file given by the client
+    pub content_type: Option<String>,
+    /// Content of the file
+    pub bytes: Vec<u8>,
+}
+
+/// AttachmentUrl struct representing a signed URL to download an attachment without a token
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentUrl {
+    /// URL of the file, relative to the API
+    pub url: String,
+    /// Time the URL stops working in UTC
+    pub expires_at: NaiveDateTime,
+}
+
+/// Implementation of the Attachment struct
+impl Attachment {
+    /// Attach a file to a todo function
+    /// The content of the file must match its MIME type, which must be one of the allowed types
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `user` - Id of the user uploading the file
+    /// * `upload` - Upload struct containing the file
+    /// * `store` - Blob store the content of the file is kept in
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Attachment, String>` - Result containing the created attachment or an error message
+    pub fn new_attachment(
+        todo: i32,
+        user: i32,
+        upload: Upload,
+        store: &dyn BlobStore,
+        conn: &mut PgConnection,
+    ) -> Result<Attachment, String> {
+        Todo::find_writable(todo, user, conn)?;
+
+        if upload.bytes.is_empty() {
+            return Err("File is empty".to_string());
+        }
+
+        if upload.bytes.len() as u64 > MAX_SIZE {
+            return Err(format!("File must be at most {} bytes", MAX_SIZE));
+        }
+
+        let mime = match upload.content_type {
+            Some(mime) if ALLOWED_TYPES.contains(&mime.as_str()) => mime,
+            _ => {
+                return Err(format!(
+                    "File type must be one of {}",
+                    ALLOWED_TYPES.join(", ")
+                ))
+            }
+        };
+
+        if !Attachment::matches_type(&mime, &upload.bytes) {
+            return Err(format!("File content is not {}", mime));
+        }
+
+        let random: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        let key = format!("todos/{}/{}", todo, random);
+
+        store.put(&key, &upload.bytes, &mime)?;
+
+        let result = diesel::insert_into(todo_attachments)
+            .values(&NewAttachment {
+                todo_id: todo,
+                user_id: Some(user),
+                file_name: Attachment::sanitize_file_name(upload.file_name),
+                content_type: mime,
+                size: upload.bytes.len() as i64,
+                storage_key: key.clone(),
+            })
+            .get_result::<Attachment>(conn);
+
+        match result {
+            Ok(attachment) => Ok(attachment),
+            Err(_) => {
+                if let Err(message) = Attachment::purge_blobs(std::slice::from_ref(&key), store) {
+                    log::warn!("{} {}", message, key);
+                }
+
+                Err("Failed to create attachment".to_string())
+            }
+        }
+    }
+
+    /// Gets the attachments of a todo function
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<Attachment>, String>` - Result containing the attachments, oldest first, or an error message
+    pub fn get_attachments(
+        todo: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<Attachment>, String> {
+        Todo::find_readable(todo, user, conn)?;
+
+        let result = todo_attachments
+            .filter(todo_id.eq(todo))
+            .order((created_at.asc(), id.asc()))
+            .load::<Attachment>(conn);
+
+        match result {
+            Ok(attachments) => Ok(attachments),
+            Err(_) => Err("Failed to get attachments".to_string()),
+        }
+    }
+
+    /// Delete an attachment function
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `attachment_id` - Id of the attachment to be deleted
+    /// * `user` - Id of the user deleting the attachment
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn delete_attachment(
+        todo: i32,
+        attachment_id: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<String, String> {
+        Todo::find_writable(todo, user, conn)?;
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let deleted = diesel::delete(todo_attachments.find(attachment_id))
+                .filter(todo_id.eq(todo))
+                .get_result::<Attachment>(conn)
+                .optional()?;
+
+            if let Some(attachment) = &deleted {
+                Attachment::queue_purge(std::slice::from_ref(&attachment.storage_key), conn)?;
+            }
+
+            Ok(deleted)
+        });
+
+        match result {
+            Ok(Some(_)) => Ok("Successfully deleted attachment".to_string()),
+            Ok(None) => Err("Attachment not found".to_string()),
+            Err(_) => Err("Failed to delete attachment".to_string()),
+        }
+    }
+
+    /// Creates a signed URL to download an attachment function
+    /// The URL can be used without a token until it expires
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `attachment_id` - Id of the attachment
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<AttachmentUrl, String>` - Result containing the signed URL or an error message
+    pub fn signed_url(
+        todo: i32,
+        attachment_id: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<AttachmentUrl, String> {
+        Todo::find_readable(todo, user, conn)?;
+
+        let result = todo_attachments
+            .find(attachment_id)
+            .filter(todo_id.eq(todo))
+            .first::<Attachment>(conn)
+            .optional();
+
+        let attachment = match result {
+            Ok(Some(attachment)) => attachment,
+            Ok(None) => return Err("Attachment not found".to_string()),
+            Err(_) => return Err("Failed to get attachment".to_string()),
+        };
+
+        let expires_at = Utc::now().naive_utc() + Duration::seconds(URL_TTL);
+        let expires = expires_at.timestamp();
+        let path = Attachment::download_path(attachment.id);
+
+        Ok(AttachmentUrl {
+            url: format!(
+                "{}?expires={}&signature={}",
+                path,
+                expires,
+                signature::sign(&path, expires)
+            ),
+            expires_at,
+        })
+    }
+
+    /// Downloads an attachment with a signed URL function
+    /// # Arguments
+    /// * `attachment_id` - Id of the attachment
+    /// * `expires` - Expiry of the URL as a unix timestamp
+    /// * `url_signature` - Signature of the URL
+    /// * `store` - Blob store the content of the file is kept in
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<(Attachment, Vec<u8>), String>` - Result containing the attachment and its content or an error message
+    pub fn download(
+        attachment_id: i32,
+        expires: i64,
+        url_signature: &str,
+        store: &dyn BlobStore,
+        conn: &mut PgConnection,
+    ) -> Result<(Attachment, Vec<u8>), String> {
+        signature::verify(
+            &Attachment::download_path(attachment_id),
+            expires,
+            url_signature,
+        )?;
+
+        let result = todo_attachments
+            .find(attachment_id)
+            .first::<Attachment>(conn)
+            .optional();
+
+        let attachment = match result {
+            Ok(Some(attachment)) => attachment,
+            Ok(None) => return Err("Attachment not found".to_string()),
+            Err(_) => return Err("Failed to get attachment".to_string()),
+        };
+
+        let bytes = store.get(&attachment.storage_key)?;
+
+        Ok((attachment, bytes))
+    }
+
+    /// Gets the blob store keys of the files attached to todos function
+    /// # Arguments
+    /// * `todo_ids` - Ids of the todos
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<Vec<String>>` - Result containing the keys
+    pub fn storage_keys(todo_ids: &[i32], conn: &mut PgConnection) -> QueryResult<Vec<String>> {
+        todo_attachments
+            .filter(todo_id.eq_any(todo_ids))
+            .select(storage_key)
+            .load::<String>(conn)
+    }
+
+    /// Queue the removal of files from the blob store function
+    /// Called in the transaction deleting the rows referencing the files, so the files are only removed once it commits
+    /// # Arguments
+    /// * `keys` - Keys of the files
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<usize>` - Result containing the number of jobs queued
+    pub fn queue_purge(keys: &[String], conn: &mut PgConnection) -> QueryResult<usize> {
+        if keys.is_empty() {
+            return Ok(0);
+        }
+
+        Job::enqueue(&NewJob::new(PURGE, json!({ "keys": keys })), conn)
+    }
+
+    /// Deletes files from the blob store function
+    /// Every file is attempted, so a retry only has the failed ones left to delete
+    /// # Arguments
+    /// * `keys` - Keys of the files
+    /// * `store` - Blob store the files are kept in
+    /// # Returns
+    /// * `Result<(), String>` - Result containing nothing or the error of the last file that could not be deleted
+    pub fn purge_blobs(keys: &[String], store: &dyn BlobStore) -> Result<(), String> {
+        let mut result = Ok(());
+
+        for key in keys {
+            if let Err(message) = store.delete(key) {
+                result = Err(format!("{} {}", message, key));
+            }
+        }
+
+        result
+    }
+
+    /// Internal function to get the path an attachment is downloaded from
+    fn download_path(attachment_id: i32) -> String {
+        format!("/attachments/{}/download", attachment_id)
+    }
+
+    /// Internal function to check the content of a file matches its MIME type
+    fn matches_type(mime: &str, bytes: &[u8]) -> bool {
+        match mime {
+            "application/pdf" => bytes.starts_with(b"%PDF-"),
+            "image/gif" => bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a"),
+            "image/jpeg" => bytes.starts_with(b"\xFF\xD8\xFF"),
+            "image/png" => bytes.starts_with(b"\x89PNG\r\n\x1a\n"),
+            "image/webp" => bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP",
+            "text/plain" => std::str::from_utf8(bytes).is_ok(),
+            _ => false,
+        }
+    }
+
+    /// Internal function to keep the last component of a file name, without control characters or quotes
+    fn sanitize_file_name(name: Option<String>) -> String {
+        let name = name.unwrap_or_default();
+        let name: String = name
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .filter(|c| !c.is_control() && *c != '"')
+            .take(255)
+            .collect();
+        let name = name.trim();
+
+        if name.is_empty() || name == "." || name == ".." {
+            "attachment".to_string()
+        } else {
+            name.to_string()
+        }
+    }
+}
@@ -0,0 +1,320 @@
+use diesel::{prelude::*, PgConnection};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::models::todos::{
+    ChildrenAction, DueFilter, Todo, TodoDTO, TodoFilter, TodoPatch, UpdateOptions,
+};
+use crate::schema::todos;
+
+/// Maximum number of operations applied by a bulk request
+pub static MAX_OPERATIONS: usize = 500;
+
+/// How a bulk request handles an operation that fails
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum BulkMode {
+    /// Nothing is applied if any operation fails
+    #[default]
+    Atomic,
+    /// Failed operations are skipped, the other ones are applied
+    BestEffort,
+}
+
+/// A single change of a bulk request, e.g. `{"op": "delete", "id": 3}`
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum BulkOperation {
+    /// Creates a todo, like `POST /todo`
+    Create {
+        /// The todo to create, for reference see `TodoDTO` struct
+        todo: TodoDTO,
+    },
+    /// Patches a todo with a JSON Merge Patch, like `PATCH /todo/<id>`
+    Update {
+        /// Id of the todo
+        id: i32,
+        /// The fields to change, for reference see `TodoPatch` struct
+        patch: TodoPatch,
+        /// Version the todo must still be at, as sent in its ETag
+        version: Option<i32>,
+    },
+    /// Deletes a todo, like `DELETE /todo/<id>`
+    Delete {
+        /// Id of the todo
+        id: i32,
+        /// What happens to the subtasks of the todo, required if it has any
+        children: Option<ChildrenAction>,
+    },
+}
+
+/// Action applied to every todo matching the filter of a bulk request
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BulkAction {
+    /// Marks the todos as completed
+    Complete,
+    /// Marks the todos as not completed
+    Reopen,
+    /// Deletes the todos
+    Delete,
+}
+
+/// BulkFilter struct representing the todos a bulk action is applied to
+/// Only todos matching every given field are selected
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkFilter {
+    /// Id of the list of the todos
+    pub list_id: Option<i32>,
+    /// Whether the todos are completed or not
+    pub completed: Option<bool>,
+    /// Names of tags the todos have any of
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Window the todos are due in
+    pub due: Option<DueFilter>,
+}
+
+/// BulkDTO struct representing a bulk request
+/// A request holds either a list of operations, or a filter along with an action
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkDTO {
+    /// Operations applied in order
+    #[serde(default)]
+    pub operations: Vec<BulkOperation>,
+    /// Filter selecting the todos the action is applied to
+    pub filter: Option<BulkFilter>,
+    /// Action applied to the todos matching the filter
+    pub action: Option<BulkAction>,
+    /// What happens to the subtasks of the todos deleted by the action, required if any has subtasks
+    pub children: Option<ChildrenAction>,
+    /// Whether a failed operation cancels the whole request, defaults to atomic
+    #[serde(default)]
+    pub mode: BulkMode,
+}
+
+/// BulkResult struct representing the outcome of an operation of a bulk request
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkResult {
+    /// Position of the operation in the request, or of the todo among the ones matching the filter
+    pub index: usize,
+    /// Id of the todo the operation applied to, the id of the created todo for a create
+    pub id: Option<i32>,
+    /// Whether the change is saved
+    pub success: bool,
+    /// Success message or error message of the operation
+    pub message: String,
+}
+
+/// Implementation of the BulkOperation enum
+impl BulkOperation {
+    /// Apply a bulk request function
+    /// All operations run in a single transaction, in atomic mode the first failure rolls back the
+    /// transaction, in best effort mode only the failed operation is rolled back
+    /// # Arguments
+    /// * `user` - Id of the user making the changes
+    /// * `data` - BulkDTO struct containing the operations or the filter and action
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<(String, Vec<BulkResult>), String>` - Result containing a summary and the result of each operation, or an error message if nothing was attempted
+    pub fn apply_bulk(
+        user: i32,
+        data: BulkDTO,
+        conn: &mut PgConnection,
+    ) -> Result<(String, Vec<BulkResult>), String> {
+        let mode = data.mode;
+        let (operations, from_filter) = BulkOperation::resolve(user, data, conn)?;
+
+        if operations.is_empty() {
+            return Err("No todo to change".to_string());
+        }
+
+        if operations.len() > MAX_OPERATIONS {
+            return Err(format!(
+                "A bulk request can change at most {} todos",
+                MAX_OPERATIONS
+            ));
+        }
+
+        let count = operations.len();
+        let mut results: Vec<BulkResult> = vec![];
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            for (index, operation) in operations.into_iter().enumerate() {
+                match BulkOperation::apply_one(user, operation, from_filter, conn) {
+                    Ok((todo, message)) => {
+                        results.push(BulkResult {
+                            index,
+                            id: todo,
+                            success: true,
+                            message,
+                        });
+                    }
+                    Err((todo, message)) => {
+                        results.push(BulkResult {
+                            index,
+                            id: todo,
+                            success: false,
+                            message,
+                        });
+
+                        if mode == BulkMode::Atomic {
+                            return Err(diesel::result::Error::RollbackTransaction);
+                        }
+                    }
+                }
+            }
+
+            Ok(())
+        });
+
+        match result {
+            Ok(_) => {
+                let failed = results.iter().filter(|result| !result.success).count();
+                let summary = match failed {
+                    0 => format!("Successfully applied {} operations", count),
+                    _ => format!(
+                        "Applied {} of {} operations, {} failed",
+                        count - failed,
+                        count,
+                        failed
+                    ),
+                };
+
+                Ok((summary, results))
+            }
+            Err(diesel::result::Error::RollbackTransaction) => {
+                let failed = results.len() - 1;
+
+                for result in results.iter_mut().filter(|result| result.success) {
+                    result.success = false;
+                    result.message = format!("Rolled back as operation {} failed", failed);
+                }
+
+                for index in results.len()..count {
+                    results.push(BulkResult {
+                        index,
+                        id: None,
+                        success: false,
+                        message: format!("Not applied as operation {} failed", failed),
+                    });
+                }
+
+                Ok((
+                    format!("Operation {} failed, no operation was applied", failed),
+                    results,
+                ))
+            }
+            Err(_) => Err("Failed to apply operations".to_string()),
+        }
+    }
+
+    /// Internal function to turn a bulk request into the operations to apply
+    /// Returns whether the operations come from a filter
+    fn resolve(
+        user: i32,
+        data: BulkDTO,
+        conn: &mut PgConnection,
+    ) -> Result<(Vec<BulkOperation>, bool), String> {
+        let (filter, action) = match (data.filter, data.action) {
+            (None, None) => return Ok((data.operations, false)),
+            (Some(filter), Some(action)) if data.operations.is_empty() => (filter, action),
+            _ => {
+                return Err(
+                    "A bulk request needs either operations or a filter with an action".to_string(),
+                )
+            }
+        };
+
+        let todo_filter = TodoFilter {
+            tags: filter.tags,
+            due: filter.due,
+            ..Default::default()
+        };
+
+        let operations = Todo::get_todos(user, &todo_filter, conn)?
+            .into_iter()
+            .map(|todo| todo.todo)
+            .filter(|todo| filter.list_id.is_none_or(|list| todo.list_id == Some(list)))
+            .filter(|todo| {
+                filter
+                    .completed
+                    .is_none_or(|is_completed| todo.completed == is_completed)
+            })
+            .map(|todo| match action {
+                BulkAction::Complete | BulkAction::Reopen => BulkOperation::Update {
+                    id: todo.id,
+                    patch: TodoPatch {
+                        completed: Some(Some(action == BulkAction::Complete)),
+                        ..Default::default()
+                    },
+                    version: None,
+                },
+                BulkAction::Delete => BulkOperation::Delete {
+                    id: todo.id,
+                    children: data.children,
+                },
+            })
+            .collect();
+
+        Ok((operations, true))
+    }
+
+    /// Internal function to apply one operation in a savepoint, so a failed operation leaves no change behind
+    /// Returns the id of the todo and a message
+    fn apply_one(
+        user: i32,
+        operation: BulkOperation,
+        from_filter: bool,
+        conn: &mut PgConnection,
+    ) -> Result<(Option<i32>, String), (Option<i32>, String)> {
+        let target = match &operation {
+            BulkOperation::Create { .. } => None,
+            BulkOperation::Update { id, .. } | BulkOperation::Delete { id, .. } => Some(*id),
+        };
+        let mut failure = None;
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let applied = match operation {
+                BulkOperation::Create { todo } => Todo::create_for(user, todo, conn)
+                    .map(|created| (Some(created.id), "Successfully created todo".to_string())),
+                BulkOperation::Update { id, patch, version } => {
+                    let options = UpdateOptions {
+                        versions: version.map(|version| vec![version]),
+                        ..Default::default()
+                    };
+
+                    Todo::patch_todo(id, user, patch, options, conn)
+                        .map(|_| (Some(id), "Successfully updated todo".to_string()))
+                }
+                BulkOperation::Delete { id, children } => {
+                    // Subtasks matching the filter may be gone with their parent already
+                    let exists = diesel::select(diesel::dsl::exists(todos::table.find(id)))
+                        .get_result::<bool>(conn)?;
+
+                    if from_filter && !exists {
+                        Ok((Some(id), "Todo was deleted with its parent".to_string()))
+                    } else {
+                        Todo::remove_todo(id, user, children, conn)
+                            .map(|_| (Some(id), "Successfully deleted todo".to_string()))
+                    }
+                }
+            };
+
+            applied.map_err(|message| {
+                failure = Some(message);
+                diesel::result::Error::RollbackTransaction
+            })
+        });
+
+        match (result, failure) {
+            (Ok(applied), _) => Ok(applied),
+            (Err(_), Some(message)) => Err((target, message)),
+            (Err(_), None) => Err((target, "Failed to apply operation".to_string())),
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use diesel::{prelude::*, PgConnection};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::models::event::Event;
+use crate::models::list::List;
+use crate::models::todos::{Todo, TodoPatch, UpdateOptions};
+use crate::schema::todos;
+
+/// What a connection subscribes to, e.g. `{"list": 3}` or `{"todo": 5}`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum Topic {
+    /// The todos of a list
+    List(i32),
+    /// A single todo
+    Todo(i32),
+}
+
+/// What a user is doing on a topic
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Activity {
+    /// The user has the topic open, set when subscribing
+    Viewing,
+    /// The user is editing a field
+    Editing,
+}
+
+/// Presence struct representing a connection subscribed to a topic
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Presence {
+    /// Id of the user
+    pub user_id: i32,
+    /// Name of the user
+    pub name: String,
+    /// What the user is doing
+    pub activity: Activity,
+    /// Field the user is editing, e.g. `title`
+    pub field: Option<String>,
+}
+
+/// A message sent by a client, e.g. `{"type": "subscribe", "ref": "1", "topic": {"list": 3}}`
+/// Every message is answered with an ack carrying the same `ref`
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ClientMessage {
+    /// Receives the changes made to a topic and the presence of the other users on it
+    #[serde(rename_all = "camelCase")]
+    Subscribe {
+        /// Chosen by the client, sent back in the ack
+        #[serde(rename = "ref")]
+        reference: Option<String>,
+        /// The topic
+        topic: Topic,
+    },
+    /// Stops receiving the changes made to a topic
+    #[serde(rename_all = "camelCase")]
+    Unsubscribe {
+        /// Chosen by the client, sent back in the ack
+        #[serde(rename = "ref")]
+        reference: Option<String>,
+        /// The topic
+        topic: Topic,
+    },
+    /// Tells the other users on a subscribed topic what the user is doing
+    #[serde(rename_all = "camelCase")]
+    Presence {
+        /// Chosen by the client, sent back in the ack
+        #[serde(rename = "ref")]
+        reference: Option<String>,
+        /// The topic
+        topic: Topic,
+        /// What the user is doing
+        activity: Activity,
+        /// Field the user is editing, e.g. `title`
+        field: Option<String>,
+    },
+    /// Patches a todo with a JSON Merge Patch, like `PATCH /todo/<id>`
+    #[serde(rename_all = "camelCase")]
+    Edit {
+        /// Chosen by the client, sent back in the ack
+        #[serde(rename = "ref")]
+        reference: Option<String>,
+        /// Id of the todo
+        id: i32,
+        /// Version of the todo the edit was made on, the edit is rejected if the todo changed since
+        base_version: Option<i32>,
+        /// The fields to change, for reference see `TodoPatch` struct
+        patch: TodoPatch,
+    },
+}
+
+/// A message sent to a client, e.g. `{"type": "ack", "ref": "1", "ok": true, "message": "Subscribed"}`
+#[derive(Serialize, Debug, JsonSchema)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ServerMessage {
+    /// Answers a message of the client
+    #[serde(rename_all = "camelCase")]
+    Ack {
+        /// The `ref` of the message, empty if the message could not be read
+        #[serde(rename = "ref")]
+        reference: Option<String>,
+        /// Whether the message was handled
+        ok: bool,
+        /// Success message or error message
+        message: String,
+        /// The todo as it is after an edit
+        todo: Option<Box<Todo>>,
+    },
+    /// Everyone on a topic, sent whenever someone subscribes, unsubscribes or changes activity
+    #[serde(rename_all = "camelCase")]
+    Presence {
+        /// The topic
+        topic: Topic,
+        /// The connections subscribed to the topic, a user with two connections is listed twice
+        users: Vec<Presence>,
+    },
+    /// A change made to a subscribed topic by another connection, for reference see `Event` struct
+    #[serde(rename_all = "camelCase")]
+    Change {
+        /// The topic
+        topic: Topic,
+        /// The recorded event, with the changed fields
+        event: Event,
+    },
+    /// The user cannot read a subscribed topic anymore, the connection is unsubscribed from it
+    #[serde(rename_all = "camelCase")]
+    Revoked {
+        /// The topic
+        topic: Topic,
+        /// Why the topic cannot be read
+        message: String,
+    },
+    /// Changes were missed because the client read too slowly, the subscribed topics should be loaded again
+    Resync,
+}
+
+/// Implementation of the Topic struct
+impl Topic {
+    /// Check that a user can subscribe to a topic function
+    /// The user must be able to read the list or the todo
+    /// # Arguments
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<(), String>` - Result containing nothing or an error message
+    pub fn authorize(&self, user: i32, conn: &mut PgConnection) -> Result<(), String> {
+        match self {
+            Topic::List(list_id) => List::find_readable(*list_id, user, conn).map(|_| ()),
+            Topic::Todo(todo_id) => Todo::find_readable(*todo_id, user, conn).map(|_| ()),
+        }
+    }
+
+    /// Get the lists the todo of an event is in, before and after the event, function
+    /// Read from the event when the list changed, or when the todo was created or deleted
+    /// # Arguments
+    /// * `event` - The event
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<i32>, String>` - Result containing the ids of the lists or an error message
+    pub fn lists_of(event: &Event, conn: &mut PgConnection) -> Result<Vec<i32>, String> {
+        if let Some(change) = event.changes.get("listId") {
+            return Ok(["old", "new"]
+                .iter()
+                .filter_map(|side| change.get(side).and_then(Value::as_i64))
+                .map(|list_id| list_id as i32)
+                .collect());
+        }
+
+        let result = todos::table
+            .find(event.todo_id)
+            .select(todos::list_id)
+            .first::<Option<i32>>(conn)
+            .optional();
+
+        match result {
+            Ok(list_id) => Ok(list_id.flatten().into_iter().collect()),
+            Err(_) => Err("Failed to get todo".to_string()),
+        }
+    }
+
+    /// Check whether an event is a change to the topic
+    /// # Arguments
+    /// * `event` - The event
+    /// * `lists` - Ids of the lists the todo of the event is in, for reference see `lists_of`
+    /// # Returns
+    /// * `bool` - Whether the event changed the subscribed todo, or a todo in or leaving the subscribed list
+    pub fn covers(&self, event: &Event, lists: &[i32]) -> bool {
+        match self {
+            Topic::List(list_id) => lists.contains(list_id),
+            Topic::Todo(todo_id) => event.todo_id == *todo_id,
+        }
+    }
+}
+
+/// Implementation of the ClientMessage struct
+impl ClientMessage {
+    /// Edit a todo function
+    /// The edit follows the same rules as a patch, the user must own the todo or be allowed to write todos in its organization
+    /// # Arguments
+    /// * `todo_id` - Id of the todo
+    /// * `user` - Id of the user
+    /// * `base_version` - Version the todo must still be at
+    /// * `patch` - TodoPatch struct containing the fields to change
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Todo, String>` - Result containing the edited todo or an error message
+    pub fn edit(
+        todo_id: i32,
+        user: i32,
+        base_version: Option<i32>,
+        patch: TodoPatch,
+        conn: &mut PgConnection,
+    ) -> Result<Todo, String> {
+        let options = UpdateOptions {
+            versions: base_version.map(|version| vec![version]),
+            ..UpdateOptions::default()
+        };
+
+        Todo::patch_todo(todo_id, user, patch, options, conn)
+    }
+}
@@ -0,0 +1,452 @@
+use chrono::NaiveDateTime;
+use diesel::{
+    prelude::*, sql_function, Identifiable, Insertable, PgConnection, Queryable, RunQueryDsl,
+};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::models::notification::{NewNotification, Notification, NotificationKind};
+use crate::models::organization::{Membership, Role};
+use crate::models::todos::Todo;
+use crate::schema::todo_comments::{self, dsl::*};
+use crate::schema::{todo_comment_revisions, users};
+use crate::utils::markdown;
+
+sql_function!(fn lower(x: diesel::sql_types::Text) -> diesel::sql_types::Text);
+
+/// Maximum number of characters of a comment
+static MAX_BODY_LENGTH: usize = 10000;
+
+/// Comment struct representing a row in the todo_comments table in the database
+#[derive(Identifiable, Queryable, Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "todo_comments"]
+pub struct Comment {
+    /// Unique id of the comment
+    /// This is the primary key of the todo_comments table
+    /// This is auto generated by the database
+    pub id: i32,
+    /// Id of the todo the comment is on
+    pub todo_id: i32,
+    /// Id of the user who wrote the comment
+    pub user_id: i32,
+    /// Body of the comment in Markdown
+    pub body: String,
+    /// Time the comment was created in UTC
+    pub created_at: NaiveDateTime,
+    /// Time the comment was last edited in UTC
+    pub updated_at: NaiveDateTime,
+}
+
+/// CommentDTO struct representing the data sent to create or edit a comment
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentDTO {
+    /// Body of the comment in Markdown
+    /// Users can be mentioned with `@name` or `@email`
+    pub body: Option<String>,
+}
+
+/// NewComment struct representing the data to be sent to the database to create a comment
+#[derive(Insertable, Debug)]
+#[table_name = "todo_comments"]
+struct NewComment {
+    todo_id: i32,
+    user_id: i32,
+    body: String,
+}
+
+/// CommentRevision struct representing a row in the todo_comment_revisions table in the database
+/// A revision keeps the body a comment had before it was edited
+#[derive(Queryable, Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentRevision {
+    /// Unique id of the revision
+    pub id: i32,
+    /// Id of the edited comment
+    pub comment_id: i32,
+    /// Body of the comment before the edit, in Markdown
+    pub body: String,
+    /// Id of the user who edited the comment
+    pub edited_by: Option<i32>,
+    /// Time the comment was edited in UTC
+    pub edited_at: NaiveDateTime,
+}
+
+/// NewRevision struct representing the data to be sent to the database to create a revision
+#[derive(Insertable, Debug)]
+#[table_name = "todo_comment_revisions"]
+struct NewRevision {
+    comment_id: i32,
+    body: String,
+    edited_by: Option<i32>,
+}
+
+/// CommentWithHtml struct representing a comment along with its rendered body
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentWithHtml {
+    /// The comment itself, its fields are flattened into this struct
+    #[serde(flatten)]
+    pub comment: Comment,
+    /// Body of the comment rendered to HTML, raw HTML in the body is escaped
+    pub html: String,
+    /// Whether the comment was edited since it was created
+    pub edited: bool,
+}
+
+/// Implementation of the Comment struct
+impl Comment {
+    /// Create a new comment on a todo function
+    /// Users mentioned in the comment who can read the todo are notified
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `user` - Id of the user writing the comment
+    /// * `data` - CommentDTO struct containing the body of the comment
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<CommentWithHtml, String>` - Result containing the created comment or an error message
+    pub fn new_comment(
+        todo: i32,
+        user: i32,
+        data: CommentDTO,
+        conn: &mut PgConnection,
+    ) -> Result<CommentWithHtml, String> {
+        let commented = Todo::find_readable(todo, user, conn)?;
+        let comment_body = Comment::validate_body(data.body)?;
+        let recipients = Comment::mentioned_users(&commented, user, &comment_body, None, conn)?;
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let comment = diesel::insert_into(todo_comments)
+                .values(&NewComment {
+                    todo_id: todo,
+                    user_id: user,
+                    body: comment_body,
+                })
+                .get_result::<Comment>(conn)?;
+
+            Notification::notify(
+                &Comment::mention_notifications(&commented, user, &recipients),
+                conn,
+            )?;
+
+            Ok(comment)
+        });
+
+        match result {
+            Ok(comment) => Ok(Comment::with_html(comment)),
+            Err(_) => Err("Failed to create comment".to_string()),
+        }
+    }
+
+    /// Gets the comments of a todo function
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<CommentWithHtml>, String>` - Result containing the comments, oldest first, or an error message
+    pub fn get_comments(
+        todo: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<CommentWithHtml>, String> {
+        Todo::find_readable(todo, user, conn)?;
+
+        let result = todo_comments
+            .filter(todo_id.eq(todo))
+            .order((created_at.asc(), id.asc()))
+            .load::<Comment>(conn);
+
+        match result {
+            Ok(comments) => Ok(comments.into_iter().map(Comment::with_html).collect()),
+            Err(_) => Err("Failed to get comments".to_string()),
+        }
+    }
+
+    /// Edit a comment function
+    /// Only the author of the comment or an admin can edit it, the previous body is kept as a revision
+    /// Users newly mentioned in the comment are notified
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `comment_id` - Id of the comment to be edited
+    /// * `user` - Id of the user editing the comment
+    /// * `data` - CommentDTO struct containing the new body of the comment
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<CommentWithHtml, String>` - Result containing the edited comment or an error message
+    pub fn update_comment(
+        todo: i32,
+        comment_id: i32,
+        user: i32,
+        data: CommentDTO,
+        conn: &mut PgConnection,
+    ) -> Result<CommentWithHtml, String> {
+        let (commented, comment) = Comment::find_editable(todo, comment_id, user, conn)?;
+        let comment_body = Comment::validate_body(data.body)?;
+        let recipients =
+            Comment::mentioned_users(&commented, user, &comment_body, Some(&comment.body), conn)?;
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::insert_into(todo_comment_revisions::table)
+                .values(&NewRevision {
+                    comment_id,
+                    body: comment.body,
+                    edited_by: Some(user),
+                })
+                .execute(conn)?;
+
+            let edited = diesel::update(todo_comments.find(comment_id))
+                .set(body.eq(comment_body))
+                .get_result::<Comment>(conn)?;
+
+            Notification::notify(
+                &Comment::mention_notifications(&commented, user, &recipients),
+                conn,
+            )?;
+
+            Ok(edited)
+        });
+
+        match result {
+            Ok(comment) => Ok(Comment::with_html(comment)),
+            Err(_) => Err("Failed to update comment".to_string()),
+        }
+    }
+
+    /// Delete a comment function
+    /// Only the author of the comment or an admin can delete it
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `comment_id` - Id of the comment to be deleted
+    /// * `user` - Id of the user deleting the comment
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn delete_comment(
+        todo: i32,
+        comment_id: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<String, String> {
+        Comment::find_editable(todo, comment_id, user, conn)?;
+
+        let result = diesel::delete(todo_comments.find(comment_id)).execute(conn);
+
+        match result {
+            Ok(_) => Ok("Successfully deleted comment".to_string()),
+            Err(_) => Err("Failed to delete comment".to_string()),
+        }
+    }
+
+    /// Gets the edit history of a comment function
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `comment_id` - Id of the comment
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<CommentRevision>, String>` - Result containing the previous bodies, oldest first, or an error message
+    pub fn get_revisions(
+        todo: i32,
+        comment_id: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<CommentRevision>, String> {
+        Todo::find_readable(todo, user, conn)?;
+        Comment::find_in_todo(todo, comment_id, conn)?;
+
+        let result = todo_comment_revisions::table
+            .filter(todo_comment_revisions::comment_id.eq(comment_id))
+            .order((
+                todo_comment_revisions::edited_at.asc(),
+                todo_comment_revisions::id.asc(),
+            ))
+            .load::<CommentRevision>(conn);
+
+        match result {
+            Ok(revisions) => Ok(revisions),
+            Err(_) => Err("Failed to get comment history".to_string()),
+        }
+    }
+
+    /// Internal function to render a comment
+    fn with_html(comment: Comment) -> CommentWithHtml {
+        CommentWithHtml {
+            html: markdown::to_html(&comment.body),
+            edited: comment.updated_at > comment.created_at,
+            comment,
+        }
+    }
+
+    /// Internal function to find a comment of a todo
+    fn find_in_todo(
+        todo: i32,
+        comment_id: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Comment, String> {
+        let result = todo_comments
+            .find(comment_id)
+            .filter(todo_id.eq(todo))
+            .first::<Comment>(conn)
+            .optional();
+
+        match result {
+            Ok(Some(comment)) => Ok(comment),
+            Ok(None) => Err("Comment not found".to_string()),
+            Err(_) => Err("Failed to get comment".to_string()),
+        }
+    }
+
+    /// Internal function to find a comment the user is allowed to edit
+    /// The author of a comment can edit it as long as they can read the todo, admins can edit any comment
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `comment_id` - Id of the comment
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<(Todo, Comment), String>` - Result containing the todo and the comment or an error message
+    fn find_editable(
+        todo: i32,
+        comment_id: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<(Todo, Comment), String> {
+        let commented = Todo::find_readable(todo, user, conn)?;
+        let comment = Comment::find_in_todo(todo, comment_id, conn)?;
+
+        if comment.user_id == user {
+            return Ok((commented, comment));
+        }
+
+        let role =
+            Membership::resource_role(commented.user_id, commented.organization_id, user, conn)?;
+
+        match role {
+            Some(role) if role >= Role::Admin => Ok((commented, comment)),
+            _ => Err("Only the author or an admin can change this comment".to_string()),
+        }
+    }
+
+    /// Internal function to find the users mentioned in a comment who can read the todo
+    /// A mention matches the user with that email, or else the one user with that name who can read the todo,
+    /// names shared by several of them are too ambiguous to notify anyone
+    /// Users already mentioned in the previous body and the author are left out
+    /// # Arguments
+    /// * `commented` - The todo the comment is on
+    /// * `author` - Id of the user writing the comment
+    /// * `comment_body` - Body of the comment
+    /// * `previous` - Body of the comment before it was edited, if any
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<i32>, String>` - Result containing the ids of the users to notify or an error message
+    fn mentioned_users(
+        commented: &Todo,
+        author: i32,
+        comment_body: &str,
+        previous: Option<&str>,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<i32>, String> {
+        let already_mentioned = previous.map(markdown::mentions).unwrap_or_default();
+        let mentions: Vec<String> = markdown::mentions(comment_body)
+            .into_iter()
+            .filter(|mention| !already_mentioned.contains(mention))
+            .collect();
+
+        if mentions.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let result = users::table
+            .filter(
+                lower(users::email)
+                    .eq_any(&mentions)
+                    .or(lower(users::name).eq_any(&mentions)),
+            )
+            .select((users::id, lower(users::email), lower(users::name)))
+            .load::<(i32, String, String)>(conn);
+
+        let candidates = match result {
+            Ok(candidates) => candidates,
+            Err(_) => return Err("Failed to find mentioned users".to_string()),
+        };
+
+        let mut readers = vec![];
+
+        for (candidate, email, name) in candidates {
+            let role = Membership::resource_role(
+                commented.user_id,
+                commented.organization_id,
+                candidate,
+                conn,
+            )?;
+
+            if role.is_some() {
+                readers.push((candidate, email, name));
+            }
+        }
+
+        let mut recipients = vec![];
+
+        for mention in &mentions {
+            let by_email = readers.iter().find(|(_, email, _)| email == mention);
+            let by_name: Vec<&(i32, String, String)> = readers
+                .iter()
+                .filter(|(_, _, name)| name == mention)
+                .collect();
+
+            let mentioned_user = match (by_email, by_name.as_slice()) {
+                (Some((mentioned_user, _, _)), _) => *mentioned_user,
+                (None, [(mentioned_user, _, _)]) => *mentioned_user,
+                _ => continue,
+            };
+
+            if mentioned_user != author && !recipients.contains(&mentioned_user) {
+                recipients.push(mentioned_user);
+            }
+        }
+
+        Ok(recipients)
+    }
+
+    /// Internal function to build the notifications of the users mentioned in a comment
+    fn mention_notifications(
+        commented: &Todo,
+        author: i32,
+        recipients: &[i32],
+    ) -> Vec<NewNotification> {
+        recipients
+            .iter()
+            .map(|recipient| NewNotification {
+                user_id: *recipient,
+                actor_id: Some(author),
+                todo_id: Some(commented.id),
+                kind: NotificationKind::Mention,
+                message: format!("You were mentioned in a comment on {}", commented.title),
+            })
+            .collect()
+    }
+
+    /// Internal function to validate the body of a comment
+    /// # Arguments
+    /// * `comment_body` - Body of the comment
+    /// # Returns
+    /// * `Result<String, String>` - Result containing the body or an error message
+    fn validate_body(comment_body: Option<String>) -> Result<String, String> {
+        let comment_body = match comment_body {
+            Some(comment_body) if !comment_body.trim().is_empty() => comment_body,
+            _ => return Err("Body is required".to_string()),
+        };
+
+        if comment_body.chars().count() > MAX_BODY_LENGTH {
+            return Err(format!(
+                "Body must be at most {} characters",
+                MAX_BODY_LENGTH
+            ));
+        }
+
+        Ok(comment_body)
+    }
+}
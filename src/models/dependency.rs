@@ -0,0 +1,247 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use diesel::sql_types::BigInt;
+use diesel::{prelude::*, Insertable, PgConnection, Queryable, RunQueryDsl};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::models::todos::{Todo, TodoFilter};
+use crate::schema::todo_dependencies::{self, dsl::*};
+use crate::schema::todos;
+
+/// Key of the advisory lock taken while adding a dependency
+/// Dependencies link todos of different owners, so the whole graph shares one lock
+static GRAPH_LOCK: i64 = 0x746f_646f_6465_7073;
+
+/// Dependency struct representing a row in the todo_dependencies table in the database
+/// The blocker todo must be completed before the blocked todo can be completed
+#[derive(Queryable, Insertable, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "todo_dependencies"]
+pub struct Dependency {
+    /// Id of the todo that blocks the other one
+    pub blocker_id: i32,
+    /// Id of the todo that is blocked
+    pub blocked_id: i32,
+}
+
+/// GraphNode struct representing a todo in the dependency graph
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphNode {
+    /// Id of the todo
+    pub id: i32,
+    /// Title of the todo
+    pub title: String,
+    /// Whether the todo is completed or not
+    pub completed: bool,
+    /// Ids of the todos blocking this todo
+    pub blocked_by: Vec<i32>,
+    /// Ids of the todos blocked by this todo
+    pub blocks: Vec<i32>,
+}
+
+/// Implementation of the Dependency struct
+impl Dependency {
+    /// Add a dependency between two todos function
+    /// Dependencies that would create a cycle are rejected
+    /// # Arguments
+    /// * `blocked` - Id of the todo that is blocked
+    /// * `blocker` - Id of the todo that blocks it
+    /// * `user` - Id of the user adding the dependency
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn add_dependency(
+        blocked: i32,
+        blocker: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<String, String> {
+        if blocked == blocker {
+            return Err("A todo cannot block itself".to_string());
+        }
+
+        Todo::find_writable(blocked, user, conn)?;
+        Todo::find_readable(blocker, user, conn)?;
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            // Two requests adding A -> B and B -> A would otherwise both find no cycle
+            diesel::sql_query("SELECT pg_advisory_xact_lock($1)")
+                .bind::<BigInt, _>(GRAPH_LOCK)
+                .execute(conn)?;
+
+            // The new edge closes a cycle if the blocker is already reachable from the blocked todo
+            let mut visited = HashSet::new();
+            let mut pending = vec![blocked];
+
+            while let Some(current) = pending.pop() {
+                if current == blocker {
+                    return Ok(false);
+                }
+
+                if !visited.insert(current) {
+                    continue;
+                }
+
+                let next = todo_dependencies
+                    .filter(blocker_id.eq(current))
+                    .select(blocked_id)
+                    .load::<i32>(conn)?;
+
+                pending.extend(next);
+            }
+
+            diesel::insert_into(todo_dependencies)
+                .values(&Dependency {
+                    blocker_id: blocker,
+                    blocked_id: blocked,
+                })
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+
+            Ok(true)
+        });
+
+        match result {
+            Ok(true) => Ok("Successfully added dependency".to_string()),
+            Ok(false) => Err("Dependency would create a cycle".to_string()),
+            Err(_) => Err("Failed to add dependency".to_string()),
+        }
+    }
+
+    /// Remove a dependency between two todos function
+    /// # Arguments
+    /// * `blocked` - Id of the todo that is blocked
+    /// * `blocker` - Id of the todo that blocks it
+    /// * `user` - Id of the user removing the dependency
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn remove_dependency(
+        blocked: i32,
+        blocker: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<String, String> {
+        Todo::find_writable(blocked, user, conn)?;
+
+        let result = diesel::delete(todo_dependencies.find((blocker, blocked))).execute(conn);
+
+        match result {
+            Ok(0) => Err("Dependency not found".to_string()),
+            Ok(_) => Ok("Successfully removed dependency".to_string()),
+            Err(_) => Err("Failed to remove dependency".to_string()),
+        }
+    }
+
+    /// Gets the todos blocking some todos that are not completed yet function
+    /// # Arguments
+    /// * `blocked` - Ids of the todos that are blocked
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<i32>, String>` - Result containing the ids of the open blockers or an error message
+    pub fn get_open_blockers(blocked: &[i32], conn: &mut PgConnection) -> Result<Vec<i32>, String> {
+        let result = todo_dependencies
+            .inner_join(todos::table.on(todos::id.eq(blocker_id)))
+            .filter(blocked_id.eq_any(blocked))
+            .filter(todos::completed.eq(false))
+            .select(blocker_id)
+            .distinct()
+            .load::<i32>(conn);
+
+        match result {
+            Ok(data) => Ok(data),
+            Err(_) => Err("Failed to get dependencies".to_string()),
+        }
+    }
+
+    /// Gets the dependency graph of the todos of a user in topological order function
+    /// Blockers always come before the todos they block, ties are broken by id
+    /// # Arguments
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<GraphNode>, String>` - Result containing the todos in topological order or an error message
+    pub fn get_graph(user: i32, conn: &mut PgConnection) -> Result<Vec<GraphNode>, String> {
+        let user_todos = Todo::get_todos(user, &TodoFilter::default(), conn)?;
+        let todo_ids: Vec<i32> = user_todos.iter().map(|todo| todo.todo.id).collect();
+
+        let edges = match todo_dependencies
+            .filter(blocker_id.eq_any(&todo_ids))
+            .filter(blocked_id.eq_any(&todo_ids))
+            .load::<Dependency>(conn)
+        {
+            Ok(edges) => edges,
+            Err(_) => return Err("Failed to get dependencies".to_string()),
+        };
+
+        let mut blocked_by: HashMap<i32, Vec<i32>> = HashMap::new();
+        let mut blocks: HashMap<i32, Vec<i32>> = HashMap::new();
+
+        for edge in edges.iter() {
+            blocked_by
+                .entry(edge.blocked_id)
+                .or_default()
+                .push(edge.blocker_id);
+            blocks
+                .entry(edge.blocker_id)
+                .or_default()
+                .push(edge.blocked_id);
+        }
+
+        let mut nodes: HashMap<i32, GraphNode> = user_todos
+            .into_iter()
+            .map(|todo| {
+                let todo = todo.todo;
+                let node = GraphNode {
+                    id: todo.id,
+                    title: todo.title,
+                    completed: todo.completed,
+                    blocked_by: blocked_by.remove(&todo.id).unwrap_or_default(),
+                    blocks: blocks.remove(&todo.id).unwrap_or_default(),
+                };
+
+                (todo.id, node)
+            })
+            .collect();
+
+        // Kahn's algorithm, the ready set is ordered so the result is stable
+        let mut remaining: HashMap<i32, usize> = nodes
+            .values()
+            .map(|node| (node.id, node.blocked_by.len()))
+            .collect();
+        let mut ready: BTreeSet<i32> = remaining
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(node, _)| *node)
+            .collect();
+        let mut ordered = vec![];
+
+        while let Some(current) = ready.pop_first() {
+            let node = match nodes.remove(&current) {
+                Some(node) => node,
+                None => continue,
+            };
+
+            for next in node.blocks.iter() {
+                if let Some(count) = remaining.get_mut(next) {
+                    *count -= 1;
+
+                    if *count == 0 {
+                        ready.insert(*next);
+                    }
+                }
+            }
+
+            ordered.push(node);
+        }
+
+        if !nodes.is_empty() {
+            return Err("Dependency graph contains a cycle".to_string());
+        }
+
+        Ok(ordered)
+    }
+}
@@ -0,0 +1,782 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::dsl::sql;
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::{BigInt, Bool, Text};
+use diesel::{
+    prelude::*, AsChangeset, Identifiable, Insertable, PgConnection, Queryable, RunQueryDsl,
+};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::models::attachment::Attachment;
+use crate::models::organization::Membership;
+use crate::models::todos::{Priority, Todo, TodoDTO, UpdateOptions};
+use crate::models::webhook::Webhook;
+use crate::schema::todo_events::{self, dsl::*};
+use crate::schema::{lists, memberships, statuses, todos, users};
+
+/// Fields of a todo left out of the diffs, as they change on every update
+static IGNORED_FIELDS: [&str; 2] = ["updatedAt", "version"];
+
+/// Default number of events returned by the activity feed
+static DEFAULT_LIMIT: i64 = 50;
+
+/// Maximum number of events returned by the activity feed
+static MAX_LIMIT: i64 = 200;
+
+/// Number of seconds during which a change can be undone
+static UNDO_WINDOW: i64 = 5 * 60;
+
+/// What happened to a todo
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[diesel(sql_type = Text)]
+pub enum EventAction {
+    /// The todo was created
+    Create,
+    /// The todo was updated
+    Update,
+    /// The todo was deleted
+    Delete,
+}
+
+impl EventAction {
+    /// Returns the value stored in the database for the action
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventAction::Create => "create",
+            EventAction::Update => "update",
+            EventAction::Delete => "delete",
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for EventAction {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        <str as ToSql<Text, Pg>>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for EventAction {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match <String as FromSql<Text, Pg>>::from_sql(bytes)?.as_str() {
+            "create" => Ok(EventAction::Create),
+            "update" => Ok(EventAction::Update),
+            "delete" => Ok(EventAction::Delete),
+            other => Err(format!("Unknown event action {}", other).into()),
+        }
+    }
+}
+
+/// Event struct representing a row in the todo_events table in the database
+/// Every change made to a todo is recorded as an event
+#[derive(Identifiable, Queryable, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "todo_events"]
+pub struct Event {
+    /// Unique id of the event
+    /// This is the primary key of the todo_events table
+    /// This is auto generated by the database
+    pub id: i32,
+    /// Id of the todo, which may have been deleted since
+    pub todo_id: i32,
+    /// Version of the todo the event produced, starting at 1 when the todo is created
+    pub version: i32,
+    /// Id of the user who made the change, empty if the user was deleted
+    pub actor_id: Option<i32>,
+    /// What happened to the todo
+    pub action: EventAction,
+    /// Changed fields, e.g. `{"title": {"old": "Draft", "new": "Final"}}`
+    /// A created todo has no old values and a deleted todo no new values
+    pub changes: Value,
+    /// Id of the user who created the todo
+    pub owner_id: i32,
+    /// Id of the organization the todo was shared with
+    pub organization_id: Option<i32>,
+    /// Time of the change in UTC
+    pub created_at: NaiveDateTime,
+    /// Id shared by the events recorded by the same change, e.g. a todo deleted along with its subtasks
+    pub mutation_id: i64,
+}
+
+/// EventCursor struct representing a position in the history of the events, written as `<mutation_id>.<id>`
+/// Events are read in the order of their transaction, then of their id, and only once every transaction that could
+/// still record an event before them has ended, so reading on from a cursor never skips an event committed late
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct EventCursor {
+    /// Id of the transaction of the last event read
+    pub mutation_id: i64,
+    /// Id of the last event read
+    pub id: i32,
+}
+
+/// NewEvent struct representing the data to be sent to the database to record an event
+#[derive(Insertable, Debug)]
+#[table_name = "todo_events"]
+struct NewEvent {
+    todo_id: i32,
+    version: i32,
+    actor_id: Option<i32>,
+    action: EventAction,
+    changes: Value,
+    owner_id: i32,
+    organization_id: Option<i32>,
+}
+
+/// RevertDTO struct representing the version a todo is reverted to
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RevertDTO {
+    /// Version of the todo to restore, as listed in its history
+    pub version: Option<i32>,
+}
+
+/// TodoSnapshot struct representing every stored field of a todo, used to restore a todo from its history
+/// Empty fields are written as NULL, unlike with TodoDTO
+#[derive(Insertable, AsChangeset, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "todos"]
+#[diesel(treat_none_as_null = true)]
+struct TodoSnapshot {
+    id: i32,
+    user_id: i32,
+    title: String,
+    description: String,
+    completed: bool,
+    organization_id: Option<i32>,
+    list_id: Option<i32>,
+    created_at: NaiveDateTime,
+    due_at: Option<NaiveDateTime>,
+    completed_at: Option<NaiveDateTime>,
+    priority: Priority,
+    recurrence_rule: Option<String>,
+    recurrence_tz: Option<String>,
+    series_id: Option<i32>,
+    occurrence: i32,
+    parent_id: Option<i32>,
+    position: String,
+    status_id: Option<i32>,
+    assignee_id: Option<i32>,
+}
+
+/// Implementation of the EventCursor struct
+impl EventCursor {
+    /// Get the cursor right after an event
+    pub fn of(event: &Event) -> EventCursor {
+        EventCursor {
+            mutation_id: event.mutation_id,
+            id: event.id,
+        }
+    }
+
+    /// Get the cursor before the events of the transactions still running function
+    /// Every event before it is committed, the changes it records are visible to the queries made afterwards
+    /// # Arguments
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<EventCursor>` - Result containing the cursor
+    pub fn horizon(conn: &mut PgConnection) -> QueryResult<EventCursor> {
+        let oldest_running =
+            diesel::select(sql::<BigInt>("txid_snapshot_xmin(txid_current_snapshot())"))
+                .get_result::<i64>(conn)?;
+
+        Ok(EventCursor {
+            mutation_id: oldest_running,
+            id: 0,
+        })
+    }
+
+    /// Parse a cursor written as `<mutation_id>.<id>` function
+    /// # Arguments
+    /// * `value` - The cursor
+    /// # Returns
+    /// * `Option<EventCursor>` - The cursor, None if it is invalid
+    pub fn parse(value: &str) -> Option<EventCursor> {
+        let (transaction, event) = value.trim().split_once('.')?;
+
+        match (transaction.parse::<i64>(), event.parse::<i32>()) {
+            (Ok(transaction), Ok(event)) if transaction >= 0 && event >= 0 => Some(EventCursor {
+                mutation_id: transaction,
+                id: event,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for EventCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.mutation_id, self.id)
+    }
+}
+
+/// Implementation of the Event struct
+impl Event {
+    /// Records a change made to a todo function
+    /// Must be called in the transaction making the change, after the todo row was written
+    /// The version of the todo is incremented, an update that changes nothing is not recorded
+    /// The deliveries of the webhooks subscribed to the change are queued along with the event
+    /// # Arguments
+    /// * `actor` - Id of the user who made the change
+    /// * `before` - The todo before the change, None if it was created
+    /// * `after` - The todo after the change, None if it was deleted
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<()>` - Result of the query
+    pub fn record(
+        actor: i32,
+        before: Option<&Todo>,
+        after: Option<&Todo>,
+        conn: &mut PgConnection,
+    ) -> QueryResult<()> {
+        let (todo, event_action) = match (before, after) {
+            (None, Some(after)) => (after, EventAction::Create),
+            (Some(_), Some(after)) => (after, EventAction::Update),
+            (Some(before), None) => (before, EventAction::Delete),
+            (None, None) => return Ok(()),
+        };
+
+        let diff = Event::diff(before, after);
+
+        if diff.is_empty() {
+            return Ok(());
+        }
+
+        let last_version = todo_events
+            .filter(todo_id.eq(todo.id))
+            .select(diesel::dsl::max(version))
+            .first::<Option<i32>>(conn)?;
+
+        // Todos changed before their history was recorded have no events for their first versions
+        let current_version = before.map_or(0, |before| before.version);
+        let next_version = last_version.unwrap_or(0).max(current_version) + 1;
+
+        if let Some(after) = after {
+            if after.version != next_version {
+                diesel::update(todos::table.find(after.id))
+                    .set(todos::version.eq(next_version))
+                    .execute(conn)?;
+            }
+        }
+
+        let recorded = diesel::insert_into(todo_events)
+            .values(&NewEvent {
+                todo_id: todo.id,
+                version: next_version,
+                actor_id: Some(actor),
+                action: event_action,
+                changes: Value::Object(diff),
+                owner_id: todo.user_id,
+                organization_id: todo.organization_id,
+            })
+            .get_result::<Event>(conn)?;
+
+        Webhook::enqueue(&recorded, todo, conn)
+    }
+
+    /// Records the changes made to several todos function
+    /// Todos are matched by id, todos found in only one of the slices are ignored
+    /// # Arguments
+    /// * `actor` - Id of the user who made the changes
+    /// * `before` - The todos before the changes
+    /// * `after` - The todos after the changes
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<()>` - Result of the query
+    pub fn record_all(
+        actor: i32,
+        before: &[Todo],
+        after: &[Todo],
+        conn: &mut PgConnection,
+    ) -> QueryResult<()> {
+        for updated in after {
+            if let Some(previous) = before.iter().find(|todo| todo.id == updated.id) {
+                Event::record(actor, Some(previous), Some(updated), conn)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gets the history of a todo function
+    /// The history of a deleted todo stays readable by the users who could read the todo
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<Event>, String>` - Result containing the events, oldest first, or an error message
+    pub fn get_history(
+        todo: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<Event>, String> {
+        let result = todo_events
+            .filter(todo_id.eq(todo))
+            .order(version.asc())
+            .load::<Event>(conn);
+
+        let events = match result {
+            Ok(events) => events,
+            Err(_) => return Err("Failed to get history".to_string()),
+        };
+
+        // The last event tells who the todo belongs to, even once it is deleted
+        match events.last() {
+            Some(last) => {
+                let role =
+                    Membership::resource_role(last.owner_id, last.organization_id, user, conn)?;
+
+                if role.is_none() {
+                    return Err("Todo not found".to_string());
+                }
+            }
+            None => {
+                Todo::find_readable(todo, user, conn)?;
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Gets the activity feed of a user function
+    /// The feed holds the events of the todos the user owns or that are shared with their organizations
+    /// # Arguments
+    /// * `user` - Id of the user
+    /// * `before` - Only events older than this event id are returned, used to page through the feed
+    /// * `limit` - Maximum number of events returned, 50 by default and at most 200
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<Event>, String>` - Result containing the events, newest first, or an error message
+    pub fn get_activity(
+        user: i32,
+        before: Option<i32>,
+        limit: Option<i64>,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<Event>, String> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT);
+
+        if !(1..=MAX_LIMIT).contains(&limit) {
+            return Err(format!("Limit must be between 1 and {}", MAX_LIMIT));
+        }
+
+        let user_organizations = memberships::table
+            .filter(memberships::user_id.eq(user))
+            .select(memberships::organization_id.nullable());
+
+        let mut query = todo_events
+            .filter(
+                owner_id
+                    .eq(user)
+                    .or(organization_id.eq_any(user_organizations)),
+            )
+            .into_boxed();
+
+        if let Some(before) = before {
+            query = query.filter(id.lt(before));
+        }
+
+        let result = query.order(id.desc()).limit(limit).load::<Event>(conn);
+
+        match result {
+            Ok(events) => Ok(events),
+            Err(_) => Err("Failed to get activity".to_string()),
+        }
+    }
+
+    /// Gets the events committed after a cursor that may concern a user function
+    /// Events of transactions still running, and of the transactions started after them, are left for a later read
+    /// Along with the events of the todos the user can read, the events moving a todo to another organization are
+    /// returned, check each event with `is_visible_to`
+    /// # Arguments
+    /// * `user` - Id of the user
+    /// * `organizations` - Ids of the organizations the user is a member of
+    /// * `after` - Only events after this cursor are returned
+    /// * `limit` - Maximum number of events returned
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<Vec<Event>>` - Result containing the events, in the order of their cursors
+    pub fn get_committed_since(
+        user: i32,
+        organizations: &[i32],
+        after: EventCursor,
+        limit: i64,
+        conn: &mut PgConnection,
+    ) -> QueryResult<Vec<Event>> {
+        todo_events
+            .filter(
+                mutation_id
+                    .gt(after.mutation_id)
+                    .or(mutation_id.eq(after.mutation_id).and(id.gt(after.id))),
+            )
+            .filter(sql::<Bool>(
+                "mutation_id < txid_snapshot_xmin(txid_current_snapshot())",
+            ))
+            .filter(
+                owner_id
+                    .eq(user)
+                    .or(organization_id
+                        .eq_any(organizations.iter().map(|organization| Some(*organization))))
+                    .or(sql::<Bool>("changes ? 'organizationId'")),
+            )
+            .order((mutation_id.asc(), id.asc()))
+            .limit(limit)
+            .load::<Event>(conn)
+    }
+
+    /// Checks whether a user is told about an event
+    /// A todo moved to another organization is seen leaving by the members of the former one
+    /// # Arguments
+    /// * `user` - Id of the user
+    /// * `organizations` - Ids of the organizations the user is a member of
+    /// # Returns
+    /// * `bool` - Whether the user owns the todo or is a member of its organization, before or after the event
+    pub fn is_visible_to(&self, user: i32, organizations: &[i32]) -> bool {
+        let former_organization = self
+            .changes
+            .get("organizationId")
+            .and_then(|change| change.get("old"))
+            .and_then(Value::as_i64);
+
+        self.owner_id == user
+            || self
+                .organization_id
+                .is_some_and(|organization| organizations.contains(&organization))
+            || former_organization
+                .is_some_and(|organization| organizations.contains(&(organization as i32)))
+    }
+
+    /// Revert a todo to an earlier version function
+    /// The title, description and completion state of that version are restored, the revert is recorded as a new version
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `user` - Id of the user reverting the todo
+    /// * `data` - RevertDTO struct containing the version to restore
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn revert(
+        todo: i32,
+        user: i32,
+        data: RevertDTO,
+        conn: &mut PgConnection,
+    ) -> Result<String, String> {
+        Todo::find_writable(todo, user, conn)?;
+
+        let target = match data.version {
+            Some(target) => target,
+            None => return Err("Version is required".to_string()),
+        };
+
+        let result = todo_events
+            .filter(todo_id.eq(todo))
+            .filter(version.le(target))
+            .order(version.asc())
+            .load::<Event>(conn);
+
+        let events = match result {
+            Ok(events) => events,
+            Err(_) => return Err("Failed to revert todo".to_string()),
+        };
+
+        if events.last().map(|event| event.version) != Some(target) {
+            return Err("Version not found".to_string());
+        }
+
+        let fields = Event::fields_at(&events);
+        let field = |name: &str| fields.get(name).cloned().unwrap_or(Value::Null);
+
+        let (title, description, completed) = match (
+            serde_json::from_value::<String>(field("title")),
+            serde_json::from_value::<String>(field("description")),
+            serde_json::from_value::<bool>(field("completed")),
+        ) {
+            (Ok(title), Ok(description), Ok(completed)) => (title, description, completed),
+            _ => return Err("Version cannot be restored".to_string()),
+        };
+
+        let data = TodoDTO {
+            user_id: None,
+            title: Some(title),
+            description: Some(description),
+            completed: Some(completed),
+            organization_id: None,
+            list_id: None,
+            due_at: None,
+            completed_at: None,
+            priority: None,
+            recurrence_rule: None,
+            recurrence_tz: None,
+            series_id: None,
+            occurrence: None,
+            parent_id: None,
+            position: None,
+            status_id: None,
+        };
+
+        // Blockers are not checked, the todo was already in that state
+        let options = UpdateOptions {
+            cascade: false,
+            force: true,
+            versions: None,
+        };
+
+        Todo::update_todo(todo, user, data, options, conn)?;
+
+        Ok(format!("Successfully reverted todo to version {}", target))
+    }
+
+    /// Undo the last change of a user function
+    /// Only a change made in the last few minutes can be undone, and only if the todos it touched did not change since
+    /// A deleted todo is restored with its fields, its tags, comments and attachments are not restored
+    /// Undoing is recorded like any other change, so undoing twice redoes the change
+    /// # Arguments
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn undo(user: i32, conn: &mut PgConnection) -> Result<String, String> {
+        let since = Utc::now().naive_utc() - Duration::seconds(UNDO_WINDOW);
+
+        let result = todo_events
+            .filter(actor_id.eq(user))
+            .order(id.desc())
+            .first::<Event>(conn)
+            .optional();
+
+        let last = match result {
+            Ok(Some(last)) if last.created_at >= since => last,
+            Ok(_) => return Err("Nothing to undo".to_string()),
+            Err(_) => return Err("Failed to undo".to_string()),
+        };
+
+        let result = todo_events
+            .filter(mutation_id.eq(last.mutation_id))
+            .filter(actor_id.eq(user))
+            .order(id.desc())
+            .load::<Event>(conn);
+
+        let events = match result {
+            Ok(events) => events,
+            Err(_) => return Err("Failed to undo".to_string()),
+        };
+
+        for event in &events {
+            let role =
+                Membership::resource_role(event.owner_id, event.organization_id, user, conn)?;
+
+            if !role.is_some_and(|role| role.can_write()) {
+                return Err("Insufficient permissions".to_string());
+            }
+        }
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            // Latest first, so e.g. a todo is restored before its subtasks
+            for event in &events {
+                let last_version = todo_events
+                    .filter(todo_id.eq(event.todo_id))
+                    .select(diesel::dsl::max(version))
+                    .first::<Option<i32>>(conn)?;
+
+                if last_version != Some(event.version) {
+                    return Ok(None);
+                }
+
+                Event::reverse(event, user, conn)?;
+            }
+
+            Ok(Some(()))
+        });
+
+        match result {
+            Ok(Some(_)) => Ok("Successfully undid last change".to_string()),
+            Ok(None) => {
+                Err("Todo was changed since, the change can no longer be undone".to_string())
+            }
+            Err(_) => Err("Failed to undo".to_string()),
+        }
+    }
+
+    /// Internal function to reverse the change recorded by an event
+    /// The removal of the files attached to a todo the reversal deleted is queued with it
+    fn reverse(event: &Event, user: i32, conn: &mut PgConnection) -> QueryResult<()> {
+        let changed_fields = match &event.changes {
+            Value::Object(changed_fields) => changed_fields.clone(),
+            _ => Map::new(),
+        };
+        let old_values = changed_fields
+            .into_iter()
+            .map(|(key, change)| (key, change.get("old").cloned().unwrap_or(Value::Null)));
+
+        match event.action {
+            EventAction::Create => {
+                let keys = Attachment::storage_keys(&[event.todo_id], conn)?;
+                Attachment::queue_purge(&keys, conn)?;
+
+                let deleted =
+                    diesel::delete(todos::table.find(event.todo_id)).get_result::<Todo>(conn)?;
+
+                Event::record(user, Some(&deleted), None, conn)?;
+
+                Ok(())
+            }
+            EventAction::Update => {
+                let current = todos::table
+                    .find(event.todo_id)
+                    .for_update()
+                    .first::<Todo>(conn)?;
+
+                let mut fields = Event::fields(Some(&current));
+                fields.extend(old_values);
+
+                let snapshot = Event::snapshot(fields)?;
+                let restored = diesel::update(todos::table.find(event.todo_id))
+                    .set(&snapshot)
+                    .get_result::<Todo>(conn)?;
+
+                Event::record(user, Some(&current), Some(&restored), conn)?;
+
+                Ok(())
+            }
+            EventAction::Delete => {
+                let mut snapshot = Event::snapshot(old_values.collect())?;
+                Event::detach_missing(&mut snapshot, conn)?;
+
+                let restored = diesel::insert_into(todos::table)
+                    .values(&snapshot)
+                    .get_result::<Todo>(conn)?;
+
+                Event::record(user, None, Some(&restored), conn)?;
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Internal function to replay the events of a todo, returning its fields after the last event
+    fn fields_at(events: &[Event]) -> Map<String, Value> {
+        let mut fields = Map::new();
+
+        for event in events {
+            if event.action == EventAction::Delete {
+                fields.clear();
+                continue;
+            }
+
+            if let Value::Object(changed_fields) = &event.changes {
+                for (key, change) in changed_fields {
+                    fields.insert(
+                        key.clone(),
+                        change.get("new").cloned().unwrap_or(Value::Null),
+                    );
+                }
+            }
+        }
+
+        fields
+    }
+
+    /// Internal function to read a snapshot from the serialized fields of a todo
+    fn snapshot(fields: Map<String, Value>) -> QueryResult<TodoSnapshot> {
+        match serde_json::from_value::<TodoSnapshot>(Value::Object(fields)) {
+            Ok(snapshot) => Ok(snapshot),
+            Err(error) => Err(diesel::result::Error::DeserializationError(Box::new(error))),
+        }
+    }
+
+    /// Internal function to clear the references of a restored todo to rows deleted since
+    fn detach_missing(snapshot: &mut TodoSnapshot, conn: &mut PgConnection) -> QueryResult<()> {
+        if let Some(parent) = snapshot.parent_id {
+            let found = diesel::select(diesel::dsl::exists(todos::table.find(parent)))
+                .get_result::<bool>(conn)?;
+
+            if !found {
+                snapshot.parent_id = None;
+            }
+        }
+
+        if let Some(series) = snapshot.series_id {
+            let found = diesel::select(diesel::dsl::exists(todos::table.find(series)))
+                .get_result::<bool>(conn)?;
+
+            // The occurrence may have been created again since, completing the previous one
+            let taken = diesel::select(diesel::dsl::exists(
+                todos::table
+                    .filter(todos::series_id.eq(series))
+                    .filter(todos::occurrence.eq(snapshot.occurrence)),
+            ))
+            .get_result::<bool>(conn)?;
+
+            if !found || taken {
+                snapshot.series_id = None;
+            }
+        }
+
+        if let Some(list) = snapshot.list_id {
+            let found = diesel::select(diesel::dsl::exists(lists::table.find(list)))
+                .get_result::<bool>(conn)?;
+
+            if !found {
+                snapshot.list_id = None;
+                snapshot.status_id = None;
+            }
+        }
+
+        if let Some(status) = snapshot.status_id {
+            let found = diesel::select(diesel::dsl::exists(statuses::table.find(status)))
+                .get_result::<bool>(conn)?;
+
+            if !found {
+                snapshot.status_id = None;
+            }
+        }
+
+        if let Some(assignee) = snapshot.assignee_id {
+            let found = diesel::select(diesel::dsl::exists(users::table.find(assignee)))
+                .get_result::<bool>(conn)?;
+
+            if !found {
+                snapshot.assignee_id = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Internal function to compute the changed fields between two versions of a todo
+    fn diff(before: Option<&Todo>, after: Option<&Todo>) -> Map<String, Value> {
+        let before = Event::fields(before);
+        let after = Event::fields(after);
+        let mut diff = Map::new();
+
+        for key in before.keys().chain(after.keys()) {
+            if IGNORED_FIELDS.contains(&key.as_str()) || diff.contains_key(key) {
+                continue;
+            }
+
+            let old = before.get(key).cloned().unwrap_or(Value::Null);
+            let new = after.get(key).cloned().unwrap_or(Value::Null);
+
+            if old != new {
+                diff.insert(key.clone(), json!({ "old": old, "new": new }));
+            }
+        }
+
+        diff
+    }
+
+    /// Internal function to get the fields of a todo as they are serialized
+    fn fields(todo: Option<&Todo>) -> Map<String, Value> {
+        match todo.map(serde_json::to_value) {
+            Some(Ok(Value::Object(fields))) => fields,
+            _ => Map::new(),
+        }
+    }
+}
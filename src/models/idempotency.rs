@@ -0,0 +1,209 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::{prelude::*, Identifiable, Insertable, PgConnection, Queryable, RunQueryDsl};
+use serde_json::Value;
+
+use crate::schema::idempotency_keys::{self, dsl::*};
+
+/// IdempotencyRecord struct representing a row in the idempotency_keys table in the database
+#[derive(Identifiable, Queryable, Debug)]
+#[table_name = "idempotency_keys"]
+pub struct IdempotencyRecord {
+    /// Unique id of the record
+    pub id: i32,
+    /// Id of the user who sent the request, empty for requests sent without a token
+    pub user_id: Option<i32>,
+    /// Key sent by the client in the Idempotency-Key header
+    pub idempotency_key: String,
+    /// Hash of the method, path and body of the request
+    pub fingerprint: String,
+    /// Status code of the response, empty while the request is being handled
+    pub status: Option<i16>,
+    /// Body of the response
+    pub response: Option<Value>,
+    /// Time the request was first received in UTC
+    pub created_at: NaiveDateTime,
+    /// Time the response stops being replayed in UTC
+    pub expires_at: NaiveDateTime,
+    /// Headers of the response sent again when it is replayed, by name
+    pub headers: Option<Value>,
+}
+
+/// StoredResponse struct representing the response of a request replayed when it is retried
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredResponse {
+    /// Status code of the response
+    pub code: u16,
+    /// Headers of the response sent again along with the body, e.g. `Location`
+    pub headers: Vec<(String, String)>,
+    /// Body of the response
+    pub body: Value,
+}
+
+/// NewIdempotencyRecord struct representing the data to be sent to the database to claim a key
+#[derive(Insertable, Debug)]
+#[table_name = "idempotency_keys"]
+struct NewIdempotencyRecord<'a> {
+    user_id: Option<i32>,
+    idempotency_key: &'a str,
+    fingerprint: &'a str,
+    expires_at: NaiveDateTime,
+}
+
+/// What to do with a request sent with an idempotency key
+#[derive(Debug)]
+pub enum Claim {
+    /// The key is new, the request must be handled and its response stored
+    New,
+    /// The request was handled already, its response is replayed
+    Replay(StoredResponse),
+    /// The key was used for a request with a different method, path or body
+    Mismatch,
+    /// The first request with the key is still being handled
+    InProgress,
+}
+
+/// Implementation of the IdempotencyRecord struct
+impl IdempotencyRecord {
+    /// Claim an idempotency key for a request function
+    /// Expired records of the key are removed first, so the key can be used again
+    /// # Arguments
+    /// * `user` - Id of the user sending the request, None for requests sent without a token
+    /// * `key` - The idempotency key
+    /// * `request_fingerprint` - Hash of the method, path and body of the request
+    /// * `ttl` - Number of seconds the response is replayed for
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Claim, String>` - Result containing what to do with the request or an error message
+    pub fn claim(
+        user: Option<i32>,
+        key: &str,
+        request_fingerprint: &str,
+        ttl: i64,
+        conn: &mut PgConnection,
+    ) -> Result<Claim, String> {
+        let now = Utc::now().naive_utc();
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::delete(
+                idempotency_keys
+                    .filter(user_id.is_not_distinct_from(user))
+                    .filter(idempotency_key.eq(key))
+                    .filter(expires_at.le(now)),
+            )
+            .execute(conn)?;
+
+            let inserted = diesel::insert_into(idempotency_keys)
+                .values(&NewIdempotencyRecord {
+                    user_id: user,
+                    idempotency_key: key,
+                    fingerprint: request_fingerprint,
+                    expires_at: now + Duration::seconds(ttl),
+                })
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+
+            if inserted > 0 {
+                return Ok(Claim::New);
+            }
+
+            let record = idempotency_keys
+                .filter(user_id.is_not_distinct_from(user))
+                .filter(idempotency_key.eq(key))
+                .first::<IdempotencyRecord>(conn)?;
+
+            if record.fingerprint != request_fingerprint {
+                return Ok(Claim::Mismatch);
+            }
+
+            let stored_headers = match record.headers {
+                Some(Value::Object(stored_headers)) => stored_headers
+                    .into_iter()
+                    .filter_map(|(name, value)| {
+                        value.as_str().map(|value| (name, value.to_string()))
+                    })
+                    .collect(),
+                _ => vec![],
+            };
+
+            match (record.status, record.response) {
+                (Some(code), Some(body)) => Ok(Claim::Replay(StoredResponse {
+                    code: code as u16,
+                    headers: stored_headers,
+                    body,
+                })),
+                _ => Ok(Claim::InProgress),
+            }
+        });
+
+        match result {
+            Ok(claim) => Ok(claim),
+            Err(_) => Err("Failed to check idempotency key".to_string()),
+        }
+    }
+
+    /// Store the response of a request claimed with an idempotency key function
+    /// # Arguments
+    /// * `user` - Id of the user who sent the request, None for requests sent without a token
+    /// * `key` - The idempotency key
+    /// * `stored` - The response
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<(), String>` - Result containing nothing or an error message
+    pub fn store(
+        user: Option<i32>,
+        key: &str,
+        stored: StoredResponse,
+        conn: &mut PgConnection,
+    ) -> Result<(), String> {
+        let stored_headers: serde_json::Map<String, Value> = stored
+            .headers
+            .into_iter()
+            .map(|(name, value)| (name, Value::String(value)))
+            .collect();
+
+        let result = diesel::update(
+            idempotency_keys
+                .filter(user_id.is_not_distinct_from(user))
+                .filter(idempotency_key.eq(key)),
+        )
+        .set((
+            status.eq(Some(stored.code as i16)),
+            response.eq(Some(stored.body)),
+            headers.eq(Some(Value::Object(stored_headers))),
+        ))
+        .execute(conn);
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Failed to store response".to_string()),
+        }
+    }
+
+    /// Release an idempotency key whose request failed or whose response could not be stored function
+    /// The request is handled again when it is retried, keys whose response was stored are kept
+    /// # Arguments
+    /// * `user` - Id of the user who sent the request, None for requests sent without a token
+    /// * `key` - The idempotency key
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<usize>` - Result containing the number of released keys
+    pub fn release(user: Option<i32>, key: &str, conn: &mut PgConnection) -> QueryResult<usize> {
+        diesel::delete(
+            idempotency_keys
+                .filter(user_id.is_not_distinct_from(user))
+                .filter(idempotency_key.eq(key))
+                .filter(status.is_null()),
+        )
+        .execute(conn)
+    }
+
+    /// Delete the expired records function
+    /// Expired keys are otherwise only removed when they are used again
+    /// # Arguments
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<usize>` - Result containing the number of deleted records
+    pub fn purge_expired(conn: &mut PgConnection) -> QueryResult<usize> {
+        diesel::delete(idempotency_keys.filter(expires_at.le(Utc::now().naive_utc()))).execute(conn)
+    }
+}
@@ -0,0 +1,591 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
+use diesel::upsert::excluded;
+use diesel::{prelude::*, Identifiable, Insertable, PgConnection, Queryable, RunQueryDsl};
+use rocket::FromFormField;
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::schema::{job_schedules, jobs};
+use crate::utils::cron::CronSchedule;
+
+/// Number of attempts made to run a job before it is dead, by default
+pub static DEFAULT_MAX_ATTEMPTS: i32 = 5;
+
+/// Number of seconds a worker has to run a claimed job before another worker can claim it
+static LOCK: i64 = 5 * 60;
+
+/// Number of seconds before the first retry, doubled on every attempt
+static RETRY_DELAY: i64 = 30;
+
+/// Maximum number of seconds between two attempts
+static MAX_RETRY_DELAY: i64 = 60 * 60;
+
+/// Number of jobs returned at once
+static PAGE_SIZE: i64 = 50;
+
+/// Maximum number of characters of an error kept on a job
+static MAX_ERROR_LENGTH: usize = 4096;
+
+/// State of a job
+#[derive(
+    FromFormField,
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    AsExpression,
+    FromSqlRow,
+    JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[diesel(sql_type = Text)]
+pub enum JobStatus {
+    /// The job is waiting to run, or for its next attempt
+    Queued,
+    /// A worker is running the job
+    Running,
+    /// The job ran successfully
+    Succeeded,
+    /// Every attempt failed, the job stays dead until it is retried
+    Dead,
+}
+
+impl JobStatus {
+    /// Returns the value stored in the database for the status
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Succeeded => "succeeded",
+            JobStatus::Dead => "dead",
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for JobStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        <str as ToSql<Text, Pg>>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for JobStatus {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match <String as FromSql<Text, Pg>>::from_sql(bytes)?.as_str() {
+            "queued" => Ok(JobStatus::Queued),
+            "running" => Ok(JobStatus::Running),
+            "succeeded" => Ok(JobStatus::Succeeded),
+            "dead" => Ok(JobStatus::Dead),
+            other => Err(format!("Unknown job status {}", other).into()),
+        }
+    }
+}
+
+/// Job struct representing a row in the jobs table in the database
+/// A job is a piece of work run in the background by a worker, e.g. sending a webhook delivery
+#[derive(Identifiable, Queryable, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    /// Unique id of the job
+    /// This is the primary key of the jobs table
+    /// This is auto generated by the database
+    pub id: i32,
+    /// Kind of the job, telling which handler runs it, e.g. `webhook.deliver`
+    pub kind: String,
+    /// Arguments of the job, read by its handler
+    pub payload: Value,
+    /// State of the job
+    pub status: JobStatus,
+    /// Number of attempts made
+    pub attempts: i32,
+    /// Number of attempts made before the job is dead
+    pub max_attempts: i32,
+    /// Key preventing the same job from being queued twice while it waits or runs
+    pub unique_key: Option<String>,
+    /// Time the job runs at in UTC, or its next attempt
+    pub run_at: NaiveDateTime,
+    /// Time the lock of the worker running the job expires in UTC
+    pub locked_until: Option<NaiveDateTime>,
+    /// Why the last attempt failed
+    pub last_error: Option<String>,
+    /// Time the job was queued in UTC
+    pub created_at: NaiveDateTime,
+    /// Time the job succeeded or died in UTC
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+/// NewJob struct representing a job to queue
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "jobs"]
+pub struct NewJob {
+    /// Kind of the job, e.g. `webhook.deliver`
+    pub kind: String,
+    /// Arguments of the job
+    pub payload: Value,
+    /// Number of attempts made before the job is dead
+    pub max_attempts: i32,
+    /// Key preventing the same job from being queued twice while it waits or runs
+    pub unique_key: Option<String>,
+    /// Time the job runs at in UTC
+    pub run_at: NaiveDateTime,
+}
+
+impl NewJob {
+    /// Creates a job running as soon as possible
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Kind of the job
+    /// * `payload` - Arguments of the job
+    ///
+    /// # Returns
+    ///
+    /// * The job, its other fields can be changed before it is queued
+    pub fn new(kind: &str, payload: Value) -> NewJob {
+        NewJob {
+            kind: kind.to_string(),
+            payload,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            unique_key: None,
+            run_at: Utc::now().naive_utc(),
+        }
+    }
+}
+
+/// JobSchedule struct representing a row in the job_schedules table in the database
+/// A schedule queues a job every time its cron expression matches
+#[derive(Identifiable, Queryable, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[primary_key(name)]
+pub struct JobSchedule {
+    /// Unique name of the schedule
+    /// This is the primary key of the job_schedules table
+    pub name: String,
+    /// Kind of the jobs queued
+    pub kind: String,
+    /// Arguments of the jobs queued
+    pub payload: Value,
+    /// Cron expression of the schedule, evaluated in UTC, e.g. `0 3 * * *`
+    pub cron: String,
+    /// Time the next job is queued at in UTC
+    pub next_run_at: NaiveDateTime,
+    /// Time the last job was queued at in UTC
+    pub last_run_at: Option<NaiveDateTime>,
+}
+
+/// Internal struct used to register a schedule
+#[derive(Insertable, Debug)]
+#[table_name = "job_schedules"]
+struct NewJobSchedule {
+    name: String,
+    kind: String,
+    payload: Value,
+    cron: String,
+    next_run_at: NaiveDateTime,
+}
+
+/// Implementation of the Job struct
+impl Job {
+    /// Queue a job function
+    /// Can be called in the transaction of a change, so the change and its job are saved together
+    /// A job whose unique key is already used by a waiting or running job is not queued
+    /// # Arguments
+    /// * `job` - NewJob struct containing the job
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<usize>` - Result containing the number of jobs queued
+    pub fn enqueue(job: &NewJob, conn: &mut PgConnection) -> QueryResult<usize> {
+        diesel::insert_into(jobs::table)
+            .values(job)
+            .on_conflict_do_nothing()
+            .execute(conn)
+    }
+
+    /// Queue several jobs function
+    /// # Arguments
+    /// * `queued` - The jobs
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<usize>` - Result containing the number of jobs queued
+    pub fn enqueue_all(queued: &[NewJob], conn: &mut PgConnection) -> QueryResult<usize> {
+        if queued.is_empty() {
+            return Ok(0);
+        }
+
+        diesel::insert_into(jobs::table)
+            .values(queued)
+            .on_conflict_do_nothing()
+            .execute(conn)
+    }
+
+    /// Claims the next job due to run function
+    /// The job is locked for the worker until its lock expires, so a job whose worker stops while
+    /// running it is picked up again. Every claim counts as an attempt
+    /// # Arguments
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Option<Job>, String>` - Result containing the claimed job, None if no job is due, or an error message
+    pub fn claim(conn: &mut PgConnection) -> Result<Option<Job>, String> {
+        let now = Utc::now().naive_utc();
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let due = jobs::table
+                .filter(
+                    jobs::status
+                        .eq(JobStatus::Queued)
+                        .and(jobs::run_at.le(now))
+                        .or(jobs::status
+                            .eq(JobStatus::Running)
+                            .and(jobs::locked_until.le(now))),
+                )
+                .order(jobs::run_at.asc())
+                .select(jobs::id)
+                .for_update()
+                .skip_locked()
+                .first::<i32>(conn)
+                .optional()?;
+
+            let due = match due {
+                Some(due) => due,
+                None => return Ok(None),
+            };
+
+            diesel::update(jobs::table.find(due))
+                .set((
+                    jobs::status.eq(JobStatus::Running),
+                    jobs::attempts.eq(jobs::attempts + 1),
+                    jobs::locked_until.eq(Some(now + Duration::seconds(LOCK))),
+                ))
+                .get_result::<Job>(conn)
+                .map(Some)
+        });
+
+        match result {
+            Ok(claimed) => Ok(claimed),
+            Err(_) => Err("Failed to claim job".to_string()),
+        }
+    }
+
+    /// Records that a claimed job succeeded function
+    /// Nothing is recorded if the lock of the worker expired and another worker claimed the job
+    /// # Arguments
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<(), String>` - Result containing nothing or an error message
+    pub fn succeed(&self, conn: &mut PgConnection) -> Result<(), String> {
+        let result = diesel::update(
+            jobs::table
+                .find(self.id)
+                .filter(jobs::status.eq(JobStatus::Running))
+                .filter(jobs::attempts.eq(self.attempts)),
+        )
+        .set((
+            jobs::status.eq(JobStatus::Succeeded),
+            jobs::locked_until.eq(None::<NaiveDateTime>),
+            jobs::finished_at.eq(Some(Utc::now().naive_utc())),
+        ))
+        .execute(conn);
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Failed to record job".to_string()),
+        }
+    }
+
+    /// Records that a claimed job failed function
+    /// The job is retried later, waiting twice as long after each attempt, and dies once every attempt is made
+    /// # Arguments
+    /// * `error` - Why the attempt failed
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<(), String>` - Result containing nothing or an error message
+    pub fn fail(&self, error: &str, conn: &mut PgConnection) -> Result<(), String> {
+        let now = Utc::now().naive_utc();
+        let error = error.chars().take(MAX_ERROR_LENGTH).collect::<String>();
+
+        let result = if self.attempts >= self.max_attempts {
+            diesel::update(
+                jobs::table
+                    .find(self.id)
+                    .filter(jobs::status.eq(JobStatus::Running))
+                    .filter(jobs::attempts.eq(self.attempts)),
+            )
+            .set((
+                jobs::status.eq(JobStatus::Dead),
+                jobs::locked_until.eq(None::<NaiveDateTime>),
+                jobs::last_error.eq(Some(error)),
+                jobs::finished_at.eq(Some(now)),
+            ))
+            .execute(conn)
+        } else {
+            // Waits 30 seconds, 1 minute, 2 minutes... up to an hour
+            let delay = RETRY_DELAY
+                .saturating_mul(1 << (self.attempts - 1).clamp(0, 20))
+                .min(MAX_RETRY_DELAY);
+
+            diesel::update(
+                jobs::table
+                    .find(self.id)
+                    .filter(jobs::status.eq(JobStatus::Running))
+                    .filter(jobs::attempts.eq(self.attempts)),
+            )
+            .set((
+                jobs::status.eq(JobStatus::Queued),
+                jobs::run_at.eq(now + Duration::seconds(delay)),
+                jobs::locked_until.eq(None::<NaiveDateTime>),
+                jobs::last_error.eq(Some(error)),
+            ))
+            .execute(conn)
+        };
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Failed to record job".to_string()),
+        }
+    }
+
+    /// Gets jobs function
+    /// # Arguments
+    /// * `status` - Only jobs in this state are returned
+    /// * `kind` - Only jobs of this kind are returned
+    /// * `before` - Only jobs older than this job id are returned
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<Job>, String>` - Result containing the jobs, newest first, or an error message
+    pub fn get_jobs(
+        status: Option<JobStatus>,
+        kind: Option<String>,
+        before: Option<i32>,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<Job>, String> {
+        let mut query = jobs::table.into_boxed();
+
+        if let Some(status) = status {
+            query = query.filter(jobs::status.eq(status));
+        }
+
+        if let Some(kind) = kind {
+            query = query.filter(jobs::kind.eq(kind));
+        }
+
+        if let Some(before) = before {
+            query = query.filter(jobs::id.lt(before));
+        }
+
+        let result = query
+            .order(jobs::id.desc())
+            .limit(PAGE_SIZE)
+            .load::<Job>(conn);
+
+        match result {
+            Ok(data) => Ok(data),
+            Err(_) => Err("Failed to get jobs".to_string()),
+        }
+    }
+
+    /// Gets a job function
+    /// # Arguments
+    /// * `job` - Id of the job
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Job, String>` - Result containing the job or an error message
+    pub fn get_job(job: i32, conn: &mut PgConnection) -> Result<Job, String> {
+        let result = jobs::table.find(job).first::<Job>(conn);
+
+        match result {
+            Ok(found) => Ok(found),
+            Err(_) => Err("Job not found".to_string()),
+        }
+    }
+
+    /// Retry a dead job function
+    /// The job is queued to run right away, with all its attempts
+    /// # Arguments
+    /// * `job` - Id of the job
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Job, String>` - Result containing the queued job or an error message
+    pub fn retry(job: i32, conn: &mut PgConnection) -> Result<Job, String> {
+        let found = Job::get_job(job, conn)?;
+
+        if found.status != JobStatus::Dead {
+            return Err("Only dead jobs can be retried".to_string());
+        }
+
+        let result = diesel::update(
+            jobs::table
+                .find(job)
+                .filter(jobs::status.eq(JobStatus::Dead)),
+        )
+        .set((
+            jobs::status.eq(JobStatus::Queued),
+            jobs::attempts.eq(0),
+            jobs::run_at.eq(Utc::now().naive_utc()),
+            jobs::finished_at.eq(None::<NaiveDateTime>),
+        ))
+        .get_result(conn);
+
+        match result {
+            Ok(queued) => Ok(queued),
+            // A job with the same unique key is waiting or running already
+            Err(diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::UniqueViolation,
+                _,
+            )) => Err("The same job is queued already".to_string()),
+            Err(_) => Err("Failed to retry job".to_string()),
+        }
+    }
+
+    /// Delete finished jobs function
+    /// Dead jobs are kept so they can be inspected and retried
+    /// # Arguments
+    /// * `age` - Number of seconds a succeeded job is kept for
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<usize>` - Result containing the number of deleted jobs
+    pub fn purge(age: i64, conn: &mut PgConnection) -> QueryResult<usize> {
+        let threshold = Utc::now().naive_utc() - Duration::seconds(age);
+
+        diesel::delete(
+            jobs::table
+                .filter(jobs::status.eq(JobStatus::Succeeded))
+                .filter(jobs::finished_at.le(threshold)),
+        )
+        .execute(conn)
+    }
+}
+
+/// Implementation of the JobSchedule struct
+impl JobSchedule {
+    /// Register a schedule function
+    /// A schedule registered already keeps its next run, its kind, payload and cron expression are updated
+    /// # Arguments
+    /// * `name` - Unique name of the schedule
+    /// * `kind` - Kind of the jobs queued
+    /// * `cron` - Cron expression of the schedule, evaluated in UTC
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<JobSchedule, String>` - Result containing the schedule or an error message
+    pub fn register(
+        name: &str,
+        kind: &str,
+        cron: &str,
+        conn: &mut PgConnection,
+    ) -> Result<JobSchedule, String> {
+        let schedule = cron.parse::<CronSchedule>()?;
+
+        let next_run_at = match schedule.next_after(Utc::now().naive_utc()) {
+            Some(next_run_at) => next_run_at,
+            None => return Err(format!("Cron expression {} never matches", cron)),
+        };
+
+        let result = diesel::insert_into(job_schedules::table)
+            .values(&NewJobSchedule {
+                name: name.to_string(),
+                kind: kind.to_string(),
+                payload: json!({}),
+                cron: cron.to_string(),
+                next_run_at,
+            })
+            .on_conflict(job_schedules::name)
+            .do_update()
+            .set((
+                job_schedules::kind.eq(excluded(job_schedules::kind)),
+                job_schedules::payload.eq(excluded(job_schedules::payload)),
+                job_schedules::cron.eq(excluded(job_schedules::cron)),
+            ))
+            .get_result(conn);
+
+        match result {
+            Ok(registered) => Ok(registered),
+            Err(_) => Err("Failed to register schedule".to_string()),
+        }
+    }
+
+    /// Gets the schedules function
+    /// # Arguments
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<JobSchedule>, String>` - Result containing the schedules or an error message
+    pub fn get_schedules(conn: &mut PgConnection) -> Result<Vec<JobSchedule>, String> {
+        let result = job_schedules::table
+            .order(job_schedules::name.asc())
+            .load(conn);
+
+        match result {
+            Ok(data) => Ok(data),
+            Err(_) => Err("Failed to get schedules".to_string()),
+        }
+    }
+
+    /// Queues the jobs of the due schedules function
+    /// Schedules are locked while their job is queued, so every worker can call this without queuing a job twice.
+    /// Runs missed while no worker was up are not caught up, the schedule moves on to its next run
+    /// # Arguments
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<usize, String>` - Result containing the number of jobs queued or an error message
+    pub fn enqueue_due(conn: &mut PgConnection) -> Result<usize, String> {
+        let now = Utc::now().naive_utc();
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let due = job_schedules::table
+                .filter(job_schedules::next_run_at.le(now))
+                .for_update()
+                .skip_locked()
+                .load::<JobSchedule>(conn)?;
+
+            let mut queued = 0;
+
+            for schedule in due {
+                let next_run_at = match schedule.cron.parse::<CronSchedule>() {
+                    Ok(cron) => cron.next_after(now),
+                    Err(_) => None,
+                };
+
+                let next_run_at = match next_run_at {
+                    Some(next_run_at) => next_run_at,
+                    None => {
+                        log::warn!(
+                            "Schedule {} has an invalid cron expression {}",
+                            schedule.name,
+                            schedule.cron
+                        );
+                        continue;
+                    }
+                };
+
+                queued += Job::enqueue(
+                    &NewJob {
+                        unique_key: Some(format!("schedule:{}", schedule.name)),
+                        ..NewJob::new(&schedule.kind, schedule.payload)
+                    },
+                    conn,
+                )?;
+
+                diesel::update(job_schedules::table.find(&schedule.name))
+                    .set((
+                        job_schedules::next_run_at.eq(next_run_at),
+                        job_schedules::last_run_at.eq(Some(now)),
+                    ))
+                    .execute(conn)?;
+            }
+
+            Ok(queued)
+        });
+
+        match result {
+            Ok(queued) => Ok(queued),
+            Err(_) => Err("Failed to queue scheduled jobs".to_string()),
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use diesel::{
+    prelude::*, AsChangeset, Identifiable, Insertable, PgConnection, Queryable, RunQueryDsl,
+};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::models::organization::{Membership, Role};
+use crate::models::todos::{Todo, TodoWithTags};
+use crate::schema::lists::{self, dsl::*};
+use crate::schema::{memberships, todos};
+
+/// List struct representing a row in the lists table in the database
+/// A list groups todos, like a project
+#[derive(Identifiable, Queryable, Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct List {
+    /// Unique id of the list
+    /// This is the primary key of the lists table
+    /// This is auto generated by the database
+    pub id: i32,
+    /// Id of the user who created the list
+    pub user_id: i32,
+    /// Id of the organization the list is shared with
+    /// If empty, the list is only visible to its creator
+    pub organization_id: Option<i32>,
+    /// Name of the list
+    pub name: String,
+    /// Color of the list as a hex string, e.g. `#ff0000`
+    pub color: String,
+    /// Position of the list, lists are sorted by ascending position
+    pub position: i32,
+    /// Whether the list is archived or not
+    pub archived: bool,
+}
+
+/// ListDTO struct representing the data to be sent to the database to create or update a list
+#[derive(Insertable, AsChangeset, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "lists"]
+pub struct ListDTO {
+    /// Id of the user who created the list
+    /// The user_id is gotten from the token
+    pub user_id: Option<i32>,
+    /// Id of the organization the list is shared with
+    /// Organization can only be set when creating a list
+    pub organization_id: Option<i32>,
+    /// Name of the list
+    /// Name is required for creating a new list
+    pub name: Option<String>,
+    /// Color of the list as a hex string, e.g. `#ff0000`
+    pub color: Option<String>,
+    /// Position of the list
+    /// If not provided when creating a list, the list is placed last
+    pub position: Option<i32>,
+    /// Whether the list is archived or not
+    pub archived: Option<bool>,
+}
+
+/// Implementation of the List struct
+impl List {
+    /// Create a new list function
+    /// # Arguments
+    /// * `data` - ListDTO struct containing the data to be sent to the database
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<List, String>` - Result containing the created list or an error message
+    pub fn new_list(mut data: ListDTO, conn: &mut PgConnection) -> Result<List, String> {
+        List::validate_input(&data)?;
+
+        let owner = match data.user_id {
+            Some(owner) => owner,
+            None => return Err("User is required".to_string()),
+        };
+
+        match data.name {
+            Some(ref list_name) if !list_name.trim().is_empty() => {}
+            _ => return Err("Name is required".to_string()),
+        }
+
+        if let Some(organization) = data.organization_id {
+            Membership::require_role(organization, owner, Role::Member, conn)?;
+        }
+
+        if data.position.is_none() {
+            let last_position = lists
+                .filter(user_id.eq(owner))
+                .select(diesel::dsl::max(position))
+                .first::<Option<i32>>(conn);
+
+            data.position = match last_position {
+                Ok(last_position) => Some(last_position.map_or(0, |last| last + 1)),
+                Err(_) => return Err("Failed to create list".to_string()),
+            };
+        }
+
+        let result = diesel::insert_into(lists).values(&data).get_result(conn);
+
+        match result {
+            Ok(list) => Ok(list),
+            Err(_) => Err("Failed to create list".to_string()),
+        }
+    }
+
+    /// Gets all lists the user can access function
+    /// This includes the lists shared with the organizations the user is a member of
+    /// # Arguments
+    /// * `user` - Id of the user
+    /// * `include_archived` - Whether archived lists are returned
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<List>, String>` - Result containing a vector of lists or an error message
+    pub fn get_lists(
+        user: i32,
+        include_archived: bool,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<List>, String> {
+        let user_organizations = memberships::table
+            .filter(memberships::user_id.eq(user))
+            .select(memberships::organization_id.nullable());
+
+        let mut query = lists
+            .filter(
+                user_id
+                    .eq(user)
+                    .or(organization_id.eq_any(user_organizations)),
+            )
+            .order((position.asc(), id.asc()))
+            .into_boxed();
+
+        if !include_archived {
+            query = query.filter(archived.eq(false));
+        }
+
+        let result = query.load(conn);
+
+        match result {
+            Ok(data) => Ok(data),
+            Err(_) => Err("Failed to get lists".to_string()),
+        }
+    }
+
+    /// Update a list function
+    /// # Arguments
+    /// * `list_id` - Id of the list to be updated
+    /// * `user` - Id of the user updating the list
+    /// * `data` - ListDTO struct containing the data to be sent to the database
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<List, String>` - Result containing the updated list or an error message
+    pub fn update_list(
+        list_id: i32,
+        user: i32,
+        data: ListDTO,
+        conn: &mut PgConnection,
+    ) -> Result<List, String> {
+        List::validate_input(&data)?;
+        List::find_writable(list_id, user, conn)?;
+
+        let result = diesel::update(lists.find(list_id))
+            .set(&data)
+            .get_result(conn);
+
+        match result {
+            Ok(list) => Ok(list),
+            Err(_) => Err("Failed to update list".to_string()),
+        }
+    }
+
+    /// Delete a list function
+    /// The todos of the list are kept and removed from the list
+    /// Only the creator of the list or an admin of its organization can delete it
+    /// # Arguments
+    /// * `list_id` - Id of the list to be deleted
+    /// * `user` - Id of the user deleting the list
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn delete_list(list_id: i32, user: i32, conn: &mut PgConnection) -> Result<String, String> {
+        let (_, role) = List::find_with_role(list_id, user, conn)?;
+
+        match role {
+            Some(role) if role >= Role::Admin => {}
+            Some(_) => return Err("Insufficient permissions".to_string()),
+            None => return Err("List not found".to_string()),
+        }
+
+        let result = diesel::delete(lists.find(list_id)).execute(conn);
+
+        match result {
+            Ok(_) => Ok("Successfully deleted list".to_string()),
+            Err(_) => Err("Failed to delete list".to_string()),
+        }
+    }
+
+    /// Gets all todos of a list function
+    /// # Arguments
+    /// * `list_id` - Id of the list
+    /// * `user` - Id of the user requesting the todos
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<TodoWithTags>, String>` - Result containing a vector of todos or an error message
+    pub fn get_list_todos(
+        list_id: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<TodoWithTags>, String> {
+        List::find_readable(list_id, user, conn)?;
+
+        let result = todos::table
+            .inner_join(lists)
+            .filter(id.eq(list_id))
+            .select(todos::all_columns)
+            .order((todos::position.asc(), todos::id.asc()))
+            .load::<Todo>(conn);
+
+        match result {
+            Ok(data) => Todo::with_tags(data, user, conn),
+            Err(_) => Err("Failed to get todos".to_string()),
+        }
+    }
+
+    /// Find a list the user is allowed to read function
+    /// # Arguments
+    /// * `list_id` - Id of the list
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<List, String>` - Result containing the list or an error message
+    pub fn find_readable(list_id: i32, user: i32, conn: &mut PgConnection) -> Result<List, String> {
+        let (list, role) = List::find_with_role(list_id, user, conn)?;
+
+        match role {
+            Some(_) => Ok(list),
+            None => Err("List not found".to_string()),
+        }
+    }
+
+    /// Find a list the user is allowed to update function
+    /// # Arguments
+    /// * `list_id` - Id of the list
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<List, String>` - Result containing the list or an error message
+    pub fn find_writable(list_id: i32, user: i32, conn: &mut PgConnection) -> Result<List, String> {
+        let (list, role) = List::find_with_role(list_id, user, conn)?;
+
+        match role {
+            Some(role) if role.can_write() => Ok(list),
+            Some(_) => Err("Insufficient permissions".to_string()),
+            None => Err("List not found".to_string()),
+        }
+    }
+
+    /// Internal function to find a list along with the role the user has on it
+    /// # Arguments
+    /// * `list_id` - Id of the list
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<(List, Option<Role>), String>` - Result containing the list and the role, if any, or an error message
+    fn find_with_role(
+        list_id: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<(List, Option<Role>), String> {
+        let list = match lists.find(list_id).first::<List>(conn) {
+            Ok(list) => list,
+            Err(_) => return Err("List not found".to_string()),
+        };
+
+        let role = Membership::resource_role(list.user_id, list.organization_id, user, conn)?;
+
+        Ok((list, role))
+    }
+
+    /// Internal function to validate the input for creating or updating a list
+    /// # Arguments
+    /// * `data` - ListDTO struct containing the data to be sent to the database
+    /// # Returns
+    /// * `Result<(), String>` - Result containing nothing or an error message
+    fn validate_input(data: &ListDTO) -> Result<(), String> {
+        if let Some(ref list_color) = data.color {
+            let is_hex = list_color.len() == 7
+                && list_color.starts_with('#')
+                && list_color[1..].chars().all(|c| c.is_ascii_hexdigit());
+
+            if !is_hex {
+                return Err("Color must be a hex string like #ff0000".to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,2 +1,19 @@
+pub mod attachment;
+pub mod bulk;
+pub mod collab;
+pub mod comment;
+pub mod dependency;
+pub mod event;
+pub mod idempotency;
+pub mod job;
+pub mod list;
+pub mod notification;
+pub mod organization;
+pub mod reminder;
+pub mod status;
+pub mod sync;
+pub mod tag;
 pub mod todos;
 pub mod user;
+pub mod watcher;
+pub mod webhook;
@@ -0,0 +1,487 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
+use diesel::upsert::excluded;
+use diesel::{prelude::*, Identifiable, Insertable, PgConnection, Queryable, RunQueryDsl};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::models::job::{Job, NewJob};
+use crate::schema::notification_settings;
+use crate::schema::notifications::{self, dsl::*};
+use crate::utils::rrule::to_utc;
+
+/// Kind of the jobs sending a notification through a channel
+pub static DELIVER: &str = "notification.deliver";
+
+/// Number of notifications returned at once
+static PAGE_SIZE: i64 = 50;
+
+/// Kind of event a notification is about
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[diesel(sql_type = Text)]
+pub enum NotificationKind {
+    /// The user was mentioned in a comment
+    Mention,
+    /// A todo the user set a reminder on is due soon
+    Reminder,
+}
+
+impl NotificationKind {
+    /// Returns the value stored in the database for the kind
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::Mention => "mention",
+            NotificationKind::Reminder => "reminder",
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for NotificationKind {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        <str as ToSql<Text, Pg>>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for NotificationKind {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match <String as FromSql<Text, Pg>>::from_sql(bytes)?.as_str() {
+            "mention" => Ok(NotificationKind::Mention),
+            "reminder" => Ok(NotificationKind::Reminder),
+            other => Err(format!("Unknown notification kind {}", other).into()),
+        }
+    }
+}
+
+/// Notification struct representing a row in the notifications table in the database
+#[derive(Identifiable, Queryable, Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    /// Unique id of the notification
+    /// This is the primary key of the notifications table
+    /// This is auto generated by the database
+    pub id: i32,
+    /// Id of the user the notification is for
+    pub user_id: i32,
+    /// Id of the user who caused the notification, if any
+    pub actor_id: Option<i32>,
+    /// Id of the todo the notification is about, if any
+    pub todo_id: Option<i32>,
+    /// Kind of event the notification is about
+    pub kind: NotificationKind,
+    /// Human readable message of the notification
+    pub message: String,
+    /// Time the notification was created in UTC
+    pub created_at: NaiveDateTime,
+    /// Time the notification was read in UTC, empty while unread
+    pub read_at: Option<NaiveDateTime>,
+}
+
+/// Channel a notification is sent through
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    AsExpression,
+    FromSqlRow,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+#[diesel(sql_type = Text)]
+pub enum Channel {
+    /// The inbox of the user, see `GET /notifications`
+    InApp,
+    /// An email sent to the address of the user
+    Email,
+    /// A `notification.created` delivery sent to the webhooks of the user
+    Webhook,
+}
+
+impl Channel {
+    /// Returns the value stored in the database for the channel
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Channel::InApp => "in_app",
+            Channel::Email => "email",
+            Channel::Webhook => "webhook",
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for Channel {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        <str as ToSql<Text, Pg>>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for Channel {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match <String as FromSql<Text, Pg>>::from_sql(bytes)?.as_str() {
+            "in_app" => Ok(Channel::InApp),
+            "email" => Ok(Channel::Email),
+            "webhook" => Ok(Channel::Webhook),
+            other => Err(format!("Unknown channel {}", other).into()),
+        }
+    }
+}
+
+/// NewNotification struct representing the data to be sent to the database to create a notification
+#[derive(Insertable, Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "notifications"]
+pub struct NewNotification {
+    /// Id of the user the notification is for
+    pub user_id: i32,
+    /// Id of the user who caused the notification, if any
+    pub actor_id: Option<i32>,
+    /// Id of the todo the notification is about, if any
+    pub todo_id: Option<i32>,
+    /// Kind of event the notification is about
+    pub kind: NotificationKind,
+    /// Human readable message of the notification
+    pub message: String,
+}
+
+/// NotificationSettings struct representing a row in the notification_settings table in the database
+#[derive(Queryable, Insertable, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "notification_settings"]
+pub struct NotificationSettings {
+    /// Id of the user
+    #[serde(skip)]
+    pub user_id: i32,
+    /// Time zone of the user, e.g. `Europe/Lisbon`, quiet hours are evaluated in it
+    pub time_zone: String,
+    /// Local time quiet hours start at, e.g. `22:00:00`
+    /// Email and webhook notifications are held until quiet hours end, the inbox gets them right away
+    pub quiet_hours_start: Option<NaiveTime>,
+    /// Local time quiet hours end at, e.g. `07:00:00`, can be earlier than the start to span midnight
+    pub quiet_hours_end: Option<NaiveTime>,
+    /// Channels notifications are sent through
+    pub channels: Vec<Channel>,
+}
+
+/// NotificationSettingsDTO struct representing the data sent to change the notification settings of a user
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationSettingsDTO {
+    /// Time zone of the user, e.g. `Europe/Lisbon`
+    pub time_zone: String,
+    /// Local time quiet hours start at, both bounds are required to have quiet hours
+    pub quiet_hours_start: Option<NaiveTime>,
+    /// Local time quiet hours end at
+    pub quiet_hours_end: Option<NaiveTime>,
+    /// Channels notifications are sent through, e.g. `["in_app", "email"]`
+    pub channels: Vec<Channel>,
+}
+
+/// Implementation of the Notification struct
+impl Notification {
+    /// Create notifications function
+    /// # Arguments
+    /// * `data` - NewNotification structs containing the data to be sent to the database
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<usize>` - Result containing the number of notifications created
+    pub fn create(data: &[NewNotification], conn: &mut PgConnection) -> QueryResult<usize> {
+        if data.is_empty() {
+            return Ok(0);
+        }
+
+        diesel::insert_into(notifications)
+            .values(data)
+            .execute(conn)
+    }
+
+    /// Send notifications function
+    /// A job is queued for every channel of every recipient, in the transaction of the change
+    /// that caused the notifications. Email and webhook notifications wait for the quiet hours of
+    /// the recipient to end
+    /// # Arguments
+    /// * `data` - NewNotification structs containing the notifications
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<usize>` - Result containing the number of jobs queued
+    pub fn notify(data: &[NewNotification], conn: &mut PgConnection) -> QueryResult<usize> {
+        if data.is_empty() {
+            return Ok(0);
+        }
+
+        let recipients = data
+            .iter()
+            .map(|notification| notification.user_id)
+            .collect::<Vec<_>>();
+
+        let settings = notification_settings::table
+            .filter(notification_settings::user_id.eq_any(&recipients))
+            .load::<NotificationSettings>(conn)?
+            .into_iter()
+            .map(|found| (found.user_id, found))
+            .collect::<HashMap<_, _>>();
+
+        let now = Utc::now().naive_utc();
+        let mut queued = vec![];
+
+        for notification in data {
+            let recipient = match settings.get(&notification.user_id) {
+                Some(found) => found.clone(),
+                None => NotificationSettings::default_for(notification.user_id),
+            };
+
+            for channel in recipient.channels.iter() {
+                let run_at = match channel {
+                    Channel::InApp => now,
+                    _ => recipient.deliver_at(now),
+                };
+
+                queued.push(NewJob {
+                    run_at,
+                    ..NewJob::new(
+                        DELIVER,
+                        json!({ "channel": channel, "notification": notification }),
+                    )
+                });
+            }
+        }
+
+        Job::enqueue_all(&queued, conn)
+    }
+
+    /// Gets the notifications of a user function
+    /// # Arguments
+    /// * `user` - Id of the user
+    /// * `unread` - Whether only unread notifications are returned
+    /// * `before` - Only notifications older than this notification id are returned
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<Notification>, String>` - Result containing the notifications, newest first, or an error message
+    pub fn get_notifications(
+        user: i32,
+        unread: bool,
+        before: Option<i32>,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<Notification>, String> {
+        let mut query = notifications.filter(user_id.eq(user)).into_boxed();
+
+        if unread {
+            query = query.filter(read_at.is_null());
+        }
+
+        if let Some(before) = before {
+            query = query.filter(id.lt(before));
+        }
+
+        let result = query
+            .order(id.desc())
+            .limit(PAGE_SIZE)
+            .load::<Notification>(conn);
+
+        match result {
+            Ok(data) => Ok(data),
+            Err(_) => Err("Failed to get notifications".to_string()),
+        }
+    }
+
+    /// Mark a notification as read function
+    /// A notification read already keeps the time it was first read
+    /// # Arguments
+    /// * `notification` - Id of the notification
+    /// * `user` - Id of the user the notification is for
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Notification, String>` - Result containing the notification or an error message
+    pub fn mark_read(
+        notification: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Notification, String> {
+        let found = notifications
+            .find(notification)
+            .filter(user_id.eq(user))
+            .first::<Notification>(conn);
+
+        let found = match found {
+            Ok(found) => found,
+            Err(_) => return Err("Notification not found".to_string()),
+        };
+
+        if found.read_at.is_some() {
+            return Ok(found);
+        }
+
+        let result = diesel::update(notifications.find(notification))
+            .set(read_at.eq(Some(Utc::now().naive_utc())))
+            .get_result(conn);
+
+        match result {
+            Ok(updated) => Ok(updated),
+            Err(_) => Err("Failed to mark notification as read".to_string()),
+        }
+    }
+
+    /// Mark every notification of a user as read function
+    /// # Arguments
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<usize, String>` - Result containing the number of notifications marked as read or an error message
+    pub fn mark_all_read(user: i32, conn: &mut PgConnection) -> Result<usize, String> {
+        let result = diesel::update(
+            notifications
+                .filter(user_id.eq(user))
+                .filter(read_at.is_null()),
+        )
+        .set(read_at.eq(Some(Utc::now().naive_utc())))
+        .execute(conn);
+
+        match result {
+            Ok(count) => Ok(count),
+            Err(_) => Err("Failed to mark notifications as read".to_string()),
+        }
+    }
+}
+
+/// Implementation of the NotificationSettings struct
+impl NotificationSettings {
+    /// Gets the notification settings of a user function
+    /// # Arguments
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<NotificationSettings, String>` - Result containing the settings, the defaults if the user has none, or an error message
+    pub fn get_settings(
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<NotificationSettings, String> {
+        let result = notification_settings::table
+            .find(user)
+            .first::<NotificationSettings>(conn)
+            .optional();
+
+        match result {
+            Ok(Some(found)) => Ok(found),
+            Ok(None) => Ok(NotificationSettings::default_for(user)),
+            Err(_) => Err("Failed to get notification settings".to_string()),
+        }
+    }
+
+    /// Change the notification settings of a user function
+    /// # Arguments
+    /// * `user` - Id of the user
+    /// * `data` - NotificationSettingsDTO struct containing the settings
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<NotificationSettings, String>` - Result containing the settings or an error message
+    pub fn update_settings(
+        user: i32,
+        data: NotificationSettingsDTO,
+        conn: &mut PgConnection,
+    ) -> Result<NotificationSettings, String> {
+        if data.time_zone.parse::<Tz>().is_err() {
+            return Err(format!("Unknown time zone {}", data.time_zone));
+        }
+
+        if data.quiet_hours_start.is_some() != data.quiet_hours_end.is_some() {
+            return Err("Quiet hours need both a start and an end".to_string());
+        }
+
+        let mut channels = vec![];
+
+        for channel in data.channels {
+            if !channels.contains(&channel) {
+                channels.push(channel);
+            }
+        }
+
+        let settings = NotificationSettings {
+            user_id: user,
+            time_zone: data.time_zone,
+            quiet_hours_start: data.quiet_hours_start,
+            quiet_hours_end: data.quiet_hours_end,
+            channels,
+        };
+
+        let result = diesel::insert_into(notification_settings::table)
+            .values(&settings)
+            .on_conflict(notification_settings::user_id)
+            .do_update()
+            .set((
+                notification_settings::time_zone.eq(excluded(notification_settings::time_zone)),
+                notification_settings::quiet_hours_start
+                    .eq(excluded(notification_settings::quiet_hours_start)),
+                notification_settings::quiet_hours_end
+                    .eq(excluded(notification_settings::quiet_hours_end)),
+                notification_settings::channels.eq(excluded(notification_settings::channels)),
+            ))
+            .get_result(conn);
+
+        match result {
+            Ok(updated) => Ok(updated),
+            Err(_) => Err("Failed to update notification settings".to_string()),
+        }
+    }
+
+    /// Computes when a notification can be sent to the user
+    /// # Arguments
+    /// * `now` - The current time in UTC
+    /// # Returns
+    /// * `NaiveDateTime` - The current time, or the end of the quiet hours in UTC if they are on
+    pub fn deliver_at(&self, now: NaiveDateTime) -> NaiveDateTime {
+        let (start, end) = match (self.quiet_hours_start, self.quiet_hours_end) {
+            (Some(start), Some(end)) if start != end => (start, end),
+            _ => return now,
+        };
+
+        let tz = self.time_zone.parse::<Tz>().unwrap_or(Tz::UTC);
+        let local = tz.from_utc_datetime(&now).naive_local();
+        let time = local.time();
+
+        let quiet = if start < end {
+            time >= start && time < end
+        } else {
+            time >= start || time < end
+        };
+
+        if !quiet {
+            return now;
+        }
+
+        // Quiet hours spanning midnight end the next day when they started today
+        let end_date = if time < end {
+            Some(local.date())
+        } else {
+            local.date().succ_opt()
+        };
+
+        end_date
+            .and_then(|date| to_utc(date.and_time(end), tz))
+            .unwrap_or(now)
+    }
+
+    /// Internal function to get the settings of a user who never changed them
+    fn default_for(user: i32) -> NotificationSettings {
+        NotificationSettings {
+            user_id: user,
+            time_zone: "UTC".to_string(),
+            quiet_hours_start: None,
+            quiet_hours_end: None,
+            channels: vec![Channel::InApp],
+        }
+    }
+}
@@ -0,0 +1,602 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
+use diesel::{prelude::*, Identifiable, Insertable, PgConnection, Queryable};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::models::job::{Job, NewJob};
+use crate::schema::{invitations, memberships, organizations, users};
+
+/// Number of days an invitation stays valid
+static INVITATION_TTL_DAYS: i64 = 7;
+
+/// Kind of the jobs emailing invitations to the invited users
+pub static INVITE: &str = "organizations.invite";
+
+/// Role of a user inside an organization
+/// Roles are ordered from the least to the most privileged one
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    AsExpression,
+    FromSqlRow,
+    JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[diesel(sql_type = Text)]
+pub enum Role {
+    /// Can only read the todos of the organization
+    Viewer,
+    /// Can read, create and update the todos of the organization
+    Member,
+    /// Can also manage members and invitations
+    Admin,
+    /// Created the organization, has every permission
+    Owner,
+}
+
+impl Role {
+    /// Returns the value stored in the database for the role
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Member => "member",
+            Role::Admin => "admin",
+            Role::Owner => "owner",
+        }
+    }
+
+    /// Whether the role allows creating and updating todos
+    pub fn can_write(&self) -> bool {
+        *self >= Role::Member
+    }
+}
+
+impl ToSql<Text, Pg> for Role {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        <str as ToSql<Text, Pg>>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for Role {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match <String as FromSql<Text, Pg>>::from_sql(bytes)?.as_str() {
+            "viewer" => Ok(Role::Viewer),
+            "member" => Ok(Role::Member),
+            "admin" => Ok(Role::Admin),
+            "owner" => Ok(Role::Owner),
+            other => Err(format!("Unknown role {}", other).into()),
+        }
+    }
+}
+
+/// Organization struct representing a row in the organizations table in the database
+#[derive(Identifiable, Queryable, Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Organization {
+    /// Unique id of the organization
+    /// This is the primary key of the organizations table
+    /// This is auto generated by the database
+    pub id: i32,
+    /// Name of the organization
+    pub name: String,
+}
+
+/// OrganizationDTO struct representing the data to be sent to create a new organization
+#[derive(Insertable, Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "organizations"]
+pub struct OrganizationDTO {
+    /// Name of the organization
+    /// Name is required for creating a new organization
+    pub name: String,
+}
+
+/// Membership struct representing a row in the memberships table in the database
+#[derive(Identifiable, Queryable, Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Membership {
+    /// Unique id of the membership
+    pub id: i32,
+    /// Id of the organization
+    pub organization_id: i32,
+    /// Id of the member
+    pub user_id: i32,
+    /// Role of the member inside the organization
+    pub role: Role,
+}
+
+/// Internal struct used to insert a new membership
+#[derive(Insertable, Debug)]
+#[table_name = "memberships"]
+struct NewMembership {
+    organization_id: i32,
+    user_id: i32,
+    role: Role,
+}
+
+/// MemberRoleDTO struct representing the data to be sent to change the role of a member
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct MemberRoleDTO {
+    /// New role of the member
+    /// The owner role cannot be given to another member
+    pub role: Role,
+}
+
+/// Invitation struct representing a row in the invitations table in the database
+#[derive(Identifiable, Queryable, Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Invitation {
+    /// Unique id of the invitation
+    pub id: i32,
+    /// Id of the organization the user is invited to
+    pub organization_id: i32,
+    /// Id of the user who sent the invitation
+    pub invited_by: i32,
+    /// Email of the invited user
+    /// Only the user registered with this email can accept the invitation
+    pub email: String,
+    /// Role given to the user once the invitation is accepted
+    pub role: Role,
+    /// Token used to accept the invitation
+    /// Only sent by email to the invited user, never in responses
+    #[serde(skip_serializing)]
+    pub token: String,
+    /// Time the invitation expires in UTC
+    pub expires_at: NaiveDateTime,
+    /// Time the invitation was accepted in UTC
+    pub accepted_at: Option<NaiveDateTime>,
+}
+
+/// InvitationDTO struct representing the data to be sent to invite a user to an organization
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct InvitationDTO {
+    /// Email of the user to be invited
+    pub email: String,
+    /// Role given to the user once the invitation is accepted
+    /// The owner role cannot be given through an invitation
+    pub role: Role,
+}
+
+/// Internal struct used to insert a new invitation
+#[derive(Insertable, Debug)]
+#[table_name = "invitations"]
+struct NewInvitation {
+    organization_id: i32,
+    invited_by: i32,
+    email: String,
+    role: Role,
+    token: String,
+    expires_at: NaiveDateTime,
+}
+
+/// Implementation of the Organization struct
+impl Organization {
+    /// Create a new organization function
+    /// The user creating the organization becomes its owner
+    /// # Arguments
+    /// * `data` - OrganizationDTO struct containing the data to be sent to the database
+    /// * `owner` - Id of the user creating the organization
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Organization, String>` - Result containing the created organization or an error message
+    pub fn new_organization(
+        data: OrganizationDTO,
+        owner: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Organization, String> {
+        if data.name.trim().is_empty() {
+            return Err("Name is required".to_string());
+        }
+
+        let result = conn.transaction::<Organization, diesel::result::Error, _>(|conn| {
+            let organization: Organization = diesel::insert_into(organizations::table)
+                .values(&data)
+                .get_result(conn)?;
+
+            diesel::insert_into(memberships::table)
+                .values(&NewMembership {
+                    organization_id: organization.id,
+                    user_id: owner,
+                    role: Role::Owner,
+                })
+                .execute(conn)?;
+
+            Ok(organization)
+        });
+
+        match result {
+            Ok(organization) => Ok(organization),
+            Err(_) => Err("Failed to create organization".to_string()),
+        }
+    }
+
+    /// Gets all organizations the user is a member of function
+    /// # Arguments
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<Organization>, String>` - Result containing a vector of organizations or an error message
+    pub fn get_organizations(
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<Organization>, String> {
+        let result = organizations::table
+            .inner_join(memberships::table)
+            .filter(memberships::user_id.eq(user))
+            .select(organizations::all_columns)
+            .load(conn);
+
+        match result {
+            Ok(data) => Ok(data),
+            Err(_) => Err("Failed to get organizations".to_string()),
+        }
+    }
+
+    /// Gets all members of an organization function
+    /// Any member of the organization can list its members
+    /// # Arguments
+    /// * `organization` - Id of the organization
+    /// * `user` - Id of the user requesting the members
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<Membership>, String>` - Result containing a vector of memberships or an error message
+    pub fn get_members(
+        organization: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<Membership>, String> {
+        Membership::require_role(organization, user, Role::Viewer, conn)?;
+
+        let result = memberships::table
+            .filter(memberships::organization_id.eq(organization))
+            .load(conn);
+
+        match result {
+            Ok(data) => Ok(data),
+            Err(_) => Err("Failed to get members".to_string()),
+        }
+    }
+
+    /// Invite a user to an organization function
+    /// Only owners and admins can invite users
+    /// The token of the invitation is emailed to the invited user by a job queued along with it
+    /// # Arguments
+    /// * `organization` - Id of the organization
+    /// * `user` - Id of the user sending the invitation
+    /// * `data` - InvitationDTO struct containing the email and role of the invited user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Invitation, String>` - Result containing the invitation or an error message
+    pub fn invite(
+        organization: i32,
+        user: i32,
+        data: InvitationDTO,
+        conn: &mut PgConnection,
+    ) -> Result<Invitation, String> {
+        Membership::require_role(organization, user, Role::Admin, conn)?;
+
+        if data.role == Role::Owner {
+            return Err("Cannot invite a user as owner".to_string());
+        }
+
+        if data.email.trim().is_empty() {
+            return Err("Email is required".to_string());
+        }
+
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(48)
+            .map(char::from)
+            .collect();
+
+        let invitation = NewInvitation {
+            organization_id: organization,
+            invited_by: user,
+            email: data.email,
+            role: data.role,
+            token,
+            expires_at: Utc::now().naive_utc() + Duration::days(INVITATION_TTL_DAYS),
+        };
+
+        let result = conn.transaction::<Invitation, diesel::result::Error, _>(|conn| {
+            let created = diesel::insert_into(invitations::table)
+                .values(&invitation)
+                .get_result::<Invitation>(conn)?;
+
+            // The job only carries the id, the token is read when the email is sent
+            Job::enqueue(
+                &NewJob::new(INVITE, json!({ "invitationId": created.id })),
+                conn,
+            )?;
+
+            Ok(created)
+        });
+
+        match result {
+            Ok(invitation) => Ok(invitation),
+            Err(_) => Err("Failed to create invitation".to_string()),
+        }
+    }
+
+    /// Change the role of a member function
+    /// Only owners and admins can change roles, and the owner's role cannot be changed
+    /// # Arguments
+    /// * `organization` - Id of the organization
+    /// * `user` - Id of the user changing the role
+    /// * `member` - Id of the member whose role is changed
+    /// * `role` - New role of the member
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn update_member_role(
+        organization: i32,
+        user: i32,
+        member: i32,
+        role: Role,
+        conn: &mut PgConnection,
+    ) -> Result<String, String> {
+        Membership::require_role(organization, user, Role::Admin, conn)?;
+
+        if role == Role::Owner {
+            return Err("Cannot give the owner role to another member".to_string());
+        }
+
+        match Membership::find_role(organization, member, conn)? {
+            Some(Role::Owner) => return Err("Cannot change the role of the owner".to_string()),
+            Some(_) => {}
+            None => return Err("Member not found".to_string()),
+        }
+
+        let result = diesel::update(memberships::table)
+            .set(memberships::role.eq(role))
+            .filter(memberships::organization_id.eq(organization))
+            .filter(memberships::user_id.eq(member))
+            .execute(conn);
+
+        match result {
+            Ok(_) => Ok("Successfully updated member".to_string()),
+            Err(_) => Err("Failed to update member".to_string()),
+        }
+    }
+
+    /// Remove a member from an organization function
+    /// Owners and admins can remove other members, and any member can leave the organization
+    /// The owner cannot be removed
+    /// # Arguments
+    /// * `organization` - Id of the organization
+    /// * `user` - Id of the user removing the member
+    /// * `member` - Id of the member to be removed
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn remove_member(
+        organization: i32,
+        user: i32,
+        member: i32,
+        conn: &mut PgConnection,
+    ) -> Result<String, String> {
+        if user != member {
+            Membership::require_role(organization, user, Role::Admin, conn)?;
+        }
+
+        match Membership::find_role(organization, member, conn)? {
+            Some(Role::Owner) => return Err("Cannot remove the owner".to_string()),
+            Some(_) => {}
+            None => return Err("Member not found".to_string()),
+        }
+
+        let result = diesel::delete(memberships::table)
+            .filter(memberships::organization_id.eq(organization))
+            .filter(memberships::user_id.eq(member))
+            .execute(conn);
+
+        match result {
+            Ok(_) => Ok("Successfully removed member".to_string()),
+            Err(_) => Err("Failed to remove member".to_string()),
+        }
+    }
+}
+
+/// Implementation of the Membership struct
+impl Membership {
+    /// Find the role of a user inside an organization function
+    /// # Arguments
+    /// * `organization` - Id of the organization
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Option<Role>, String>` - Result containing the role, or None if the user is not a member
+    pub fn find_role(
+        organization: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Option<Role>, String> {
+        let result = memberships::table
+            .filter(memberships::organization_id.eq(organization))
+            .filter(memberships::user_id.eq(user))
+            .select(memberships::role)
+            .first::<Role>(conn)
+            .optional();
+
+        match result {
+            Ok(role) => Ok(role),
+            Err(_) => Err("Failed to get membership".to_string()),
+        }
+    }
+
+    /// Find the organizations a user is a member of function
+    /// # Arguments
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<i32>, String>` - Result containing the ids of the organizations or an error message
+    pub fn organization_ids(user: i32, conn: &mut PgConnection) -> Result<Vec<i32>, String> {
+        let result = memberships::table
+            .filter(memberships::user_id.eq(user))
+            .select(memberships::organization_id)
+            .load::<i32>(conn);
+
+        match result {
+            Ok(organizations) => Ok(organizations),
+            Err(_) => Err("Failed to get memberships".to_string()),
+        }
+    }
+
+    /// Find the role of a user on a resource owned by a user and optionally shared with an organization function
+    /// The creator of a resource is treated as its owner
+    /// # Arguments
+    /// * `owner` - Id of the user who created the resource
+    /// * `organization` - Id of the organization the resource is shared with, if any
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Option<Role>, String>` - Result containing the role, or None if the user cannot access the resource
+    pub fn resource_role(
+        owner: i32,
+        organization: Option<i32>,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Option<Role>, String> {
+        if owner == user {
+            return Ok(Some(Role::Owner));
+        }
+
+        match organization {
+            Some(organization) => Membership::find_role(organization, user, conn),
+            None => Ok(None),
+        }
+    }
+
+    /// Check that a user has at least the given role inside an organization function
+    /// # Arguments
+    /// * `organization` - Id of the organization
+    /// * `user` - Id of the user
+    /// * `minimum` - Least privileged role accepted
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Role, String>` - Result containing the role of the user or an error message
+    pub fn require_role(
+        organization: i32,
+        user: i32,
+        minimum: Role,
+        conn: &mut PgConnection,
+    ) -> Result<Role, String> {
+        match Membership::find_role(organization, user, conn)? {
+            Some(role) if role >= minimum => Ok(role),
+            Some(_) => Err("Insufficient permissions".to_string()),
+            None => Err("Organization not found".to_string()),
+        }
+    }
+}
+
+/// Implementation of the Invitation struct
+impl Invitation {
+    /// Gets an invitation that can still be accepted, along with the name of its organization, function
+    /// # Arguments
+    /// * `invitation_id` - Id of the invitation
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Option<(Invitation, String)>, String>` - Result containing the invitation and the name of its organization, None if it was accepted, expired or deleted, or an error message
+    pub fn find_pending(
+        invitation_id: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Option<(Invitation, String)>, String> {
+        let result = invitations::table
+            .inner_join(organizations::table)
+            .filter(invitations::id.eq(invitation_id))
+            .filter(invitations::accepted_at.is_null())
+            .filter(invitations::expires_at.gt(Utc::now().naive_utc()))
+            .select((invitations::all_columns, organizations::name))
+            .first::<(Invitation, String)>(conn)
+            .optional();
+
+        match result {
+            Ok(found) => Ok(found),
+            Err(_) => Err("Failed to get invitation".to_string()),
+        }
+    }
+
+    /// Accept an invitation function
+    /// The invitation must be addressed to the email of the user accepting it
+    /// # Arguments
+    /// * `invitation_token` - Token of the invitation
+    /// * `user` - Id of the user accepting the invitation
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Membership, String>` - Result containing the created membership or an error message
+    pub fn accept(
+        invitation_token: String,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Membership, String> {
+        let invitation = match invitations::table
+            .filter(invitations::token.eq(invitation_token))
+            .first::<Invitation>(conn)
+        {
+            Ok(invitation) => invitation,
+            Err(_) => return Err("Invitation not found".to_string()),
+        };
+
+        if invitation.accepted_at.is_some() {
+            return Err("Invitation already accepted".to_string());
+        }
+
+        let now = Utc::now().naive_utc();
+
+        if invitation.expires_at < now {
+            return Err("Invitation expired".to_string());
+        }
+
+        let user_email = match users::table
+            .find(user)
+            .select(users::email)
+            .first::<String>(conn)
+        {
+            Ok(user_email) => user_email,
+            Err(_) => return Err("User not found".to_string()),
+        };
+
+        if !user_email.eq_ignore_ascii_case(&invitation.email) {
+            return Err("Invitation was sent to another email".to_string());
+        }
+
+        if Membership::find_role(invitation.organization_id, user, conn)?.is_some() {
+            return Err("User is already a member".to_string());
+        }
+
+        let result = conn.transaction::<Membership, diesel::result::Error, _>(|conn| {
+            diesel::update(invitations::table.find(invitation.id))
+                .set(invitations::accepted_at.eq(now))
+                .execute(conn)?;
+
+            diesel::insert_into(memberships::table)
+                .values(&NewMembership {
+                    organization_id: invitation.organization_id,
+                    user_id: user,
+                    role: invitation.role,
+                })
+                .get_result(conn)
+        });
+
+        match result {
+            Ok(membership) => Ok(membership),
+            Err(_) => Err("Failed to accept invitation".to_string()),
+        }
+    }
+}
@@ -0,0 +1,307 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::dsl::sql;
+use diesel::sql_types::{Bool, Timestamp};
+use diesel::{prelude::*, Identifiable, Insertable, PgConnection, Queryable, RunQueryDsl};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::models::notification::{NewNotification, Notification, NotificationKind};
+use crate::models::todos::Todo;
+use crate::schema::{todo_reminders, todos};
+
+/// Maximum number of minutes a reminder can be sent before the todo is due, 30 days
+static MAX_OFFSET: i32 = 60 * 24 * 30;
+
+/// Maximum number of reminders a user can set on a todo
+static MAX_REMINDERS: i64 = 10;
+
+/// Number of minutes a reminder can be late, reminders missed for longer are skipped
+static GRACE: i64 = 60;
+
+/// Number of reminders sent at once
+static BATCH_SIZE: i64 = 500;
+
+/// Reminder struct representing a row in the todo_reminders table in the database
+/// A reminder notifies its user a number of minutes before the todo is due
+#[derive(Identifiable, Queryable, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "todo_reminders"]
+pub struct Reminder {
+    /// Unique id of the reminder
+    /// This is the primary key of the todo_reminders table
+    /// This is auto generated by the database
+    pub id: i32,
+    /// Id of the todo
+    pub todo_id: i32,
+    /// Id of the user reminded
+    pub user_id: i32,
+    /// Number of minutes before the due date the reminder is sent, 0 sends it when the todo is due
+    pub offset_minutes: i32,
+    /// Due date the reminder was last sent for in UTC
+    pub fired_for: Option<NaiveDateTime>,
+    /// Time the reminder was set in UTC
+    pub created_at: NaiveDateTime,
+}
+
+/// ReminderDTO struct representing the data sent to set a reminder
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReminderDTO {
+    /// Number of minutes before the due date the reminder is sent, up to 30 days
+    pub offset_minutes: i32,
+}
+
+/// Internal struct used to set a reminder
+#[derive(Insertable, Debug)]
+#[table_name = "todo_reminders"]
+struct NewReminder {
+    todo_id: i32,
+    user_id: i32,
+    offset_minutes: i32,
+}
+
+/// Implementation of the Reminder struct
+impl Reminder {
+    /// Set a reminder on a todo function
+    /// The user must be allowed to read the todo, the reminder is only for them
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `user` - Id of the user
+    /// * `data` - ReminderDTO struct containing the offset of the reminder
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Reminder, String>` - Result containing the reminder or an error message
+    pub fn new_reminder(
+        todo: i32,
+        user: i32,
+        data: ReminderDTO,
+        conn: &mut PgConnection,
+    ) -> Result<Reminder, String> {
+        Todo::find_readable(todo, user, conn)?;
+
+        if data.offset_minutes < 0 || data.offset_minutes > MAX_OFFSET {
+            return Err(format!(
+                "Offset must be between 0 and {} minutes",
+                MAX_OFFSET
+            ));
+        }
+
+        let count = todo_reminders::table
+            .filter(todo_reminders::todo_id.eq(todo))
+            .filter(todo_reminders::user_id.eq(user))
+            .count()
+            .get_result::<i64>(conn);
+
+        match count {
+            Ok(count) if count >= MAX_REMINDERS => {
+                return Err(format!(
+                    "A todo can have at most {} reminders",
+                    MAX_REMINDERS
+                ));
+            }
+            Ok(_) => {}
+            Err(_) => return Err("Failed to create reminder".to_string()),
+        }
+
+        let result = diesel::insert_into(todo_reminders::table)
+            .values(&NewReminder {
+                todo_id: todo,
+                user_id: user,
+                offset_minutes: data.offset_minutes,
+            })
+            .on_conflict_do_nothing()
+            .get_result::<Reminder>(conn)
+            .optional();
+
+        match result {
+            Ok(Some(reminder)) => Ok(reminder),
+            Ok(None) => Err("Reminder already exists".to_string()),
+            Err(_) => Err("Failed to create reminder".to_string()),
+        }
+    }
+
+    /// Gets the reminders of a user on a todo function
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<Reminder>, String>` - Result containing the reminders, earliest first, or an error message
+    pub fn get_reminders(
+        todo: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<Reminder>, String> {
+        Todo::find_readable(todo, user, conn)?;
+
+        let result = todo_reminders::table
+            .filter(todo_reminders::todo_id.eq(todo))
+            .filter(todo_reminders::user_id.eq(user))
+            .order(todo_reminders::offset_minutes.desc())
+            .load(conn);
+
+        match result {
+            Ok(data) => Ok(data),
+            Err(_) => Err("Failed to get reminders".to_string()),
+        }
+    }
+
+    /// Delete a reminder function
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `reminder` - Id of the reminder
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn delete_reminder(
+        todo: i32,
+        reminder: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<String, String> {
+        let result = diesel::delete(
+            todo_reminders::table
+                .find(reminder)
+                .filter(todo_reminders::todo_id.eq(todo))
+                .filter(todo_reminders::user_id.eq(user)),
+        )
+        .execute(conn);
+
+        match result {
+            Ok(0) => Err("Reminder not found".to_string()),
+            Ok(_) => Ok("Successfully deleted reminder".to_string()),
+            Err(_) => Err("Failed to delete reminder".to_string()),
+        }
+    }
+
+    /// Copy the reminders of a todo to another todo function
+    /// # Arguments
+    /// * `from` - Id of the todo the reminders are copied from
+    /// * `to` - Id of the todo the reminders are copied to
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<usize>` - Number of reminders copied, used inside transactions
+    pub fn copy_reminders(from: i32, to: i32, conn: &mut PgConnection) -> QueryResult<usize> {
+        let rows = todo_reminders::table
+            .filter(todo_reminders::todo_id.eq(from))
+            .load::<Reminder>(conn)?
+            .into_iter()
+            .map(|reminder| NewReminder {
+                todo_id: to,
+                user_id: reminder.user_id,
+                offset_minutes: reminder.offset_minutes,
+            })
+            .collect::<Vec<_>>();
+
+        if rows.is_empty() {
+            return Ok(0);
+        }
+
+        diesel::insert_into(todo_reminders::table)
+            .values(&rows)
+            .on_conflict_do_nothing()
+            .execute(conn)
+    }
+
+    /// Sends the due reminders function
+    /// A reminder is due once the due date of its todo minus its offset is reached, as long as the
+    /// todo is not completed. It is sent once per due date, moving the due date sends it again.
+    /// Reminders missed for longer than the grace period are never selected, so they cannot fill the batch.
+    /// Reminders of users who can no longer read the todo are skipped
+    /// # Arguments
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<usize, String>` - Result containing the number of reminders sent or an error message
+    pub fn send_due(conn: &mut PgConnection) -> Result<usize, String> {
+        let now = Utc::now().naive_utc();
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            // Reminders are sent at most 30 days before their todo, only those todos are looked at
+            let due = todo_reminders::table
+                .inner_join(todos::table)
+                .filter(todos::completed.eq(false))
+                .filter(todos::due_at.gt(now - Duration::minutes(GRACE)))
+                .filter(todos::due_at.le(now + Duration::minutes(MAX_OFFSET as i64)))
+                .filter(todo_reminders::fired_for.is_distinct_from(todos::due_at))
+                .filter(
+                    sql::<Bool>(
+                        "todos.due_at - todo_reminders.offset_minutes * INTERVAL '1 minute' <= ",
+                    )
+                    .bind::<Timestamp, _>(now),
+                )
+                // Reminders missed for too long, e.g. set after their time, are not sent late
+                .filter(
+                    sql::<Bool>(
+                        "todos.due_at - todo_reminders.offset_minutes * INTERVAL '1 minute' > ",
+                    )
+                    .bind::<Timestamp, _>(now - Duration::minutes(GRACE)),
+                )
+                .order(todos::due_at.asc())
+                .limit(BATCH_SIZE)
+                .load::<(Reminder, Todo)>(conn)?;
+
+            let mut sent = vec![];
+
+            for (reminder, todo) in due {
+                let due = match todo.due_at {
+                    Some(due) => due,
+                    None => continue,
+                };
+
+                // Another worker sent it in the meantime
+                let claimed = diesel::update(
+                    todo_reminders::table
+                        .find(reminder.id)
+                        .filter(todo_reminders::fired_for.is_distinct_from(Some(due))),
+                )
+                .set(todo_reminders::fired_for.eq(Some(due)))
+                .execute(conn)?;
+
+                if claimed == 0 || Todo::find_readable(todo.id, reminder.user_id, conn).is_err() {
+                    continue;
+                }
+
+                sent.push(NewNotification {
+                    user_id: reminder.user_id,
+                    actor_id: None,
+                    todo_id: Some(todo.id),
+                    kind: NotificationKind::Reminder,
+                    message: Reminder::message(&todo.title, due - now),
+                });
+            }
+
+            Notification::notify(&sent, conn)?;
+
+            Ok(sent.len())
+        });
+
+        match result {
+            Ok(sent) => Ok(sent),
+            Err(_) => Err("Failed to send reminders".to_string()),
+        }
+    }
+
+    /// Internal function to write the message of a reminder
+    fn message(title: &str, left: Duration) -> String {
+        let minutes = (left.num_seconds() + 59) / 60;
+
+        let when = match minutes {
+            minutes if minutes <= 0 => return format!("\"{}\" is due now", title),
+            minutes if minutes % (60 * 24) == 0 => plural(minutes / (60 * 24), "day"),
+            minutes if minutes % 60 == 0 => plural(minutes / 60, "hour"),
+            minutes => plural(minutes, "minute"),
+        };
+
+        format!("\"{}\" is due in {}", title, when)
+    }
+}
+
+/// Internal function to write a number of units, e.g. `2 hours`
+fn plural(count: i64, unit: &str) -> String {
+    match count {
+        1 => format!("1 {}", unit),
+        count => format!("{} {}s", count, unit),
+    }
+}
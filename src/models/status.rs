@@ -0,0 +1,597 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::{
+    prelude::*, sql_function, AsChangeset, Identifiable, Insertable, PgConnection, Queryable,
+    RunQueryDsl,
+};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::models::event::Event;
+use crate::models::list::List;
+use crate::models::todos::{Todo, TodoWithTags};
+use crate::schema::statuses::{self, dsl::*};
+use crate::schema::{status_transitions, todos};
+
+sql_function!(fn lower(x: diesel::sql_types::Text) -> diesel::sql_types::Text);
+
+/// Status struct representing a row in the statuses table in the database
+/// Statuses are the columns of the workflow of a list, e.g. `In progress` or `Review`
+#[derive(Identifiable, Queryable, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "statuses"]
+pub struct Status {
+    /// Unique id of the status
+    /// This is the primary key of the statuses table
+    /// This is auto generated by the database
+    pub id: i32,
+    /// Id of the list the status belongs to
+    pub list_id: i32,
+    /// Name of the status, unique in its list ignoring case
+    pub name: String,
+    /// Position of the status, statuses are sorted by ascending position
+    pub position: i32,
+    /// Whether todos in this status are completed or not
+    pub done: bool,
+}
+
+/// StatusDTO struct representing the data to be sent to the database to create or update a status
+#[derive(Insertable, AsChangeset, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "statuses"]
+pub struct StatusDTO {
+    /// Id of the list the status belongs to
+    /// The list_id is gotten from the path
+    pub list_id: Option<i32>,
+    /// Name of the status
+    /// Name is required for creating a new status
+    pub name: Option<String>,
+    /// Position of the status
+    /// If not provided when creating a status, the status is placed last
+    pub position: Option<i32>,
+    /// Whether todos in this status are completed or not, defaults to false
+    pub done: Option<bool>,
+}
+
+/// Transition struct representing a row in the status_transitions table in the database
+#[derive(Queryable, Insertable, Debug)]
+#[table_name = "status_transitions"]
+struct Transition {
+    /// Id of the status todos move from
+    from_status_id: i32,
+    /// Id of the status todos move to
+    to_status_id: i32,
+}
+
+/// StatusWithTransitions struct representing a status along with the statuses todos can move to
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusWithTransitions {
+    /// The status itself, its fields are flattened into this struct
+    #[serde(flatten)]
+    pub status: Status,
+    /// Ids of the statuses todos in this status can move to
+    /// If no status of the list has transitions, todos can move to any status
+    pub transitions: Vec<i32>,
+}
+
+/// BoardColumn struct representing the todos of a list in a given status
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BoardColumn {
+    /// Id of the status, empty for the todos that have no status yet
+    pub status_id: Option<i32>,
+    /// Name of the status
+    pub name: String,
+    /// Whether todos in this column are completed or not
+    pub done: bool,
+    /// Todos in this status, in their manual order
+    pub todos: Vec<TodoWithTags>,
+}
+
+/// Implementation of the Status struct
+impl Status {
+    /// Create a new status in a list function
+    /// # Arguments
+    /// * `list` - Id of the list
+    /// * `user` - Id of the user creating the status
+    /// * `data` - StatusDTO struct containing the data to be sent to the database
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Status, String>` - Result containing the created status or an error message
+    pub fn new_status(
+        list: i32,
+        user: i32,
+        mut data: StatusDTO,
+        conn: &mut PgConnection,
+    ) -> Result<Status, String> {
+        List::find_writable(list, user, conn)?;
+
+        let status_name = Status::validate_name(data.name.clone())?;
+        Status::validate_unique(list, &status_name, None, conn)?;
+
+        if data.position.is_none() {
+            let last = statuses
+                .filter(list_id.eq(list))
+                .select(diesel::dsl::max(position))
+                .first::<Option<i32>>(conn);
+
+            data.position = match last {
+                Ok(last) => Some(last.map_or(0, |last| last + 1)),
+                Err(_) => return Err("Failed to create status".to_string()),
+            };
+        }
+
+        data.list_id = Some(list);
+        data.name = Some(status_name);
+
+        let result = diesel::insert_into(statuses)
+            .values(&data)
+            .get_result::<Status>(conn);
+
+        match result {
+            Ok(status) => Ok(status),
+            Err(_) => Err("Failed to create status".to_string()),
+        }
+    }
+
+    /// Gets the statuses of a list along with their transitions function
+    /// # Arguments
+    /// * `list` - Id of the list
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<StatusWithTransitions>, String>` - Result containing the statuses sorted by position or an error message
+    pub fn get_statuses(
+        list: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<StatusWithTransitions>, String> {
+        List::find_readable(list, user, conn)?;
+
+        let list_statuses = match Status::get_list_statuses(list, conn) {
+            Ok(list_statuses) => list_statuses,
+            Err(_) => return Err("Failed to get statuses".to_string()),
+        };
+
+        let status_ids: Vec<i32> = list_statuses.iter().map(|status| status.id).collect();
+
+        let result = status_transitions::table
+            .filter(status_transitions::from_status_id.eq_any(&status_ids))
+            .load::<Transition>(conn);
+
+        let transitions = match result {
+            Ok(transitions) => transitions,
+            Err(_) => return Err("Failed to get statuses".to_string()),
+        };
+
+        Ok(list_statuses
+            .into_iter()
+            .map(|status| StatusWithTransitions {
+                transitions: transitions
+                    .iter()
+                    .filter(|transition| transition.from_status_id == status.id)
+                    .map(|transition| transition.to_status_id)
+                    .collect(),
+                status,
+            })
+            .collect())
+    }
+
+    /// Update a status function
+    /// Changing whether a status is done also updates the todos in that status
+    /// # Arguments
+    /// * `list` - Id of the list
+    /// * `status_id` - Id of the status to be updated
+    /// * `user` - Id of the user updating the status
+    /// * `data` - StatusDTO struct containing the data to be sent to the database
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Status, String>` - Result containing the updated status or an error message
+    pub fn update_status(
+        list: i32,
+        status_id: i32,
+        user: i32,
+        mut data: StatusDTO,
+        conn: &mut PgConnection,
+    ) -> Result<Status, String> {
+        List::find_writable(list, user, conn)?;
+        Status::find_in_list(status_id, list, conn)?;
+
+        if data.name.is_some() {
+            let status_name = Status::validate_name(data.name.clone())?;
+            Status::validate_unique(list, &status_name, Some(status_id), conn)?;
+            data.name = Some(status_name);
+        }
+
+        data.list_id = None;
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let status = diesel::update(statuses.find(status_id))
+                .set(&data)
+                .get_result::<Status>(conn)?;
+
+            let done_at = match status.done {
+                true => Some(Utc::now().naive_utc()),
+                false => None::<NaiveDateTime>,
+            };
+
+            let before = todos::table
+                .filter(todos::status_id.eq(status_id))
+                .filter(todos::completed.ne(status.done))
+                .for_update()
+                .load::<Todo>(conn)?;
+
+            let after = diesel::update(todos::table)
+                .filter(todos::status_id.eq(status_id))
+                .filter(todos::completed.ne(status.done))
+                .set((
+                    todos::completed.eq(status.done),
+                    todos::completed_at.eq(done_at),
+                ))
+                .get_results::<Todo>(conn)?;
+
+            Event::record_all(user, &before, &after, conn)?;
+
+            Ok(status)
+        });
+
+        match result {
+            Ok(status) => Ok(status),
+            Err(_) => Err("Failed to update status".to_string()),
+        }
+    }
+
+    /// Delete a status function
+    /// A status can only be deleted once no todo is in it
+    /// # Arguments
+    /// * `list` - Id of the list
+    /// * `status_id` - Id of the status to be deleted
+    /// * `user` - Id of the user deleting the status
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn delete_status(
+        list: i32,
+        status_id: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<String, String> {
+        List::find_writable(list, user, conn)?;
+        Status::find_in_list(status_id, list, conn)?;
+
+        let in_use = todos::table
+            .filter(todos::status_id.eq(status_id))
+            .count()
+            .get_result::<i64>(conn);
+
+        match in_use {
+            Ok(0) => {}
+            Ok(_) => {
+                return Err("Status is used by todos, move them to another status first".to_string())
+            }
+            Err(_) => return Err("Failed to delete status".to_string()),
+        }
+
+        let result = diesel::delete(statuses.find(status_id)).execute(conn);
+
+        match result {
+            Ok(_) => Ok("Successfully deleted status".to_string()),
+            Err(_) => Err("Failed to delete status".to_string()),
+        }
+    }
+
+    /// Allow todos to move from a status to another one function
+    /// Once a list has transitions, todos can only move along them
+    /// # Arguments
+    /// * `list` - Id of the list
+    /// * `from` - Id of the status todos move from
+    /// * `to` - Id of the status todos move to
+    /// * `user` - Id of the user allowing the transition
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn allow_transition(
+        list: i32,
+        from: i32,
+        to: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<String, String> {
+        List::find_writable(list, user, conn)?;
+        Status::find_in_list(from, list, conn)?;
+        Status::find_in_list(to, list, conn)?;
+
+        if from == to {
+            return Err("A status cannot transition to itself".to_string());
+        }
+
+        let result = diesel::insert_into(status_transitions::table)
+            .values(&Transition {
+                from_status_id: from,
+                to_status_id: to,
+            })
+            .on_conflict_do_nothing()
+            .execute(conn);
+
+        match result {
+            Ok(_) => Ok("Successfully allowed transition".to_string()),
+            Err(_) => Err("Failed to allow transition".to_string()),
+        }
+    }
+
+    /// Stop allowing todos to move from a status to another one function
+    /// # Arguments
+    /// * `list` - Id of the list
+    /// * `from` - Id of the status todos move from
+    /// * `to` - Id of the status todos move to
+    /// * `user` - Id of the user removing the transition
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn remove_transition(
+        list: i32,
+        from: i32,
+        to: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<String, String> {
+        List::find_writable(list, user, conn)?;
+        Status::find_in_list(from, list, conn)?;
+
+        let result = diesel::delete(status_transitions::table.find((from, to))).execute(conn);
+
+        match result {
+            Ok(0) => Err("Transition not found".to_string()),
+            Ok(_) => Ok("Successfully removed transition".to_string()),
+            Err(_) => Err("Failed to remove transition".to_string()),
+        }
+    }
+
+    /// Gets the todos of a list grouped by status function
+    /// Todos that have no status yet come first, in a column of their own
+    /// # Arguments
+    /// * `list` - Id of the list
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<BoardColumn>, String>` - Result containing the columns sorted by status position or an error message
+    pub fn get_board(
+        list: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<BoardColumn>, String> {
+        let list_todos = List::get_list_todos(list, user, conn)?;
+
+        let list_statuses = match Status::get_list_statuses(list, conn) {
+            Ok(list_statuses) => list_statuses,
+            Err(_) => return Err("Failed to get board".to_string()),
+        };
+
+        let mut columns: Vec<BoardColumn> = list_statuses
+            .into_iter()
+            .map(|status| BoardColumn {
+                status_id: Some(status.id),
+                name: status.name,
+                done: status.done,
+                todos: vec![],
+            })
+            .collect();
+
+        let mut unsorted = BoardColumn {
+            status_id: None,
+            name: "No status".to_string(),
+            done: false,
+            todos: vec![],
+        };
+
+        for todo in list_todos {
+            let column = columns.iter_mut().find(|column| {
+                column.status_id.is_some() && column.status_id == todo.todo.status_id
+            });
+
+            match column {
+                Some(column) => column.todos.push(todo),
+                None => unsorted.todos.push(todo),
+            }
+        }
+
+        if !unsorted.todos.is_empty() {
+            columns.insert(0, unsorted);
+        }
+
+        Ok(columns)
+    }
+
+    /// Find a status of a list function
+    /// # Arguments
+    /// * `status_id` - Id of the status
+    /// * `list` - Id of the list the status must belong to
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Status, String>` - Result containing the status or an error message
+    pub fn find_in_list(
+        status_id: i32,
+        list: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Status, String> {
+        let result = statuses
+            .find(status_id)
+            .filter(list_id.eq(list))
+            .first::<Status>(conn)
+            .optional();
+
+        match result {
+            Ok(Some(status)) => Ok(status),
+            Ok(None) => Err("Status not found in this list".to_string()),
+            Err(_) => Err("Failed to get status".to_string()),
+        }
+    }
+
+    /// Validate that a todo can move from a status to another one function
+    /// If no status of the list has transitions, todos can move to any status
+    /// # Arguments
+    /// * `from` - Status the todo is in
+    /// * `to` - Status the todo moves to
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<(), String>` - Result containing nothing or an error message
+    pub fn validate_transition(
+        from: &Status,
+        to: &Status,
+        conn: &mut PgConnection,
+    ) -> Result<(), String> {
+        if from.id == to.id {
+            return Ok(());
+        }
+
+        let list_statuses = statuses.filter(list_id.eq(from.list_id)).select(id);
+
+        let result = status_transitions::table
+            .filter(status_transitions::from_status_id.eq_any(list_statuses))
+            .load::<Transition>(conn);
+
+        let transitions = match result {
+            Ok(transitions) => transitions,
+            Err(_) => return Err("Failed to validate transition".to_string()),
+        };
+
+        let allowed = transitions.is_empty()
+            || transitions.iter().any(|transition| {
+                transition.from_status_id == from.id && transition.to_status_id == to.id
+            });
+
+        if !allowed {
+            return Err(format!(
+                "Moving a todo from {} to {} is not allowed in this list",
+                from.name, to.name
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Find the first status a todo can move to to be completed or reopened function
+    /// # Arguments
+    /// * `list` - Id of the list of the todo
+    /// * `from` - Status the todo is in, if any
+    /// * `is_done` - Whether the todo is being completed or reopened
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Option<Status>, String>` - Result containing the status, None if the list has no statuses, or an error message
+    pub fn find_for_completion(
+        list: i32,
+        from: Option<&Status>,
+        is_done: bool,
+        conn: &mut PgConnection,
+    ) -> Result<Option<Status>, String> {
+        let list_statuses = match Status::get_list_statuses(list, conn) {
+            Ok(list_statuses) => list_statuses,
+            Err(_) => return Err("Failed to get statuses".to_string()),
+        };
+
+        if list_statuses.is_empty() {
+            return Ok(None);
+        }
+
+        for status in list_statuses
+            .into_iter()
+            .filter(|status| status.done == is_done)
+        {
+            match from {
+                Some(from) if Status::validate_transition(from, &status, conn).is_err() => {}
+                _ => return Ok(Some(status)),
+            }
+        }
+
+        let kind = if is_done { "done" } else { "open" };
+
+        match from {
+            Some(from) => Err(format!(
+                "No {} status can be reached from {}",
+                kind, from.name
+            )),
+            None => Err(format!("List has no {} status", kind)),
+        }
+    }
+
+    /// Find the first status of a list that is done or not function
+    /// Transitions are not taken into account
+    /// # Arguments
+    /// * `list` - Id of the list
+    /// * `is_done` - Whether the status is done or not
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<Option<Status>>` - Result containing the status, if any
+    pub fn first_with_done(
+        list: i32,
+        is_done: bool,
+        conn: &mut PgConnection,
+    ) -> QueryResult<Option<Status>> {
+        statuses
+            .filter(list_id.eq(list))
+            .filter(done.eq(is_done))
+            .order((position.asc(), id.asc()))
+            .first::<Status>(conn)
+            .optional()
+    }
+
+    /// Internal function to get the statuses of a list sorted by position
+    fn get_list_statuses(list: i32, conn: &mut PgConnection) -> QueryResult<Vec<Status>> {
+        statuses
+            .filter(list_id.eq(list))
+            .order((position.asc(), id.asc()))
+            .load::<Status>(conn)
+    }
+
+    /// Internal function to validate the name of a status
+    /// # Arguments
+    /// * `status_name` - Name of the status
+    /// # Returns
+    /// * `Result<String, String>` - Result containing the trimmed name or an error message
+    fn validate_name(status_name: Option<String>) -> Result<String, String> {
+        let status_name = match status_name {
+            Some(status_name) => status_name.trim().to_string(),
+            None => return Err("Name is required".to_string()),
+        };
+
+        if status_name.is_empty() {
+            return Err("Name is required".to_string());
+        }
+
+        if status_name.chars().count() > 64 {
+            return Err("Name must be at most 64 characters".to_string());
+        }
+
+        Ok(status_name)
+    }
+
+    /// Internal function to validate that no other status of the list has the same name, ignoring case
+    /// # Arguments
+    /// * `list` - Id of the list
+    /// * `status_name` - Name of the status
+    /// * `exclude` - Id of a status to ignore, used when renaming a status
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<(), String>` - Result containing nothing or an error message
+    fn validate_unique(
+        list: i32,
+        status_name: &str,
+        exclude: Option<i32>,
+        conn: &mut PgConnection,
+    ) -> Result<(), String> {
+        let result = statuses
+            .filter(list_id.eq(list))
+            .filter(lower(name).eq(status_name.to_lowercase()))
+            .filter(id.ne(exclude.unwrap_or(0)))
+            .select(id)
+            .first::<i32>(conn)
+            .optional();
+
+        match result {
+            Ok(Some(_)) => Err("Status already exists".to_string()),
+            Ok(None) => Ok(()),
+            Err(_) => Err("Failed to validate status".to_string()),
+        }
+    }
+}
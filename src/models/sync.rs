@@ -0,0 +1,437 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use diesel::{prelude::*, PgConnection};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::models::bulk::MAX_OPERATIONS;
+use crate::models::event::{Event, EventCursor};
+use crate::models::organization::Membership;
+use crate::models::todos::{
+    ChildrenAction, Todo, TodoDTO, TodoFilter, TodoPatch, UpdateOptions, STALE_VERSION,
+};
+use crate::schema::{todo_events, todos};
+
+/// Maximum number of changes read by a pull, the other ones are sent by the next pulls
+static PAGE_SIZE: i64 = 500;
+
+/// SyncPull struct representing the changes a client pulls since its last sync
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPull {
+    /// Token to send as `since` on the next pull, opaque to the client
+    pub token: String,
+    /// Todos created or changed since the token, as they are now
+    pub todos: Vec<Todo>,
+    /// Todos deleted since the token, or that the user cannot read anymore
+    pub deleted: Vec<Tombstone>,
+    /// Whether more changes are waiting, the client pulls again with the new token right away
+    pub has_more: bool,
+}
+
+/// Tombstone struct representing a todo the client must remove
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Tombstone {
+    /// Id of the todo
+    pub id: i32,
+    /// Time of the last change of the todo in UTC, usually its deletion
+    pub removed_at: NaiveDateTime,
+}
+
+/// A change made by a client while offline, e.g. `{"op": "delete", "id": 3, "baseVersion": 4}`
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum SyncChange {
+    /// Creates a todo, like `POST /todo`
+    #[serde(rename_all = "camelCase")]
+    Create {
+        /// Id given to the todo by the client, sent back along with the id given by the server
+        client_id: String,
+        /// The todo to create, for reference see `TodoDTO` struct
+        todo: TodoDTO,
+    },
+    /// Patches a todo with a JSON Merge Patch, like `PATCH /todo/<id>`
+    #[serde(rename_all = "camelCase")]
+    Update {
+        /// Id of the todo
+        id: i32,
+        /// Version of the todo the change was made on, the change conflicts if the todo changed since
+        base_version: Option<i32>,
+        /// The fields to change, for reference see `TodoPatch` struct
+        patch: TodoPatch,
+    },
+    /// Deletes a todo, like `DELETE /todo/<id>`
+    #[serde(rename_all = "camelCase")]
+    Delete {
+        /// Id of the todo
+        id: i32,
+        /// Version of the todo the change was made on, the change conflicts if the todo changed since
+        base_version: Option<i32>,
+        /// What happens to the subtasks of the todo, required if it has any
+        children: Option<ChildrenAction>,
+    },
+}
+
+/// SyncPushDTO struct representing the changes a client pushes
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPushDTO {
+    /// Changes applied in order, each one on its own
+    pub changes: Vec<SyncChange>,
+}
+
+/// What happened to a pushed change
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncStatus {
+    /// The change is saved
+    Applied,
+    /// The todo changed or was deleted since the client read it, the change is not saved
+    Conflict,
+    /// The change is invalid, e.g. a todo without a title
+    Rejected,
+}
+
+/// SyncResult struct representing the outcome of a pushed change
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncResult {
+    /// Position of the change in the request
+    pub index: usize,
+    /// Id given to the todo by the client, for creates
+    pub client_id: Option<String>,
+    /// Id of the todo, the id given by the server for creates
+    pub id: Option<i32>,
+    /// Whether the change is saved
+    pub status: SyncStatus,
+    /// Success message or error message of the change
+    pub message: String,
+    /// The todo as it is on the server, empty if it is deleted
+    pub todo: Option<Todo>,
+}
+
+/// Implementation of the SyncPull struct
+impl SyncPull {
+    /// Pull the changes made since a sync token function
+    /// Without a token every todo the user can read is returned, along with the token to pull the next changes with
+    /// # Arguments
+    /// * `user` - Id of the user
+    /// * `since` - The token returned by the last pull
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<SyncPull, String>` - Result containing the changes or an error message
+    pub fn pull(
+        user: i32,
+        since: Option<&str>,
+        conn: &mut PgConnection,
+    ) -> Result<SyncPull, String> {
+        let since = match since {
+            Some(token) => SyncPull::parse_token(token, conn)?,
+            None => return SyncPull::snapshot(user, conn),
+        };
+
+        let user_organizations = Membership::organization_ids(user, conn)?;
+
+        // Todos moved to another organization are removed from the clients of the former one
+        let result =
+            Event::get_committed_since(user, &user_organizations, since, PAGE_SIZE + 1, conn);
+
+        let mut events = match result {
+            Ok(events) => events,
+            Err(_) => return Err("Failed to get changes".to_string()),
+        };
+
+        let has_more = events.len() as i64 > PAGE_SIZE;
+        events.truncate(PAGE_SIZE as usize);
+
+        let token = events.last().map_or(since, EventCursor::of);
+
+        // Last change of each todo, in the order the todos changed
+        let mut touched: Vec<i32> = vec![];
+        let mut last_changes: HashMap<i32, NaiveDateTime> = HashMap::new();
+
+        for event in events.iter() {
+            if !event.is_visible_to(user, &user_organizations) {
+                continue;
+            }
+
+            if last_changes
+                .insert(event.todo_id, event.created_at)
+                .is_none()
+            {
+                touched.push(event.todo_id);
+            }
+        }
+
+        let result = todos::table
+            .filter(todos::id.eq_any(&touched))
+            .filter(
+                todos::user_id.eq(user).or(todos::organization_id.eq_any(
+                    user_organizations
+                        .iter()
+                        .map(|organization| Some(*organization)),
+                )),
+            )
+            .order(todos::id.asc())
+            .load::<Todo>(conn);
+
+        let changed = match result {
+            Ok(changed) => changed,
+            Err(_) => return Err("Failed to get changes".to_string()),
+        };
+
+        let deleted = touched
+            .into_iter()
+            .filter(|todo| !changed.iter().any(|current| current.id == *todo))
+            .map(|todo| Tombstone {
+                id: todo,
+                removed_at: last_changes[&todo],
+            })
+            .collect();
+
+        Ok(SyncPull {
+            token: token.to_string(),
+            todos: changed,
+            deleted,
+            has_more,
+        })
+    }
+
+    /// Internal function to get every todo the user can read, along with the token to pull the next changes with
+    fn snapshot(user: i32, conn: &mut PgConnection) -> Result<SyncPull, String> {
+        // Read first, so changes committed while the todos are loaded are pulled again rather than missed
+        let horizon = match EventCursor::horizon(conn) {
+            Ok(horizon) => horizon,
+            Err(_) => return Err("Failed to get changes".to_string()),
+        };
+
+        let current = Todo::get_todos(user, &TodoFilter::default(), conn)?
+            .into_iter()
+            .map(|todo| todo.todo)
+            .collect();
+
+        Ok(SyncPull {
+            token: horizon.to_string(),
+            todos: current,
+            deleted: vec![],
+            has_more: false,
+        })
+    }
+
+    /// Internal function to read the position of the last change pulled from a sync token
+    /// Tokens holding a plain event id, handed out before tokens held cursors, start from the transaction of that event
+    fn parse_token(token: &str, conn: &mut PgConnection) -> Result<EventCursor, String> {
+        if let Some(since) = EventCursor::parse(token) {
+            return Ok(since);
+        }
+
+        let since = match token.parse::<i32>() {
+            Ok(since) if since >= 0 => since,
+            _ => return Err("Invalid sync token".to_string()),
+        };
+
+        let result = todo_events::table
+            .filter(todo_events::id.le(since))
+            .order(todo_events::id.desc())
+            .select(todo_events::mutation_id)
+            .first::<i64>(conn)
+            .optional();
+
+        match result {
+            Ok(transaction) => Ok(EventCursor {
+                mutation_id: transaction.unwrap_or(0),
+                id: since,
+            }),
+            Err(_) => Err("Failed to get changes".to_string()),
+        }
+    }
+}
+
+/// Implementation of the SyncChange enum
+impl SyncChange {
+    /// Push the changes made by a client function
+    /// Each change is applied on its own, a change made on an outdated version of a todo is reported as a conflict
+    /// along with the todo as it is on the server
+    /// # Arguments
+    /// * `user` - Id of the user making the changes
+    /// * `data` - SyncPushDTO struct containing the changes
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<(String, Vec<SyncResult>), String>` - Result containing a summary and the result of each change, or an error message if nothing was attempted
+    pub fn push(
+        user: i32,
+        data: SyncPushDTO,
+        conn: &mut PgConnection,
+    ) -> Result<(String, Vec<SyncResult>), String> {
+        if data.changes.is_empty() {
+            return Err("No change to push".to_string());
+        }
+
+        if data.changes.len() > MAX_OPERATIONS {
+            return Err(format!(
+                "A sync can push at most {} changes",
+                MAX_OPERATIONS
+            ));
+        }
+
+        let count = data.changes.len();
+        let results: Vec<SyncResult> = data
+            .changes
+            .into_iter()
+            .enumerate()
+            .map(|(index, change)| SyncChange::apply(index, user, change, conn))
+            .collect();
+
+        let applied = results
+            .iter()
+            .filter(|result| result.status == SyncStatus::Applied)
+            .count();
+        let conflicts = results
+            .iter()
+            .filter(|result| result.status == SyncStatus::Conflict)
+            .count();
+
+        Ok((
+            format!(
+                "Applied {} of {} changes, {} conflicts",
+                applied, count, conflicts
+            ),
+            results,
+        ))
+    }
+
+    /// Internal function to apply one change
+    fn apply(index: usize, user: i32, change: SyncChange, conn: &mut PgConnection) -> SyncResult {
+        match change {
+            SyncChange::Create { client_id, todo } => match Todo::create_for(user, todo, conn) {
+                Ok(created) => SyncResult {
+                    index,
+                    client_id: Some(client_id),
+                    id: Some(created.id),
+                    status: SyncStatus::Applied,
+                    message: "Successfully created todo".to_string(),
+                    todo: Some(created),
+                },
+                Err(message) => SyncResult {
+                    index,
+                    client_id: Some(client_id),
+                    id: None,
+                    status: SyncStatus::Rejected,
+                    message,
+                    todo: None,
+                },
+            },
+            SyncChange::Update {
+                id,
+                base_version,
+                patch,
+            } => {
+                let options = UpdateOptions {
+                    versions: base_version.map(|version| vec![version]),
+                    ..Default::default()
+                };
+
+                match Todo::patch_todo(id, user, patch, options, conn) {
+                    Ok(updated) => SyncResult {
+                        index,
+                        client_id: None,
+                        id: Some(id),
+                        status: SyncStatus::Applied,
+                        message: "Successfully updated todo".to_string(),
+                        todo: Some(updated),
+                    },
+                    Err(message) => SyncChange::failure(index, id, user, message, conn),
+                }
+            }
+            SyncChange::Delete {
+                id,
+                base_version,
+                children,
+            } => {
+                let mut failure = None;
+
+                let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+                    todos::table
+                        .find(id)
+                        .for_update()
+                        .first::<Todo>(conn)
+                        .optional()?;
+
+                    // A todo the user cannot write is treated as gone, so its version is not revealed
+                    let current = Todo::find_writable(id, user, conn).ok();
+
+                    if let (Some(current), Some(expected)) = (&current, base_version) {
+                        if current.version != expected {
+                            failure = Some(STALE_VERSION.to_string());
+                            return Err(diesel::result::Error::RollbackTransaction);
+                        }
+                    }
+
+                    // Deleting a todo that is gone already leaves the client where it wants to be
+                    if current.is_none() {
+                        return Ok(());
+                    }
+
+                    Todo::remove_todo(id, user, children, conn).map_err(|message| {
+                        failure = Some(message);
+                        diesel::result::Error::RollbackTransaction
+                    })
+                });
+
+                match (result, failure) {
+                    (Ok(_), _) => SyncResult {
+                        index,
+                        client_id: None,
+                        id: Some(id),
+                        status: SyncStatus::Applied,
+                        message: "Successfully deleted todo".to_string(),
+                        todo: None,
+                    },
+                    (Err(_), Some(message)) => SyncChange::failure(index, id, user, message, conn),
+                    (Err(_), None) => SyncChange::failure(
+                        index,
+                        id,
+                        user,
+                        "Failed to delete todo".to_string(),
+                        conn,
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Internal function to report a change of a todo that failed
+    /// The change conflicts if the todo changed since the client read it or cannot be read anymore
+    fn failure(
+        index: usize,
+        id: i32,
+        user: i32,
+        message: String,
+        conn: &mut PgConnection,
+    ) -> SyncResult {
+        let current = Todo::find_readable(id, user, conn).ok();
+
+        let status = if message == STALE_VERSION || current.is_none() {
+            SyncStatus::Conflict
+        } else {
+            SyncStatus::Rejected
+        };
+
+        let message = match current {
+            Some(_) => message,
+            None => "Todo was deleted".to_string(),
+        };
+
+        SyncResult {
+            index,
+            client_id: None,
+            id: Some(id),
+            status,
+            message,
+            todo: current,
+        }
+    }
+}
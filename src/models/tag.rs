@@ -0,0 +1,407 @@
+use std::collections::{HashMap, HashSet};
+
+use diesel::pg::Pg;
+use diesel::sql_types::Integer;
+use diesel::{
+    prelude::*, sql_function, AsChangeset, Identifiable, Insertable, PgConnection, Queryable,
+    RunQueryDsl,
+};
+use rocket::FromFormField;
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::models::todos::Todo;
+use crate::schema::tags::{self, dsl::*};
+use crate::schema::{todo_tags, todos};
+
+sql_function!(fn lower(x: diesel::sql_types::Text) -> diesel::sql_types::Text);
+
+/// Tag struct representing a row in the tags table in the database
+/// Tag names are unique per user, ignoring case
+#[derive(Identifiable, Queryable, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    /// Unique id of the tag
+    /// This is the primary key of the tags table
+    /// This is auto generated by the database
+    pub id: i32,
+    /// Id of the user who created the tag
+    pub user_id: i32,
+    /// Name of the tag
+    pub name: String,
+}
+
+/// TagDTO struct representing the data to be sent to the database to create or rename a tag
+#[derive(Insertable, AsChangeset, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "tags"]
+pub struct TagDTO {
+    /// Id of the user who created the tag
+    /// The user_id is gotten from the token
+    pub user_id: Option<i32>,
+    /// Name of the tag
+    /// Name is required
+    pub name: Option<String>,
+}
+
+/// Internal struct used to attach a tag to a todo
+#[derive(Insertable, Debug)]
+#[table_name = "todo_tags"]
+struct TodoTag {
+    todo_id: i32,
+    tag_id: i32,
+}
+
+/// How todos are matched when filtering by several tags
+#[derive(FromFormField, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMode {
+    /// Todos having at least one of the tags
+    Any,
+    /// Todos having every one of the tags
+    All,
+}
+
+/// Implementation of the Tag struct
+impl Tag {
+    /// Create a new tag function
+    /// # Arguments
+    /// * `data` - TagDTO struct containing the data to be sent to the database
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Tag, String>` - Result containing the created tag or an error message
+    pub fn new_tag(data: TagDTO, conn: &mut PgConnection) -> Result<Tag, String> {
+        let owner = match data.user_id {
+            Some(owner) => owner,
+            None => return Err("User is required".to_string()),
+        };
+        let tag_name = Tag::validate_name(data.name.clone())?;
+
+        Tag::validate_unique(owner, &tag_name, None, conn)?;
+
+        let result = diesel::insert_into(tags)
+            .values(&TagDTO {
+                user_id: Some(owner),
+                name: Some(tag_name),
+            })
+            .get_result(conn);
+
+        match result {
+            Ok(tag) => Ok(tag),
+            Err(_) => Err("Failed to create tag".to_string()),
+        }
+    }
+
+    /// Gets all tags of a user function
+    /// # Arguments
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<Tag>, String>` - Result containing a vector of tags or an error message
+    pub fn get_tags(user: i32, conn: &mut PgConnection) -> Result<Vec<Tag>, String> {
+        let result = tags
+            .filter(user_id.eq(user))
+            .order(lower(name).asc())
+            .load(conn);
+
+        match result {
+            Ok(data) => Ok(data),
+            Err(_) => Err("Failed to get tags".to_string()),
+        }
+    }
+
+    /// Rename a tag function
+    /// # Arguments
+    /// * `tag_id` - Id of the tag to be renamed
+    /// * `user` - Id of the user renaming the tag
+    /// * `data` - TagDTO struct containing the new name
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Tag, String>` - Result containing the renamed tag or an error message
+    pub fn update_tag(
+        tag_id: i32,
+        user: i32,
+        data: TagDTO,
+        conn: &mut PgConnection,
+    ) -> Result<Tag, String> {
+        Tag::find_owned(tag_id, user, conn)?;
+        let tag_name = Tag::validate_name(data.name)?;
+
+        Tag::validate_unique(user, &tag_name, Some(tag_id), conn)?;
+
+        let result = diesel::update(tags.find(tag_id))
+            .set(name.eq(tag_name))
+            .get_result(conn);
+
+        match result {
+            Ok(tag) => Ok(tag),
+            Err(_) => Err("Failed to update tag".to_string()),
+        }
+    }
+
+    /// Delete a tag function
+    /// The tag is detached from every todo
+    /// # Arguments
+    /// * `tag_id` - Id of the tag to be deleted
+    /// * `user` - Id of the user deleting the tag
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn delete_tag(tag_id: i32, user: i32, conn: &mut PgConnection) -> Result<String, String> {
+        Tag::find_owned(tag_id, user, conn)?;
+
+        let result = diesel::delete(tags.find(tag_id)).execute(conn);
+
+        match result {
+            Ok(_) => Ok("Successfully deleted tag".to_string()),
+            Err(_) => Err("Failed to delete tag".to_string()),
+        }
+    }
+
+    /// Attach a tag to a todo function
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `tag_id` - Id of the tag
+    /// * `user` - Id of the user attaching the tag
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn attach(
+        todo: i32,
+        tag_id: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<String, String> {
+        Todo::find_writable(todo, user, conn)?;
+        Tag::find_owned(tag_id, user, conn)?;
+
+        let result = diesel::insert_into(todo_tags::table)
+            .values(&TodoTag {
+                todo_id: todo,
+                tag_id,
+            })
+            .on_conflict_do_nothing()
+            .execute(conn);
+
+        match result {
+            Ok(_) => Ok("Successfully attached tag".to_string()),
+            Err(_) => Err("Failed to attach tag".to_string()),
+        }
+    }
+
+    /// Detach a tag from a todo function
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `tag_id` - Id of the tag
+    /// * `user` - Id of the user detaching the tag
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn detach(
+        todo: i32,
+        tag_id: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<String, String> {
+        Todo::find_writable(todo, user, conn)?;
+
+        // Only the tags of the user are detached, the tags other members attached to a shared todo stay
+        let owned = tags
+            .filter(user_id.eq(user))
+            .filter(id.eq(tag_id))
+            .select(id);
+
+        let result = diesel::delete(todo_tags::table)
+            .filter(todo_tags::todo_id.eq(todo))
+            .filter(todo_tags::tag_id.eq_any(owned))
+            .execute(conn);
+
+        match result {
+            Ok(0) => Err("Tag is not attached to this todo".to_string()),
+            Ok(_) => Ok("Successfully detached tag".to_string()),
+            Err(_) => Err("Failed to detach tag".to_string()),
+        }
+    }
+
+    /// Copy the tags of a todo to another todo function
+    /// # Arguments
+    /// * `from` - Id of the todo the tags are copied from
+    /// * `to` - Id of the todo the tags are copied to
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<usize>` - Number of tags copied, used inside transactions
+    pub fn copy_tags(from: i32, to: i32, conn: &mut PgConnection) -> QueryResult<usize> {
+        let tag_ids = todo_tags::table
+            .filter(todo_tags::todo_id.eq(from))
+            .select(todo_tags::tag_id)
+            .load::<i32>(conn)?;
+
+        let rows: Vec<TodoTag> = tag_ids
+            .into_iter()
+            .map(|tag_id| TodoTag { todo_id: to, tag_id })
+            .collect();
+
+        diesel::insert_into(todo_tags::table)
+            .values(&rows)
+            .on_conflict_do_nothing()
+            .execute(conn)
+    }
+
+    /// Gets the tags of a user attached to several todos function
+    /// Tags of other users attached to shared todos are left out
+    /// # Arguments
+    /// * `todo_ids` - Ids of the todos
+    /// * `user` - Id of the user whose tags are returned
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<HashMap<i32, Vec<Tag>>, String>` - Result containing the tags of each todo or an error message
+    pub fn get_todos_tags(
+        todo_ids: &[i32],
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<HashMap<i32, Vec<Tag>>, String> {
+        let result = todo_tags::table
+            .inner_join(tags)
+            .filter(todo_tags::todo_id.eq_any(todo_ids))
+            .filter(tags::user_id.eq(user))
+            .select((todo_tags::todo_id, tags::all_columns))
+            .order(lower(name).asc())
+            .load::<(i32, Tag)>(conn);
+
+        let rows = match result {
+            Ok(rows) => rows,
+            Err(_) => return Err("Failed to get tags".to_string()),
+        };
+
+        let mut todos_tags: HashMap<i32, Vec<Tag>> = HashMap::new();
+
+        for (todo, tag) in rows {
+            todos_tags.entry(todo).or_default().push(tag);
+        }
+
+        Ok(todos_tags)
+    }
+
+    /// Filter a todos query by tag names function
+    /// Only the tags of the user are matched, tag names are compared ignoring case
+    /// # Arguments
+    /// * `query` - Boxed query on the todos table
+    /// * `user` - Id of the user whose tags are matched
+    /// * `tag_names` - Names of the tags
+    /// * `mode` - Whether todos need any or all of the tags
+    /// # Returns
+    /// * `todos::BoxedQuery<Pg>` - The query keeping only the matching todos
+    pub fn filter_tagged<'a>(
+        query: todos::BoxedQuery<'a, Pg>,
+        user: i32,
+        tag_names: &[String],
+        mode: TagMode,
+    ) -> todos::BoxedQuery<'a, Pg> {
+        let wanted: Vec<String> = tag_names
+            .iter()
+            .map(|tag| tag.to_lowercase())
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect();
+
+        match mode {
+            TagMode::Any => query.filter(todos::id.eq_any(Tag::tagged_todos(user, wanted))),
+            // Tag names are unique per user, so a todo has all the tags once it has each of them
+            TagMode::All => wanted.into_iter().fold(query, |query, tag_name| {
+                query.filter(todos::id.eq_any(Tag::tagged_todos(user, vec![tag_name])))
+            }),
+        }
+    }
+
+    /// Internal function to select the todos tagged with any of the given tags of a user
+    /// # Arguments
+    /// * `user` - Id of the user whose tags are matched
+    /// * `tag_names` - Lowercase names of the tags
+    /// # Returns
+    /// * `todo_tags::BoxedQuery<Pg, Integer>` - Subquery selecting the ids of the tagged todos
+    fn tagged_todos<'a>(
+        user: i32,
+        tag_names: Vec<String>,
+    ) -> todo_tags::BoxedQuery<'a, Pg, Integer> {
+        let user_tags = tags
+            .filter(user_id.eq(user))
+            .filter(lower(name).eq_any(tag_names))
+            .select(id);
+
+        todo_tags::table
+            .filter(todo_tags::tag_id.eq_any(user_tags))
+            .select(todo_tags::todo_id)
+            .into_boxed()
+    }
+
+    /// Internal function to find a tag owned by the user
+    /// # Arguments
+    /// * `tag_id` - Id of the tag
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Tag, String>` - Result containing the tag or an error message
+    fn find_owned(tag_id: i32, user: i32, conn: &mut PgConnection) -> Result<Tag, String> {
+        let result = tags
+            .filter(id.eq(tag_id))
+            .filter(user_id.eq(user))
+            .first(conn);
+
+        match result {
+            Ok(tag) => Ok(tag),
+            Err(_) => Err("Tag not found".to_string()),
+        }
+    }
+
+    /// Internal function to validate and normalize a tag name
+    /// # Arguments
+    /// * `tag_name` - Name of the tag
+    /// # Returns
+    /// * `Result<String, String>` - Result containing the trimmed name or an error message
+    fn validate_name(tag_name: Option<String>) -> Result<String, String> {
+        let tag_name = match tag_name {
+            Some(tag_name) => tag_name.trim().to_string(),
+            None => return Err("Name is required".to_string()),
+        };
+
+        if tag_name.is_empty() {
+            return Err("Name is required".to_string());
+        }
+
+        if tag_name.chars().count() > 64 {
+            return Err("Name must be at most 64 characters".to_string());
+        }
+
+        Ok(tag_name)
+    }
+
+    /// Internal function to validate that no other tag of the user has the same name, ignoring case
+    /// # Arguments
+    /// * `user` - Id of the user
+    /// * `tag_name` - Name of the tag
+    /// * `exclude` - Id of a tag to ignore, used when renaming a tag
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<(), String>` - Result containing nothing or an error message
+    fn validate_unique(
+        user: i32,
+        tag_name: &str,
+        exclude: Option<i32>,
+        conn: &mut PgConnection,
+    ) -> Result<(), String> {
+        let result = tags
+            .filter(user_id.eq(user))
+            .filter(lower(name).eq(tag_name.to_lowercase()))
+            .filter(id.ne(exclude.unwrap_or(0)))
+            .select(id)
+            .first::<i32>(conn)
+            .optional();
+
+        match result {
+            Ok(Some(_)) => Err("Tag already exists".to_string()),
+            Ok(None) => Ok(()),
+            Err(_) => Err("Failed to validate tag".to_string()),
+        }
+    }
+}
@@ -1,12 +1,35 @@
-use chrono::NaiveDateTime;
+use chrono::{Datelike, Duration, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::query_builder::QueryFragment;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::{Array, Integer, Text};
 use diesel::{
     prelude::*, AsChangeset, Identifiable, Insertable, PgConnection, Queryable, RunQueryDsl,
 };
+use rocket::FromFormField;
 use rocket_okapi::okapi::schemars;
 use rocket_okapi::okapi::schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
+use crate::models::attachment::Attachment;
+use crate::models::dependency::Dependency;
+use crate::models::event::Event;
+use crate::models::list::List;
+use crate::models::organization::{Membership, Role};
+use crate::models::reminder::Reminder;
+use crate::models::status::Status;
+use crate::models::tag::{Tag, TagMode};
+use crate::models::watcher::Watcher;
+use crate::schema::memberships;
 use crate::schema::todos::{self, dsl::*};
+use crate::utils::rank;
+use crate::utils::rrule::RecurrenceRule;
+
+/// Error message of an update rejected because the todo changed since the version it was based on
+pub static STALE_VERSION: &str = "Todo was changed since it was read";
 
 /// Todo struct representing a row in the todos table in the database
 #[derive(Identifiable, Queryable, Serialize, Deserialize, Debug, JsonSchema)]
@@ -24,14 +47,142 @@ pub struct Todo {
     pub description: String,
     /// Whether the todo is completed or not
     pub completed: bool,
-    // /// Time the todo was created
-    // /// This is auto generated by the database
-    // /// This is the time the todo was created in UTC
-    // pub created_at: NaiveDateTime,
-    // /// Time the todo was updated
-    // /// This is auto generated by the database
-    // /// This is the time the todo was updated in UTC
-    // pub updated_at: NaiveDateTime,
+    /// Id of the organization the todo is shared with
+    /// If empty, the todo is only visible to its creator
+    pub organization_id: Option<i32>,
+    /// Id of the list the todo belongs to
+    pub list_id: Option<i32>,
+    /// Time the todo was created
+    /// This is auto generated by the database
+    /// This is the time the todo was created in UTC
+    pub created_at: NaiveDateTime,
+    /// Time the todo was updated
+    /// This is auto generated by the database
+    /// This is the time the todo was updated in UTC
+    pub updated_at: NaiveDateTime,
+    /// Time the todo is due in UTC
+    pub due_at: Option<NaiveDateTime>,
+    /// Time the todo was completed in UTC
+    /// This is set when the todo is marked as completed
+    pub completed_at: Option<NaiveDateTime>,
+    /// Priority of the todo
+    pub priority: Priority,
+    /// Recurrence rule of the todo following RFC 5545, e.g. `FREQ=WEEKLY;BYDAY=MO`
+    /// Completing a recurring todo creates its next occurrence
+    pub recurrence_rule: Option<String>,
+    /// Time zone the recurrence rule is evaluated in, e.g. `Europe/Lisbon`
+    /// If empty, the rule is evaluated in UTC
+    pub recurrence_tz: Option<String>,
+    /// Id of the first todo of the series this todo is an occurrence of
+    pub series_id: Option<i32>,
+    /// Position of the todo in its series, starting at 1
+    pub occurrence: i32,
+    /// Id of the parent todo when the todo is a subtask
+    pub parent_id: Option<i32>,
+    /// Rank of the todo in the manual order, todos are sorted by comparing ranks as strings
+    pub position: String,
+    /// Id of the workflow status of the todo, only set for todos in a list with statuses
+    /// The completed field is derived from the status
+    pub status_id: Option<i32>,
+    /// Id of the user the todo is assigned to
+    pub assignee_id: Option<i32>,
+    /// Version of the todo, incremented on every change
+    /// Sent as the ETag of the todo, updates must send it back in the If-Match header
+    pub version: i32,
+}
+
+/// Priority of a todo
+/// Priorities are ordered from the lowest to the highest one
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    AsExpression,
+    FromSqlRow,
+    JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[diesel(sql_type = Text)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+    Urgent,
+}
+
+impl Priority {
+    /// Returns the value stored in the database for the priority
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+            Priority::Urgent => "urgent",
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for Priority {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        <str as ToSql<Text, Pg>>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for Priority {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match <String as FromSql<Text, Pg>>::from_sql(bytes)?.as_str() {
+            "low" => Ok(Priority::Low),
+            "normal" => Ok(Priority::Normal),
+            "high" => Ok(Priority::High),
+            "urgent" => Ok(Priority::Urgent),
+            other => Err(format!("Unknown priority {}", other).into()),
+        }
+    }
+}
+
+/// Due date windows todos can be filtered by
+/// Days and weeks are computed in UTC, weeks start on Monday
+#[derive(FromFormField, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DueFilter {
+    /// Todos not completed whose due date has passed
+    Overdue,
+    /// Todos due today
+    Today,
+    /// Todos due this week
+    Week,
+}
+
+/// TodoWithTags struct representing a todo along with the tags attached to it
+/// This is the shape todos are serialized with when listed
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TodoWithTags {
+    /// The todo itself, its fields are flattened into this struct
+    #[serde(flatten)]
+    pub todo: Todo,
+    /// Tags attached to the todo
+    pub tags: Vec<Tag>,
+}
+
+/// TodoFilter struct representing the filters that can be applied when listing todos
+#[derive(Debug, Default)]
+pub struct TodoFilter {
+    /// Only todos tagged with these tag names are returned, ignoring case
+    /// If empty, todos are not filtered by tag
+    pub tags: Vec<String>,
+    /// Whether todos need any or all of the tags, defaults to any
+    pub tag_mode: Option<TagMode>,
+    /// Only todos due in this window are returned
+    pub due: Option<DueFilter>,
+    /// Only todos assigned to this user are returned
+    pub assigned_to: Option<i32>,
 }
 
 /// TodoDTO struct representing the data to be sent to the database to create or update a new todo
@@ -54,6 +205,289 @@ pub struct TodoDTO {
     pub description: Option<String>,
     /// Whether the todo is completed or not
     pub completed: Option<bool>,
+    /// Id of the organization the todo is shared with
+    /// The user must be allowed to write todos in the organization
+    pub organization_id: Option<i32>,
+    /// Id of the list the todo belongs to
+    /// The list must be shared with the same organization as the todo
+    pub list_id: Option<i32>,
+    /// Time the todo is due in UTC
+    pub due_at: Option<NaiveDateTime>,
+    /// Time the todo was completed in UTC
+    /// The completed_at is set from the completed field
+    pub completed_at: Option<NaiveDateTime>,
+    /// Priority of the todo
+    /// If not provided when creating a todo, the priority is normal
+    pub priority: Option<Priority>,
+    /// Recurrence rule of the todo following RFC 5545, e.g. `FREQ=WEEKLY;BYDAY=MO`
+    /// A recurring todo must have a due date
+    pub recurrence_rule: Option<String>,
+    /// Time zone the recurrence rule is evaluated in, e.g. `Europe/Lisbon`
+    pub recurrence_tz: Option<String>,
+    /// Id of the first todo of the series
+    /// The series_id is set by the server when creating the next occurrence
+    pub series_id: Option<i32>,
+    /// Position of the todo in its series
+    /// The occurrence is set by the server when creating the next occurrence
+    pub occurrence: Option<i32>,
+    /// Id of the parent todo, making the todo a subtask
+    /// The parent must be shared with the same organization as the todo
+    pub parent_id: Option<i32>,
+    /// Rank of the todo in the manual order
+    /// The position is set by the server, new todos are placed last
+    pub position: Option<String>,
+    /// Id of the workflow status of the todo
+    /// The status must belong to the list of the todo and be reachable from the current status
+    /// If not provided, completing or reopening a todo moves it to the first matching status of its list
+    pub status_id: Option<i32>,
+}
+
+/// TodoPatch struct representing a JSON Merge Patch (RFC 7396) of a todo
+/// A missing field is left unchanged, a field set to null is cleared
+#[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TodoPatch {
+    /// Title of the todo, cannot be null
+    #[serde(
+        default,
+        deserialize_with = "patch_field",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub title: Option<Option<String>>,
+    /// Description of the todo, cannot be null
+    #[serde(
+        default,
+        deserialize_with = "patch_field",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub description: Option<Option<String>>,
+    /// Whether the todo is completed or not, cannot be null
+    #[serde(
+        default,
+        deserialize_with = "patch_field",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub completed: Option<Option<bool>>,
+    /// Id of the organization the todo is shared with, null makes the todo private
+    #[serde(
+        default,
+        deserialize_with = "patch_field",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub organization_id: Option<Option<i32>>,
+    /// Id of the list the todo belongs to, null removes the todo from its list and status
+    #[serde(
+        default,
+        deserialize_with = "patch_field",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub list_id: Option<Option<i32>>,
+    /// Time the todo is due in UTC
+    #[serde(
+        default,
+        deserialize_with = "patch_field",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub due_at: Option<Option<NaiveDateTime>>,
+    /// Priority of the todo, cannot be null
+    #[serde(
+        default,
+        deserialize_with = "patch_field",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub priority: Option<Option<Priority>>,
+    /// Recurrence rule of the todo following RFC 5545, null stops the recurrence
+    #[serde(
+        default,
+        deserialize_with = "patch_field",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recurrence_rule: Option<Option<String>>,
+    /// Time zone the recurrence rule is evaluated in, null evaluates it in UTC
+    #[serde(
+        default,
+        deserialize_with = "patch_field",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub recurrence_tz: Option<Option<String>>,
+    /// Id of the parent todo, null makes the todo a top level todo
+    #[serde(
+        default,
+        deserialize_with = "patch_field",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub parent_id: Option<Option<i32>>,
+    /// Id of the workflow status of the todo
+    /// Can only be null for todos in a list without statuses
+    #[serde(
+        default,
+        deserialize_with = "patch_field",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub status_id: Option<Option<i32>>,
+}
+
+/// TodoReplace struct representing the full content of a todo, replacing the current one
+/// Missing nullable fields are cleared, fields set by the server such as the position are kept
+#[derive(Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TodoReplace {
+    /// Title of the todo
+    pub title: String,
+    /// Description of the todo, empty if not provided
+    #[serde(default)]
+    pub description: String,
+    /// Whether the todo is completed or not
+    /// If not provided, it is derived from the status, or false for todos without a status
+    pub completed: Option<bool>,
+    /// Id of the organization the todo is shared with
+    pub organization_id: Option<i32>,
+    /// Id of the list the todo belongs to
+    pub list_id: Option<i32>,
+    /// Time the todo is due in UTC
+    pub due_at: Option<NaiveDateTime>,
+    /// Priority of the todo, normal if not provided
+    pub priority: Option<Priority>,
+    /// Recurrence rule of the todo following RFC 5545
+    pub recurrence_rule: Option<String>,
+    /// Time zone the recurrence rule is evaluated in
+    pub recurrence_tz: Option<String>,
+    /// Id of the parent todo
+    pub parent_id: Option<i32>,
+    /// Id of the workflow status of the todo
+    /// If not provided, the status is derived from completed like with an update
+    pub status_id: Option<i32>,
+}
+
+/// ClearedFields struct representing the nullable columns an update sets to NULL
+/// Each cleared column holds `Some(None)`, the other ones are left unchanged
+#[derive(AsChangeset, Debug, Default)]
+#[table_name = "todos"]
+struct ClearedFields {
+    organization_id: Option<Option<i32>>,
+    list_id: Option<Option<i32>>,
+    due_at: Option<Option<NaiveDateTime>>,
+    recurrence_rule: Option<Option<String>>,
+    recurrence_tz: Option<Option<String>>,
+    parent_id: Option<Option<i32>>,
+    status_id: Option<Option<i32>>,
+}
+
+/// Internal struct identifying the todos a todo is ordered among
+/// Subtasks are ordered among the subtasks of their parent, other todos among the todos of their list,
+/// and todos outside of lists among the todos of their owner
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RankScope {
+    owner: i32,
+    list: Option<i32>,
+    parent: Option<i32>,
+}
+
+impl RankScope {
+    /// Internal function to get the scope of a todo
+    fn of(todo: &Todo) -> RankScope {
+        RankScope {
+            owner: todo.user_id,
+            list: todo.list_id,
+            parent: todo.parent_id,
+        }
+    }
+
+    /// Internal function to query the todos of the scope
+    fn todos(&self) -> todos::BoxedQuery<'static, Pg> {
+        match (self.parent, self.list) {
+            (Some(parent), _) => todos.filter(parent_id.eq(parent)).into_boxed(),
+            (None, Some(list)) => todos
+                .filter(list_id.eq(list))
+                .filter(parent_id.is_null())
+                .into_boxed(),
+            (None, None) => todos
+                .filter(user_id.eq(self.owner))
+                .filter(list_id.is_null())
+                .filter(parent_id.is_null())
+                .into_boxed(),
+        }
+    }
+}
+
+/// Internal function to deserialize a field of a merge patch, telling a null field from a missing one
+fn patch_field<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// MoveDTO struct representing where a todo is moved to in the manual order
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveDTO {
+    /// Id of the todo the moved todo is placed before
+    /// If only before is given, the todo is placed right before it
+    pub before: Option<i32>,
+    /// Id of the todo the moved todo is placed after
+    /// If only after is given, the todo is placed right after it
+    pub after: Option<i32>,
+}
+
+/// Progress struct representing how many subtasks of a todo are completed
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Progress {
+    /// Number of completed subtasks, at any nesting level
+    pub completed: usize,
+    /// Number of subtasks, at any nesting level
+    pub total: usize,
+    /// Human readable progress, e.g. `3/5 done`
+    pub summary: String,
+}
+
+/// TodoTree struct representing a todo along with its subtasks
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TodoTree {
+    /// The todo itself, its fields are flattened into this struct
+    #[serde(flatten)]
+    pub todo: TodoWithTags,
+    /// Progress of the subtasks of the todo
+    pub progress: Progress,
+    /// Direct subtasks of the todo, each with its own subtasks
+    /// Only filled when the children are requested
+    pub children: Vec<TodoTree>,
+}
+
+/// What happens to the subtasks of a todo when it is deleted
+#[derive(FromFormField, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChildrenAction {
+    /// Subtasks are deleted along with the todo, at any nesting level
+    Delete,
+    /// Direct subtasks take the place of the todo under its own parent
+    Promote,
+}
+
+/// UpdateOptions struct controlling how an update is applied to a todo
+#[derive(Debug, Default, Clone)]
+pub struct UpdateOptions {
+    /// Whether completing the todo also completes its subtasks, at any nesting level
+    pub cascade: bool,
+    /// Whether the todo can be completed while todos blocking it are still open
+    pub force: bool,
+    /// Versions the todo must still be at one of, the update is rejected if the todo changed since
+    pub versions: Option<Vec<i32>>,
+}
+
+/// Implementation of the Progress struct
+impl Progress {
+    /// Create a progress from the number of completed subtasks and the number of subtasks
+    pub fn new(completed_count: usize, total: usize) -> Progress {
+        Progress {
+            completed: completed_count,
+            total,
+            summary: format!("{}/{} done", completed_count, total),
+        }
+    }
 }
 
 /// Implementation of the Todo struct
@@ -63,61 +497,1484 @@ impl Todo {
     /// * `data` - TodoDTO struct containing the data to be sent to the database
     /// * `conn` - Mutable reference to the database connection
     /// # Returns
-    /// * `Result<String, String>` - Result containing a success message or an error message
-    ///
-    pub fn new_todo(data: TodoDTO, conn: &mut PgConnection) -> Result<String, String> {
+    /// * `Result<Todo, String>` - Result containing the created todo or an error message
+    pub fn create_todo(mut data: TodoDTO, conn: &mut PgConnection) -> Result<Todo, String> {
         let validation = Todo::validate_input_new_todo(data.clone());
 
         if validation.is_err() {
             return Err(validation.err().unwrap());
         }
 
-        let result = diesel::insert_into(todos).values(&data).execute(conn);
+        Todo::validate_recurrence(
+            data.recurrence_rule.as_deref(),
+            data.recurrence_tz.as_deref(),
+            data.due_at,
+        )?;
+
+        if let Some(user) = data.user_id {
+            if let Some(organization) = data.organization_id {
+                Todo::validate_organization(organization, user, conn)?;
+            }
+
+            if let Some(list) = data.list_id {
+                Todo::validate_list(list, user, data.organization_id, conn)?;
+            }
+
+            if let Some(parent) = data.parent_id {
+                Todo::validate_parent(parent, None, user, data.organization_id, conn)?;
+            }
+        }
+
+        match (data.list_id, data.status_id) {
+            (Some(list), Some(status)) => {
+                let status = Status::find_in_list(status, list, conn)?;
+                data.completed = Some(status.done);
+            }
+            (Some(list), None) => {
+                let is_done = data.completed.unwrap_or(false);
+
+                if let Some(status) = Status::find_for_completion(list, None, is_done, conn)? {
+                    data.status_id = Some(status.id);
+                }
+            }
+            (None, Some(_)) => return Err("Only todos in a list can have a status".to_string()),
+            (None, None) => {}
+        }
+
+        if data.completed == Some(true) {
+            data.completed_at = Some(Utc::now().naive_utc());
+        }
+
+        let owner = data.user_id.unwrap_or_default();
+        let scope = RankScope {
+            owner,
+            list: data.list_id,
+            parent: data.parent_id,
+        };
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            data.position = Some(Todo::next_rank(scope, None, None, None, owner, conn)?);
+
+            let created = diesel::insert_into(todos)
+                .values(&data)
+                .get_result::<Todo>(conn)?;
+
+            Event::record(created.user_id, None, Some(&created), conn)?;
+
+            Ok(created)
+        });
 
         match result {
-            Ok(_) => Ok("Successfully created todo".to_string()),
+            Ok(created) => Ok(created),
             Err(_) => Err("Failed to create todo".to_string()),
         }
     }
 
+    /// Create a todo from the fields a client can set, like `POST /todo`, function
+    /// # Arguments
+    /// * `user` - Id of the user creating the todo
+    /// * `todo` - TodoDTO struct containing the todo sent by the client
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Todo, String>` - Result containing the created todo or an error message
+    pub fn create_for(user: i32, todo: TodoDTO, conn: &mut PgConnection) -> Result<Todo, String> {
+        if todo.user_id.is_some()
+            || todo.completed.is_some()
+            || todo.completed_at.is_some()
+            || todo.position.is_some()
+        {
+            return Err("userId, completed, completedAt and position cannot be set".to_string());
+        }
+
+        if todo.title.is_none() || todo.description.is_none() {
+            return Err("title and description are required".to_string());
+        }
+
+        let data = TodoDTO {
+            user_id: Some(user),
+            completed: Some(false),
+            series_id: None,
+            occurrence: None,
+            ..todo
+        };
+
+        Todo::create_todo(data, conn)
+    }
+
     /// Update a todo function
+    /// The user must own the todo or be allowed to write todos in its organization
+    /// Completing a recurring todo creates its next occurrence
     /// # Arguments
     /// * `todo_id` - Id of the todo to be updated
+    /// * `user` - Id of the user updating the todo
     /// * `data` - TodoDTO struct containing the data to be sent to the database
+    /// * `options` - UpdateOptions struct controlling how the update is applied
     /// * `conn` - Mutable reference to the database connection
     /// # Returns
-    /// * `Result<String, String>` - Result containing a success message or an error message
+    /// * `Result<Todo, String>` - Result containing the updated todo or an error message
     pub fn update_todo(
         todo_id: i32,
+        user: i32,
         data: TodoDTO,
+        options: UpdateOptions,
         conn: &mut PgConnection,
-    ) -> Result<String, String> {
-        let result = diesel::update(todos)
-            .set(&data)
-            .filter(id.eq(todo_id))
-            .execute(conn);
+    ) -> Result<Todo, String> {
+        Todo::apply_update(todo_id, user, data, ClearedFields::default(), options, conn)
+    }
+
+    /// Patch a todo function
+    /// The patch follows JSON Merge Patch, a field set to null clears the field
+    /// # Arguments
+    /// * `todo_id` - Id of the todo to be patched
+    /// * `user` - Id of the user patching the todo
+    /// * `patch` - TodoPatch struct containing the fields to change
+    /// * `options` - UpdateOptions struct controlling how the update is applied
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Todo, String>` - Result containing the updated todo or an error message
+    pub fn patch_todo(
+        todo_id: i32,
+        user: i32,
+        patch: TodoPatch,
+        options: UpdateOptions,
+        conn: &mut PgConnection,
+    ) -> Result<Todo, String> {
+        let (data, cleared) = Todo::split_patch(patch)?;
+
+        Todo::apply_update(todo_id, user, data, cleared, options, conn)
+    }
+
+    /// Replace a todo function
+    /// Every field the client can set is replaced, missing nullable fields are cleared
+    /// # Arguments
+    /// * `todo_id` - Id of the todo to be replaced
+    /// * `user` - Id of the user replacing the todo
+    /// * `data` - TodoReplace struct containing the new content of the todo
+    /// * `options` - UpdateOptions struct controlling how the update is applied
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Todo, String>` - Result containing the updated todo or an error message
+    pub fn replace_todo(
+        todo_id: i32,
+        user: i32,
+        data: TodoReplace,
+        options: UpdateOptions,
+        conn: &mut PgConnection,
+    ) -> Result<Todo, String> {
+        // Without a status, completed is required like any other field, otherwise it is derived
+        let is_completed = match data.status_id {
+            Some(_) => data.completed.map(Some),
+            None => Some(Some(data.completed.unwrap_or(false))),
+        };
+
+        let patch = TodoPatch {
+            title: Some(Some(data.title)),
+            description: Some(Some(data.description)),
+            completed: is_completed,
+            organization_id: Some(data.organization_id),
+            list_id: Some(data.list_id),
+            due_at: Some(data.due_at),
+            priority: Some(Some(data.priority.unwrap_or(Priority::Normal))),
+            recurrence_rule: Some(data.recurrence_rule),
+            recurrence_tz: Some(data.recurrence_tz),
+            parent_id: Some(data.parent_id),
+            status_id: data.status_id.map(Some),
+        };
+
+        Todo::patch_todo(todo_id, user, patch, options, conn)
+    }
+
+    /// Internal function to update a todo, setting the fields present in data and clearing the cleared ones
+    fn apply_update(
+        todo_id: i32,
+        user: i32,
+        mut data: TodoDTO,
+        mut cleared: ClearedFields,
+        options: UpdateOptions,
+        conn: &mut PgConnection,
+    ) -> Result<Todo, String> {
+        let todo = Todo::find_writable(todo_id, user, conn)?;
+        let cascade = options.cascade;
+
+        if let Some(organization) = data.organization_id {
+            Todo::validate_organization(organization, user, conn)?;
+        }
+
+        // Fields as they are after the update, checked again when the organization changes
+        let todo_organization = Todo::merged(
+            data.organization_id,
+            &cleared.organization_id,
+            todo.organization_id,
+        );
+        let todo_list = Todo::merged(data.list_id, &cleared.list_id, todo.list_id);
+        let todo_parent = Todo::merged(data.parent_id, &cleared.parent_id, todo.parent_id);
+        let organization_changed =
+            data.organization_id.is_some() || cleared.organization_id.is_some();
+
+        if data.list_id.is_some() || organization_changed {
+            if let Some(list) = todo_list {
+                Todo::validate_list(list, user, todo_organization, conn)?;
+            }
+        }
+
+        Todo::validate_recurrence(
+            Todo::merged(
+                data.recurrence_rule.clone(),
+                &cleared.recurrence_rule,
+                todo.recurrence_rule.clone(),
+            )
+            .as_deref(),
+            Todo::merged(
+                data.recurrence_tz.clone(),
+                &cleared.recurrence_tz,
+                todo.recurrence_tz.clone(),
+            )
+            .as_deref(),
+            Todo::merged(data.due_at, &cleared.due_at, todo.due_at),
+        )?;
+
+        if data.parent_id.is_some() || organization_changed {
+            if let Some(parent) = todo_parent {
+                Todo::validate_parent(parent, Some(todo_id), user, todo_organization, conn)?;
+            }
+        }
+
+        let clear_status = if cleared.list_id.is_some() {
+            if data.status_id.is_some() {
+                return Err("Only todos in a list can have a status".to_string());
+            }
+
+            true
+        } else if cleared.status_id.is_some() {
+            if let Some(list) = todo_list {
+                let is_done = data.completed.unwrap_or(todo.completed);
+
+                if Status::find_for_completion(list, None, is_done, conn)?.is_some() {
+                    return Err("Todos in a list with statuses must have a status".to_string());
+                }
+            }
+
+            true
+        } else {
+            Todo::resolve_status(&todo, &mut data, conn)?
+        };
+
+        if clear_status {
+            cleared.status_id = Some(None);
+        }
+
+        if data.completed == Some(true) && !options.force {
+            Todo::validate_blockers(todo_id, cascade, conn)?;
+        }
+
+        // Set from the locked row below
+        data.completed_at = None;
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let before = todos.find(todo_id).for_update().first::<Todo>(conn)?;
+
+            if options
+                .versions
+                .as_ref()
+                .is_some_and(|expected| !expected.contains(&before.version))
+            {
+                return Ok(None);
+            }
+
+            // Decided on the locked row, so concurrent completions complete the todo once
+            let completing = data.completed == Some(true) && !before.completed;
+
+            let changes = (&data, &cleared);
+
+            // A patch that only clears fields sets nothing from data, and an empty patch nothing at all
+            if !changes.as_changeset().is_noop(&Pg)? {
+                diesel::update(todos)
+                    .set(changes)
+                    .filter(id.eq(todo_id))
+                    .execute(conn)?;
+            }
+
+            if completing {
+                diesel::update(todos.find(todo_id))
+                    .set(completed_at.eq(Some(Utc::now().naive_utc())))
+                    .execute(conn)?;
+            }
+
+            Todo::place_in_scope(&before, user, conn)?;
+
+            if data.completed == Some(false) {
+                diesel::update(todos.find(todo_id))
+                    .set(completed_at.eq(None::<NaiveDateTime>))
+                    .execute(conn)?;
+            }
+
+            let updated = todos.find(todo_id).first::<Todo>(conn)?;
+            Event::record(user, Some(&before), Some(&updated), conn)?;
+
+            let start = if completing {
+                updated.series_start(conn)?
+            } else {
+                None
+            };
+
+            if let Some(mut next) = start.and_then(|start| updated.next_occurrence(start)) {
+                next.position = Some(Todo::next_rank(
+                    RankScope::of(&updated),
+                    None,
+                    Some(todo_id),
+                    None,
+                    user,
+                    conn,
+                )?);
+
+                if let Some(list) = next.list_id {
+                    next.status_id =
+                        Status::first_with_done(list, false, conn)?.map(|status| status.id);
+                }
+
+                // Completing the todo again after reopening it finds its next occurrence already there
+                let next_todo = diesel::insert_into(todos)
+                    .values(&next)
+                    .on_conflict((series_id, occurrence))
+                    .do_nothing()
+                    .get_result::<Todo>(conn)
+                    .optional()?;
+
+                if let Some(next_todo) = next_todo {
+                    Tag::copy_tags(todo_id, next_todo.id, conn)?;
+                    Reminder::copy_reminders(todo_id, next_todo.id, conn)?;
+                    Event::record(user, None, Some(&next_todo), conn)?;
+                }
+            }
+
+            if data.completed == Some(true) && cascade {
+                let descendants = Todo::get_descendants(todo_id, conn)?;
+                let descendant_ids: Vec<i32> =
+                    descendants.iter().map(|descendant| descendant.id).collect();
+
+                diesel::update(todos)
+                    .filter(id.eq_any(&descendant_ids))
+                    .filter(completed.eq(false))
+                    .set((completed.eq(true), completed_at.eq(Utc::now().naive_utc())))
+                    .execute(conn)?;
+
+                // Subtasks in a workflow move to its first done status, whatever the transitions
+                for descendant in descendants
+                    .iter()
+                    .filter(|descendant| !descendant.completed)
+                {
+                    if let Some(list) = descendant.list_id {
+                        if let Some(status) = Status::first_with_done(list, true, conn)? {
+                            diesel::update(todos.find(descendant.id))
+                                .set(status_id.eq(status.id))
+                                .execute(conn)?;
+                        }
+                    }
+                }
+
+                let updated_descendants = todos
+                    .filter(id.eq_any(&descendant_ids))
+                    .load::<Todo>(conn)?;
+                Event::record_all(user, &descendants, &updated_descendants, conn)?;
+            }
+
+            // Read again, recording the change bumped the version
+            todos.find(todo_id).first::<Todo>(conn).map(Some)
+        });
 
         match result {
-            Ok(_) => Ok("Successfully updated todo".to_string()),
+            Ok(Some(updated)) => Ok(updated),
+            Ok(None) => Err(STALE_VERSION.to_string()),
             Err(_) => Err("Failed to update todo".to_string()),
         }
     }
 
+    /// Delete a todo function
+    /// A todo with subtasks can only be deleted if the action for its subtasks is given
+    /// The files attached to the deleted todos are removed from the blob store by a job
+    /// # Arguments
+    /// * `todo_id` - Id of the todo to be deleted
+    /// * `user` - Id of the user deleting the todo
+    /// * `children` - What happens to the subtasks of the todo, required if it has any
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn delete_todo(
+        todo_id: i32,
+        user: i32,
+        children: Option<ChildrenAction>,
+        conn: &mut PgConnection,
+    ) -> Result<String, String> {
+        Todo::remove_todo(todo_id, user, children, conn)?;
+
+        Ok("Successfully deleted todo".to_string())
+    }
+
+    /// Delete a todo without its success message function
+    /// The removal of the attached files is queued in the same transaction, so it only runs once the todo is gone for good
+    /// # Arguments
+    /// * `todo_id` - Id of the todo to be deleted
+    /// * `user` - Id of the user deleting the todo
+    /// * `children` - What happens to the subtasks of the todo, required if it has any
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<(), String>` - Result containing nothing or an error message
+    pub fn remove_todo(
+        todo_id: i32,
+        user: i32,
+        children: Option<ChildrenAction>,
+        conn: &mut PgConnection,
+    ) -> Result<(), String> {
+        let todo = Todo::find_writable(todo_id, user, conn)?;
+
+        let descendants = match Todo::get_descendants(todo_id, conn) {
+            Ok(descendants) => descendants,
+            Err(_) => return Err("Failed to delete todo".to_string()),
+        };
+
+        if !descendants.is_empty() && children.is_none() {
+            return Err("Todo has subtasks, children must be either delete or promote".to_string());
+        }
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let mut purged = vec![todo_id];
+
+            if let Some(ChildrenAction::Delete) = children {
+                purged.extend(descendants.iter().map(|descendant| descendant.id));
+            }
+
+            let keys = Attachment::storage_keys(&purged, conn)?;
+            Attachment::queue_purge(&keys, conn)?;
+
+            match children {
+                Some(ChildrenAction::Delete) => {
+                    // Deepest subtasks first, so no subtask outlives its parent
+                    for descendant in descendants.iter().rev() {
+                        let deleted =
+                            diesel::delete(todos.find(descendant.id)).get_result::<Todo>(conn)?;
+                        Event::record(user, Some(&deleted), None, conn)?;
+                    }
+                }
+                Some(ChildrenAction::Promote) => {
+                    let children: Vec<&Todo> = descendants
+                        .iter()
+                        .filter(|descendant| descendant.parent_id == Some(todo_id))
+                        .collect();
+
+                    // Promoted subtasks go after the todos of their new scope, keeping their order
+                    for child in &children {
+                        diesel::update(todos.find(child.id))
+                            .set(parent_id.eq(todo.parent_id))
+                            .execute(conn)?;
+                        Todo::place_in_scope(child, user, conn)?;
+                    }
+
+                    let promoted = todos
+                        .filter(id.eq_any(children.iter().map(|child| child.id)))
+                        .load::<Todo>(conn)?;
+                    Event::record_all(user, &descendants, &promoted, conn)?;
+                }
+                None => {}
+            }
+
+            let deleted = diesel::delete(todos.find(todo_id)).get_result::<Todo>(conn)?;
+            Event::record(user, Some(&deleted), None, conn)?;
+
+            Ok(())
+        });
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Failed to delete todo".to_string()),
+        }
+    }
+
+    /// Gets a todo along with the progress of its subtasks function
+    /// # Arguments
+    /// * `todo_id` - Id of the todo
+    /// * `user` - Id of the user requesting the todo
+    /// * `include_children` - Whether the subtasks are returned as a tree
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<TodoTree, String>` - Result containing the todo or an error message
+    pub fn get_todo(
+        todo_id: i32,
+        user: i32,
+        include_children: bool,
+        conn: &mut PgConnection,
+    ) -> Result<TodoTree, String> {
+        let todo = Todo::find_readable(todo_id, user, conn)?;
+
+        let descendants = match Todo::get_descendants(todo_id, conn) {
+            Ok(descendants) => descendants,
+            Err(_) => return Err("Failed to get todo".to_string()),
+        };
+
+        let mut nodes = Todo::with_tags(descendants, user, conn)?;
+        let root = match Todo::with_tags(vec![todo], user, conn)?.pop() {
+            Some(root) => root,
+            None => return Err("Todo not found".to_string()),
+        };
+
+        if !include_children {
+            let progress = Progress::new(
+                nodes.iter().filter(|node| node.todo.completed).count(),
+                nodes.len(),
+            );
+
+            return Ok(TodoTree {
+                todo: root,
+                progress,
+                children: vec![],
+            });
+        }
+
+        nodes.insert(0, root);
+
+        Ok(Todo::build_tree(
+            &mut nodes.into_iter().map(Some).collect(),
+            0,
+        ))
+    }
+
+    /// Gets the upcoming occurrences of a recurring todo function
+    /// # Arguments
+    /// * `todo_id` - Id of the todo
+    /// * `user` - Id of the user requesting the occurrences
+    /// * `count` - Maximum number of occurrences returned
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<NaiveDateTime>, String>` - Result containing the due dates of the upcoming occurrences in UTC or an error message
+    pub fn get_occurrences(
+        todo_id: i32,
+        user: i32,
+        count: usize,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<NaiveDateTime>, String> {
+        let todo = Todo::find_readable(todo_id, user, conn)?;
+
+        let (rule, tz) = match todo.recurrence()? {
+            Some(recurrence) => recurrence,
+            None => return Err("Todo is not recurring".to_string()),
+        };
+
+        let due = match todo.due_at {
+            Some(due) => due,
+            None => return Err("Recurring todo has no due date".to_string()),
+        };
+
+        let start = match todo.series_start(conn) {
+            Ok(start) => start.unwrap_or(due),
+            Err(_) => return Err("Failed to get occurrences".to_string()),
+        };
+
+        Ok(rule.occurrences_after(due, todo.occurrence as u32, start, tz, count))
+    }
+
+    /// Assign a todo to a user function
+    /// The assignee must be allowed to read the todo, and starts watching it
+    /// # Arguments
+    /// * `todo_id` - Id of the todo to be assigned
+    /// * `user` - Id of the user assigning the todo
+    /// * `assignee` - Id of the user the todo is assigned to
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn assign(
+        todo_id: i32,
+        user: i32,
+        assignee: i32,
+        conn: &mut PgConnection,
+    ) -> Result<String, String> {
+        let todo = Todo::find_writable(todo_id, user, conn)?;
+
+        if Todo::find_readable(todo_id, assignee, conn).is_err() {
+            return Err("Assignee cannot access this todo".to_string());
+        }
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let updated = diesel::update(todos.find(todo_id))
+                .set(assignee_id.eq(assignee))
+                .get_result::<Todo>(conn)?;
+
+            Event::record(user, Some(&todo), Some(&updated), conn)?;
+
+            Watcher::add(todo_id, assignee, conn)
+        });
+
+        match result {
+            Ok(_) => Ok("Successfully assigned todo".to_string()),
+            Err(_) => Err("Failed to assign todo".to_string()),
+        }
+    }
+
+    /// Unassign a todo function
+    /// # Arguments
+    /// * `todo_id` - Id of the todo to be unassigned
+    /// * `user` - Id of the user unassigning the todo
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn unassign(todo_id: i32, user: i32, conn: &mut PgConnection) -> Result<String, String> {
+        let todo = Todo::find_writable(todo_id, user, conn)?;
+
+        if todo.assignee_id.is_none() {
+            return Err("Todo is not assigned".to_string());
+        }
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let updated = diesel::update(todos.find(todo_id))
+                .set(assignee_id.eq(None::<i32>))
+                .get_result::<Todo>(conn)?;
+
+            Event::record(user, Some(&todo), Some(&updated), conn)
+        });
+
+        match result {
+            Ok(_) => Ok("Successfully unassigned todo".to_string()),
+            Err(_) => Err("Failed to unassign todo".to_string()),
+        }
+    }
+
+    /// Move a todo to a list function
+    /// # Arguments
+    /// * `todo_id` - Id of the todo to be moved
+    /// * `user` - Id of the user moving the todo
+    /// * `list` - Id of the list the todo is moved to, or None to remove the todo from its list
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn move_to_list(
+        todo_id: i32,
+        user: i32,
+        list: Option<i32>,
+        conn: &mut PgConnection,
+    ) -> Result<String, String> {
+        let todo = Todo::find_writable(todo_id, user, conn)?;
+
+        if let Some(list) = list {
+            Todo::validate_list(list, user, todo.organization_id, conn)?;
+        }
+
+        let status = match list {
+            Some(list) => Status::find_for_completion(list, None, todo.completed, conn)?,
+            None => None,
+        };
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            diesel::update(todos.find(todo_id))
+                .set((
+                    list_id.eq(list),
+                    status_id.eq(status.map(|status| status.id)),
+                ))
+                .execute(conn)?;
+            Todo::place_in_scope(&todo, user, conn)?;
+
+            let updated = todos.find(todo_id).first::<Todo>(conn)?;
+            Event::record(user, Some(&todo), Some(&updated), conn)
+        });
+
+        match result {
+            Ok(_) => Ok("Successfully moved todo".to_string()),
+            Err(_) => Err("Failed to move todo".to_string()),
+        }
+    }
+
+    /// Move a todo in the manual order function
+    /// Todos are ordered among the subtasks of the same parent, the todos of the same list, or the todos
+    /// of the same owner outside of lists, and can only be placed next to todos ordered among the same todos
+    /// Only the moved todo is updated, unless the ranks have to be rebalanced
+    /// # Arguments
+    /// * `todo_id` - Id of the todo to be moved
+    /// * `user` - Id of the user moving the todo
+    /// * `data` - MoveDTO struct containing the todos the todo is placed between
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn move_todo(
+        todo_id: i32,
+        user: i32,
+        data: MoveDTO,
+        conn: &mut PgConnection,
+    ) -> Result<String, String> {
+        let todo = Todo::find_writable(todo_id, user, conn)?;
+
+        if data.before.is_none() && data.after.is_none() {
+            return Err("Either before or after is required".to_string());
+        }
+
+        if data.before == Some(todo_id) || data.after == Some(todo_id) {
+            return Err("A todo cannot be moved next to itself".to_string());
+        }
+
+        let after_todo = match data.after {
+            Some(after) => Some(Todo::find_readable(after, user, conn)?),
+            None => None,
+        };
+
+        let before_todo = match data.before {
+            Some(before) => Some(Todo::find_readable(before, user, conn)?),
+            None => None,
+        };
+
+        let scope = RankScope::of(&todo);
+
+        for neighbour in after_todo.iter().chain(before_todo.iter()) {
+            if RankScope::of(neighbour) != scope {
+                return Err(
+                    "A todo can only be moved next to todos with the same parent, list and owner"
+                        .to_string(),
+                );
+            }
+        }
+
+        if let (Some(after_todo), Some(before_todo)) = (&after_todo, &before_todo) {
+            if after_todo.position > before_todo.position {
+                return Err("after must be ordered before before".to_string());
+            }
+        }
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let rank = Todo::next_rank(scope, Some(todo_id), data.after, data.before, user, conn)?;
+
+            let updated = diesel::update(todos.find(todo_id))
+                .set(position.eq(rank))
+                .get_result::<Todo>(conn)?;
+
+            Event::record(user, Some(&todo), Some(&updated), conn)
+        });
+
+        match result {
+            Ok(_) => Ok("Successfully moved todo".to_string()),
+            Err(_) => Err("Failed to move todo".to_string()),
+        }
+    }
+
     /// Gets all todos from the user function
+    /// This includes the todos shared with the organizations the user is a member of
     /// # Arguments
     /// * `user` - Id of the user to get todos from
+    /// * `filter` - TodoFilter struct containing the filters to apply
     /// * `conn` - Mutable reference to the database connection
     /// # Returns
-    /// * `Result<Vec<Todo>, String>` - Result containing a vector of todos or an error message
-    pub fn get_todos(user: i32, conn: &mut PgConnection) -> Result<Vec<Todo>, String> {
-        let result = todos.filter(user_id.eq(user)).load(conn);
+    /// * `Result<Vec<TodoWithTags>, String>` - Result containing a vector of todos or an error message
+    pub fn get_todos(
+        user: i32,
+        filter: &TodoFilter,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<TodoWithTags>, String> {
+        let user_organizations = memberships::table
+            .filter(memberships::user_id.eq(user))
+            .select(memberships::organization_id.nullable());
+
+        let mut query = todos
+            .filter(
+                user_id
+                    .eq(user)
+                    .or(organization_id.eq_any(user_organizations)),
+            )
+            .into_boxed();
+
+        if !filter.tags.is_empty() {
+            query = Tag::filter_tagged(
+                query,
+                user,
+                &filter.tags,
+                filter.tag_mode.unwrap_or(TagMode::Any),
+            );
+        }
+
+        if let Some(assignee) = filter.assigned_to {
+            query = query.filter(assignee_id.eq(assignee));
+        }
+
+        if let Some(due) = filter.due {
+            let now = Utc::now().naive_utc();
+            let start_of_today = now.date().and_hms_opt(0, 0, 0).unwrap_or(now);
+
+            query = match due {
+                DueFilter::Overdue => query.filter(due_at.lt(now)).filter(completed.eq(false)),
+                DueFilter::Today => query
+                    .filter(due_at.ge(start_of_today))
+                    .filter(due_at.lt(start_of_today + Duration::days(1))),
+                DueFilter::Week => {
+                    let days_since_monday = now.weekday().num_days_from_monday() as i64;
+                    let start_of_week = start_of_today - Duration::days(days_since_monday);
+
+                    query
+                        .filter(due_at.ge(start_of_week))
+                        .filter(due_at.lt(start_of_week + Duration::days(7)))
+                }
+            };
+        }
+
+        let result = query.order((position.asc(), id.asc())).load(conn);
 
         match result {
-            Ok(data) => Ok(data),
+            Ok(data) => Todo::with_tags(data, user, conn),
             Err(_) => Err("Failed to get todos".to_string()),
         }
     }
 
+    /// Attach the tags of the user to each todo function
+    /// # Arguments
+    /// * `data` - Vector of todos
+    /// * `user` - Id of the user whose tags are attached
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<TodoWithTags>, String>` - Result containing a vector of todos with their tags or an error message
+    pub fn with_tags(
+        data: Vec<Todo>,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<TodoWithTags>, String> {
+        let todo_ids: Vec<i32> = data.iter().map(|todo| todo.id).collect();
+        let mut todos_tags = Tag::get_todos_tags(&todo_ids, user, conn)?;
+
+        Ok(data
+            .into_iter()
+            .map(|todo| TodoWithTags {
+                tags: todos_tags.remove(&todo.id).unwrap_or_default(),
+                todo,
+            })
+            .collect())
+    }
+
+    /// Find a todo the user is allowed to read function
+    /// The user can read a todo if they created it or are a member of its organization
+    /// # Arguments
+    /// * `todo_id` - Id of the todo
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Todo, String>` - Result containing the todo or an error message
+    pub fn find_readable(todo_id: i32, user: i32, conn: &mut PgConnection) -> Result<Todo, String> {
+        let (todo, role) = Todo::find_with_role(todo_id, user, conn)?;
+
+        match role {
+            Some(_) => Ok(todo),
+            None => Err("Todo not found".to_string()),
+        }
+    }
+
+    /// Find a todo the user is allowed to update function
+    /// The user can update a todo if they created it or have at least the member role in its organization
+    /// # Arguments
+    /// * `todo_id` - Id of the todo
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Todo, String>` - Result containing the todo or an error message
+    pub fn find_writable(todo_id: i32, user: i32, conn: &mut PgConnection) -> Result<Todo, String> {
+        let (todo, role) = Todo::find_with_role(todo_id, user, conn)?;
+
+        match role {
+            Some(role) if role.can_write() => Ok(todo),
+            Some(_) => Err("Insufficient permissions".to_string()),
+            None => Err("Todo not found".to_string()),
+        }
+    }
+
+    /// Internal function to find a todo along with the role the user has on it
+    /// # Arguments
+    /// * `todo_id` - Id of the todo
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<(Todo, Option<Role>), String>` - Result containing the todo and the role, if any, or an error message
+    fn find_with_role(
+        todo_id: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<(Todo, Option<Role>), String> {
+        let todo = match todos.find(todo_id).first::<Todo>(conn) {
+            Ok(todo) => todo,
+            Err(_) => return Err("Todo not found".to_string()),
+        };
+
+        let role = Membership::resource_role(todo.user_id, todo.organization_id, user, conn)?;
+
+        Ok((todo, role))
+    }
+
+    /// Internal function to validate that a user can share todos with an organization
+    /// # Arguments
+    /// * `organization` - Id of the organization
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<(), String>` - Result containing nothing or an error message
+    fn validate_organization(
+        organization: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<(), String> {
+        Membership::require_role(organization, user, Role::Member, conn)?;
+
+        Ok(())
+    }
+
+    /// Internal function to parse the recurrence rule and time zone of a todo
+    /// # Returns
+    /// * `Result<Option<(RecurrenceRule, Tz)>, String>` - Result containing the rule and time zone, None if the todo is not recurring, or an error message
+    fn recurrence(&self) -> Result<Option<(RecurrenceRule, Tz)>, String> {
+        let rule = match self.recurrence_rule {
+            Some(ref rule) => rule.parse::<RecurrenceRule>()?,
+            None => return Ok(None),
+        };
+
+        let tz = match self.recurrence_tz {
+            Some(ref tz) => tz.parse::<Tz>()?,
+            None => Tz::UTC,
+        };
+
+        Ok(Some((rule, tz)))
+    }
+
+    /// Internal function to get the due date of the first todo of the series of a todo
+    /// Falls back to the due date of the todo when it starts the series or the first todo was deleted
+    /// # Arguments
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<Option<NaiveDateTime>>` - The due date in UTC, None if the todo has no due date
+    fn series_start(&self, conn: &mut PgConnection) -> QueryResult<Option<NaiveDateTime>> {
+        let start = match self.series_id {
+            Some(series) => todos
+                .find(series)
+                .select(due_at)
+                .first::<Option<NaiveDateTime>>(conn)
+                .optional()?
+                .flatten(),
+            None => None,
+        };
+
+        Ok(start.or(self.due_at))
+    }
+
+    /// Internal function to build the next occurrence of a recurring todo
+    /// The next occurrence keeps the content of the todo and belongs to the same series
+    /// # Arguments
+    /// * `start` - Due date of the first todo of the series in UTC, its wall clock time is kept
+    /// # Returns
+    /// * `Option<TodoDTO>` - The next occurrence, or None if the todo is not recurring or its series has ended
+    fn next_occurrence(&self, start: NaiveDateTime) -> Option<TodoDTO> {
+        let (rule, tz) = match self.recurrence() {
+            Ok(Some(recurrence)) => recurrence,
+            _ => return None,
+        };
+
+        let next_due = rule
+            .occurrences_after(self.due_at?, self.occurrence as u32, start, tz, 1)
+            .into_iter()
+            .next()?;
+
+        Some(TodoDTO {
+            user_id: Some(self.user_id),
+            title: Some(self.title.clone()),
+            description: Some(self.description.clone()),
+            completed: Some(false),
+            organization_id: self.organization_id,
+            list_id: self.list_id,
+            due_at: Some(next_due),
+            completed_at: None,
+            priority: Some(self.priority),
+            recurrence_rule: self.recurrence_rule.clone(),
+            recurrence_tz: self.recurrence_tz.clone(),
+            series_id: Some(self.series_id.unwrap_or(self.id)),
+            occurrence: Some(self.occurrence + 1),
+            parent_id: self.parent_id,
+            position: None,
+            status_id: None,
+        })
+    }
+
+    /// Internal function to validate the recurrence of a todo
+    /// # Arguments
+    /// * `rule` - Recurrence rule of the todo, if any
+    /// * `tz` - Time zone of the recurrence rule, if any
+    /// * `due` - Due date of the todo, if any
+    /// # Returns
+    /// * `Result<(), String>` - Result containing nothing or an error message
+    fn validate_recurrence(
+        rule: Option<&str>,
+        tz: Option<&str>,
+        due: Option<NaiveDateTime>,
+    ) -> Result<(), String> {
+        if let Some(tz) = tz {
+            if tz.parse::<Tz>().is_err() {
+                return Err(format!("Unknown time zone {}", tz));
+            }
+        }
+
+        if let Some(rule) = rule {
+            rule.parse::<RecurrenceRule>()?;
+
+            if due.is_none() {
+                return Err("Recurring todos require a due date".to_string());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Internal function to get the subtasks of a todo at any nesting level
+    /// Subtasks are returned level by level, parents before their children
+    /// # Arguments
+    /// * `todo_id` - Id of the todo
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<Vec<Todo>>` - The subtasks of the todo
+    fn get_descendants(todo_id: i32, conn: &mut PgConnection) -> QueryResult<Vec<Todo>> {
+        let mut descendants: Vec<Todo> = vec![];
+        let mut level = vec![todo_id];
+
+        while !level.is_empty() {
+            let children = todos
+                .filter(parent_id.eq_any(&level))
+                .order((position.asc(), id.asc()))
+                .load::<Todo>(conn)?;
+
+            level = children.iter().map(|child| child.id).collect();
+            descendants.extend(children);
+        }
+
+        Ok(descendants)
+    }
+
+    /// Internal function to compute the rank of a todo placed between two todos of a scope
+    /// The ranks of the scope are rebalanced when the new rank grows too long
+    /// # Arguments
+    /// * `scope` - Todos the todo is ordered among
+    /// * `todo_id` - Id of the todo being placed, None when creating a new todo
+    /// * `after` - Id of the todo it is placed after, if any
+    /// * `before` - Id of the todo it is placed before, if any
+    /// * `user` - Id of the user placing the todo, recorded as the author of rebalanced ranks
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<String>` - Result containing the rank of the todo
+    fn next_rank(
+        scope: RankScope,
+        todo_id: Option<i32>,
+        after: Option<i32>,
+        before: Option<i32>,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> QueryResult<String> {
+        match Todo::rank_between(scope, todo_id, after, before, conn)? {
+            Some(rank) if rank.len() <= rank::MAX_LENGTH => Ok(rank),
+            _ => {
+                Todo::rebalance(scope, user, conn)?;
+
+                match Todo::rank_between(scope, todo_id, after, before, conn)? {
+                    Some(rank) => Ok(rank),
+                    None => Err(diesel::result::Error::RollbackTransaction),
+                }
+            }
+        }
+    }
+
+    /// Internal function to compute a rank between two todos of a scope
+    /// When only one of them is given, the rank is placed right next to it
+    /// When none is given, the rank is placed after every other todo of the scope
+    /// # Arguments
+    /// * `scope` - Todos the todo is ordered among, after and before must be part of it
+    /// * `todo_id` - Id of the todo being placed, None when creating a new todo
+    /// * `after` - Id of the todo it is placed after, if any
+    /// * `before` - Id of the todo it is placed before, if any
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<Option<String>>` - Result containing the rank, or None if the todos have the same rank
+    fn rank_between(
+        scope: RankScope,
+        todo_id: Option<i32>,
+        after: Option<i32>,
+        before: Option<i32>,
+        conn: &mut PgConnection,
+    ) -> QueryResult<Option<String>> {
+        let lower = match after {
+            Some(after) => Some(
+                scope
+                    .todos()
+                    .filter(id.eq(after))
+                    .select(position)
+                    .first::<String>(conn)?,
+            ),
+            None => None,
+        };
+
+        let upper = match before {
+            Some(before) => Some(
+                scope
+                    .todos()
+                    .filter(id.eq(before))
+                    .select(position)
+                    .first::<String>(conn)?,
+            ),
+            None => None,
+        };
+
+        let mut others = scope.todos().select(position);
+
+        if let Some(todo_id) = todo_id {
+            others = others.filter(id.ne(todo_id));
+        }
+
+        let (lower, upper) = match (lower, upper) {
+            (Some(lower), None) => {
+                let upper = others
+                    .filter(position.gt(lower.clone()))
+                    .order(position.asc())
+                    .first::<String>(conn)
+                    .optional()?;
+
+                (Some(lower), upper)
+            }
+            (None, Some(upper)) => {
+                let lower = others
+                    .filter(position.lt(upper.clone()))
+                    .order(position.desc())
+                    .first::<String>(conn)
+                    .optional()?;
+
+                (lower, Some(upper))
+            }
+            (None, None) => {
+                let lower = others
+                    .order(position.desc())
+                    .first::<String>(conn)
+                    .optional()?;
+
+                (lower, None)
+            }
+            neighbours => neighbours,
+        };
+
+        Ok(rank::between(lower.as_deref(), upper.as_deref()))
+    }
+
+    /// Internal function to place a todo after the other todos of its scope when an update changed its scope
+    /// # Arguments
+    /// * `before` - The todo before the update
+    /// * `user` - Id of the user updating the todo
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<()>` - Result containing nothing
+    fn place_in_scope(before: &Todo, user: i32, conn: &mut PgConnection) -> QueryResult<()> {
+        let current = todos.find(before.id).first::<Todo>(conn)?;
+        let scope = RankScope::of(&current);
+
+        if scope == RankScope::of(before) {
+            return Ok(());
+        }
+
+        let rank = Todo::next_rank(scope, Some(current.id), None, None, user, conn)?;
+
+        diesel::update(todos.find(current.id))
+            .set(position.eq(rank))
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// Internal function to spread the ranks of the todos of a scope evenly, keeping their order
+    /// The todos whose rank changes get a new version and an event like any other change
+    /// # Arguments
+    /// * `scope` - Todos to rebalance
+    /// * `user` - Id of the user whose change triggered the rebalance
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<()>` - Result containing nothing
+    fn rebalance(scope: RankScope, user: i32, conn: &mut PgConnection) -> QueryResult<()> {
+        let ordered = todos
+            .filter(id.eq_any(scope.todos().select(id)))
+            .order((position.asc(), id.asc()))
+            .for_update()
+            .load::<Todo>(conn)?;
+        let ids: Vec<i32> = ordered.iter().map(|todo| todo.id).collect();
+
+        diesel::sql_query(
+            "UPDATE todos SET position = ($1::TEXT[])[ranked.row_number] \
+             FROM (SELECT id, ROW_NUMBER() OVER (ORDER BY position, id)::INTEGER AS row_number \
+                   FROM todos WHERE id = ANY($2)) AS ranked \
+             WHERE todos.id = ranked.id AND todos.position <> ($1::TEXT[])[ranked.row_number]",
+        )
+        .bind::<Array<Text>, _>(rank::spread(ordered.len()))
+        .bind::<Array<Integer>, _>(&ids)
+        .execute(conn)?;
+
+        let rebalanced = todos.filter(id.eq_any(&ids)).load::<Todo>(conn)?;
+
+        Event::record_all(user, &ordered, &rebalanced, conn)
+    }
+
+    /// Internal function to build the tree of a todo from its subtasks
+    /// # Arguments
+    /// * `nodes` - The todo followed by its subtasks, taken out as they are placed in the tree
+    /// * `index` - Index of the todo the tree is built for
+    /// # Returns
+    /// * `TodoTree` - The tree of the todo
+    fn build_tree(nodes: &mut Vec<Option<TodoWithTags>>, index: usize) -> TodoTree {
+        let node = nodes[index].take().expect("todo placed twice in the tree");
+
+        let child_indexes: Vec<usize> = nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, child)| match child {
+                Some(child) => child.todo.parent_id == Some(node.todo.id),
+                None => false,
+            })
+            .map(|(child_index, _)| child_index)
+            .collect();
+
+        let children: Vec<TodoTree> = child_indexes
+            .into_iter()
+            .map(|child_index| Todo::build_tree(nodes, child_index))
+            .collect();
+
+        let completed_count = children
+            .iter()
+            .map(|child| child.progress.completed + child.todo.todo.completed as usize)
+            .sum();
+        let total = children.iter().map(|child| child.progress.total + 1).sum();
+
+        TodoTree {
+            todo: node,
+            progress: Progress::new(completed_count, total),
+            children,
+        }
+    }
+
+    /// Internal function to keep the status and the completed field of a todo consistent
+    /// Setting a status derives completed from it, setting completed moves the todo to the first matching status
+    /// # Arguments
+    /// * `todo` - The todo being updated
+    /// * `data` - TodoDTO struct containing the data to be sent to the database, updated in place
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<bool, String>` - Result containing whether the status of the todo has to be cleared or an error message
+    fn resolve_status(
+        todo: &Todo,
+        data: &mut TodoDTO,
+        conn: &mut PgConnection,
+    ) -> Result<bool, String> {
+        let list_changed = data.list_id.is_some() && data.list_id != todo.list_id;
+
+        let current = match todo.status_id {
+            Some(current) if !list_changed => match todo.list_id {
+                Some(list) => Status::find_in_list(current, list, conn).ok(),
+                None => None,
+            },
+            _ => None,
+        };
+
+        let list = match data.list_id.or(todo.list_id) {
+            Some(list) => list,
+            None if data.status_id.is_some() => {
+                return Err("Only todos in a list can have a status".to_string())
+            }
+            None => return Ok(false),
+        };
+
+        if let Some(target) = data.status_id {
+            let target = Status::find_in_list(target, list, conn)?;
+
+            if let Some(current) = &current {
+                Status::validate_transition(current, &target, conn)?;
+            }
+
+            if data.completed.is_some() && data.completed != Some(target.done) {
+                return Err("completed is derived from the status of the todo".to_string());
+            }
+
+            data.completed = Some(target.done);
+            return Ok(false);
+        }
+
+        let is_done = data.completed.unwrap_or(todo.completed);
+        let status_matches = current.as_ref().map(|current| current.done) == Some(is_done);
+
+        if !list_changed && (data.completed.is_none() || status_matches) {
+            return Ok(false);
+        }
+
+        match Status::find_for_completion(list, current.as_ref(), is_done, conn)? {
+            Some(target) => {
+                data.status_id = Some(target.id);
+                Ok(false)
+            }
+            None => Ok(list_changed),
+        }
+    }
+
+    /// Internal function to validate that no open todo blocks a todo being completed
+    /// When cascading, the subtasks being completed along with the todo are checked as well
+    /// # Arguments
+    /// * `todo_id` - Id of the todo being completed
+    /// * `cascade` - Whether the subtasks of the todo are completed too
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<(), String>` - Result containing nothing or an error message
+    fn validate_blockers(
+        todo_id: i32,
+        cascade: bool,
+        conn: &mut PgConnection,
+    ) -> Result<(), String> {
+        let mut completing = vec![todo_id];
+
+        if cascade {
+            match Todo::get_descendants(todo_id, conn) {
+                Ok(descendants) => completing.extend(descendants.iter().map(|todo| todo.id)),
+                Err(_) => return Err("Failed to update todo".to_string()),
+            }
+        }
+
+        let blockers: Vec<String> = Dependency::get_open_blockers(&completing, conn)?
+            .into_iter()
+            .filter(|blocker| !completing.contains(blocker))
+            .map(|blocker| blocker.to_string())
+            .collect();
+
+        if !blockers.is_empty() {
+            return Err(format!(
+                "Todo is blocked by open todos {}, use force to complete it anyway",
+                blockers.join(", ")
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Internal function to validate that a todo can be placed under a parent
+    /// The parent must be writable by the user, shared with the same organization, and not one of the todo's subtasks
+    /// # Arguments
+    /// * `parent` - Id of the parent todo
+    /// * `todo_id` - Id of the todo, None when creating a new todo
+    /// * `user` - Id of the user
+    /// * `organization` - Id of the organization of the todo, if any
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<(), String>` - Result containing nothing or an error message
+    fn validate_parent(
+        parent: i32,
+        todo_id: Option<i32>,
+        user: i32,
+        organization: Option<i32>,
+        conn: &mut PgConnection,
+    ) -> Result<(), String> {
+        let mut ancestor = Todo::find_writable(parent, user, conn)?;
+
+        if ancestor.organization_id != organization {
+            return Err("Parent belongs to another organization".to_string());
+        }
+
+        loop {
+            if Some(ancestor.id) == todo_id {
+                return Err("A todo cannot be a subtask of itself or of its subtasks".to_string());
+            }
+
+            ancestor = match ancestor.parent_id {
+                Some(next) => match todos.find(next).first::<Todo>(conn) {
+                    Ok(next) => next,
+                    Err(_) => return Err("Failed to validate parent".to_string()),
+                },
+                None => return Ok(()),
+            };
+        }
+    }
+
+    /// Internal function to validate that a todo can be placed in a list
+    /// The list must be writable by the user and shared with the same organization as the todo
+    /// # Arguments
+    /// * `list` - Id of the list
+    /// * `user` - Id of the user
+    /// * `organization` - Id of the organization of the todo, if any
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<(), String>` - Result containing nothing or an error message
+    fn validate_list(
+        list: i32,
+        user: i32,
+        organization: Option<i32>,
+        conn: &mut PgConnection,
+    ) -> Result<(), String> {
+        let list = List::find_writable(list, user, conn)?;
+
+        if list.organization_id != organization {
+            return Err("List belongs to another organization".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Internal function to get a field as it is after an update
+    /// A cleared field is empty, otherwise the new value is taken over the current one
+    fn merged<T>(value: Option<T>, cleared: &Option<Option<T>>, current: Option<T>) -> Option<T> {
+        match cleared {
+            Some(_) => None,
+            None => value.or(current),
+        }
+    }
+
+    /// Internal function to split a merge patch into the fields to set and the fields to clear
+    /// # Arguments
+    /// * `patch` - TodoPatch struct containing the fields to change
+    /// # Returns
+    /// * `Result<(TodoDTO, ClearedFields), String>` - Result containing the fields to set and to clear, or an error message if a required field is null
+    fn split_patch(patch: TodoPatch) -> Result<(TodoDTO, ClearedFields), String> {
+        fn required<T>(field: Option<Option<T>>, name: &str) -> Result<Option<T>, String> {
+            match field {
+                Some(None) => Err(format!("{} cannot be null", name)),
+                field => Ok(field.flatten()),
+            }
+        }
+
+        fn nullable<T>(field: Option<Option<T>>) -> (Option<T>, Option<Option<T>>) {
+            match field {
+                Some(None) => (None, Some(None)),
+                field => (field.flatten(), None),
+            }
+        }
+
+        let (new_organization, cleared_organization) = nullable(patch.organization_id);
+        let (new_list, cleared_list) = nullable(patch.list_id);
+        let (new_due, cleared_due) = nullable(patch.due_at);
+        let (new_rule, cleared_rule) = nullable(patch.recurrence_rule);
+        let (new_tz, cleared_tz) = nullable(patch.recurrence_tz);
+        let (new_parent, cleared_parent) = nullable(patch.parent_id);
+        let (new_status, cleared_status) = nullable(patch.status_id);
+
+        let data = TodoDTO {
+            user_id: None,
+            title: required(patch.title, "title")?,
+            description: required(patch.description, "description")?,
+            completed: required(patch.completed, "completed")?,
+            organization_id: new_organization,
+            list_id: new_list,
+            due_at: new_due,
+            completed_at: None,
+            priority: required(patch.priority, "priority")?,
+            recurrence_rule: new_rule,
+            recurrence_tz: new_tz,
+            series_id: None,
+            occurrence: None,
+            parent_id: new_parent,
+            position: None,
+            status_id: new_status,
+        };
+
+        let cleared = ClearedFields {
+            organization_id: cleared_organization,
+            list_id: cleared_list,
+            due_at: cleared_due,
+            recurrence_rule: cleared_rule,
+            recurrence_tz: cleared_tz,
+            parent_id: cleared_parent,
+            status_id: cleared_status,
+        };
+
+        Ok((data, cleared))
+    }
+
     /// Internal function to validate input for creating a new todo
     /// # Arguments
     /// * `data` - TodoDTO struct containing the data to be sent to the database
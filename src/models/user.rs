@@ -22,7 +22,9 @@ pub struct User {
     pub name: String,
     /// Email of the user
     pub email: String,
-    /// Password of the user
+    /// Password of the user, hashed
+    /// Never sent in responses
+    #[serde(skip_serializing)]
     pub password: String,
     // /// Time the user was created
     // /// This is auto generated by the database
@@ -74,8 +76,8 @@ impl User {
     /// * `user` - UserDTO struct containing the data needed to create a new user
     /// * `conn` - Connection to the database
     /// # Returns
-    /// * `Result<User, String>` - Result containing a message if it was successful or not
-    pub fn signup(user: UserDTO, conn: &mut Connection) -> Result<String, String> {
+    /// * `Result<User, String>` - Result containing the created user or an error message
+    pub fn signup(user: UserDTO, conn: &mut Connection) -> Result<User, String> {
         let already_exists = User::find_by_email(user.email.clone(), conn);
 
         if already_exists.is_ok() {
@@ -94,13 +96,14 @@ impl User {
             email: user.email,
         };
 
-        let result = diesel::insert_into(users).values(&new_user).execute(conn);
+        let result = diesel::insert_into(users)
+            .values(&new_user)
+            .get_result::<User>(conn);
 
-        if result.is_err() {
-            return Err("Failed to create user".to_string());
+        match result {
+            Ok(created) => Ok(created),
+            Err(_) => Err("Failed to create user".to_string()),
         }
-
-        return Ok("User created".to_string());
     }
 
     /// Login a user function
@@ -136,6 +139,21 @@ impl User {
         return Ok(user_result);
     }
 
+    /// Find a user by id function
+    /// # Arguments
+    /// * `user_id` - Id of the user to find
+    /// * `conn` - Connection to the database
+    /// # Returns
+    /// * `Result<User, String>` - Result containing the user data if successful or a message if not
+    pub fn find_by_id(user_id: i32, conn: &mut PgConnection) -> Result<User, String> {
+        let result = users.find(user_id).first(conn);
+
+        match result {
+            Ok(user) => Ok(user),
+            Err(_) => Err("User not found".to_string()),
+        }
+    }
+
     /// Internal function to find a user by email
     /// # Arguments
     /// * `requested_email` - String containing the email of the user to find
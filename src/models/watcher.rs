@@ -0,0 +1,88 @@
+use diesel::{prelude::*, Insertable, PgConnection, Queryable, RunQueryDsl};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::models::todos::Todo;
+use crate::schema::todo_watchers::{self, dsl::*};
+
+/// Watcher struct representing a row in the todo_watchers table in the database
+/// Watchers follow the changes made to a todo
+#[derive(Queryable, Insertable, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "todo_watchers"]
+pub struct Watcher {
+    /// Id of the watched todo
+    pub todo_id: i32,
+    /// Id of the user watching the todo
+    pub user_id: i32,
+}
+
+/// Implementation of the Watcher struct
+impl Watcher {
+    /// Watch a todo function
+    /// The user must be allowed to read the todo
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `user` - Id of the user watching the todo
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn watch(todo: i32, user: i32, conn: &mut PgConnection) -> Result<String, String> {
+        Todo::find_readable(todo, user, conn)?;
+
+        match Watcher::add(todo, user, conn) {
+            Ok(_) => Ok("Successfully watched todo".to_string()),
+            Err(_) => Err("Failed to watch todo".to_string()),
+        }
+    }
+
+    /// Stop watching a todo function
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `user` - Id of the user watching the todo
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn unwatch(todo: i32, user: i32, conn: &mut PgConnection) -> Result<String, String> {
+        let result = diesel::delete(todo_watchers.find((todo, user))).execute(conn);
+
+        match result {
+            Ok(0) => Err("Todo is not watched".to_string()),
+            Ok(_) => Ok("Successfully unwatched todo".to_string()),
+            Err(_) => Err("Failed to unwatch todo".to_string()),
+        }
+    }
+
+    /// Add a watcher to a todo, doing nothing if the user already watches it
+    /// Access to the todo is not checked
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `user` - Id of the user watching the todo
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<usize>` - Result containing the number of watchers added
+    pub fn add(todo: i32, user: i32, conn: &mut PgConnection) -> QueryResult<usize> {
+        diesel::insert_into(todo_watchers)
+            .values(&Watcher {
+                todo_id: todo,
+                user_id: user,
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)
+    }
+
+    /// Gets the users watching a todo
+    /// # Arguments
+    /// * `todo` - Id of the todo
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<Vec<i32>>` - Result containing the ids of the watchers
+    pub fn get_watchers(todo: i32, conn: &mut PgConnection) -> QueryResult<Vec<i32>> {
+        todo_watchers
+            .filter(todo_id.eq(todo))
+            .select(user_id)
+            .order(user_id.asc())
+            .load::<i32>(conn)
+    }
+}
@@ -0,0 +1,848 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
+use diesel::{prelude::*, Identifiable, Insertable, PgConnection, Queryable, RunQueryDsl};
+use rocket_okapi::okapi::schemars;
+use rocket_okapi::okapi::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::models::event::{Event, EventAction};
+use crate::models::job::{Job, NewJob};
+use crate::models::notification::NewNotification;
+use crate::models::todos::Todo;
+use crate::schema::memberships;
+use crate::schema::{webhook_deliveries, webhooks};
+
+/// Event types a webhook can subscribe to
+pub static EVENT_TYPES: [&str; 5] = [
+    "todo.created",
+    "todo.updated",
+    "todo.completed",
+    "todo.deleted",
+    "notification.created",
+];
+
+/// Event type of the deliveries sent for the notifications of the user, e.g. reminders
+static NOTIFICATION: &str = "notification.created";
+
+/// Event type of the deliveries sent to test a webhook
+static PING: &str = "ping";
+
+/// Kind of the jobs sending deliveries, one job is queued for every attempt
+pub static DELIVER: &str = "webhook.deliver";
+
+/// Number of attempts made to send a delivery before it fails
+static MAX_ATTEMPTS: i32 = 8;
+
+/// Number of failed attempts in a row after which a webhook is disabled
+static MAX_FAILURES: i32 = 20;
+
+/// Number of seconds before the first retry, doubled on every attempt
+static RETRY_DELAY: i64 = 60;
+
+/// Maximum number of seconds between two attempts
+static MAX_RETRY_DELAY: i64 = 60 * 60;
+
+/// Number of deliveries returned by the delivery log at once
+static PAGE_SIZE: i64 = 50;
+
+/// Minimum number of characters of a secret
+static MIN_SECRET_LENGTH: usize = 16;
+
+/// State of a delivery
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[diesel(sql_type = Text)]
+pub enum DeliveryStatus {
+    /// The delivery is waiting for its next attempt
+    Pending,
+    /// The endpoint answered with a 2xx status
+    Succeeded,
+    /// Every attempt failed
+    Failed,
+}
+
+impl DeliveryStatus {
+    /// Returns the value stored in the database for the status
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Succeeded => "succeeded",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for DeliveryStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        <str as ToSql<Text, Pg>>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for DeliveryStatus {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match <String as FromSql<Text, Pg>>::from_sql(bytes)?.as_str() {
+            "pending" => Ok(DeliveryStatus::Pending),
+            "succeeded" => Ok(DeliveryStatus::Succeeded),
+            "failed" => Ok(DeliveryStatus::Failed),
+            other => Err(format!("Unknown delivery status {}", other).into()),
+        }
+    }
+}
+
+/// Webhook struct representing a row in the webhooks table in the database
+/// A webhook receives the changes made to every todo its user can read
+#[derive(Identifiable, Queryable, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+    /// Unique id of the webhook
+    /// This is the primary key of the webhooks table
+    /// This is auto generated by the database
+    pub id: i32,
+    /// Id of the user who registered the webhook
+    pub user_id: i32,
+    /// URL the deliveries are posted to
+    pub url: String,
+    /// Secret the deliveries are signed with
+    /// Never sent in responses
+    #[serde(skip_serializing)]
+    pub secret: String,
+    /// Event types sent to the webhook, e.g. `todo.completed`
+    pub event_types: Vec<String>,
+    /// Whether deliveries are sent, a webhook failing too many times in a row is disabled
+    pub active: bool,
+    /// Number of failed attempts in a row
+    pub failure_count: i32,
+    /// Time the webhook was disabled for failing in UTC
+    pub disabled_at: Option<NaiveDateTime>,
+    /// Time the webhook was registered in UTC
+    pub created_at: NaiveDateTime,
+}
+
+/// WebhookDTO struct representing the data sent to register a webhook
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDTO {
+    /// URL the deliveries are posted to, http or https
+    pub url: String,
+    /// Secret the deliveries are signed with, at least 16 characters
+    pub secret: String,
+    /// Event types sent to the webhook, e.g. `["todo.created", "todo.completed"]`
+    pub event_types: Vec<String>,
+}
+
+/// WebhookPatch struct representing the changes made to a webhook, missing fields are left as they are
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookPatch {
+    /// URL the deliveries are posted to, http or https
+    pub url: Option<String>,
+    /// Secret the deliveries are signed with, at least 16 characters
+    pub secret: Option<String>,
+    /// Event types sent to the webhook
+    pub event_types: Option<Vec<String>>,
+    /// Whether deliveries are sent, enabling a disabled webhook resets its failures
+    pub active: Option<bool>,
+}
+
+/// Internal struct used to register a webhook
+#[derive(Insertable, Debug)]
+#[table_name = "webhooks"]
+struct NewWebhook {
+    user_id: i32,
+    url: String,
+    secret: String,
+    event_types: Vec<String>,
+}
+
+/// WebhookDelivery struct representing a row in the webhook_deliveries table in the database
+#[derive(Identifiable, Queryable, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[table_name = "webhook_deliveries"]
+pub struct WebhookDelivery {
+    /// Unique id of the delivery, sent in the `X-Webhook-Id` header
+    /// This is the primary key of the webhook_deliveries table
+    /// This is auto generated by the database
+    pub id: i32,
+    /// Id of the webhook
+    pub webhook_id: i32,
+    /// Id of the event the delivery is about, empty for pings
+    pub event_id: Option<i32>,
+    /// Event type, e.g. `todo.updated`
+    pub event_type: String,
+    /// Body posted to the webhook
+    pub payload: Value,
+    /// State of the delivery
+    pub status: DeliveryStatus,
+    /// Number of attempts made
+    pub attempts: i32,
+    /// Time of the next attempt in UTC, while pending
+    pub next_attempt_at: NaiveDateTime,
+    /// HTTP status of the last response, empty if the endpoint could not be reached
+    pub response_status: Option<i32>,
+    /// Why the last attempt failed
+    pub error: Option<String>,
+    /// Time the delivery was created in UTC
+    pub created_at: NaiveDateTime,
+    /// Time the endpoint accepted the delivery in UTC
+    pub delivered_at: Option<NaiveDateTime>,
+}
+
+/// Internal struct used to queue a delivery
+#[derive(Insertable, Debug)]
+#[table_name = "webhook_deliveries"]
+struct NewDelivery {
+    webhook_id: i32,
+    event_id: Option<i32>,
+    event_type: String,
+    payload: Value,
+}
+
+/// DeliveryAttempt struct representing the outcome of sending a delivery
+#[derive(Debug, Clone, Default)]
+pub struct DeliveryAttempt {
+    /// HTTP status of the response, empty if the endpoint could not be reached
+    pub response_status: Option<i32>,
+    /// Why the attempt failed
+    pub error: Option<String>,
+}
+
+impl DeliveryAttempt {
+    /// Returns whether the endpoint accepted the delivery
+    pub fn succeeded(&self) -> bool {
+        matches!(self.response_status, Some(200..=299))
+    }
+}
+
+/// Implementation of the Webhook struct
+impl Webhook {
+    /// Register a webhook function
+    /// # Arguments
+    /// * `user` - Id of the user registering the webhook
+    /// * `data` - WebhookDTO struct containing the URL, secret and event types
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Webhook, String>` - Result containing the registered webhook or an error message
+    pub fn new_webhook(
+        user: i32,
+        data: WebhookDTO,
+        conn: &mut PgConnection,
+    ) -> Result<Webhook, String> {
+        let new_webhook = NewWebhook {
+            user_id: user,
+            url: Webhook::validate_url(data.url)?,
+            secret: Webhook::validate_secret(data.secret)?,
+            event_types: Webhook::validate_event_types(data.event_types)?,
+        };
+
+        let result = diesel::insert_into(webhooks::table)
+            .values(&new_webhook)
+            .get_result(conn);
+
+        match result {
+            Ok(webhook) => Ok(webhook),
+            Err(_) => Err("Failed to create webhook".to_string()),
+        }
+    }
+
+    /// Gets the webhooks of a user function
+    /// # Arguments
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<Webhook>, String>` - Result containing the webhooks or an error message
+    pub fn get_webhooks(user: i32, conn: &mut PgConnection) -> Result<Vec<Webhook>, String> {
+        let result = webhooks::table
+            .filter(webhooks::user_id.eq(user))
+            .order(webhooks::id.asc())
+            .load(conn);
+
+        match result {
+            Ok(data) => Ok(data),
+            Err(_) => Err("Failed to get webhooks".to_string()),
+        }
+    }
+
+    /// Update a webhook function
+    /// # Arguments
+    /// * `webhook` - Id of the webhook
+    /// * `user` - Id of the user updating the webhook
+    /// * `patch` - WebhookPatch struct containing the changes
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Webhook, String>` - Result containing the updated webhook or an error message
+    pub fn update_webhook(
+        webhook: i32,
+        user: i32,
+        patch: WebhookPatch,
+        conn: &mut PgConnection,
+    ) -> Result<Webhook, String> {
+        let mut current = Webhook::find_owned(webhook, user, conn)?;
+
+        if let Some(new_url) = patch.url {
+            current.url = Webhook::validate_url(new_url)?;
+        }
+
+        if let Some(new_secret) = patch.secret {
+            current.secret = Webhook::validate_secret(new_secret)?;
+        }
+
+        if let Some(new_event_types) = patch.event_types {
+            current.event_types = Webhook::validate_event_types(new_event_types)?;
+        }
+
+        let enabled = patch.active == Some(true) && !current.active;
+
+        match patch.active {
+            Some(true) if !current.active => {
+                current.active = true;
+                current.failure_count = 0;
+                current.disabled_at = None;
+            }
+            Some(false) => current.active = false,
+            _ => {}
+        }
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let updated = diesel::update(webhooks::table.find(webhook))
+                .set((
+                    webhooks::url.eq(current.url),
+                    webhooks::secret.eq(current.secret),
+                    webhooks::event_types.eq(current.event_types),
+                    webhooks::active.eq(current.active),
+                    webhooks::failure_count.eq(current.failure_count),
+                    webhooks::disabled_at.eq(current.disabled_at),
+                ))
+                .get_result::<Webhook>(conn)?;
+
+            // The jobs of the pending deliveries ended while the webhook was disabled
+            if enabled {
+                let pending = webhook_deliveries::table
+                    .filter(webhook_deliveries::webhook_id.eq(webhook))
+                    .filter(webhook_deliveries::status.eq(DeliveryStatus::Pending))
+                    .load::<WebhookDelivery>(conn)?;
+
+                Job::enqueue_all(
+                    &pending.iter().map(WebhookDelivery::job).collect::<Vec<_>>(),
+                    conn,
+                )?;
+            }
+
+            Ok(updated)
+        });
+
+        match result {
+            Ok(updated) => Ok(updated),
+            Err(_) => Err("Failed to update webhook".to_string()),
+        }
+    }
+
+    /// Delete a webhook function
+    /// The delivery log of the webhook is deleted along with it
+    /// # Arguments
+    /// * `webhook` - Id of the webhook
+    /// * `user` - Id of the user deleting the webhook
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<String, String>` - Result containing a success message or an error message
+    pub fn delete_webhook(
+        webhook: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<String, String> {
+        Webhook::find_owned(webhook, user, conn)?;
+
+        let result = diesel::delete(webhooks::table.find(webhook)).execute(conn);
+
+        match result {
+            Ok(_) => Ok("Successfully deleted webhook".to_string()),
+            Err(_) => Err("Failed to delete webhook".to_string()),
+        }
+    }
+
+    /// Send a test delivery to a webhook function
+    /// The delivery has the `ping` event type and is sent whatever the event types of the webhook
+    /// # Arguments
+    /// * `webhook` - Id of the webhook
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<WebhookDelivery, String>` - Result containing the queued delivery or an error message
+    pub fn ping(
+        webhook: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<WebhookDelivery, String> {
+        let found = Webhook::find_owned(webhook, user, conn)?;
+
+        if !found.active {
+            return Err("Webhook is disabled".to_string());
+        }
+
+        WebhookDelivery::queue(
+            &NewDelivery {
+                webhook_id: webhook,
+                event_id: None,
+                event_type: PING.to_string(),
+                payload: json!({
+                    "type": PING,
+                    "webhookId": webhook,
+                    "occurredAt": Utc::now().naive_utc(),
+                }),
+            },
+            conn,
+        )
+    }
+
+    /// Queues the deliveries of an event function
+    /// Must be called in the transaction recording the event, so a change and its deliveries are saved together
+    /// Every active webhook whose user can read the todo gets a delivery for each of its event types the event matches
+    /// # Arguments
+    /// * `event` - The recorded event
+    /// * `todo` - The todo after the change, or before it for a deletion
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<()>` - Result of the query
+    pub fn enqueue(event: &Event, todo: &Todo, conn: &mut PgConnection) -> QueryResult<()> {
+        let types = Webhook::event_types_of(event);
+
+        let readers = memberships::table
+            .filter(
+                memberships::organization_id
+                    .nullable()
+                    .eq(event.organization_id),
+            )
+            .select(memberships::user_id);
+
+        let subscribed = webhooks::table
+            .filter(webhooks::active.eq(true))
+            .filter(webhooks::event_types.overlaps_with(&types))
+            .filter(
+                webhooks::user_id
+                    .eq(event.owner_id)
+                    .or(webhooks::user_id.eq_any(readers)),
+            )
+            .load::<Webhook>(conn)?;
+
+        if subscribed.is_empty() {
+            return Ok(());
+        }
+
+        let mut todo = serde_json::to_value(todo).unwrap_or(Value::Null);
+
+        // The todo was read before the event bumped its version
+        if let Some(fields) = todo.as_object_mut() {
+            fields.insert("version".to_string(), json!(event.version));
+        }
+
+        let deliveries = subscribed
+            .iter()
+            .flat_map(|webhook| {
+                types
+                    .iter()
+                    .filter(|event_type| webhook.event_types.contains(event_type))
+                    .map(|event_type| NewDelivery {
+                        webhook_id: webhook.id,
+                        event_id: Some(event.id),
+                        event_type: event_type.clone(),
+                        payload: json!({
+                            "type": event_type,
+                            "eventId": event.id,
+                            "occurredAt": event.created_at,
+                            "actorId": event.actor_id,
+                            "todo": todo,
+                            "changes": event.changes,
+                        }),
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let queued = diesel::insert_into(webhook_deliveries::table)
+            .values(&deliveries)
+            .get_results::<WebhookDelivery>(conn)?;
+
+        Job::enqueue_all(
+            &queued.iter().map(WebhookDelivery::job).collect::<Vec<_>>(),
+            conn,
+        )?;
+
+        Ok(())
+    }
+
+    /// Queues the deliveries of a notification function
+    /// The active webhooks of the user subscribed to `notification.created` get a delivery
+    /// # Arguments
+    /// * `notification` - The notification
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `QueryResult<usize>` - Result containing the number of deliveries queued
+    pub fn notify(notification: &NewNotification, conn: &mut PgConnection) -> QueryResult<usize> {
+        let subscribed = webhooks::table
+            .filter(webhooks::user_id.eq(notification.user_id))
+            .filter(webhooks::active.eq(true))
+            .filter(webhooks::event_types.contains(vec![NOTIFICATION]))
+            .select(webhooks::id)
+            .load::<i32>(conn)?;
+
+        if subscribed.is_empty() {
+            return Ok(0);
+        }
+
+        let deliveries = subscribed
+            .into_iter()
+            .map(|webhook| NewDelivery {
+                webhook_id: webhook,
+                event_id: None,
+                event_type: NOTIFICATION.to_string(),
+                payload: json!({
+                    "type": NOTIFICATION,
+                    "occurredAt": Utc::now().naive_utc(),
+                    "notification": notification,
+                }),
+            })
+            .collect::<Vec<_>>();
+
+        conn.transaction(|conn| {
+            let queued = diesel::insert_into(webhook_deliveries::table)
+                .values(&deliveries)
+                .get_results::<WebhookDelivery>(conn)?;
+
+            Job::enqueue_all(
+                &queued.iter().map(WebhookDelivery::job).collect::<Vec<_>>(),
+                conn,
+            )?;
+
+            Ok(queued.len())
+        })
+    }
+
+    /// Internal function to get the event types an event matches
+    /// Completing a todo is both an update and a completion
+    fn event_types_of(event: &Event) -> Vec<String> {
+        let completed = event
+            .changes
+            .get("completed")
+            .and_then(|change| change.get("new"))
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+
+        let types: &[&str] = match event.action {
+            EventAction::Create => &["todo.created"],
+            EventAction::Update if completed => &["todo.updated", "todo.completed"],
+            EventAction::Update => &["todo.updated"],
+            EventAction::Delete => &["todo.deleted"],
+        };
+
+        types
+            .iter()
+            .map(|event_type| event_type.to_string())
+            .collect()
+    }
+
+    /// Internal function to find a webhook of a user
+    fn find_owned(webhook: i32, user: i32, conn: &mut PgConnection) -> Result<Webhook, String> {
+        let result = webhooks::table
+            .find(webhook)
+            .filter(webhooks::user_id.eq(user))
+            .first::<Webhook>(conn);
+
+        match result {
+            Ok(found) => Ok(found),
+            Err(_) => Err("Webhook not found".to_string()),
+        }
+    }
+
+    /// Internal function to validate the URL of a webhook
+    fn validate_url(value: String) -> Result<String, String> {
+        let value = value.trim().to_string();
+
+        if !value.starts_with("http://") && !value.starts_with("https://") {
+            return Err("URL must start with http:// or https://".to_string());
+        }
+
+        if value.len() > 2048 {
+            return Err("URL must be at most 2048 characters".to_string());
+        }
+
+        Ok(value)
+    }
+
+    /// Internal function to validate the secret of a webhook
+    fn validate_secret(value: String) -> Result<String, String> {
+        if value.chars().count() < MIN_SECRET_LENGTH {
+            return Err(format!(
+                "Secret must be at least {} characters",
+                MIN_SECRET_LENGTH
+            ));
+        }
+
+        if value.len() > 255 {
+            return Err("Secret must be at most 255 characters".to_string());
+        }
+
+        Ok(value)
+    }
+
+    /// Internal function to validate the event types of a webhook, duplicates are removed
+    fn validate_event_types(values: Vec<String>) -> Result<Vec<String>, String> {
+        let mut types: Vec<String> = vec![];
+
+        for value in values {
+            if !EVENT_TYPES.contains(&value.as_str()) {
+                return Err(format!(
+                    "Unknown event type {}, expected one of {}",
+                    value,
+                    EVENT_TYPES.join(", ")
+                ));
+            }
+
+            if !types.contains(&value) {
+                types.push(value);
+            }
+        }
+
+        if types.is_empty() {
+            return Err("At least one event type is required".to_string());
+        }
+
+        Ok(types)
+    }
+}
+
+/// Implementation of the WebhookDelivery struct
+impl WebhookDelivery {
+    /// Gets the delivery log of a webhook function
+    /// # Arguments
+    /// * `webhook` - Id of the webhook
+    /// * `user` - Id of the user
+    /// * `before` - Only deliveries older than this delivery id are returned
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Vec<WebhookDelivery>, String>` - Result containing the deliveries, newest first, or an error message
+    pub fn get_deliveries(
+        webhook: i32,
+        user: i32,
+        before: Option<i32>,
+        conn: &mut PgConnection,
+    ) -> Result<Vec<WebhookDelivery>, String> {
+        Webhook::find_owned(webhook, user, conn)?;
+
+        let mut query = webhook_deliveries::table
+            .filter(webhook_deliveries::webhook_id.eq(webhook))
+            .into_boxed();
+
+        if let Some(before) = before {
+            query = query.filter(webhook_deliveries::id.lt(before));
+        }
+
+        let result = query
+            .order(webhook_deliveries::id.desc())
+            .limit(PAGE_SIZE)
+            .load::<WebhookDelivery>(conn);
+
+        match result {
+            Ok(data) => Ok(data),
+            Err(_) => Err("Failed to get deliveries".to_string()),
+        }
+    }
+
+    /// Send a delivery again function
+    /// A new delivery with the same payload is queued, the log of the former one is kept
+    /// # Arguments
+    /// * `webhook` - Id of the webhook
+    /// * `delivery` - Id of the delivery
+    /// * `user` - Id of the user
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<WebhookDelivery, String>` - Result containing the queued delivery or an error message
+    pub fn redeliver(
+        webhook: i32,
+        delivery: i32,
+        user: i32,
+        conn: &mut PgConnection,
+    ) -> Result<WebhookDelivery, String> {
+        let found = Webhook::find_owned(webhook, user, conn)?;
+
+        if !found.active {
+            return Err("Webhook is disabled".to_string());
+        }
+
+        let original = webhook_deliveries::table
+            .find(delivery)
+            .filter(webhook_deliveries::webhook_id.eq(webhook))
+            .first::<WebhookDelivery>(conn);
+
+        let original = match original {
+            Ok(original) => original,
+            Err(_) => return Err("Delivery not found".to_string()),
+        };
+
+        WebhookDelivery::queue(
+            &NewDelivery {
+                webhook_id: webhook,
+                event_id: original.event_id,
+                event_type: original.event_type,
+                payload: original.payload,
+            },
+            conn,
+        )
+    }
+
+    /// Gets a delivery to send function
+    /// A delivery is only sent if it is still waiting for the attempt, and its webhook is active
+    /// # Arguments
+    /// * `delivery` - Id of the delivery
+    /// * `attempt` - Number of attempts made when the attempt was queued
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<Option<(WebhookDelivery, Webhook)>, String>` - Result containing the delivery along with its webhook, None if there is nothing to send, or an error message
+    pub fn find_due(
+        delivery: i32,
+        attempt: i32,
+        conn: &mut PgConnection,
+    ) -> Result<Option<(WebhookDelivery, Webhook)>, String> {
+        let result = webhook_deliveries::table
+            .inner_join(webhooks::table)
+            .filter(webhook_deliveries::id.eq(delivery))
+            .filter(webhook_deliveries::status.eq(DeliveryStatus::Pending))
+            .filter(webhook_deliveries::attempts.eq(attempt))
+            .filter(webhooks::active.eq(true))
+            .first::<(WebhookDelivery, Webhook)>(conn)
+            .optional();
+
+        match result {
+            Ok(found) => Ok(found),
+            Err(_) => Err("Failed to get delivery".to_string()),
+        }
+    }
+
+    /// Records the outcome of an attempt function
+    /// A failed delivery is retried later, waiting twice as long after each attempt, and fails once
+    /// every attempt is made. The webhook is disabled after too many failed attempts in a row
+    /// The job of the next attempt is queued along with the outcome
+    /// # Arguments
+    /// * `attempt` - The outcome of the attempt
+    /// * `conn` - Mutable reference to the database connection
+    /// # Returns
+    /// * `Result<(), String>` - Result containing nothing or an error message
+    pub fn finish(&self, attempt: DeliveryAttempt, conn: &mut PgConnection) -> Result<(), String> {
+        let now = Utc::now().naive_utc();
+        let succeeded = attempt.succeeded();
+        let made = self.attempts + 1;
+
+        let (next_status, next_attempt) = if succeeded {
+            (DeliveryStatus::Succeeded, now)
+        } else if made >= MAX_ATTEMPTS {
+            (DeliveryStatus::Failed, now)
+        } else {
+            // Waits 1, 2, 4... minutes, up to an hour
+            let delay = RETRY_DELAY
+                .saturating_mul(1 << (made - 1).min(20))
+                .min(MAX_RETRY_DELAY);
+
+            (DeliveryStatus::Pending, now + Duration::seconds(delay))
+        };
+
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let updated = diesel::update(
+                webhook_deliveries::table
+                    .find(self.id)
+                    .filter(webhook_deliveries::attempts.eq(self.attempts)),
+            )
+            .set((
+                webhook_deliveries::status.eq(next_status),
+                webhook_deliveries::attempts.eq(made),
+                webhook_deliveries::next_attempt_at.eq(next_attempt),
+                webhook_deliveries::response_status.eq(attempt.response_status),
+                webhook_deliveries::error.eq(attempt.error),
+                webhook_deliveries::delivered_at.eq(succeeded.then_some(now)),
+            ))
+            .execute(conn)?;
+
+            // The outcome of the attempt was recorded already
+            if updated == 0 {
+                return Ok(());
+            }
+
+            if next_status == DeliveryStatus::Pending {
+                let next = WebhookDelivery {
+                    attempts: made,
+                    next_attempt_at: next_attempt,
+                    ..self.clone()
+                };
+
+                Job::enqueue(&next.job(), conn)?;
+            }
+
+            if succeeded {
+                diesel::update(webhooks::table.find(self.webhook_id))
+                    .set(webhooks::failure_count.eq(0))
+                    .execute(conn)?;
+
+                return Ok(());
+            }
+
+            let failures = diesel::update(webhooks::table.find(self.webhook_id))
+                .set(webhooks::failure_count.eq(webhooks::failure_count + 1))
+                .returning(webhooks::failure_count)
+                .get_result::<i32>(conn)?;
+
+            if failures >= MAX_FAILURES {
+                diesel::update(
+                    webhooks::table
+                        .find(self.webhook_id)
+                        .filter(webhooks::active.eq(true)),
+                )
+                .set((
+                    webhooks::active.eq(false),
+                    webhooks::disabled_at.eq(Some(now)),
+                ))
+                .execute(conn)?;
+            }
+
+            Ok(())
+        });
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Failed to record delivery".to_string()),
+        }
+    }
+
+    /// Internal function to insert a delivery along with the job sending it
+    fn queue(delivery: &NewDelivery, conn: &mut PgConnection) -> Result<WebhookDelivery, String> {
+        let result = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let queued = diesel::insert_into(webhook_deliveries::table)
+                .values(delivery)
+                .get_result::<WebhookDelivery>(conn)?;
+
+            Job::enqueue(&queued.job(), conn)?;
+
+            Ok(queued)
+        });
+
+        match result {
+            Ok(queued) => Ok(queued),
+            Err(_) => Err("Failed to queue delivery".to_string()),
+        }
+    }
+
+    /// Internal function to create the job making the next attempt of a delivery
+    /// The number of attempts made is part of the unique key, so an attempt is queued only once
+    fn job(&self) -> NewJob {
+        NewJob {
+            unique_key: Some(format!("{}:{}:{}", DELIVER, self.id, self.attempts)),
+            run_at: self.next_attempt_at,
+            ..NewJob::new(
+                DELIVER,
+                json!({ "deliveryId": self.id, "attempt": self.attempts }),
+            )
+        }
+    }
+}
@@ -0,0 +1,402 @@
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use rocket::http::{ContentType, Header};
+use rocket::response::{self, Responder};
+use rocket::serde::json::Json;
+use rocket::tokio::fs;
+use rocket::tokio::task::spawn_blocking;
+use rocket::{delete, form::Form, get, post, Request, State};
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::okapi::openapi3::Responses;
+use rocket_okapi::openapi;
+use rocket_okapi::response::OpenApiResponderInner;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::config::db::{get_connection, PoolConnection};
+use crate::config::storage::Storage;
+use crate::consts::Response;
+use crate::models::attachment::{Attachment, AttachmentUpload, AttachmentUrl, Upload, MAX_SIZE};
+use crate::utils::idempotency::{IdempotencyKey, Idempotent};
+use crate::utils::jwt::TokenValidation;
+
+/// Content of an attachment, sent as a file download
+pub struct AttachmentFile {
+    /// The attachment
+    attachment: Attachment,
+    /// Content of the attachment
+    bytes: Vec<u8>,
+}
+
+impl<'r> Responder<'r, 'static> for AttachmentFile {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let content_type = ContentType::parse_flexible(&self.attachment.content_type)
+            .unwrap_or(ContentType::Binary);
+        let disposition = format!("attachment; filename=\"{}\"", self.attachment.file_name);
+
+        response::Response::build_from(self.bytes.respond_to(request)?)
+            .header(content_type)
+            .header(Header::new("Content-Disposition", disposition))
+            .header(Header::new("X-Content-Type-Options", "nosniff"))
+            .ok()
+    }
+}
+
+impl OpenApiResponderInner for AttachmentFile {
+    fn responses(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        Vec::<u8>::responses(gen)
+    }
+}
+
+/// Route to attach a file to a todo
+///
+/// The file is sent as the `file` field of a multipart form, it must be at most 10 MiB and be a PDF,
+/// a GIF, JPEG, PNG or WebP image, or plain text
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo
+/// * `upload` - The multipart form containing the file. For reference, see `AttachmentUpload` struct in `models/attachment.rs`
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_storage` - The blob store attached files are kept in
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the created attachment - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Attachment")]
+#[post(
+    "/todo/<todo_id>/attachments",
+    format = "multipart/form-data",
+    data = "<upload>"
+)]
+pub async fn new_attachment(
+    todo_id: i32,
+    mut upload: Form<AttachmentUpload<'_>>,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _storage: &State<Storage>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<Attachment>>> {
+    if upload.file.len() > MAX_SIZE {
+        return Idempotent::Handled(Err(Json(Response {
+            message: format!("File must be at most {} bytes", MAX_SIZE),
+            data: vec![],
+        })));
+    }
+
+    // The form keeps small files in memory, so the file is copied out to read it either way
+    let staging_name: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect();
+    let staging = std::env::temp_dir().join(staging_name);
+
+    let read_result = match upload.file.copy_to(&staging).await {
+        Ok(_) => fs::read(&staging).await,
+        Err(error) => Err(error),
+    };
+    let _ = fs::remove_file(&staging).await;
+
+    let bytes = match read_result {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            return Idempotent::Handled(Err(Json(Response {
+                message: "Failed to read file".to_string(),
+                data: vec![],
+            })));
+        }
+    };
+
+    let file = Upload {
+        file_name: upload
+            .file
+            .raw_name()
+            .map(|name| name.dangerous_unsafe_unsanitized_raw().to_string()),
+        content_type: upload
+            .file
+            .content_type()
+            .map(|content_type| format!("{}/{}", content_type.top(), content_type.sub()))
+            .map(|content_type| content_type.to_lowercase()),
+        bytes,
+    };
+
+    // A retry of the same upload is recognized by a digest of the file, not by the file itself
+    let idempotency_key = idempotency_key.with_payload(&json!({
+        "fileName": file.file_name,
+        "contentType": file.content_type,
+        "sha256": hex::encode(Sha256::digest(&file.bytes)),
+    }));
+
+    let pool = _dbpool.inner().clone();
+    let storage = _storage.inner().clone();
+    let user = _token_validation.claims.sub;
+
+    // The database and the blob store are used through blocking clients, kept off the async workers
+    spawn_blocking(move || {
+        idempotency_key.run(&pool, Some(user), || {
+            let db_connection_result = get_connection(&pool);
+            let mut db_connection = match db_connection_result {
+                Ok(conn) => conn,
+                Err(_) => {
+                    return Err(Json(Response {
+                        message: "Failed to get connection".to_string(),
+                        data: vec![],
+                    }));
+                }
+            };
+
+            let new_attachment_result = Attachment::new_attachment(
+                todo_id,
+                user,
+                file,
+                storage.as_ref(),
+                &mut db_connection,
+            );
+
+            match new_attachment_result {
+                Ok(attachment) => {
+                    return Ok(Json(Response {
+                        message: "Successfully created attachment".to_string(),
+                        data: vec![attachment],
+                    }));
+                }
+                Err(message) => {
+                    return Err(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+            }
+        })
+    })
+    .await
+    .unwrap_or_else(|_| {
+        Idempotent::Handled(Err(Json(Response {
+            message: "Failed to create attachment".to_string(),
+            data: vec![],
+        })))
+    })
+}
+
+/// Route to get the attachments of a todo, oldest first
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the attachments - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Attachment")]
+#[get("/todo/<todo_id>/attachments", format = "application/json")]
+pub fn get_attachments(
+    todo_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<Attachment>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let attachments_result =
+        Attachment::get_attachments(todo_id, _token_validation.claims.sub, &mut db_connection);
+
+    match attachments_result {
+        Ok(attachments) => {
+            return Json(Response {
+                message: "Successfully retrieved attachments".to_string(),
+                data: attachments,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to delete an attachment
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo
+/// * `attachment_id` - The id of the attachment
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Attachment")]
+#[delete(
+    "/todo/<todo_id>/attachments/<attachment_id>",
+    format = "application/json"
+)]
+pub fn delete_attachment(
+    todo_id: i32,
+    attachment_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<i8>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let delete_attachment_result = Attachment::delete_attachment(
+        todo_id,
+        attachment_id,
+        _token_validation.claims.sub,
+        &mut db_connection,
+    );
+
+    match delete_attachment_result {
+        Ok(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to get a signed URL to download an attachment
+///
+/// The URL can be opened without a token, e.g. by a browser, and expires after 15 minutes
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo
+/// * `attachment_id` - The id of the attachment
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the signed URL - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Attachment")]
+#[get(
+    "/todo/<todo_id>/attachments/<attachment_id>/url",
+    format = "application/json"
+)]
+pub fn get_attachment_url(
+    todo_id: i32,
+    attachment_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<AttachmentUrl>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let url_result = Attachment::signed_url(
+        todo_id,
+        attachment_id,
+        _token_validation.claims.sub,
+        &mut db_connection,
+    );
+
+    match url_result {
+        Ok(url) => {
+            return Json(Response {
+                message: "Successfully created download URL".to_string(),
+                data: vec![url],
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to download an attachment with a signed URL
+///
+/// # Arguments
+///
+/// * `attachment_id` - The id of the attachment
+/// * `expires` - Expiry of the URL, as given in the signed URL
+/// * `signature` - Signature of the URL, as given in the signed URL
+/// * `_dbpool` - A pool of database connections
+/// * `_storage` - The blob store attached files are kept in
+///
+/// # Returns
+///
+/// * The content of the file, or a Json containing the error - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Attachment")]
+#[get("/attachments/<attachment_id>/download?<expires>&<signature>")]
+pub async fn download_attachment(
+    attachment_id: i32,
+    expires: i64,
+    signature: String,
+    _dbpool: &State<PoolConnection>,
+    _storage: &State<Storage>,
+) -> Result<AttachmentFile, Json<Response<i8>>> {
+    let pool = _dbpool.inner().clone();
+    let storage = _storage.inner().clone();
+
+    // The database and the blob store are used through blocking clients, kept off the async workers
+    let download_result = spawn_blocking(move || {
+        let mut db_connection = match get_connection(&pool) {
+            Ok(conn) => conn,
+            Err(_) => return Err("Failed to get connection".to_string()),
+        };
+
+        Attachment::download(
+            attachment_id,
+            expires,
+            &signature,
+            storage.as_ref(),
+            &mut db_connection,
+        )
+    })
+    .await
+    .unwrap_or_else(|_| Err("Failed to download attachment".to_string()));
+
+    match download_result {
+        Ok((attachment, bytes)) => {
+            return Ok(AttachmentFile { attachment, bytes });
+        }
+        Err(message) => {
+            return Err(Json(Response {
+                message: message,
+                data: vec![],
+            }));
+        }
+    }
+}
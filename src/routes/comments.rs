@@ -0,0 +1,302 @@
+use rocket::serde::json::Json;
+use rocket::{delete, get, patch, post, State};
+use rocket_okapi::openapi;
+
+use crate::config::db::{get_connection, PoolConnection};
+use crate::consts::Response;
+use crate::models::comment::{Comment, CommentDTO, CommentRevision, CommentWithHtml};
+use crate::utils::idempotency::{IdempotencyKey, Idempotent};
+use crate::utils::jwt::TokenValidation;
+
+/// Route to comment on a todo
+///
+/// Users mentioned in the body with `@name` or `@email` who can see the todo are notified. A name shared by
+/// several of them notifies nobody, mention them by email instead
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo
+/// * `new_comment` - A Json containing the comment. For reference, see `CommentDTO` struct in `models/comment.rs`
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the created comment - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Comment")]
+#[post(
+    "/todo/<todo_id>/comments",
+    format = "application/json",
+    data = "<new_comment>"
+)]
+pub fn new_comment(
+    todo_id: i32,
+    new_comment: Json<CommentDTO>,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<CommentWithHtml>>> {
+    idempotency_key.with_payload(&*new_comment).run(
+        _dbpool,
+        Some(_token_validation.claims.sub),
+        || {
+            let db_connection_result = get_connection(_dbpool);
+            let mut db_connection = match db_connection_result {
+                Ok(conn) => conn,
+                Err(_) => {
+                    return Err(Json(Response {
+                        message: "Failed to get connection".to_string(),
+                        data: vec![],
+                    }));
+                }
+            };
+
+            let new_comment_result = Comment::new_comment(
+                todo_id,
+                _token_validation.claims.sub,
+                new_comment.into_inner(),
+                &mut db_connection,
+            );
+
+            match new_comment_result {
+                Ok(comment) => {
+                    return Ok(Json(Response {
+                        message: "Successfully created comment".to_string(),
+                        data: vec![comment],
+                    }));
+                }
+                Err(message) => {
+                    return Err(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+            }
+        },
+    )
+}
+
+/// Route to get the comments of a todo, oldest first
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the comments - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Comment")]
+#[get("/todo/<todo_id>/comments", format = "application/json")]
+pub fn get_comments(
+    todo_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<CommentWithHtml>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let comments_result =
+        Comment::get_comments(todo_id, _token_validation.claims.sub, &mut db_connection);
+
+    match comments_result {
+        Ok(comments) => {
+            return Json(Response {
+                message: "Successfully retrieved comments".to_string(),
+                data: comments,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to edit a comment
+///
+/// Only the author of the comment or an admin can edit it, the previous body is kept in the history
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo
+/// * `comment_id` - The id of the comment
+/// * `comment` - A Json containing the new body. For reference, see `CommentDTO` struct in `models/comment.rs`
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the edited comment - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Comment")]
+#[patch(
+    "/todo/<todo_id>/comments/<comment_id>",
+    format = "application/json",
+    data = "<comment>"
+)]
+pub fn update_comment(
+    todo_id: i32,
+    comment_id: i32,
+    comment: Json<CommentDTO>,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<CommentWithHtml>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let update_comment_result = Comment::update_comment(
+        todo_id,
+        comment_id,
+        _token_validation.claims.sub,
+        comment.into_inner(),
+        &mut db_connection,
+    );
+
+    match update_comment_result {
+        Ok(comment) => {
+            return Json(Response {
+                message: "Successfully updated comment".to_string(),
+                data: vec![comment],
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to delete a comment
+///
+/// Only the author of the comment or an admin can delete it
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo
+/// * `comment_id` - The id of the comment
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Comment")]
+#[delete("/todo/<todo_id>/comments/<comment_id>", format = "application/json")]
+pub fn delete_comment(
+    todo_id: i32,
+    comment_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<i8>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let delete_comment_result = Comment::delete_comment(
+        todo_id,
+        comment_id,
+        _token_validation.claims.sub,
+        &mut db_connection,
+    );
+
+    match delete_comment_result {
+        Ok(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to get the edit history of a comment, oldest first
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo
+/// * `comment_id` - The id of the comment
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the previous bodies of the comment - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Comment")]
+#[get(
+    "/todo/<todo_id>/comments/<comment_id>/revisions",
+    format = "application/json"
+)]
+pub fn get_revisions(
+    todo_id: i32,
+    comment_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<CommentRevision>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let revisions_result = Comment::get_revisions(
+        todo_id,
+        comment_id,
+        _token_validation.claims.sub,
+        &mut db_connection,
+    );
+
+    match revisions_result {
+        Ok(revisions) => {
+            return Json(Response {
+                message: "Successfully retrieved comment history".to_string(),
+                data: revisions,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, State};
+use rocket_okapi::openapi;
+
+use crate::config::db::{get_connection, PoolConnection};
+use crate::consts::Response;
+use crate::models::dependency::{Dependency, GraphNode};
+use crate::utils::idempotency::{IdempotencyKey, Idempotent};
+use crate::utils::jwt::TokenValidation;
+
+/// Route to mark a todo as blocked by another todo
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo that is blocked
+/// * `blocker_id` - The id of the todo that blocks it
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Dependency")]
+#[post(
+    "/todo/<todo_id>/dependencies/<blocker_id>",
+    format = "application/json"
+)]
+pub fn add_dependency(
+    todo_id: i32,
+    blocker_id: i32,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<i8>>> {
+    idempotency_key.run(_dbpool, Some(_token_validation.claims.sub), || {
+        let db_connection_result = get_connection(_dbpool);
+        let mut db_connection = match db_connection_result {
+            Ok(conn) => conn,
+            Err(_) => {
+                return Err(Json(Response {
+                    message: "Failed to get connection".to_string(),
+                    data: vec![],
+                }));
+            }
+        };
+
+        let add_result = Dependency::add_dependency(
+            todo_id,
+            blocker_id,
+            _token_validation.claims.sub,
+            &mut db_connection,
+        );
+
+        match add_result {
+            Ok(message) => {
+                return Ok(Json(Response {
+                    message: message,
+                    data: vec![],
+                }));
+            }
+            Err(message) => {
+                return Err(Json(Response {
+                    message: message,
+                    data: vec![],
+                }));
+            }
+        }
+    })
+}
+
+/// Route to remove a dependency between two todos
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo that is blocked
+/// * `blocker_id` - The id of the todo that blocks it
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Dependency")]
+#[delete(
+    "/todo/<todo_id>/dependencies/<blocker_id>",
+    format = "application/json"
+)]
+pub fn remove_dependency(
+    todo_id: i32,
+    blocker_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<i8>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let remove_result = Dependency::remove_dependency(
+        todo_id,
+        blocker_id,
+        _token_validation.claims.sub,
+        &mut db_connection,
+    );
+
+    match remove_result {
+        Ok(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to get the todos of a user in topological order of their dependencies
+///
+/// # Arguments
+///
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the todos, blockers first - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Dependency")]
+#[get("/todos/graph", format = "application/json")]
+pub fn get_graph(
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<GraphNode>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let graph_result = Dependency::get_graph(_token_validation.claims.sub, &mut db_connection);
+
+    match graph_result {
+        Ok(graph) => {
+            return Json(Response {
+                message: "Graph fetched successfully".to_string(),
+                data: graph,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
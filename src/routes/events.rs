@@ -0,0 +1,270 @@
+use rocket::serde::json::Json;
+use rocket::{get, post, Shutdown, State};
+use rocket_okapi::openapi;
+
+use crate::config::db::{get_connection, PoolConnection};
+use crate::consts::Response;
+use crate::models::event::{Event, RevertDTO};
+use crate::utils::event_bus::{EventBus, EventFeed, LastEventId};
+use crate::utils::idempotency::{IdempotencyKey, Idempotent};
+use crate::utils::jwt::TokenValidation;
+
+/// Route to get the history of a todo
+///
+/// Every create, update and delete of the todo is listed with its author and the changed fields,
+/// the history of a deleted todo can still be read
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the events, oldest first - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Activity")]
+#[get("/todo/<todo_id>/history", format = "application/json")]
+pub fn get_history(
+    todo_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<Event>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let history_result =
+        Event::get_history(todo_id, _token_validation.claims.sub, &mut db_connection);
+
+    match history_result {
+        Ok(events) => {
+            return Json(Response {
+                message: "Successfully retrieved history".to_string(),
+                data: events,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to get the activity feed of the user
+///
+/// The feed lists the changes made to the todos the user owns or that are shared with their organizations
+///
+/// # Arguments
+///
+/// * `before` - Only events older than this event id are returned, pass the id of the last event to get the next page
+/// * `limit` - Maximum number of events returned, 50 by default and at most 200
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the events, newest first - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Activity")]
+#[get("/activity?<before>&<limit>", format = "application/json")]
+pub fn get_activity(
+    before: Option<i32>,
+    limit: Option<i64>,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<Event>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let activity_result = Event::get_activity(
+        _token_validation.claims.sub,
+        before,
+        limit,
+        &mut db_connection,
+    );
+
+    match activity_result {
+        Ok(events) => {
+            return Json(Response {
+                message: "Successfully retrieved activity".to_string(),
+                data: events,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to stream the changes made to the todos of the user as Server-Sent Events
+///
+/// Every create, update and delete of a todo the user can read is sent once committed, whichever
+/// server instance made it. Each event carries its position in the history as its id, so a client
+/// reconnecting with `Last-Event-ID` first receives the events it missed, even those committed late
+///
+/// # Arguments
+///
+/// * `last_event_id` - The Last-Event-ID header, the id of the last Server-Sent Event the client received
+/// * `_dbpool` - A pool of database connections
+/// * `_event_bus` - The event bus the changes are published on
+/// * `_token_validation` - A struct containing the token validation result
+/// * `shutdown` - Ends the stream when the server stops
+///
+/// # Returns
+///
+/// * A stream of `create`, `update` and `delete` events, holding the events as listed by `GET /activity`
+#[openapi(tag = "Activity")]
+#[get("/events")]
+pub fn stream_events(
+    last_event_id: LastEventId,
+    _dbpool: &State<PoolConnection>,
+    _event_bus: &State<EventBus>,
+    _token_validation: TokenValidation,
+    shutdown: Shutdown,
+) -> EventFeed {
+    _event_bus.stream(
+        _dbpool,
+        _token_validation.claims.sub,
+        last_event_id.0,
+        shutdown,
+    )
+}
+
+/// Route to revert a todo to an earlier version
+///
+/// The title, description and completion state of the version are restored, and the revert is
+/// recorded as a new version
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo
+/// * `revert` - A Json containing the version to restore. For reference, see `RevertDTO` struct in `models/event.rs`
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Activity")]
+#[post(
+    "/todo/<todo_id>/revert",
+    format = "application/json",
+    data = "<revert>"
+)]
+pub fn revert_todo(
+    todo_id: i32,
+    revert: Json<RevertDTO>,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<i8>>> {
+    idempotency_key
+        .with_payload(&*revert)
+        .run(_dbpool, Some(_token_validation.claims.sub), || {
+            let db_connection_result = get_connection(_dbpool);
+            let mut db_connection = match db_connection_result {
+                Ok(conn) => conn,
+                Err(_) => {
+                    return Err(Json(Response {
+                        message: "Failed to get connection".to_string(),
+                        data: vec![],
+                    }));
+                }
+            };
+
+            let revert_result = Event::revert(
+                todo_id,
+                _token_validation.claims.sub,
+                revert.into_inner(),
+                &mut db_connection,
+            );
+
+            match revert_result {
+                Ok(message) => {
+                    return Ok(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+                Err(message) => {
+                    return Err(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+            }
+        })
+}
+
+/// Route to undo the last change of the user
+///
+/// Only a change made in the last 5 minutes can be undone, deleted todos are restored without
+/// their tags, comments and attachments
+///
+/// # Arguments
+///
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Activity")]
+#[post("/undo", format = "application/json")]
+pub fn undo(
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<i8>>> {
+    idempotency_key.run(_dbpool, Some(_token_validation.claims.sub), || {
+        let db_connection_result = get_connection(_dbpool);
+        let mut db_connection = match db_connection_result {
+            Ok(conn) => conn,
+            Err(_) => {
+                return Err(Json(Response {
+                    message: "Failed to get connection".to_string(),
+                    data: vec![],
+                }));
+            }
+        };
+
+        let undo_result = Event::undo(_token_validation.claims.sub, &mut db_connection);
+
+        match undo_result {
+            Ok(message) => {
+                return Ok(Json(Response {
+                    message: message,
+                    data: vec![],
+                }));
+            }
+            Err(message) => {
+                return Err(Json(Response {
+                    message: message,
+                    data: vec![],
+                }));
+            }
+        }
+    })
+}
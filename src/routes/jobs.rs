@@ -0,0 +1,252 @@
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use rocket_okapi::openapi;
+
+use crate::config::db::{get_connection, PoolConnection};
+use crate::config::jobs::JobsConfig;
+use crate::consts::Response;
+use crate::models::job::{Job, JobSchedule, JobStatus};
+use crate::utils::idempotency::{IdempotencyKey, Idempotent};
+use crate::utils::jwt::TokenValidation;
+
+/// Route to get the background jobs, newest first
+///
+/// Only the admins and the owner of the organization set in `JOB_ADMIN_ORGANIZATION` can see jobs
+///
+/// # Arguments
+///
+/// * `status` - Only jobs in this state are returned, e.g. `dead`
+/// * `kind` - Only jobs of this kind are returned, e.g. `webhook.deliver`
+/// * `before` - Only jobs older than this job id are returned, to get the next page
+/// * `_jobs_config` - The settings of the background jobs
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the jobs - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Jobs")]
+#[get("/admin/jobs?<status>&<kind>&<before>", format = "application/json")]
+pub fn get_jobs(
+    status: Option<JobStatus>,
+    kind: Option<String>,
+    before: Option<i32>,
+    _jobs_config: &State<JobsConfig>,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<Job>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    if let Err(message) =
+        _jobs_config.require_admin(_token_validation.claims.sub, &mut db_connection)
+    {
+        return Json(Response {
+            message: message,
+            data: vec![],
+        });
+    }
+
+    let jobs_result = Job::get_jobs(status, kind, before, &mut db_connection);
+
+    match jobs_result {
+        Ok(jobs) => {
+            return Json(Response {
+                message: "Jobs fetched successfully".to_string(),
+                data: jobs,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to get a background job
+///
+/// # Arguments
+///
+/// * `job_id` - The id of the job
+/// * `_jobs_config` - The settings of the background jobs
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the job - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Jobs")]
+#[get("/admin/jobs/<job_id>", format = "application/json")]
+pub fn get_job(
+    job_id: i32,
+    _jobs_config: &State<JobsConfig>,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<Job>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    if let Err(message) =
+        _jobs_config.require_admin(_token_validation.claims.sub, &mut db_connection)
+    {
+        return Json(Response {
+            message: message,
+            data: vec![],
+        });
+    }
+
+    let job_result = Job::get_job(job_id, &mut db_connection);
+
+    match job_result {
+        Ok(job) => {
+            return Json(Response {
+                message: "Job fetched successfully".to_string(),
+                data: vec![job],
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to run a dead job again
+///
+/// The job is queued right away and gets all its attempts back
+///
+/// # Arguments
+///
+/// * `job_id` - The id of the job
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_jobs_config` - The settings of the background jobs
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the queued job - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Jobs")]
+#[post("/admin/jobs/<job_id>/retry", format = "application/json")]
+pub fn retry_job(
+    job_id: i32,
+    idempotency_key: IdempotencyKey,
+    _jobs_config: &State<JobsConfig>,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<Job>>> {
+    idempotency_key.run(_dbpool, Some(_token_validation.claims.sub), || {
+        let db_connection_result = get_connection(_dbpool);
+        let mut db_connection = match db_connection_result {
+            Ok(conn) => conn,
+            Err(_) => {
+                return Err(Json(Response {
+                    message: "Failed to get connection".to_string(),
+                    data: vec![],
+                }));
+            }
+        };
+
+        if let Err(message) =
+            _jobs_config.require_admin(_token_validation.claims.sub, &mut db_connection)
+        {
+            return Err(Json(Response {
+                message: message,
+                data: vec![],
+            }));
+        }
+
+        let retry_result = Job::retry(job_id, &mut db_connection);
+
+        match retry_result {
+            Ok(job) => {
+                return Ok(Json(Response {
+                    message: "Successfully queued job".to_string(),
+                    data: vec![job],
+                }));
+            }
+            Err(message) => {
+                return Err(Json(Response {
+                    message: message,
+                    data: vec![],
+                }));
+            }
+        }
+    })
+}
+
+/// Route to get the schedules queuing jobs
+///
+/// # Arguments
+///
+/// * `_jobs_config` - The settings of the background jobs
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the schedules - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Jobs")]
+#[get("/admin/schedules", format = "application/json")]
+pub fn get_schedules(
+    _jobs_config: &State<JobsConfig>,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<JobSchedule>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    if let Err(message) =
+        _jobs_config.require_admin(_token_validation.claims.sub, &mut db_connection)
+    {
+        return Json(Response {
+            message: message,
+            data: vec![],
+        });
+    }
+
+    let schedules_result = JobSchedule::get_schedules(&mut db_connection);
+
+    match schedules_result {
+        Ok(schedules) => {
+            return Json(Response {
+                message: "Schedules fetched successfully".to_string(),
+                data: schedules,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
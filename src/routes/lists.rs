@@ -0,0 +1,433 @@
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, State};
+use rocket_okapi::openapi;
+
+use crate::config::db::{get_connection, PoolConnection};
+use crate::consts::Response;
+use crate::models::list::{List, ListDTO};
+use crate::models::todos::{Todo, TodoWithTags};
+use crate::utils::idempotency::{IdempotencyKey, Idempotent};
+use crate::utils::jwt::TokenValidation;
+
+/// Route to create a new list
+///
+/// # Arguments
+///
+/// * `new_list` - A Json containing the new list details. For reference, see `ListDTO` struct in `models/list.rs`
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the created list - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "List")]
+#[post("/lists", format = "application/json", data = "<new_list>")]
+pub fn new_list(
+    new_list: Json<ListDTO>,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<List>>> {
+    idempotency_key.with_payload(&*new_list).run(
+        _dbpool,
+        Some(_token_validation.claims.sub),
+        || {
+            if new_list.user_id.is_some() {
+                return Err(Json(Response {
+                    message: "user_id cannot be a parameter".to_string(),
+                    data: vec![],
+                }));
+            }
+
+            let db_connection_result = get_connection(_dbpool);
+            let mut db_connection = match db_connection_result {
+                Ok(conn) => conn,
+                Err(_) => {
+                    return Err(Json(Response {
+                        message: "Failed to get connection".to_string(),
+                        data: vec![],
+                    }));
+                }
+            };
+
+            let list = ListDTO {
+                user_id: Some(_token_validation.claims.sub),
+                ..new_list.into_inner()
+            };
+
+            let new_list_result = List::new_list(list, &mut db_connection);
+
+            match new_list_result {
+                Ok(list) => {
+                    return Ok(Json(Response {
+                        message: "Successfully created list".to_string(),
+                        data: vec![list],
+                    }));
+                }
+                Err(message) => {
+                    return Err(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+            }
+        },
+    )
+}
+
+/// Route to get all lists of a user
+///
+/// # Arguments
+///
+/// * `archived` - Whether archived lists are returned, defaults to false
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "List")]
+#[get("/lists?<archived>", format = "application/json")]
+pub fn get_lists(
+    archived: Option<bool>,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<List>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let lists_result = List::get_lists(
+        _token_validation.claims.sub,
+        archived.unwrap_or(false),
+        &mut db_connection,
+    );
+
+    match lists_result {
+        Ok(lists) => {
+            return Json(Response {
+                message: "Lists fetched successfully".to_string(),
+                data: lists,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to update a list
+///
+/// # Arguments
+///
+/// * `list_id` - The id of the list to be updated
+/// * `update_list` - A Json containing the updated list details. For reference, see `ListDTO` struct in `models/list.rs`
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the updated list - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "List")]
+#[post("/lists/<list_id>", format = "application/json", data = "<update_list>")]
+pub fn update_list(
+    list_id: i32,
+    update_list: Json<ListDTO>,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<List>>> {
+    idempotency_key.with_payload(&*update_list).run(
+        _dbpool,
+        Some(_token_validation.claims.sub),
+        || {
+            if update_list.user_id.is_some() {
+                return Err(Json(Response {
+                    message: "user_id cannot be a parameter".to_string(),
+                    data: vec![],
+                }));
+            }
+
+            if update_list.organization_id.is_some() {
+                return Err(Json(Response {
+                    message: "organization_id cannot be a parameter".to_string(),
+                    data: vec![],
+                }));
+            }
+
+            let db_connection_result = get_connection(_dbpool);
+            let mut db_connection = match db_connection_result {
+                Ok(conn) => conn,
+                Err(_) => {
+                    return Err(Json(Response {
+                        message: "Failed to get connection".to_string(),
+                        data: vec![],
+                    }));
+                }
+            };
+
+            let update_list_result = List::update_list(
+                list_id,
+                _token_validation.claims.sub,
+                update_list.into_inner(),
+                &mut db_connection,
+            );
+
+            match update_list_result {
+                Ok(list) => {
+                    return Ok(Json(Response {
+                        message: "Successfully updated list".to_string(),
+                        data: vec![list],
+                    }));
+                }
+                Err(message) => {
+                    return Err(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+            }
+        },
+    )
+}
+
+/// Route to delete a list
+/// The todos of the list are kept and removed from the list
+///
+/// # Arguments
+///
+/// * `list_id` - The id of the list to be deleted
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "List")]
+#[delete("/lists/<list_id>", format = "application/json")]
+pub fn delete_list(
+    list_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<i8>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let delete_list_result =
+        List::delete_list(list_id, _token_validation.claims.sub, &mut db_connection);
+
+    match delete_list_result {
+        Ok(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to get all todos of a list
+///
+/// # Arguments
+///
+/// * `list_id` - The id of the list
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "List")]
+#[get("/lists/<list_id>/todos", format = "application/json")]
+pub fn get_list_todos(
+    list_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<TodoWithTags>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let todos_result =
+        List::get_list_todos(list_id, _token_validation.claims.sub, &mut db_connection);
+
+    match todos_result {
+        Ok(todos) => {
+            return Json(Response {
+                message: "Todos fetched successfully".to_string(),
+                data: todos,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to move a todo to a list
+/// The todo is removed from the list it was in before
+///
+/// # Arguments
+///
+/// * `list_id` - The id of the list the todo is moved to
+/// * `todo_id` - The id of the todo to be moved
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "List")]
+#[post("/lists/<list_id>/todos/<todo_id>", format = "application/json")]
+pub fn add_list_todo(
+    list_id: i32,
+    todo_id: i32,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<i8>>> {
+    idempotency_key.run(_dbpool, Some(_token_validation.claims.sub), || {
+        let db_connection_result = get_connection(_dbpool);
+        let mut db_connection = match db_connection_result {
+            Ok(conn) => conn,
+            Err(_) => {
+                return Err(Json(Response {
+                    message: "Failed to get connection".to_string(),
+                    data: vec![],
+                }));
+            }
+        };
+
+        let move_result = Todo::move_to_list(
+            todo_id,
+            _token_validation.claims.sub,
+            Some(list_id),
+            &mut db_connection,
+        );
+
+        match move_result {
+            Ok(message) => {
+                return Ok(Json(Response {
+                    message: message,
+                    data: vec![],
+                }));
+            }
+            Err(message) => {
+                return Err(Json(Response {
+                    message: message,
+                    data: vec![],
+                }));
+            }
+        }
+    })
+}
+
+/// Route to remove a todo from a list
+/// The todo itself is kept
+///
+/// # Arguments
+///
+/// * `list_id` - The id of the list the todo is removed from
+/// * `todo_id` - The id of the todo to be removed
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "List")]
+#[delete("/lists/<list_id>/todos/<todo_id>", format = "application/json")]
+pub fn remove_list_todo(
+    list_id: i32,
+    todo_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<i8>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let todo = match Todo::find_writable(todo_id, _token_validation.claims.sub, &mut db_connection)
+    {
+        Ok(todo) => todo,
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    };
+
+    if todo.list_id != Some(list_id) {
+        return Json(Response {
+            message: "Todo is not in this list".to_string(),
+            data: vec![],
+        });
+    }
+
+    let move_result = Todo::move_to_list(
+        todo_id,
+        _token_validation.claims.sub,
+        None,
+        &mut db_connection,
+    );
+
+    match move_result {
+        Ok(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
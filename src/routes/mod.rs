@@ -1,2 +1,16 @@
+pub mod attachments;
+pub mod comments;
+pub mod dependencies;
+pub mod events;
+pub mod jobs;
+pub mod lists;
+pub mod notifications;
+pub mod organizations;
+pub mod reminders;
+pub mod statuses;
+pub mod sync;
+pub mod tags;
 pub mod todos;
 pub mod user;
+pub mod watchers;
+pub mod webhooks;
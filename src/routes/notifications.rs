@@ -0,0 +1,272 @@
+use rocket::serde::json::Json;
+use rocket::{get, post, put, State};
+use rocket_okapi::openapi;
+
+use crate::config::db::{get_connection, PoolConnection};
+use crate::consts::Response;
+use crate::models::notification::{Notification, NotificationSettings, NotificationSettingsDTO};
+use crate::utils::idempotency::{IdempotencyKey, Idempotent};
+use crate::utils::jwt::TokenValidation;
+
+/// Route to get the notification inbox of a user, newest first
+///
+/// # Arguments
+///
+/// * `unread` - Whether only unread notifications are returned, false by default
+/// * `before` - Only notifications older than this notification id are returned, to get the next page
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the notifications - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Notifications")]
+#[get("/notifications?<unread>&<before>", format = "application/json")]
+pub fn get_notifications(
+    unread: Option<bool>,
+    before: Option<i32>,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<Notification>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let notifications_result = Notification::get_notifications(
+        _token_validation.claims.sub,
+        unread.unwrap_or(false),
+        before,
+        &mut db_connection,
+    );
+
+    match notifications_result {
+        Ok(notifications) => {
+            return Json(Response {
+                message: "Notifications fetched successfully".to_string(),
+                data: notifications,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to mark a notification as read
+///
+/// # Arguments
+///
+/// * `notification_id` - The id of the notification
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the notification - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Notifications")]
+#[post("/notifications/<notification_id>/read", format = "application/json")]
+pub fn mark_read(
+    notification_id: i32,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<Notification>>> {
+    idempotency_key.run(_dbpool, Some(_token_validation.claims.sub), || {
+        let db_connection_result = get_connection(_dbpool);
+        let mut db_connection = match db_connection_result {
+            Ok(conn) => conn,
+            Err(_) => {
+                return Err(Json(Response {
+                    message: "Failed to get connection".to_string(),
+                    data: vec![],
+                }));
+            }
+        };
+
+        let mark_read_result = Notification::mark_read(
+            notification_id,
+            _token_validation.claims.sub,
+            &mut db_connection,
+        );
+
+        match mark_read_result {
+            Ok(notification) => {
+                return Ok(Json(Response {
+                    message: "Successfully marked notification as read".to_string(),
+                    data: vec![notification],
+                }));
+            }
+            Err(message) => {
+                return Err(Json(Response {
+                    message: message,
+                    data: vec![],
+                }));
+            }
+        }
+    })
+}
+
+/// Route to mark every notification of a user as read
+///
+/// # Arguments
+///
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the number of notifications marked as read - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Notifications")]
+#[post("/notifications/read-all", format = "application/json")]
+pub fn mark_all_read(
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<usize>>> {
+    idempotency_key.run(_dbpool, Some(_token_validation.claims.sub), || {
+        let db_connection_result = get_connection(_dbpool);
+        let mut db_connection = match db_connection_result {
+            Ok(conn) => conn,
+            Err(_) => {
+                return Err(Json(Response {
+                    message: "Failed to get connection".to_string(),
+                    data: vec![],
+                }));
+            }
+        };
+
+        let mark_all_read_result =
+            Notification::mark_all_read(_token_validation.claims.sub, &mut db_connection);
+
+        match mark_all_read_result {
+            Ok(count) => {
+                return Ok(Json(Response {
+                    message: "Successfully marked notifications as read".to_string(),
+                    data: vec![count],
+                }));
+            }
+            Err(message) => {
+                return Err(Json(Response {
+                    message: message,
+                    data: vec![],
+                }));
+            }
+        }
+    })
+}
+
+/// Route to get the notification settings of a user
+///
+/// # Arguments
+///
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the settings - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Notifications")]
+#[get("/notifications/settings", format = "application/json")]
+pub fn get_settings(
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<NotificationSettings>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let settings_result =
+        NotificationSettings::get_settings(_token_validation.claims.sub, &mut db_connection);
+
+    match settings_result {
+        Ok(settings) => {
+            return Json(Response {
+                message: "Notification settings fetched successfully".to_string(),
+                data: vec![settings],
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to replace the notification settings of a user
+///
+/// Quiet hours are read in the time zone of the user, email and webhook notifications sent during
+/// them are held until they end. The inbox always gets notifications right away
+///
+/// # Arguments
+///
+/// * `settings` - A Json containing the settings. For reference, see `NotificationSettingsDTO` struct in `models/notification.rs`
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the settings - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Notifications")]
+#[put(
+    "/notifications/settings",
+    format = "application/json",
+    data = "<settings>"
+)]
+pub fn update_settings(
+    settings: Json<NotificationSettingsDTO>,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<NotificationSettings>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let settings_result = NotificationSettings::update_settings(
+        _token_validation.claims.sub,
+        settings.into_inner(),
+        &mut db_connection,
+    );
+
+    match settings_result {
+        Ok(settings) => {
+            return Json(Response {
+                message: "Successfully updated notification settings".to_string(),
+                data: vec![settings],
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
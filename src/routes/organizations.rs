@@ -0,0 +1,413 @@
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, State};
+use rocket_okapi::openapi;
+
+use crate::config::db::{get_connection, PoolConnection};
+use crate::consts::Response;
+use crate::models::organization::{
+    Invitation, InvitationDTO, MemberRoleDTO, Membership, Organization, OrganizationDTO,
+};
+use crate::utils::idempotency::{IdempotencyKey, Idempotent};
+use crate::utils::jwt::TokenValidation;
+
+/// Route to create a new organization
+/// The user creating the organization becomes its owner
+///
+/// # Arguments
+///
+/// * `new_organization` - A Json containing the new organization details. For reference, see `OrganizationDTO` struct in `models/organization.rs`
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the created organization - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Organization")]
+#[post("/organizations", format = "application/json", data = "<new_organization>")]
+pub fn new_organization(
+    new_organization: Json<OrganizationDTO>,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<Organization>>> {
+    idempotency_key.with_payload(&*new_organization).run(
+        _dbpool,
+        Some(_token_validation.claims.sub),
+        || {
+            let db_connection_result = get_connection(_dbpool);
+            let mut db_connection = match db_connection_result {
+                Ok(conn) => conn,
+                Err(_) => {
+                    return Err(Json(Response {
+                        message: "Failed to get connection".to_string(),
+                        data: vec![],
+                    }));
+                }
+            };
+
+            let new_organization_result = Organization::new_organization(
+                new_organization.into_inner(),
+                _token_validation.claims.sub,
+                &mut db_connection,
+            );
+
+            match new_organization_result {
+                Ok(organization) => {
+                    return Ok(Json(Response {
+                        message: "Successfully created organization".to_string(),
+                        data: vec![organization],
+                    }));
+                }
+                Err(message) => {
+                    return Err(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+            }
+        },
+    )
+}
+
+/// Route to get all organizations the user is a member of
+///
+/// # Arguments
+///
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Organization")]
+#[get("/organizations", format = "application/json")]
+pub fn get_organizations(
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<Organization>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let organizations_result =
+        Organization::get_organizations(_token_validation.claims.sub, &mut db_connection);
+
+    match organizations_result {
+        Ok(organizations) => {
+            return Json(Response {
+                message: "Organizations fetched successfully".to_string(),
+                data: organizations,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to get all members of an organization
+///
+/// # Arguments
+///
+/// * `organization_id` - The id of the organization
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Organization")]
+#[get("/organizations/<organization_id>/members", format = "application/json")]
+pub fn get_members(
+    organization_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<Membership>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let members_result = Organization::get_members(
+        organization_id,
+        _token_validation.claims.sub,
+        &mut db_connection,
+    );
+
+    match members_result {
+        Ok(members) => {
+            return Json(Response {
+                message: "Members fetched successfully".to_string(),
+                data: members,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to invite a user to an organization by email
+/// The invitation token is emailed to the invited user, it is not returned
+///
+/// # Arguments
+///
+/// * `organization_id` - The id of the organization
+/// * `invitation` - A Json containing the invitation details. For reference, see `InvitationDTO` struct in `models/organization.rs`
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the created invitation - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Organization")]
+#[post(
+    "/organizations/<organization_id>/invitations",
+    format = "application/json",
+    data = "<invitation>"
+)]
+pub fn invite(
+    organization_id: i32,
+    invitation: Json<InvitationDTO>,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<Invitation>>> {
+    idempotency_key.with_payload(&*invitation).run(
+        _dbpool,
+        Some(_token_validation.claims.sub),
+        || {
+            let db_connection_result = get_connection(_dbpool);
+            let mut db_connection = match db_connection_result {
+                Ok(conn) => conn,
+                Err(_) => {
+                    return Err(Json(Response {
+                        message: "Failed to get connection".to_string(),
+                        data: vec![],
+                    }));
+                }
+            };
+
+            let invitation_result = Organization::invite(
+                organization_id,
+                _token_validation.claims.sub,
+                invitation.into_inner(),
+                &mut db_connection,
+            );
+
+            match invitation_result {
+                Ok(invitation) => {
+                    return Ok(Json(Response {
+                        message: "Successfully created invitation".to_string(),
+                        data: vec![invitation],
+                    }));
+                }
+                Err(message) => {
+                    return Err(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+            }
+        },
+    )
+}
+
+/// Route to accept an invitation to an organization
+///
+/// # Arguments
+///
+/// * `token` - The token of the invitation
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the created membership - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Organization")]
+#[post("/invitations/<token>/accept", format = "application/json")]
+pub fn accept_invitation(
+    token: String,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<Membership>>> {
+    idempotency_key.run(_dbpool, Some(_token_validation.claims.sub), || {
+        let db_connection_result = get_connection(_dbpool);
+        let mut db_connection = match db_connection_result {
+            Ok(conn) => conn,
+            Err(_) => {
+                return Err(Json(Response {
+                    message: "Failed to get connection".to_string(),
+                    data: vec![],
+                }));
+            }
+        };
+
+        let accept_result =
+            Invitation::accept(token, _token_validation.claims.sub, &mut db_connection);
+
+        match accept_result {
+            Ok(membership) => {
+                return Ok(Json(Response {
+                    message: "Successfully accepted invitation".to_string(),
+                    data: vec![membership],
+                }));
+            }
+            Err(message) => {
+                return Err(Json(Response {
+                    message: message,
+                    data: vec![],
+                }));
+            }
+        }
+    })
+}
+
+/// Route to change the role of a member of an organization
+///
+/// # Arguments
+///
+/// * `organization_id` - The id of the organization
+/// * `member_id` - The id of the member
+/// * `member_role` - A Json containing the new role. For reference, see `MemberRoleDTO` struct in `models/organization.rs`
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Organization")]
+#[post(
+    "/organizations/<organization_id>/members/<member_id>",
+    format = "application/json",
+    data = "<member_role>"
+)]
+pub fn update_member(
+    organization_id: i32,
+    member_id: i32,
+    member_role: Json<MemberRoleDTO>,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<i8>>> {
+    idempotency_key.with_payload(&*member_role).run(
+        _dbpool,
+        Some(_token_validation.claims.sub),
+        || {
+            let db_connection_result = get_connection(_dbpool);
+            let mut db_connection = match db_connection_result {
+                Ok(conn) => conn,
+                Err(_) => {
+                    return Err(Json(Response {
+                        message: "Failed to get connection".to_string(),
+                        data: vec![],
+                    }));
+                }
+            };
+
+            let update_result = Organization::update_member_role(
+                organization_id,
+                _token_validation.claims.sub,
+                member_id,
+                member_role.role,
+                &mut db_connection,
+            );
+
+            match update_result {
+                Ok(message) => {
+                    return Ok(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+                Err(message) => {
+                    return Err(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+            }
+        },
+    )
+}
+
+/// Route to remove a member from an organization
+///
+/// # Arguments
+///
+/// * `organization_id` - The id of the organization
+/// * `member_id` - The id of the member
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Organization")]
+#[delete(
+    "/organizations/<organization_id>/members/<member_id>",
+    format = "application/json"
+)]
+pub fn remove_member(
+    organization_id: i32,
+    member_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<i8>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let remove_result = Organization::remove_member(
+        organization_id,
+        _token_validation.claims.sub,
+        member_id,
+        &mut db_connection,
+    );
+
+    match remove_result {
+        Ok(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
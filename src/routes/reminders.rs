@@ -0,0 +1,181 @@
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, State};
+use rocket_okapi::openapi;
+
+use crate::config::db::{get_connection, PoolConnection};
+use crate::consts::Response;
+use crate::models::reminder::{Reminder, ReminderDTO};
+use crate::utils::idempotency::{IdempotencyKey, Idempotent};
+use crate::utils::jwt::TokenValidation;
+
+/// Route to set a reminder on a todo
+///
+/// The reminder notifies the user `offsetMinutes` before the todo is due, through the channels of their
+/// notification settings. Reminders follow the todo when its due date moves and are copied to the
+/// next occurrence of a recurring todo
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo
+/// * `new_reminder` - A Json containing the offset. For reference, see `ReminderDTO` struct in `models/reminder.rs`
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the reminder - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Reminders")]
+#[post(
+    "/todo/<todo_id>/reminders",
+    format = "application/json",
+    data = "<new_reminder>"
+)]
+pub fn new_reminder(
+    todo_id: i32,
+    new_reminder: Json<ReminderDTO>,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<Reminder>>> {
+    idempotency_key.with_payload(&*new_reminder).run(
+        _dbpool,
+        Some(_token_validation.claims.sub),
+        || {
+            let db_connection_result = get_connection(_dbpool);
+            let mut db_connection = match db_connection_result {
+                Ok(conn) => conn,
+                Err(_) => {
+                    return Err(Json(Response {
+                        message: "Failed to get connection".to_string(),
+                        data: vec![],
+                    }));
+                }
+            };
+
+            let new_reminder_result = Reminder::new_reminder(
+                todo_id,
+                _token_validation.claims.sub,
+                new_reminder.into_inner(),
+                &mut db_connection,
+            );
+
+            match new_reminder_result {
+                Ok(reminder) => {
+                    return Ok(Json(Response {
+                        message: "Successfully created reminder".to_string(),
+                        data: vec![reminder],
+                    }));
+                }
+                Err(message) => {
+                    return Err(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+            }
+        },
+    )
+}
+
+/// Route to get the reminders of a user on a todo
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the reminders - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Reminders")]
+#[get("/todo/<todo_id>/reminders", format = "application/json")]
+pub fn get_reminders(
+    todo_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<Reminder>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let reminders_result =
+        Reminder::get_reminders(todo_id, _token_validation.claims.sub, &mut db_connection);
+
+    match reminders_result {
+        Ok(reminders) => {
+            return Json(Response {
+                message: "Reminders fetched successfully".to_string(),
+                data: reminders,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to delete a reminder
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo
+/// * `reminder_id` - The id of the reminder
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Reminders")]
+#[delete("/todo/<todo_id>/reminders/<reminder_id>", format = "application/json")]
+pub fn delete_reminder(
+    todo_id: i32,
+    reminder_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<i8>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let delete_reminder_result = Reminder::delete_reminder(
+        todo_id,
+        reminder_id,
+        _token_validation.claims.sub,
+        &mut db_connection,
+    );
+
+    match delete_reminder_result {
+        Ok(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
@@ -0,0 +1,427 @@
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, State};
+use rocket_okapi::openapi;
+
+use crate::config::db::{get_connection, PoolConnection};
+use crate::consts::Response;
+use crate::models::status::{BoardColumn, Status, StatusDTO, StatusWithTransitions};
+use crate::utils::idempotency::{IdempotencyKey, Idempotent};
+use crate::utils::jwt::TokenValidation;
+
+/// Route to create a new status in the workflow of a list
+///
+/// # Arguments
+///
+/// * `list_id` - The id of the list
+/// * `new_status` - A Json containing the new status details. For reference, see `StatusDTO` struct in `models/status.rs`
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the created status - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Status")]
+#[post(
+    "/lists/<list_id>/statuses",
+    format = "application/json",
+    data = "<new_status>"
+)]
+pub fn new_status(
+    list_id: i32,
+    new_status: Json<StatusDTO>,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<Status>>> {
+    idempotency_key.with_payload(&*new_status).run(
+        _dbpool,
+        Some(_token_validation.claims.sub),
+        || {
+            let db_connection_result = get_connection(_dbpool);
+            let mut db_connection = match db_connection_result {
+                Ok(conn) => conn,
+                Err(_) => {
+                    return Err(Json(Response {
+                        message: "Failed to get connection".to_string(),
+                        data: vec![],
+                    }));
+                }
+            };
+
+            let new_status_result = Status::new_status(
+                list_id,
+                _token_validation.claims.sub,
+                new_status.into_inner(),
+                &mut db_connection,
+            );
+
+            match new_status_result {
+                Ok(status) => {
+                    return Ok(Json(Response {
+                        message: "Successfully created status".to_string(),
+                        data: vec![status],
+                    }));
+                }
+                Err(message) => {
+                    return Err(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+            }
+        },
+    )
+}
+
+/// Route to get the workflow statuses of a list along with their transitions
+///
+/// # Arguments
+///
+/// * `list_id` - The id of the list
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Status")]
+#[get("/lists/<list_id>/statuses", format = "application/json")]
+pub fn get_statuses(
+    list_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<StatusWithTransitions>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let statuses_result =
+        Status::get_statuses(list_id, _token_validation.claims.sub, &mut db_connection);
+
+    match statuses_result {
+        Ok(statuses) => {
+            return Json(Response {
+                message: "Statuses fetched successfully".to_string(),
+                data: statuses,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to update a status
+///
+/// # Arguments
+///
+/// * `list_id` - The id of the list
+/// * `status_id` - The id of the status to be updated
+/// * `update_status` - A Json containing the updated status details. For reference, see `StatusDTO` struct in `models/status.rs`
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the updated status - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Status")]
+#[post(
+    "/lists/<list_id>/statuses/<status_id>",
+    format = "application/json",
+    data = "<update_status>"
+)]
+pub fn update_status(
+    list_id: i32,
+    status_id: i32,
+    update_status: Json<StatusDTO>,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<Status>>> {
+    idempotency_key.with_payload(&*update_status).run(
+        _dbpool,
+        Some(_token_validation.claims.sub),
+        || {
+            let db_connection_result = get_connection(_dbpool);
+            let mut db_connection = match db_connection_result {
+                Ok(conn) => conn,
+                Err(_) => {
+                    return Err(Json(Response {
+                        message: "Failed to get connection".to_string(),
+                        data: vec![],
+                    }));
+                }
+            };
+
+            let update_status_result = Status::update_status(
+                list_id,
+                status_id,
+                _token_validation.claims.sub,
+                update_status.into_inner(),
+                &mut db_connection,
+            );
+
+            match update_status_result {
+                Ok(status) => {
+                    return Ok(Json(Response {
+                        message: "Successfully updated status".to_string(),
+                        data: vec![status],
+                    }));
+                }
+                Err(message) => {
+                    return Err(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+            }
+        },
+    )
+}
+
+/// Route to delete a status, only once no todo is in it
+///
+/// # Arguments
+///
+/// * `list_id` - The id of the list
+/// * `status_id` - The id of the status to be deleted
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Status")]
+#[delete("/lists/<list_id>/statuses/<status_id>", format = "application/json")]
+pub fn delete_status(
+    list_id: i32,
+    status_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<i8>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let delete_status_result = Status::delete_status(
+        list_id,
+        status_id,
+        _token_validation.claims.sub,
+        &mut db_connection,
+    );
+
+    match delete_status_result {
+        Ok(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to allow todos to move from a status to another one
+///
+/// # Arguments
+///
+/// * `list_id` - The id of the list
+/// * `status_id` - The id of the status todos move from
+/// * `to_status_id` - The id of the status todos move to
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Status")]
+#[post(
+    "/lists/<list_id>/statuses/<status_id>/transitions/<to_status_id>",
+    format = "application/json"
+)]
+pub fn allow_transition(
+    list_id: i32,
+    status_id: i32,
+    to_status_id: i32,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<i8>>> {
+    idempotency_key.run(_dbpool, Some(_token_validation.claims.sub), || {
+        let db_connection_result = get_connection(_dbpool);
+        let mut db_connection = match db_connection_result {
+            Ok(conn) => conn,
+            Err(_) => {
+                return Err(Json(Response {
+                    message: "Failed to get connection".to_string(),
+                    data: vec![],
+                }));
+            }
+        };
+
+        let allow_result = Status::allow_transition(
+            list_id,
+            status_id,
+            to_status_id,
+            _token_validation.claims.sub,
+            &mut db_connection,
+        );
+
+        match allow_result {
+            Ok(message) => {
+                return Ok(Json(Response {
+                    message: message,
+                    data: vec![],
+                }));
+            }
+            Err(message) => {
+                return Err(Json(Response {
+                    message: message,
+                    data: vec![],
+                }));
+            }
+        }
+    })
+}
+
+/// Route to stop allowing todos to move from a status to another one
+///
+/// # Arguments
+///
+/// * `list_id` - The id of the list
+/// * `status_id` - The id of the status todos move from
+/// * `to_status_id` - The id of the status todos move to
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Status")]
+#[delete(
+    "/lists/<list_id>/statuses/<status_id>/transitions/<to_status_id>",
+    format = "application/json"
+)]
+pub fn remove_transition(
+    list_id: i32,
+    status_id: i32,
+    to_status_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<i8>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let remove_result = Status::remove_transition(
+        list_id,
+        status_id,
+        to_status_id,
+        _token_validation.claims.sub,
+        &mut db_connection,
+    );
+
+    match remove_result {
+        Ok(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to get the todos of a list grouped by workflow status
+///
+/// # Arguments
+///
+/// * `list` - The id of the list, required
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with one column per status - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Status")]
+#[get("/board?<list>", format = "application/json")]
+pub fn get_board(
+    list: Option<i32>,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<BoardColumn>> {
+    let list = match list {
+        Some(list) => list,
+        None => {
+            return Json(Response {
+                message: "list is required".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let board_result = Status::get_board(list, _token_validation.claims.sub, &mut db_connection);
+
+    match board_result {
+        Ok(columns) => {
+            return Json(Response {
+                message: "Board fetched successfully".to_string(),
+                data: columns,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
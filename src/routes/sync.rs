@@ -0,0 +1,126 @@
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use rocket_okapi::openapi;
+
+use crate::config::db::{get_connection, PoolConnection};
+use crate::consts::Response;
+use crate::models::sync::{SyncChange, SyncPull, SyncPushDTO, SyncResult};
+use crate::utils::idempotency::{IdempotencyKey, Idempotent};
+use crate::utils::jwt::TokenValidation;
+
+/// Route to pull the changes made to the todos of the user since the last sync
+///
+/// Without `since` every todo the user can read is returned. Otherwise the todos changed since the
+/// token are returned as they are now, and the todos deleted since, or that the user cannot read
+/// anymore, as tombstones. The returned token is sent as `since` on the next pull, and the client
+/// pulls again right away while `hasMore` is true
+///
+/// # Arguments
+///
+/// * `since` - The token returned by the last pull
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the changes - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Sync")]
+#[get("/sync?<since>", format = "application/json")]
+pub fn pull(
+    since: Option<String>,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<SyncPull>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let pull_result = SyncPull::pull(
+        _token_validation.claims.sub,
+        since.as_deref(),
+        &mut db_connection,
+    );
+
+    match pull_result {
+        Ok(changes) => {
+            return Json(Response {
+                message: "Successfully retrieved changes".to_string(),
+                data: vec![changes],
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to push the changes a client made while offline
+///
+/// Each change is applied on its own, in order. A change made on an outdated version of a todo, or on
+/// a deleted todo, is not applied and reported as a conflict along with the todo as it is on the
+/// server, so the client can merge it and push again
+///
+/// # Arguments
+///
+/// * `push` - A Json containing the changes. For reference, see `SyncPushDTO` struct in `models/sync.rs`
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the result of each change - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Sync")]
+#[post("/sync", format = "application/json", data = "<push>")]
+pub fn push(
+    push: Json<SyncPushDTO>,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<SyncResult>>> {
+    idempotency_key
+        .with_payload(&*push)
+        .run(_dbpool, Some(_token_validation.claims.sub), || {
+            let db_connection_result = get_connection(_dbpool);
+            let mut db_connection = match db_connection_result {
+                Ok(conn) => conn,
+                Err(_) => {
+                    return Err(Json(Response {
+                        message: "Failed to get connection".to_string(),
+                        data: vec![],
+                    }));
+                }
+            };
+
+            let push_result = SyncChange::push(
+                _token_validation.claims.sub,
+                push.into_inner(),
+                &mut db_connection,
+            );
+
+            match push_result {
+                Ok((message, results)) => {
+                    return Ok(Json(Response {
+                        message: message,
+                        data: results,
+                    }));
+                }
+                Err(message) => {
+                    return Err(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+            }
+        })
+}
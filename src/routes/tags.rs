@@ -0,0 +1,341 @@
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, State};
+use rocket_okapi::openapi;
+
+use crate::config::db::{get_connection, PoolConnection};
+use crate::consts::Response;
+use crate::models::tag::{Tag, TagDTO};
+use crate::utils::idempotency::{IdempotencyKey, Idempotent};
+use crate::utils::jwt::TokenValidation;
+
+/// Route to create a new tag
+///
+/// # Arguments
+///
+/// * `new_tag` - A Json containing the new tag details. For reference, see `TagDTO` struct in `models/tag.rs`
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the created tag - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Tag")]
+#[post("/tags", format = "application/json", data = "<new_tag>")]
+pub fn new_tag(
+    new_tag: Json<TagDTO>,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<Tag>>> {
+    idempotency_key
+        .with_payload(&*new_tag)
+        .run(_dbpool, Some(_token_validation.claims.sub), || {
+            if new_tag.user_id.is_some() {
+                return Err(Json(Response {
+                    message: "user_id cannot be a parameter".to_string(),
+                    data: vec![],
+                }));
+            }
+
+            let db_connection_result = get_connection(_dbpool);
+            let mut db_connection = match db_connection_result {
+                Ok(conn) => conn,
+                Err(_) => {
+                    return Err(Json(Response {
+                        message: "Failed to get connection".to_string(),
+                        data: vec![],
+                    }));
+                }
+            };
+
+            let tag = TagDTO {
+                user_id: Some(_token_validation.claims.sub),
+                name: new_tag.name.clone(),
+            };
+
+            let new_tag_result = Tag::new_tag(tag, &mut db_connection);
+
+            match new_tag_result {
+                Ok(tag) => {
+                    return Ok(Json(Response {
+                        message: "Successfully created tag".to_string(),
+                        data: vec![tag],
+                    }));
+                }
+                Err(message) => {
+                    return Err(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+            }
+        })
+}
+
+/// Route to get all tags of a user
+///
+/// # Arguments
+///
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Tag")]
+#[get("/tags", format = "application/json")]
+pub fn get_tags(
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<Tag>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let tags_result = Tag::get_tags(_token_validation.claims.sub, &mut db_connection);
+
+    match tags_result {
+        Ok(tags) => {
+            return Json(Response {
+                message: "Tags fetched successfully".to_string(),
+                data: tags,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to rename a tag
+///
+/// # Arguments
+///
+/// * `tag_id` - The id of the tag to be renamed
+/// * `update_tag` - A Json containing the new tag name. For reference, see `TagDTO` struct in `models/tag.rs`
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the renamed tag - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Tag")]
+#[post("/tags/<tag_id>", format = "application/json", data = "<update_tag>")]
+pub fn update_tag(
+    tag_id: i32,
+    update_tag: Json<TagDTO>,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<Tag>>> {
+    idempotency_key.with_payload(&*update_tag).run(
+        _dbpool,
+        Some(_token_validation.claims.sub),
+        || {
+            let db_connection_result = get_connection(_dbpool);
+            let mut db_connection = match db_connection_result {
+                Ok(conn) => conn,
+                Err(_) => {
+                    return Err(Json(Response {
+                        message: "Failed to get connection".to_string(),
+                        data: vec![],
+                    }));
+                }
+            };
+
+            let update_tag_result = Tag::update_tag(
+                tag_id,
+                _token_validation.claims.sub,
+                update_tag.into_inner(),
+                &mut db_connection,
+            );
+
+            match update_tag_result {
+                Ok(tag) => {
+                    return Ok(Json(Response {
+                        message: "Successfully updated tag".to_string(),
+                        data: vec![tag],
+                    }));
+                }
+                Err(message) => {
+                    return Err(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+            }
+        },
+    )
+}
+
+/// Route to delete a tag
+/// The tag is detached from every todo
+///
+/// # Arguments
+///
+/// * `tag_id` - The id of the tag to be deleted
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Tag")]
+#[delete("/tags/<tag_id>", format = "application/json")]
+pub fn delete_tag(
+    tag_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<i8>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let delete_tag_result = Tag::delete_tag(tag_id, _token_validation.claims.sub, &mut db_connection);
+
+    match delete_tag_result {
+        Ok(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to attach a tag to a todo
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo
+/// * `tag_id` - The id of the tag
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Tag")]
+#[post("/todo/<todo_id>/tags/<tag_id>", format = "application/json")]
+pub fn attach_tag(
+    todo_id: i32,
+    tag_id: i32,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<i8>>> {
+    idempotency_key.run(_dbpool, Some(_token_validation.claims.sub), || {
+        let db_connection_result = get_connection(_dbpool);
+        let mut db_connection = match db_connection_result {
+            Ok(conn) => conn,
+            Err(_) => {
+                return Err(Json(Response {
+                    message: "Failed to get connection".to_string(),
+                    data: vec![],
+                }));
+            }
+        };
+
+        let attach_result = Tag::attach(
+            todo_id,
+            tag_id,
+            _token_validation.claims.sub,
+            &mut db_connection,
+        );
+
+        match attach_result {
+            Ok(message) => {
+                return Ok(Json(Response {
+                    message: message,
+                    data: vec![],
+                }));
+            }
+            Err(message) => {
+                return Err(Json(Response {
+                    message: message,
+                    data: vec![],
+                }));
+            }
+        }
+    })
+}
+
+/// Route to detach a tag from a todo
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo
+/// * `tag_id` - The id of the tag
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Tag")]
+#[delete("/todo/<todo_id>/tags/<tag_id>", format = "application/json")]
+pub fn detach_tag(
+    todo_id: i32,
+    tag_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<i8>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let detach_result = Tag::detach(
+        todo_id,
+        tag_id,
+        _token_validation.claims.sub,
+        &mut db_connection,
+    );
+
+    match detach_result {
+        Ok(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
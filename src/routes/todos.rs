@@ -1,17 +1,32 @@
+use chrono::NaiveDateTime;
+use rocket::http::Status;
 use rocket::serde::json::Json;
-use rocket::{get, post, put, State};
+use rocket::{delete, get, patch, post, put, State};
 use rocket_okapi::openapi;
 use serde::{Deserialize, Serialize};
 
 use crate::config::db::{get_connection, PoolConnection};
 use crate::consts::Response;
-use crate::models::todos::{Todo, TodoDTO};
+use crate::models::bulk::{BulkDTO, BulkOperation, BulkResult};
+use crate::models::tag::TagMode;
+use crate::models::todos::{
+    ChildrenAction, DueFilter, MoveDTO, Todo, TodoDTO, TodoFilter, TodoPatch, TodoReplace,
+    TodoTree, TodoWithTags, UpdateOptions, STALE_VERSION,
+};
+use crate::utils::created::Created;
+use crate::utils::deprecation::Deprecated;
+use crate::utils::idempotency::{IdempotencyKey, Idempotent};
 use crate::utils::jwt::TokenValidation;
+use crate::utils::precondition::{Conditional, IfMatch, IfNoneMatch};
 
-/// Route to get all todos from a user
+/// Route to get all todos from a user, in their manual order
 ///
 /// # Arguments
 ///
+/// * `tag` - Tag names to filter by, can be repeated, e.g. `?tag=a&tag=b`
+/// * `tag_mode` - Whether todos need `any` or `all` of the tags, defaults to `any`
+/// * `due` - Only return todos that are `overdue`, due `today` or due this `week`
+/// * `assigned_to` - Only return todos assigned to a user, either `me` or the id of the user
 /// * `_dbpool` - A pool of database connections
 /// * `_token_validation` - A struct containing the token validation result
 ///
@@ -19,11 +34,32 @@ use crate::utils::jwt::TokenValidation;
 ///
 /// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
 #[openapi(tag = "Todo")]
-#[get("/todos", format = "application/json")]
+#[get(
+    "/todos?<tag>&<tag_mode>&<due>&<assigned_to>",
+    format = "application/json"
+)]
 pub fn get_todos(
+    tag: Vec<String>,
+    tag_mode: Option<TagMode>,
+    due: Option<DueFilter>,
+    assigned_to: Option<String>,
     _dbpool: &State<PoolConnection>,
     _token_validation: TokenValidation,
-) -> Json<Response<Todo>> {
+) -> Json<Response<TodoWithTags>> {
+    let assignee = match assigned_to.as_deref() {
+        Some("me") => Some(_token_validation.claims.sub),
+        Some(assignee) => match assignee.parse::<i32>() {
+            Ok(assignee) => Some(assignee),
+            Err(_) => {
+                return Json(Response {
+                    message: "assigned_to must be me or a user id".to_string(),
+                    data: vec![],
+                });
+            }
+        },
+        None => None,
+    };
+
     let db_connection_result = get_connection(_dbpool);
     let mut db_connection = match db_connection_result {
         Ok(conn) => conn,
@@ -35,7 +71,14 @@ pub fn get_todos(
         }
     };
 
-    let todos_result = Todo::get_todos(_token_validation.claims.sub, &mut db_connection);
+    let filter = TodoFilter {
+        tags: tag,
+        tag_mode,
+        due,
+        assigned_to: assignee,
+    };
+
+    let todos_result = Todo::get_todos(_token_validation.claims.sub, &filter, &mut db_connection);
 
     match todos_result {
         Ok(todos) => {
@@ -58,29 +101,455 @@ pub fn get_todos(
 /// # Arguments
 ///
 /// * `new_todo` - A Json containing the new todo details. For reference, see `TodoDTO` struct in `models/todos.rs`
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
 /// * `_dbpool` - A pool of database connections
 /// * `_token_validation` - A struct containing the token validation result
 ///
 /// # Returns
 ///
-/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+/// * 201 Created with the created todo and its path in the `Location` header - for reference, see `Response` struct in `consts.rs`
+/// * A Json containing the error message if the todo cannot be created, e.g. if `completed` or `position` is set
 #[openapi(tag = "Todo")]
 #[post("/todo", format = "application/json", data = "<new_todo>")]
 pub fn new_todo(
     new_todo: Json<TodoDTO>,
+    idempotency_key: IdempotencyKey,
     _dbpool: &State<PoolConnection>,
     _token_validation: TokenValidation,
-) -> Json<Response<i8>> {
-    match validate_input(new_todo.clone().into_inner()) {
-        Ok(_) => {}
+) -> Idempotent<Created<Json<Response<Todo>>>, Json<Response<Todo>>> {
+    idempotency_key.with_payload(&*new_todo).run(
+        _dbpool,
+        Some(_token_validation.claims.sub),
+        || {
+            match validate_input(new_todo.clone().into_inner()) {
+                Ok(_) => {}
+                Err(message) => {
+                    return Err(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+            }
+
+            let db_connection_result = get_connection(_dbpool);
+            let mut db_connection = match db_connection_result {
+                Ok(conn) => conn,
+                Err(_) => {
+                    return Err(Json(Response {
+                        message: "Failed to get connection".to_string(),
+                        data: vec![],
+                    }));
+                }
+            };
+
+            let new_todo_result = Todo::create_for(
+                _token_validation.claims.sub,
+                new_todo.into_inner(),
+                &mut db_connection,
+            );
+
+            match new_todo_result {
+                Ok(created) => {
+                    return Ok(Created {
+                        location: format!("/todo/{}", created.id),
+                        inner: Json(Response {
+                            message: "Successfully created todo".to_string(),
+                            data: vec![created],
+                        }),
+                    });
+                }
+                Err(message) => {
+                    return Err(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+            }
+        },
+    )
+}
+
+/// Route to patch a todo
+///
+/// The body is a JSON Merge Patch (RFC 7396): missing fields are left unchanged and fields set to
+/// null are cleared. The `If-Match` header must hold the ETag the todo was read with, so changes
+/// made by someone else in the meantime are not overwritten
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo to be patched
+/// * `cascade` - Whether completing the todo also completes its subtasks, defaults to false
+/// * `force` - Whether the todo can be completed while todos blocking it are still open, defaults to false
+/// * `patch` - A Json containing the fields to change. For reference, see `TodoPatch` struct in `models/todos.rs`
+/// * `if_match` - The If-Match header, the ETag of the version of the todo the patch is based on
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the updated todo - for reference, see `Response` struct in `consts.rs`
+/// * 428 Precondition Required without If-Match, 412 Precondition Failed if the todo changed since
+#[openapi(tag = "Todo")]
+#[patch("/todo/<todo_id>?<cascade>&<force>", data = "<patch>")]
+pub fn patch_todo(
+    todo_id: i32,
+    cascade: Option<bool>,
+    force: Option<bool>,
+    patch: Json<TodoPatch>,
+    if_match: IfMatch,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> (Status, Json<Response<Todo>>) {
+    let expected_versions = match if_match.versions() {
+        Ok(expected_versions) => expected_versions,
+        Err(status) => {
+            return (
+                status,
+                Json(Response {
+                    message: "If-Match must hold strong ETags of the todo".to_string(),
+                    data: vec![],
+                }),
+            );
+        }
+    };
+
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return (
+                Status::Ok,
+                Json(Response {
+                    message: "Failed to get connection".to_string(),
+                    data: vec![],
+                }),
+            );
+        }
+    };
+
+    let patch_todo_result = Todo::patch_todo(
+        todo_id,
+        _token_validation.claims.sub,
+        patch.into_inner(),
+        UpdateOptions {
+            cascade: cascade.unwrap_or(false),
+            force: force.unwrap_or(false),
+            versions: expected_versions,
+        },
+        &mut db_connection,
+    );
+
+    match patch_todo_result {
+        Ok(updated) => {
+            return (
+                Status::Ok,
+                Json(Response {
+                    message: "Successfully updated todo".to_string(),
+                    data: vec![updated],
+                }),
+            );
+        }
         Err(message) => {
-            return Json(Response {
-                message: message,
-                data: vec![],
-            });
+            let status = if message == STALE_VERSION {
+                Status::PreconditionFailed
+            } else {
+                Status::Ok
+            };
+
+            return (
+                status,
+                Json(Response {
+                    message: message,
+                    data: vec![],
+                }),
+            );
+        }
+    }
+}
+
+/// Route to replace a todo
+///
+/// Every field a client can set is replaced, missing nullable fields are cleared. The `If-Match`
+/// header must hold the ETag the todo was read with
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo to be replaced
+/// * `cascade` - Whether completing the todo also completes its subtasks, defaults to false
+/// * `force` - Whether the todo can be completed while todos blocking it are still open, defaults to false
+/// * `replace_todo` - A Json containing the new content of the todo. For reference, see `TodoReplace` struct in `models/todos.rs`
+/// * `if_match` - The If-Match header, the ETag of the version of the todo being replaced
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the updated todo - for reference, see `Response` struct in `consts.rs`
+/// * 428 Precondition Required without If-Match, 412 Precondition Failed if the todo changed since
+#[openapi(tag = "Todo")]
+#[put(
+    "/todo/<todo_id>?<cascade>&<force>",
+    format = "application/json",
+    data = "<replace_todo>"
+)]
+pub fn replace_todo(
+    todo_id: i32,
+    cascade: Option<bool>,
+    force: Option<bool>,
+    replace_todo: Json<TodoReplace>,
+    if_match: IfMatch,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> (Status, Json<Response<Todo>>) {
+    let expected_versions = match if_match.versions() {
+        Ok(expected_versions) => expected_versions,
+        Err(status) => {
+            return (
+                status,
+                Json(Response {
+                    message: "If-Match must hold strong ETags of the todo".to_string(),
+                    data: vec![],
+                }),
+            );
+        }
+    };
+
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return (
+                Status::Ok,
+                Json(Response {
+                    message: "Failed to get connection".to_string(),
+                    data: vec![],
+                }),
+            );
+        }
+    };
+
+    let replace_todo_result = Todo::replace_todo(
+        todo_id,
+        _token_validation.claims.sub,
+        replace_todo.into_inner(),
+        UpdateOptions {
+            cascade: cascade.unwrap_or(false),
+            force: force.unwrap_or(false),
+            versions: expected_versions,
+        },
+        &mut db_connection,
+    );
+
+    match replace_todo_result {
+        Ok(updated) => {
+            return (
+                Status::Ok,
+                Json(Response {
+                    message: "Successfully updated todo".to_string(),
+                    data: vec![updated],
+                }),
+            );
+        }
+        Err(message) => {
+            let status = if message == STALE_VERSION {
+                Status::PreconditionFailed
+            } else {
+                Status::Ok
+            };
+
+            return (
+                status,
+                Json(Response {
+                    message: message,
+                    data: vec![],
+                }),
+            );
         }
     }
+}
+
+/// Route to update a todo
+///
+/// Deprecated, use `PATCH /todo/<todo_id>` instead. Fields that are not provided are left unchanged,
+/// so fields cannot be cleared
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo to be updated
+/// * `cascade` - Whether completing the todo also completes its subtasks, defaults to false
+/// * `force` - Whether the todo can be completed while todos blocking it are still open, defaults to false
+/// * `update_todo` - A Json containing the updated todo details. For reference, see `TodoDTO` struct in `models/todos.rs`
+/// * `if_match` - The If-Match header, the ETag of the version of the todo the update is based on
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * The response of `PATCH /todo/<todo_id>`, with a `Deprecation` header
+#[openapi(tag = "Todo")]
+#[post(
+    "/todo/<todo_id>?<cascade>&<force>",
+    format = "application/json",
+    data = "<update_todo>"
+)]
+#[allow(clippy::too_many_arguments)]
+pub fn update_todo(
+    todo_id: i32,
+    cascade: Option<bool>,
+    force: Option<bool>,
+    update_todo: Json<TodoDTO>,
+    if_match: IfMatch,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Deprecated<Idempotent<(Status, Json<Response<Todo>>)>> {
+    let idempotency_key = idempotency_key.with_payload(&*update_todo);
+    let user = _token_validation.claims.sub;
+    let update_todo = update_todo.into_inner();
+
+    let patch = TodoPatch {
+        title: update_todo.title.map(Some),
+        description: update_todo.description.map(Some),
+        completed: update_todo.completed.map(Some),
+        organization_id: update_todo.organization_id.map(Some),
+        list_id: update_todo.list_id.map(Some),
+        due_at: update_todo.due_at.map(Some),
+        priority: update_todo.priority.map(Some),
+        recurrence_rule: update_todo.recurrence_rule.map(Some),
+        recurrence_tz: update_todo.recurrence_tz.map(Some),
+        parent_id: update_todo.parent_id.map(Some),
+        status_id: update_todo.status_id.map(Some),
+    };
+
+    Deprecated {
+        inner: idempotency_key.run(_dbpool, Some(user), || {
+            let response = patch_todo(
+                todo_id,
+                cascade,
+                force,
+                Json(patch),
+                if_match,
+                _dbpool,
+                _token_validation,
+            );
+
+            if response.1.data.is_empty() {
+                Err(response)
+            } else {
+                Ok(response)
+            }
+        }),
+        successor: format!("/todo/{}", todo_id),
+    }
+}
+
+/// Route to get a todo along with the progress of its subtasks
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo
+/// * `include` - Set to `children` to return the subtasks of the todo as a tree
+/// * `if_none_match` - The If-None-Match header, the ETags of the versions of the todo the client has
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the todo - for reference, see `Response` struct in `consts.rs`
+/// * The version of the todo is sent in the ETag header, 304 Not Modified if the client has it already
+#[openapi(tag = "Todo")]
+#[get("/todo/<todo_id>?<include>", format = "application/json")]
+pub fn get_todo(
+    todo_id: i32,
+    include: Option<String>,
+    if_none_match: IfNoneMatch,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Conditional<Json<Response<TodoTree>>> {
+    let include_children = match include.as_deref() {
+        Some("children") => true,
+        Some(_) => {
+            return Conditional::Modified(
+                None,
+                Json(Response {
+                    message: "include can only be children".to_string(),
+                    data: vec![],
+                }),
+            );
+        }
+        None => false,
+    };
 
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Conditional::Modified(
+                None,
+                Json(Response {
+                    message: "Failed to get connection".to_string(),
+                    data: vec![],
+                }),
+            );
+        }
+    };
+
+    let todo_result = Todo::get_todo(
+        todo_id,
+        _token_validation.claims.sub,
+        include_children,
+        &mut db_connection,
+    );
+
+    match todo_result {
+        Ok(todo) => {
+            let version = todo.todo.todo.version;
+
+            if if_none_match.matches(version) {
+                return Conditional::NotModified(version);
+            }
+
+            return Conditional::Modified(
+                Some(version),
+                Json(Response {
+                    message: "Todo fetched successfully".to_string(),
+                    data: vec![todo],
+                }),
+            );
+        }
+        Err(message) => {
+            return Conditional::Modified(
+                None,
+                Json(Response {
+                    message: message,
+                    data: vec![],
+                }),
+            );
+        }
+    }
+}
+
+/// Route to delete a todo
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo to be deleted
+/// * `children` - What happens to the subtasks of the todo, `delete` or `promote`, required if it has any
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Todo")]
+#[delete("/todo/<todo_id>?<children>", format = "application/json")]
+pub fn delete_todo(
+    todo_id: i32,
+    children: Option<ChildrenAction>,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<i8>> {
     let db_connection_result = get_connection(_dbpool);
     let mut db_connection = match db_connection_result {
         Ok(conn) => conn,
@@ -92,16 +561,14 @@ pub fn new_todo(
         }
     };
 
-    let todo = TodoDTO {
-        user_id: Some(_token_validation.claims.sub),
-        title: new_todo.title.clone(),
-        description: new_todo.description.clone(),
-        completed: Some(false),
-    };
-
-    let new_todo_result = Todo::new_todo(todo, &mut db_connection);
+    let delete_todo_result = Todo::delete_todo(
+        todo_id,
+        _token_validation.claims.sub,
+        children,
+        &mut db_connection,
+    );
 
-    match new_todo_result {
+    match delete_todo_result {
         Ok(message) => {
             return Json(Response {
                 message: message,
@@ -117,12 +584,75 @@ pub fn new_todo(
     }
 }
 
-/// Route to update a todo
+/// Route to apply many changes to todos at once
+///
+/// The body holds either a list of `create`, `update` and `delete` operations, or a filter along with
+/// an action applied to every matching todo, e.g. completing all todos of a list. All changes run in
+/// one transaction, in `atomic` mode nothing is saved if an operation fails, in `bestEffort` mode
+/// only the failed operations are skipped
 ///
 /// # Arguments
 ///
-/// * `todo_id` - The id of the todo to be updated
-/// * `update_todo` - A Json containing the updated todo details. For reference, see `TodoDTO` struct in `models/todos.rs`
+/// * `bulk` - A Json containing the operations. For reference, see `BulkDTO` struct in `models/bulk.rs`
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the result of each operation - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Todo")]
+#[post("/todos/bulk", format = "application/json", data = "<bulk>")]
+pub fn bulk_todos(
+    bulk: Json<BulkDTO>,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<BulkResult>>> {
+    idempotency_key
+        .with_payload(&*bulk)
+        .run(_dbpool, Some(_token_validation.claims.sub), || {
+            let db_connection_result = get_connection(_dbpool);
+            let mut db_connection = match db_connection_result {
+                Ok(conn) => conn,
+                Err(_) => {
+                    return Err(Json(Response {
+                        message: "Failed to get connection".to_string(),
+                        data: vec![],
+                    }));
+                }
+            };
+
+            let bulk_result = BulkOperation::apply_bulk(
+                _token_validation.claims.sub,
+                bulk.into_inner(),
+                &mut db_connection,
+            );
+
+            match bulk_result {
+                Ok((message, results)) => {
+                    return Ok(Json(Response {
+                        message: message,
+                        data: results,
+                    }));
+                }
+                Err(message) => {
+                    return Err(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+            }
+        })
+}
+
+/// Route to move a todo in the manual order
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo to be moved
+/// * `move_todo` - A Json containing the todos the todo is placed between. For reference, see `MoveDTO` struct in `models/todos.rs`
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
 /// * `_dbpool` - A pool of database connections
 /// * `_token_validation` - A struct containing the token validation result
 ///
@@ -130,10 +660,131 @@ pub fn new_todo(
 ///
 /// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
 #[openapi(tag = "Todo")]
-#[post("/todo/<todo_id>", format = "application/json", data = "<update_todo>")]
-pub fn update_todo(
+#[post(
+    "/todo/<todo_id>/move",
+    format = "application/json",
+    data = "<move_todo>"
+)]
+pub fn move_todo(
+    todo_id: i32,
+    move_todo: Json<MoveDTO>,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<i8>>> {
+    idempotency_key.with_payload(&*move_todo).run(
+        _dbpool,
+        Some(_token_validation.claims.sub),
+        || {
+            let db_connection_result = get_connection(_dbpool);
+            let mut db_connection = match db_connection_result {
+                Ok(conn) => conn,
+                Err(_) => {
+                    return Err(Json(Response {
+                        message: "Failed to get connection".to_string(),
+                        data: vec![],
+                    }));
+                }
+            };
+
+            let move_todo_result = Todo::move_todo(
+                todo_id,
+                _token_validation.claims.sub,
+                move_todo.into_inner(),
+                &mut db_connection,
+            );
+
+            match move_todo_result {
+                Ok(message) => {
+                    return Ok(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+                Err(message) => {
+                    return Err(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+            }
+        },
+    )
+}
+
+/// Route to assign a todo to a user who can access it
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo
+/// * `assignee_id` - The id of the user the todo is assigned to
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Todo")]
+#[post("/todo/<todo_id>/assignee/<assignee_id>", format = "application/json")]
+pub fn assign_todo(
+    todo_id: i32,
+    assignee_id: i32,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<i8>>> {
+    idempotency_key.run(_dbpool, Some(_token_validation.claims.sub), || {
+        let db_connection_result = get_connection(_dbpool);
+        let mut db_connection = match db_connection_result {
+            Ok(conn) => conn,
+            Err(_) => {
+                return Err(Json(Response {
+                    message: "Failed to get connection".to_string(),
+                    data: vec![],
+                }));
+            }
+        };
+
+        let assign_result = Todo::assign(
+            todo_id,
+            _token_validation.claims.sub,
+            assignee_id,
+            &mut db_connection,
+        );
+
+        match assign_result {
+            Ok(message) => {
+                return Ok(Json(Response {
+                    message: message,
+                    data: vec![],
+                }));
+            }
+            Err(message) => {
+                return Err(Json(Response {
+                    message: message,
+                    data: vec![],
+                }));
+            }
+        }
+    })
+}
+
+/// Route to unassign a todo
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the todo
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Todo")]
+#[delete("/todo/<todo_id>/assignee", format = "application/json")]
+pub fn unassign_todo(
     todo_id: i32,
-    update_todo: Json<TodoDTO>,
     _dbpool: &State<PoolConnection>,
     _token_validation: TokenValidation,
 ) -> Json<Response<i8>> {
@@ -148,16 +799,9 @@ pub fn update_todo(
         }
     };
 
-    let todo = TodoDTO {
-        user_id: Some(_token_validation.claims.sub),
-        title: update_todo.title.clone(),
-        description: update_todo.description.clone(),
-        completed: update_todo.completed.clone(),
-    };
-
-    let update_todo_result = Todo::update_todo(todo_id, todo, &mut db_connection);
+    let unassign_result = Todo::unassign(todo_id, _token_validation.claims.sub, &mut db_connection);
 
-    match update_todo_result {
+    match unassign_result {
         Ok(message) => {
             return Json(Response {
                 message: message,
@@ -173,6 +817,60 @@ pub fn update_todo(
     }
 }
 
+/// Route to preview the upcoming occurrences of a recurring todo
+///
+/// # Arguments
+///
+/// * `todo_id` - The id of the recurring todo
+/// * `count` - The number of occurrences to return, defaults to 5 and is capped at 50
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the due dates of the upcoming occurrences in UTC - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Todo")]
+#[get("/todo/<todo_id>/occurrences?<count>", format = "application/json")]
+pub fn get_occurrences(
+    todo_id: i32,
+    count: Option<usize>,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<NaiveDateTime>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let occurrences_result = Todo::get_occurrences(
+        todo_id,
+        _token_validation.claims.sub,
+        count.unwrap_or(5).min(50),
+        &mut db_connection,
+    );
+
+    match occurrences_result {
+        Ok(occurrences) => {
+            return Json(Response {
+                message: "Occurrences fetched successfully".to_string(),
+                data: occurrences,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
 /// Internal function to validate the input on creating a new todo
 ///
 /// # Arguments
@@ -191,6 +889,14 @@ fn validate_input(todo: TodoDTO) -> Result<(), String> {
         return Err("completed cannot be a parameter".to_string());
     }
 
+    if todo.completed_at.is_some() {
+        return Err("completed_at cannot be a parameter".to_string());
+    }
+
+    if todo.position.is_some() {
+        return Err("position cannot be a parameter".to_string());
+    }
+
     if todo.title.is_none() {
         return Err("paramter title is required".to_string());
     }
@@ -1,3 +1,4 @@
+use rocket::http::Status;
 use rocket::serde::json::Json;
 use rocket::{get, post, State};
 use rocket_okapi::okapi::schemars;
@@ -8,6 +9,7 @@ use serde::{Deserialize, Serialize};
 use crate::config::db::{get_connection, PoolConnection};
 use crate::consts::Response;
 use crate::models::user::{User, UserDTO, UserLoginDTO};
+use crate::utils::idempotency::{IdempotencyKey, Idempotent};
 use crate::utils::jwt::{generate_token, TokenValidation};
 
 /// Struct to hold the response for login
@@ -22,41 +24,60 @@ pub struct LoginResponse {
 /// # Arguments
 ///
 /// * `user_signup` - A Json containing the new user details. For reference, see `UserDTO` struct in `models/user.rs`
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
 /// * `_dbpool` - A pool of database connections
 ///
 /// # Returns
 ///
-/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+/// * 201 Created with the created user, without its password - for reference, see `Response` struct in `consts.rs`
+/// * A Json containing the error message if the user cannot be created
 #[openapi(tag = "User")]
 #[post("/signup", format = "application/json", data = "<user_signup>")]
-pub fn signup(user_signup: Json<UserDTO>, _dbpool: &State<PoolConnection>) -> Json<Response<i32>> {
-    let db_connection_result = get_connection(_dbpool);
-    let mut db_connection = match db_connection_result {
-        Ok(conn) => conn,
-        Err(_) => {
-            return Json(Response {
-                message: "Failed to get connection".to_string(),
-                data: vec![],
-            });
-        }
-    };
+pub fn signup(
+    user_signup: Json<UserDTO>,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+) -> Idempotent<(Status, Json<Response<User>>)> {
+    idempotency_key
+        .with_payload(&*user_signup)
+        .run(_dbpool, None, || {
+            let db_connection_result = get_connection(_dbpool);
+            let mut db_connection = match db_connection_result {
+                Ok(conn) => conn,
+                Err(_) => {
+                    return Err((
+                        Status::Ok,
+                        Json(Response {
+                            message: "Failed to get connection".to_string(),
+                            data: vec![],
+                        }),
+                    ));
+                }
+            };
 
-    let signup_result = User::signup(user_signup.into_inner(), &mut db_connection);
+            let signup_result = User::signup(user_signup.into_inner(), &mut db_connection);
 
-    match signup_result {
-        Ok(message) => {
-            return Json(Response {
-                message: message,
-                data: vec![],
-            });
-        }
-        Err(message) => {
-            return Json(Response {
-                message: message,
-                data: vec![],
-            });
-        }
-    }
+            match signup_result {
+                Ok(created) => {
+                    return Ok((
+                        Status::Created,
+                        Json(Response {
+                            message: "User created".to_string(),
+                            data: vec![created],
+                        }),
+                    ));
+                }
+                Err(message) => {
+                    return Err((
+                        Status::Ok,
+                        Json(Response {
+                            message: message,
+                            data: vec![],
+                        }),
+                    ));
+                }
+            }
+        })
 }
 
 /// Route to login a user
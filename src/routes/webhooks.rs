@@ -0,0 +1,400 @@
+use rocket::serde::json::Json;
+use rocket::{delete, get, patch, post, State};
+use rocket_okapi::openapi;
+
+use crate::config::db::{get_connection, PoolConnection};
+use crate::consts::Response;
+use crate::models::webhook::{Webhook, WebhookDTO, WebhookDelivery, WebhookPatch};
+use crate::utils::idempotency::{IdempotencyKey, Idempotent};
+use crate::utils::jwt::TokenValidation;
+
+/// Route to register a webhook
+///
+/// The webhook receives the changes made to every todo the user can read, for the event types it
+/// subscribes to: `todo.created`, `todo.updated`, `todo.completed` and `todo.deleted`. With the
+/// `webhook` notification channel on, `notification.created` sends the notifications of the user.
+/// Deliveries are signed with the secret, see the `X-Webhook-Signature` header
+///
+/// # Arguments
+///
+/// * `new_webhook` - A Json containing the URL, secret and event types. For reference, see `WebhookDTO` struct in `models/webhook.rs`
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the registered webhook - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Webhooks")]
+#[post("/webhooks", format = "application/json", data = "<new_webhook>")]
+pub fn new_webhook(
+    new_webhook: Json<WebhookDTO>,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<Webhook>>> {
+    idempotency_key.with_payload(&*new_webhook).run(
+        _dbpool,
+        Some(_token_validation.claims.sub),
+        || {
+            let db_connection_result = get_connection(_dbpool);
+            let mut db_connection = match db_connection_result {
+                Ok(conn) => conn,
+                Err(_) => {
+                    return Err(Json(Response {
+                        message: "Failed to get connection".to_string(),
+                        data: vec![],
+                    }));
+                }
+            };
+
+            let new_webhook_result = Webhook::new_webhook(
+                _token_validation.claims.sub,
+                new_webhook.into_inner(),
+                &mut db_connection,
+            );
+
+            match new_webhook_result {
+                Ok(webhook) => {
+                    return Ok(Json(Response {
+                        message: "Successfully created webhook".to_string(),
+                        data: vec![webhook],
+                    }));
+                }
+                Err(message) => {
+                    return Err(Json(Response {
+                        message: message,
+                        data: vec![],
+                    }));
+                }
+            }
+        },
+    )
+}
+
+/// Route to get the webhooks of a user
+///
+/// # Arguments
+///
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the webhooks - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Webhooks")]
+#[get("/webhooks", format = "application/json")]
+pub fn get_webhooks(
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<Webhook>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let webhooks_result = Webhook::get_webhooks(_token_validation.claims.sub, &mut db_connection);
+
+    match webhooks_result {
+        Ok(webhooks) => {
+            return Json(Response {
+                message: "Webhooks fetched successfully".to_string(),
+                data: webhooks,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to update a webhook
+///
+/// Setting `active` to true enables a webhook disabled for failing, its pending deliveries are sent again
+///
+/// # Arguments
+///
+/// * `webhook_id` - The id of the webhook
+/// * `patch` - A Json containing the fields to change. For reference, see `WebhookPatch` struct in `models/webhook.rs`
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the updated webhook - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Webhooks")]
+#[patch(
+    "/webhooks/<webhook_id>",
+    format = "application/json",
+    data = "<patch>"
+)]
+pub fn update_webhook(
+    webhook_id: i32,
+    patch: Json<WebhookPatch>,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<Webhook>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let update_webhook_result = Webhook::update_webhook(
+        webhook_id,
+        _token_validation.claims.sub,
+        patch.into_inner(),
+        &mut db_connection,
+    );
+
+    match update_webhook_result {
+        Ok(webhook) => {
+            return Json(Response {
+                message: "Successfully updated webhook".to_string(),
+                data: vec![webhook],
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to delete a webhook, along with its delivery log
+///
+/// # Arguments
+///
+/// * `webhook_id` - The id of the webhook
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Webhooks")]
+#[delete("/webhooks/<webhook_id>", format = "application/json")]
+pub fn delete_webhook(
+    webhook_id: i32,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<i8>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let delete_webhook_result =
+        Webhook::delete_webhook(webhook_id, _token_validation.claims.sub, &mut db_connection);
+
+    match delete_webhook_result {
+        Ok(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to send a test delivery to a webhook
+///
+/// The delivery has the `ping` event type, it shows up in the delivery log like any other
+///
+/// # Arguments
+///
+/// * `webhook_id` - The id of the webhook
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the queued delivery - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Webhooks")]
+#[post("/webhooks/<webhook_id>/ping", format = "application/json")]
+pub fn ping_webhook(
+    webhook_id: i32,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<WebhookDelivery>>> {
+    idempotency_key.run(_dbpool, Some(_token_validation.claims.sub), || {
+        let db_connection_result = get_connection(_dbpool);
+        let mut db_connection = match db_connection_result {
+            Ok(conn) => conn,
+            Err(_) => {
+                return Err(Json(Response {
+                    message: "Failed to get connection".to_string(),
+                    data: vec![],
+                }));
+            }
+        };
+
+        let ping_result =
+            Webhook::ping(webhook_id, _token_validation.claims.sub, &mut db_connection);
+
+        match ping_result {
+            Ok(delivery) => {
+                return Ok(Json(Response {
+                    message: "Successfully queued delivery".to_string(),
+                    data: vec![delivery],
+                }));
+            }
+            Err(message) => {
+                return Err(Json(Response {
+                    message: message,
+                    data: vec![],
+                }));
+            }
+        }
+    })
+}
+
+/// Route to get the delivery log of a webhook, newest first
+///
+/// # Arguments
+///
+/// * `webhook_id` - The id of the webhook
+/// * `before` - Only deliveries older than this delivery id are returned, to get the next page
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the deliveries - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Webhooks")]
+#[get(
+    "/webhooks/<webhook_id>/deliveries?<before>",
+    format = "application/json"
+)]
+pub fn get_deliveries(
+    webhook_id: i32,
+    before: Option<i32>,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Json<Response<WebhookDelivery>> {
+    let db_connection_result = get_connection(_dbpool);
+    let mut db_connection = match db_connection_result {
+        Ok(conn) => conn,
+        Err(_) => {
+            return Json(Response {
+                message: "Failed to get connection".to_string(),
+                data: vec![],
+            });
+        }
+    };
+
+    let deliveries_result = WebhookDelivery::get_deliveries(
+        webhook_id,
+        _token_validation.claims.sub,
+        before,
+        &mut db_connection,
+    );
+
+    match deliveries_result {
+        Ok(deliveries) => {
+            return Json(Response {
+                message: "Deliveries fetched successfully".to_string(),
+                data: deliveries,
+            });
+        }
+        Err(message) => {
+            return Json(Response {
+                message: message,
+                data: vec![],
+            });
+        }
+    }
+}
+
+/// Route to send a delivery again
+///
+/// A new delivery with the same payload is queued, the former one stays in the log
+///
+/// # Arguments
+///
+/// * `webhook_id` - The id of the webhook
+/// * `delivery_id` - The id of the delivery
+/// * `idempotency_key` - The Idempotency-Key header, a retry sent with the same key gets the first response back
+/// * `_dbpool` - A pool of database connections
+/// * `_token_validation` - A struct containing the token validation result
+///
+/// # Returns
+///
+/// * A Json containing the response with the queued delivery - for reference, see `Response` struct in `consts.rs`
+#[openapi(tag = "Webhooks")]
+#[post(
+    "/webhooks/<webhook_id>/deliveries/<delivery_id>/redeliver",
+    format = "application/json"
+)]
+pub fn redeliver(
+    webhook_id: i32,
+    delivery_id: i32,
+    idempotency_key: IdempotencyKey,
+    _dbpool: &State<PoolConnection>,
+    _token_validation: TokenValidation,
+) -> Idempotent<Json<Response<WebhookDelivery>>> {
+    idempotency_key.run(_dbpool, Some(_token_validation.claims.sub), || {
+        let db_connection_result = get_connection(_dbpool);
+        let mut db_connection = match db_connection_result {
+            Ok(conn) => conn,
+            Err(_) => {
+                return Err(Json(Response {
+                    message: "Failed to get connection".to_string(),
+                    data: vec![],
+                }));
+            }
+        };
+
+        let redeliver_result = WebhookDelivery::redeliver(
+            webhook_id,
+            delivery_id,
+            _token_validation.claims.sub,
+            &mut db_connection,
+        );
+
+        match redeliver_result {
+            Ok(delivery) => {
+                return Ok(Json(Response {
+                    message: "Successfully queued delivery".to_string(),
+                    data: vec![delivery],
+                }));
+            }
+            Err(message) => {
+                return Err(Json(Response {
+                    message: message,
+                    data: vec![],
+                }));
+            }
+        }
+    })
+}
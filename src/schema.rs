@@ -1,5 +1,217 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    idempotency_keys (id) {
+        id -> Int4,
+        user_id -> Nullable<Int4>,
+        idempotency_key -> Varchar,
+        fingerprint -> Varchar,
+        status -> Nullable<Int2>,
+        response -> Nullable<Jsonb>,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+        headers -> Nullable<Jsonb>,
+    }
+}
+
+diesel::table! {
+    invitations (id) {
+        id -> Int4,
+        organization_id -> Int4,
+        invited_by -> Int4,
+        email -> Varchar,
+        role -> Varchar,
+        token -> Varchar,
+        expires_at -> Timestamp,
+        accepted_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    job_schedules (name) {
+        name -> Varchar,
+        kind -> Varchar,
+        payload -> Jsonb,
+        cron -> Varchar,
+        next_run_at -> Timestamp,
+        last_run_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    jobs (id) {
+        id -> Int4,
+        kind -> Varchar,
+        payload -> Jsonb,
+        status -> Varchar,
+        attempts -> Int4,
+        max_attempts -> Int4,
+        unique_key -> Nullable<Varchar>,
+        run_at -> Timestamp,
+        locked_until -> Nullable<Timestamp>,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+        finished_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    lists (id) {
+        id -> Int4,
+        user_id -> Int4,
+        organization_id -> Nullable<Int4>,
+        name -> Varchar,
+        color -> Varchar,
+        position -> Int4,
+        archived -> Bool,
+    }
+}
+
+diesel::table! {
+    memberships (id) {
+        id -> Int4,
+        organization_id -> Int4,
+        user_id -> Int4,
+        role -> Varchar,
+    }
+}
+
+diesel::table! {
+    notification_settings (user_id) {
+        user_id -> Int4,
+        time_zone -> Varchar,
+        quiet_hours_start -> Nullable<Time>,
+        quiet_hours_end -> Nullable<Time>,
+        channels -> Array<Text>,
+    }
+}
+
+diesel::table! {
+    notifications (id) {
+        id -> Int4,
+        user_id -> Int4,
+        actor_id -> Nullable<Int4>,
+        todo_id -> Nullable<Int4>,
+        kind -> Varchar,
+        message -> Text,
+        created_at -> Timestamp,
+        read_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    organizations (id) {
+        id -> Int4,
+        name -> Varchar,
+    }
+}
+
+diesel::table! {
+    status_transitions (from_status_id, to_status_id) {
+        from_status_id -> Int4,
+        to_status_id -> Int4,
+    }
+}
+
+diesel::table! {
+    statuses (id) {
+        id -> Int4,
+        list_id -> Int4,
+        name -> Varchar,
+        position -> Int4,
+        done -> Bool,
+    }
+}
+
+diesel::table! {
+    tags (id) {
+        id -> Int4,
+        user_id -> Int4,
+        name -> Varchar,
+    }
+}
+
+diesel::table! {
+    todo_attachments (id) {
+        id -> Int4,
+        todo_id -> Int4,
+        user_id -> Nullable<Int4>,
+        file_name -> Varchar,
+        content_type -> Varchar,
+        size -> Int8,
+        storage_key -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    todo_comment_revisions (id) {
+        id -> Int4,
+        comment_id -> Int4,
+        body -> Text,
+        edited_by -> Nullable<Int4>,
+        edited_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    todo_comments (id) {
+        id -> Int4,
+        todo_id -> Int4,
+        user_id -> Int4,
+        body -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    todo_dependencies (blocker_id, blocked_id) {
+        blocker_id -> Int4,
+        blocked_id -> Int4,
+    }
+}
+
+diesel::table! {
+    todo_events (id) {
+        id -> Int4,
+        todo_id -> Int4,
+        version -> Int4,
+        actor_id -> Nullable<Int4>,
+        action -> Varchar,
+        changes -> Jsonb,
+        owner_id -> Int4,
+        organization_id -> Nullable<Int4>,
+        created_at -> Timestamp,
+        mutation_id -> Int8,
+    }
+}
+
+diesel::table! {
+    todo_reminders (id) {
+        id -> Int4,
+        todo_id -> Int4,
+        user_id -> Int4,
+        offset_minutes -> Int4,
+        fired_for -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    todo_tags (todo_id, tag_id) {
+        todo_id -> Int4,
+        tag_id -> Int4,
+    }
+}
+
+diesel::table! {
+    todo_watchers (todo_id, user_id) {
+        todo_id -> Int4,
+        user_id -> Int4,
+    }
+}
+
 diesel::table! {
     todos (id) {
         id -> Int4,
@@ -7,6 +219,22 @@ diesel::table! {
         title -> Varchar,
         description -> Text,
         completed -> Bool,
+        organization_id -> Nullable<Int4>,
+        list_id -> Nullable<Int4>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        due_at -> Nullable<Timestamp>,
+        completed_at -> Nullable<Timestamp>,
+        priority -> Varchar,
+        recurrence_rule -> Nullable<Text>,
+        recurrence_tz -> Nullable<Varchar>,
+        series_id -> Nullable<Int4>,
+        occurrence -> Int4,
+        parent_id -> Nullable<Int4>,
+        position -> Varchar,
+        status_id -> Nullable<Int4>,
+        assignee_id -> Nullable<Int4>,
+        version -> Int4,
     }
 }
 
@@ -19,6 +247,91 @@ diesel::table! {
     }
 }
 
-diesel::joinable!(todos -> users (user_id));
+diesel::table! {
+    webhook_deliveries (id) {
+        id -> Int4,
+        webhook_id -> Int4,
+        event_id -> Nullable<Int4>,
+        event_type -> Varchar,
+        payload -> Jsonb,
+        status -> Varchar,
+        attempts -> Int4,
+        next_attempt_at -> Timestamp,
+        response_status -> Nullable<Int4>,
+        error -> Nullable<Text>,
+        created_at -> Timestamp,
+        delivered_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    webhooks (id) {
+        id -> Int4,
+        user_id -> Int4,
+        url -> Varchar,
+        secret -> Varchar,
+        event_types -> Array<Text>,
+        active -> Bool,
+        failure_count -> Int4,
+        disabled_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::joinable!(idempotency_keys -> users (user_id));
+diesel::joinable!(invitations -> organizations (organization_id));
+diesel::joinable!(invitations -> users (invited_by));
+diesel::joinable!(lists -> organizations (organization_id));
+diesel::joinable!(lists -> users (user_id));
+diesel::joinable!(memberships -> organizations (organization_id));
+diesel::joinable!(memberships -> users (user_id));
+diesel::joinable!(notification_settings -> users (user_id));
+diesel::joinable!(notifications -> todos (todo_id));
+diesel::joinable!(statuses -> lists (list_id));
+diesel::joinable!(tags -> users (user_id));
+diesel::joinable!(todo_attachments -> todos (todo_id));
+diesel::joinable!(todo_attachments -> users (user_id));
+diesel::joinable!(todo_comment_revisions -> todo_comments (comment_id));
+diesel::joinable!(todo_comment_revisions -> users (edited_by));
+diesel::joinable!(todo_comments -> todos (todo_id));
+diesel::joinable!(todo_comments -> users (user_id));
+diesel::joinable!(todo_events -> organizations (organization_id));
+diesel::joinable!(todo_reminders -> todos (todo_id));
+diesel::joinable!(todo_reminders -> users (user_id));
+diesel::joinable!(todo_tags -> tags (tag_id));
+diesel::joinable!(todo_tags -> todos (todo_id));
+diesel::joinable!(todo_watchers -> todos (todo_id));
+diesel::joinable!(todo_watchers -> users (user_id));
+diesel::joinable!(todos -> lists (list_id));
+diesel::joinable!(todos -> organizations (organization_id));
+diesel::joinable!(todos -> statuses (status_id));
+diesel::joinable!(webhook_deliveries -> todo_events (event_id));
+diesel::joinable!(webhook_deliveries -> webhooks (webhook_id));
+diesel::joinable!(webhooks -> users (user_id));
 
-diesel::allow_tables_to_appear_in_same_query!(todos, users,);
+diesel::allow_tables_to_appear_in_same_query!(
+    idempotency_keys,
+    invitations,
+    job_schedules,
+    jobs,
+    lists,
+    memberships,
+    notification_settings,
+    notifications,
+    organizations,
+    status_transitions,
+    statuses,
+    tags,
+    todo_attachments,
+    todo_comment_revisions,
+    todo_comments,
+    todo_dependencies,
+    todo_events,
+    todo_reminders,
+    todo_tags,
+    todo_watchers,
+    todos,
+    users,
+    webhook_deliveries,
+    webhooks,
+);
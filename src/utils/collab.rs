@@ -0,0 +1,551 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+use rocket::fairing::AdHoc;
+use rocket::tokio::net::{TcpListener, TcpStream};
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast::error::RecvError;
+use rocket::tokio::sync::mpsc::{self, Receiver, Sender};
+use rocket::tokio::time::{sleep, timeout};
+use rocket::Shutdown;
+use tokio_tungstenite::tungstenite::http::header::AUTHORIZATION;
+use tokio_tungstenite::tungstenite::http::StatusCode;
+
+use crate::config::db::PoolConnection;
+use crate::models::collab::{Activity, ClientMessage, Presence, ServerMessage, Topic};
+use crate::models::event::Event;
+use crate::models::organization::Membership;
+use crate::models::user::User;
+use crate::utils::event_bus::EventBus;
+use crate::utils::jwt::TokenValidation;
+use crate::utils::websocket::{self, Frame, Message, ProtocolError, Reader, Request, Writer};
+
+/// Path clients open the WebSocket on
+static PATH: &str = "/collab";
+
+/// Time a client has to send its handshake once connected
+static HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Number of messages waiting to be sent to a client, a client reading slower than that misses presence updates
+static OUTGOING_CAPACITY: usize = 256;
+
+/// Maximum number of topics a connection subscribes to
+static MAX_SUBSCRIPTIONS: usize = 100;
+
+/// Close code sent when the server stops
+static CLOSE_GOING_AWAY: u16 = 1001;
+
+/// Server for the live editing of todos over WebSocket
+///
+/// Rocket cannot upgrade its connections, so the WebSocket is served on an address of its own,
+/// e.g. `ws://localhost:8001/collab` with the token in the `Authorization` header. The server starts
+/// once Rocket is up and uses the same database pool and event bus, so changes made through the HTTP
+/// API or on other server instances reach the subscribers too. Presence is only shared between the
+/// connections of an instance
+pub struct CollabServer {
+    /// Address to listen on, e.g. `127.0.0.1:8001`
+    address: String,
+}
+
+/// State shared by the connections of the server
+struct Hub {
+    /// A pool of database connections
+    pool: PoolConnection,
+    /// The event bus changes are read from
+    event_bus: EventBus,
+    /// Id given to the next connection
+    next_connection: AtomicU64,
+    /// Connections subscribed to each topic, along with their presence
+    topics: Mutex<HashMap<Topic, HashMap<u64, Member>>>,
+}
+
+/// Connection subscribed to a topic
+struct Member {
+    /// What the user is doing on the topic
+    presence: Presence,
+    /// Sends messages to the connection
+    sender: Sender<Frame>,
+}
+
+/// A connection of a user
+struct Session {
+    /// Id of the connection
+    id: u64,
+    /// Id of the user
+    user: i32,
+    /// Name of the user, shown to the other users
+    name: String,
+    /// Time the token of the connection expires, as a Unix timestamp, the connection is closed then
+    expires_at: i64,
+    /// Topics the connection subscribes to
+    topics: HashSet<Topic>,
+    /// Versions of todos produced by the edits of this connection, their events are not sent back
+    own_edits: HashSet<(i32, i32)>,
+    /// Sends messages to the client
+    sender: Sender<Frame>,
+}
+
+impl CollabServer {
+    /// Creates the server
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Address to listen on, e.g. `127.0.0.1:8001`
+    ///
+    /// # Returns
+    ///
+    /// * The server
+    pub fn new(address: String) -> CollabServer {
+        CollabServer { address }
+    }
+
+    /// Fairing starting the server once Rocket is up, the database pool and the event bus must be managed
+    ///
+    /// # Returns
+    ///
+    /// * The fairing
+    pub fn fairing(self) -> AdHoc {
+        AdHoc::on_liftoff("Collaboration", move |rocket| {
+            Box::pin(async move {
+                let (pool, event_bus) =
+                    match (rocket.state::<PoolConnection>(), rocket.state::<EventBus>()) {
+                        (Some(pool), Some(event_bus)) => (pool.clone(), event_bus.clone()),
+                        _ => {
+                            log::error!("Collaboration needs the database pool and the event bus");
+                            return;
+                        }
+                    };
+
+                let listener = match TcpListener::bind(&self.address).await {
+                    Ok(listener) => listener,
+                    Err(error) => {
+                        log::error!("Failed to listen on {}: {}", self.address, error);
+                        return;
+                    }
+                };
+
+                let hub = Arc::new(Hub {
+                    pool,
+                    event_bus,
+                    next_connection: AtomicU64::new(1),
+                    topics: Mutex::new(HashMap::new()),
+                });
+
+                rocket::tokio::spawn(CollabServer::accept(listener, hub, rocket.shutdown()));
+            })
+        })
+    }
+
+    /// Internal function accepting connections until the server stops
+    async fn accept(listener: TcpListener, hub: Arc<Hub>, mut shutdown: Shutdown) {
+        loop {
+            let stream = select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(error) => {
+                        log::warn!("Failed to accept connection: {}", error);
+                        continue;
+                    }
+                },
+                _ = &mut shutdown => break,
+            };
+
+            rocket::tokio::spawn(Session::open(stream, hub.clone(), shutdown.clone()));
+        }
+    }
+}
+
+impl Hub {
+    /// Internal function to add or update the presence of a connection on a topic, telling everyone on it
+    fn join(&self, topic: Topic, connection: u64, presence: Presence, sender: &Sender<Frame>) {
+        let mut topics = self
+            .topics
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+        let members = topics.entry(topic).or_default();
+
+        members.insert(
+            connection,
+            Member {
+                presence,
+                sender: sender.clone(),
+            },
+        );
+
+        Hub::announce(topic, members);
+    }
+
+    /// Internal function to remove a connection from a topic, telling the others on it
+    fn leave(&self, topic: Topic, connection: u64) {
+        let mut topics = self
+            .topics
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+
+        if let Some(members) = topics.get_mut(&topic) {
+            members.remove(&connection);
+
+            if members.is_empty() {
+                topics.remove(&topic);
+            } else {
+                Hub::announce(topic, members);
+            }
+        }
+    }
+
+    /// Internal function to send the presence on a topic to everyone on it
+    /// Skipped for the connections whose queue is full, the next update carries the whole presence again
+    fn announce(topic: Topic, members: &HashMap<u64, Member>) {
+        let mut users = members
+            .iter()
+            .map(|(connection, member)| (*connection, member.presence.clone()))
+            .collect::<Vec<_>>();
+        users.sort_by_key(|(connection, _)| *connection);
+
+        let message = ServerMessage::Presence {
+            topic,
+            users: users.into_iter().map(|(_, presence)| presence).collect(),
+        };
+
+        let text = match serde_json::to_string(&message) {
+            Ok(text) => text,
+            Err(_) => return,
+        };
+
+        for member in members.values() {
+            let _ = member.sender.try_send(Frame::Text(text.clone()));
+        }
+    }
+}
+
+impl Session {
+    /// Internal function to run a connection, from the handshake until it closes
+    async fn open(stream: TcpStream, hub: Arc<Hub>, shutdown: Shutdown) {
+        let handshake = timeout(
+            HANDSHAKE_TIMEOUT,
+            websocket::accept(stream, Session::authenticate),
+        )
+        .await;
+
+        let (validation, reader, mut writer) = match handshake {
+            Ok(Ok(accepted)) => accepted,
+            _ => return,
+        };
+
+        let user = validation.claims.sub;
+
+        let profile = EventBus::query(&hub.pool, move |conn| User::find_by_id(user, conn)).await;
+
+        let name = match profile {
+            Ok(profile) => profile.name,
+            Err(message) => {
+                let frame = Frame::Close(websocket::CLOSE_POLICY_VIOLATION, message);
+                let _ = websocket::write_frame(&mut writer, frame).await;
+                return;
+            }
+        };
+
+        // Subscribed before the first message is read, so no change falls in between
+        let events = hub.event_bus.subscribe();
+
+        let (sender, outgoing) = mpsc::channel(OUTGOING_CAPACITY);
+        let (incoming_sender, incoming) = mpsc::channel(1);
+
+        let writer = rocket::tokio::spawn(Session::write(writer, outgoing));
+        let reader = rocket::tokio::spawn(Session::read(reader, incoming_sender));
+
+        let mut session = Session {
+            id: hub.next_connection.fetch_add(1, Ordering::Relaxed),
+            user,
+            name,
+            expires_at: validation.claims.exp,
+            topics: HashSet::new(),
+            own_edits: HashSet::new(),
+            sender,
+        };
+
+        session.run(&hub, incoming, events, shutdown).await;
+
+        for topic in session.topics.iter() {
+            hub.leave(*topic, session.id);
+        }
+
+        // The writer stops once every sender is dropped, after sending what is left
+        reader.abort();
+        drop(session);
+        let _ = writer.await;
+    }
+
+    /// Internal function checking the path and the token of the handshake request
+    /// The token is only read from the `Authorization` header, a query parameter would end up in access logs
+    fn authenticate(request: &Request) -> Result<TokenValidation, (StatusCode, String)> {
+        if request.uri().path() != PATH {
+            return Err((StatusCode::NOT_FOUND, "Not found".to_string()));
+        }
+
+        let token = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|header| header.to_str().ok())
+            .map(|header| header.trim_start_matches("Bearer "));
+
+        match token.map(TokenValidation::from_token) {
+            Some(Ok(validation)) => Ok(validation),
+            Some(Err(message)) => Err((StatusCode::UNAUTHORIZED, message)),
+            None => Err((StatusCode::UNAUTHORIZED, "Token not found".to_string())),
+        }
+    }
+
+    /// Internal function handling the messages of the client and the changes made to todos until the connection closes
+    async fn run(
+        &mut self,
+        hub: &Hub,
+        mut incoming: Receiver<Result<Message, ProtocolError>>,
+        mut events: rocket::tokio::sync::broadcast::Receiver<Arc<Event>>,
+        mut shutdown: Shutdown,
+    ) {
+        let remaining = (self.expires_at - Utc::now().timestamp()).max(0) as u64;
+        let expiry = sleep(Duration::from_secs(remaining));
+        rocket::tokio::pin!(expiry);
+
+        loop {
+            select! {
+                message = incoming.recv() => match message {
+                    Some(Ok(Message::Text(text))) => self.handle(hub, &text).await,
+                    Some(Ok(Message::Ping(payload))) => self.send_frame(Frame::Pong(payload)).await,
+                    Some(Ok(Message::Close)) | None => {
+                        self.send_frame(Frame::Close(1000, String::new())).await;
+                        break;
+                    }
+                    Some(Err(error)) => {
+                        self.send_frame(Frame::Close(error.code, error.message)).await;
+                        break;
+                    }
+                },
+                event = events.recv() => match event {
+                    Ok(event) => self.forward(hub, event).await,
+                    Err(RecvError::Lagged(_)) => self.send(ServerMessage::Resync).await,
+                    Err(RecvError::Closed) => break,
+                },
+                _ = &mut expiry => {
+                    self.send_frame(Frame::Close(websocket::CLOSE_POLICY_VIOLATION, "Token expired".to_string()))
+                        .await;
+                    break;
+                }
+                _ = &mut shutdown => {
+                    self.send_frame(Frame::Close(CLOSE_GOING_AWAY, "Server is stopping".to_string()))
+                        .await;
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Internal function handling a message of the client
+    async fn handle(&mut self, hub: &Hub, text: &str) {
+        let message = match serde_json::from_str::<ClientMessage>(text) {
+            Ok(message) => message,
+            Err(error) => {
+                self.ack(None, Err(format!("Invalid message: {}", error)))
+                    .await;
+                return;
+            }
+        };
+
+        match message {
+            ClientMessage::Subscribe { reference, topic } => {
+                if !self.topics.contains(&topic) && self.topics.len() >= MAX_SUBSCRIPTIONS {
+                    self.ack(reference, Err("Too many subscriptions".to_string()))
+                        .await;
+                    return;
+                }
+
+                let user = self.user;
+                let result =
+                    EventBus::query(&hub.pool, move |conn| topic.authorize(user, conn)).await;
+
+                if result.is_ok() {
+                    self.topics.insert(topic);
+                    hub.join(
+                        topic,
+                        self.id,
+                        self.presence(Activity::Viewing, None),
+                        &self.sender,
+                    );
+                }
+
+                self.ack(reference, result.map(|_| "Subscribed".to_string()))
+                    .await;
+            }
+            ClientMessage::Unsubscribe { reference, topic } => {
+                if self.topics.remove(&topic) {
+                    hub.leave(topic, self.id);
+                }
+
+                self.ack(reference, Ok("Unsubscribed".to_string())).await;
+            }
+            ClientMessage::Presence {
+                reference,
+                topic,
+                activity,
+                field,
+            } => {
+                if !self.topics.contains(&topic) {
+                    self.ack(reference, Err("Not subscribed".to_string())).await;
+                    return;
+                }
+
+                hub.join(topic, self.id, self.presence(activity, field), &self.sender);
+                self.ack(reference, Ok("Presence updated".to_string()))
+                    .await;
+            }
+            ClientMessage::Edit {
+                reference,
+                id,
+                base_version,
+                patch,
+            } => {
+                let user = self.user;
+                let result = EventBus::query(&hub.pool, move |conn| {
+                    ClientMessage::edit(id, user, base_version, patch, conn)
+                })
+                .await;
+
+                match result {
+                    Ok(todo) => {
+                        self.own_edits.insert((todo.id, todo.version));
+                        self.send(ServerMessage::Ack {
+                            reference,
+                            ok: true,
+                            message: "Successfully updated todo".to_string(),
+                            todo: Some(Box::new(todo)),
+                        })
+                        .await;
+                    }
+                    Err(message) => self.ack(reference, Err(message)).await,
+                }
+            }
+        }
+    }
+
+    /// Internal function sending an event to the client, once for every subscribed topic it changed
+    /// The memberships of the user and the access to each topic are read again first, so a user who
+    /// lost access to a topic is unsubscribed from it instead of receiving its changes
+    async fn forward(&mut self, hub: &Hub, event: Arc<Event>) {
+        // The client got the todo in the ack of its edit already
+        if self.own_edits.remove(&(event.todo_id, event.version)) {
+            return;
+        }
+
+        if self.topics.is_empty() {
+            return;
+        }
+
+        let user = self.user;
+        let topics = self.topics.iter().copied().collect::<Vec<_>>();
+        let changed = event.clone();
+
+        let checked = EventBus::query(&hub.pool, move |conn| {
+            let organizations = Membership::organization_ids(user, conn)?;
+
+            if !changed.is_visible_to(user, &organizations) {
+                return Ok(vec![]);
+            }
+
+            let lists = if topics.iter().any(|topic| matches!(topic, Topic::List(_))) {
+                Topic::lists_of(&changed, conn).unwrap_or_default()
+            } else {
+                vec![]
+            };
+
+            Ok(topics
+                .into_iter()
+                .filter(|topic| topic.covers(&changed, &lists))
+                .map(|topic| (topic, topic.authorize(user, conn)))
+                .collect::<Vec<_>>())
+        })
+        .await
+        .unwrap_or_default();
+
+        for (topic, access) in checked {
+            match access {
+                Ok(()) => {
+                    self.send(ServerMessage::Change {
+                        topic,
+                        event: Event::clone(&event),
+                    })
+                    .await;
+                }
+                Err(message) => {
+                    self.topics.remove(&topic);
+                    hub.leave(topic, self.id);
+                    self.send(ServerMessage::Revoked { topic, message }).await;
+                }
+            }
+        }
+    }
+
+    /// Internal function building the presence of the connection
+    fn presence(&self, activity: Activity, field: Option<String>) -> Presence {
+        Presence {
+            user_id: self.user,
+            name: self.name.clone(),
+            activity,
+            field,
+        }
+    }
+
+    /// Internal function answering a message of the client
+    async fn ack(&self, reference: Option<String>, result: Result<String, String>) {
+        let (ok, message) = match result {
+            Ok(message) => (true, message),
+            Err(message) => (false, message),
+        };
+
+        self.send(ServerMessage::Ack {
+            reference,
+            ok,
+            message,
+            todo: None,
+        })
+        .await;
+    }
+
+    /// Internal function sending a message to the client
+    async fn send(&self, message: ServerMessage) {
+        if let Ok(text) = serde_json::to_string(&message) {
+            self.send_frame(Frame::Text(text)).await;
+        }
+    }
+
+    /// Internal function queueing a frame for the client, dropped if the connection is closing
+    async fn send_frame(&self, frame: Frame) {
+        let _ = self.sender.send(frame).await;
+    }
+
+    /// Internal function reading the messages of the client, on a task of its own so no frame is lost to `select!`
+    async fn read(mut reader: Reader, incoming: Sender<Result<Message, ProtocolError>>) {
+        loop {
+            let message = websocket::read_message(&mut reader).await;
+            let last = !matches!(message, Ok(Message::Text(_)) | Ok(Message::Ping(_)));
+
+            if incoming.send(message).await.is_err() || last {
+                break;
+            }
+        }
+    }
+
+    /// Internal function sending the queued frames to the client until the connection is closed
+    async fn write(mut writer: Writer, mut outgoing: Receiver<Frame>) {
+        while let Some(frame) = outgoing.recv().await {
+            let closing = matches!(frame, Frame::Close(..));
+
+            if websocket::write_frame(&mut writer, frame).await.is_err() || closing {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use rocket::http::{Header, Status};
+use rocket::response::{self, Responder};
+use rocket::Request;
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::okapi::openapi3::Responses;
+use rocket_okapi::response::OpenApiResponderInner;
+
+use crate::models::idempotency::StoredResponse;
+use crate::utils::idempotency::Replayable;
+
+/// Response of a route creating a resource, sent as 201 Created with a `Location` header
+pub struct Created<R> {
+    /// The response of the route
+    pub inner: R,
+    /// Path of the created resource, sent in the `Location` header
+    pub location: String,
+}
+
+impl<'r, R: Responder<'r, 'static>> Responder<'r, 'static> for Created<R> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        response::Response::build_from(self.inner.respond_to(request)?)
+            .status(Status::Created)
+            .header(Header::new("Location", self.location))
+            .ok()
+    }
+}
+
+impl<R: Replayable> Replayable for Created<R> {
+    fn snapshot(&self) -> Option<StoredResponse> {
+        self.inner.snapshot().map(|mut stored| {
+            stored.code = Status::Created.code;
+            stored
+                .headers
+                .push(("Location".to_string(), self.location.clone()));
+            stored
+        })
+    }
+}
+
+impl<R: OpenApiResponderInner> OpenApiResponderInner for Created<R> {
+    fn responses(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        let mut responses = R::responses(gen)?;
+
+        if let Some(created) = responses.responses.remove("200") {
+            responses.responses.insert("201".to_string(), created);
+        }
+
+        Ok(responses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::serde::json::Json;
+
+    use super::*;
+    use crate::consts::Response;
+
+    #[test]
+    fn snapshot_keeps_the_location() {
+        let created = Created {
+            inner: Json(Response {
+                message: "Successfully created todo".to_string(),
+                data: vec![1],
+            }),
+            location: "/todo/1".to_string(),
+        };
+
+        let stored = created.snapshot().unwrap();
+
+        assert_eq!(stored.code, 201);
+        assert_eq!(
+            stored.headers,
+            vec![("Location".to_string(), "/todo/1".to_string())]
+        );
+        assert_eq!(stored.body["data"][0], 1);
+    }
+}
@@ -0,0 +1,328 @@
+use std::str::FromStr;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+
+/// Number of years searched for the next run, so a schedule that never matches, e.g. `0 0 31 2 *`, stops
+static MAX_YEARS: i32 = 5;
+
+/// Cron schedule with the five usual fields: minute, hour, day of month, month and day of week
+///
+/// Fields accept `*`, values, ranges (`1-5`), lists (`1,15`) and steps (`*/15`, `0-30/10`).
+/// Days of week go from 0 (Sunday) to 6, 7 is Sunday too. As in Vixie cron, when both the day of
+/// month and the day of week are restricted a day matching either one runs the job, a field
+/// starting with `*`, e.g. `*/2`, does not count as restricted.
+/// `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly` are accepted as shorthands.
+/// Schedules are evaluated in UTC
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    /// Minutes, 0 to 59
+    minutes: Vec<bool>,
+    /// Hours, 0 to 23
+    hours: Vec<bool>,
+    /// Days of month, 1 to 31
+    days_of_month: Vec<bool>,
+    /// Months, 1 to 12
+    months: Vec<bool>,
+    /// Days of week, 0 (Sunday) to 6
+    days_of_week: Vec<bool>,
+    /// Whether the day of month field does not start with `*`
+    day_of_month_restricted: bool,
+    /// Whether the day of week field does not start with `*`
+    day_of_week_restricted: bool,
+}
+
+impl FromStr for CronSchedule {
+    type Err = String;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+
+        let fields = expression.split_whitespace().collect::<Vec<_>>();
+
+        if fields.len() != 5 {
+            return Err(format!(
+                "Invalid cron expression {}, expected 5 fields",
+                expression
+            ));
+        }
+
+        let mut days_of_week = parse_field(fields[4], 0, 7)?;
+
+        // 7 is another name for Sunday
+        if days_of_week[7] {
+            days_of_week[0] = true;
+        }
+        days_of_week.truncate(7);
+
+        Ok(CronSchedule {
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days_of_month: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            days_of_week,
+            day_of_month_restricted: !fields[2].starts_with('*'),
+            day_of_week_restricted: !fields[4].starts_with('*'),
+        })
+    }
+}
+
+impl CronSchedule {
+    /// Computes the next time the schedule runs
+    ///
+    /// # Arguments
+    ///
+    /// * `after` - A time in UTC, the next run is strictly after it
+    ///
+    /// # Returns
+    ///
+    /// * The next run in UTC, at the start of a minute, None if the schedule does not run in the next years
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start =
+            after.date().and_hms_opt(after.hour(), after.minute(), 0)? + Duration::minutes(1);
+        let end_year = start.year() + MAX_YEARS;
+        let mut time = start;
+
+        while time.year() <= end_year {
+            if !self.months[time.month() as usize] {
+                let (year, month) = match time.month() {
+                    12 => (time.year() + 1, 1),
+                    month => (time.year(), month + 1),
+                };
+                time = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+
+            if !self.matches_day(time.date()) {
+                time = time.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+
+            if !self.hours[time.hour() as usize] {
+                time = time.date().and_hms_opt(time.hour(), 0, 0)? + Duration::hours(1);
+                continue;
+            }
+
+            if !self.minutes[time.minute() as usize] {
+                time += Duration::minutes(1);
+                continue;
+            }
+
+            return Some(time);
+        }
+
+        None
+    }
+
+    /// Internal function to check whether the schedule runs on a day
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day_of_month = self.days_of_month[date.day() as usize];
+        let day_of_week = self.days_of_week[date.weekday().num_days_from_sunday() as usize];
+
+        match (self.day_of_month_restricted, self.day_of_week_restricted) {
+            (true, true) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        }
+    }
+}
+
+/// Internal function to parse a field, returning which values from 0 to `max` it matches
+fn parse_field(field: &str, min: u32, max: u32) -> Result<Vec<bool>, String> {
+    let mut matches = vec![false; max as usize + 1];
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(format!("Invalid step in {}", part)),
+            },
+            None => (part, 1),
+        };
+
+        let (first, last) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((first, last)) => {
+                    (parse_value(first, min, max)?, parse_value(last, min, max)?)
+                }
+                // A single value with a step runs from the value to the end, e.g. `5/15`
+                None if step > 1 => (parse_value(range, min, max)?, max),
+                None => {
+                    let value = parse_value(range, min, max)?;
+                    (value, value)
+                }
+            },
+        };
+
+        if first > last {
+            return Err(format!("Invalid range {}", range));
+        }
+
+        for value in (first..=last).step_by(step as usize) {
+            matches[value as usize] = true;
+        }
+    }
+
+    Ok(matches)
+}
+
+/// Internal function to parse a value of a field
+fn parse_value(value: &str, min: u32, max: u32) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(value) if (min..=max).contains(&value) => Ok(value),
+        _ => Err(format!(
+            "Invalid value {}, expected {} to {}",
+            value, min, max
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn next(expression: &str, after: &str) -> Option<NaiveDateTime> {
+        expression
+            .parse::<CronSchedule>()
+            .unwrap()
+            .next_after(utc(after))
+    }
+
+    #[test]
+    fn expands_shorthands() {
+        for (shorthand, expression) in [
+            ("@hourly", "0 * * * *"),
+            ("@daily", "0 0 * * *"),
+            ("@weekly", "0 0 * * 0"),
+            ("@monthly", "0 0 1 * *"),
+            ("@yearly", "0 0 1 1 *"),
+        ] {
+            assert_eq!(
+                shorthand.parse::<CronSchedule>(),
+                expression.parse::<CronSchedule>()
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for expression in [
+            "* * * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "*/0 * * * *",
+            "30-10 * * * *",
+            "a * * * *",
+        ] {
+            assert!(
+                expression.parse::<CronSchedule>().is_err(),
+                "{}",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn runs_strictly_after_the_start_of_the_next_minute() {
+        let after = utc("2023-06-10 10:15") + Duration::seconds(30);
+        let schedule = "* * * * *".parse::<CronSchedule>().unwrap();
+
+        assert_eq!(schedule.next_after(after), Some(utc("2023-06-10 10:16")));
+        assert_eq!(
+            next("* * * * *", "2023-06-10 10:15"),
+            Some(utc("2023-06-10 10:16"))
+        );
+    }
+
+    #[test]
+    fn follows_steps_and_ranges() {
+        assert_eq!(
+            next("*/15 * * * *", "2023-06-10 10:16"),
+            Some(utc("2023-06-10 10:30"))
+        );
+        assert_eq!(
+            next("*/15 * * * *", "2023-06-10 10:45"),
+            Some(utc("2023-06-10 11:00"))
+        );
+        assert_eq!(
+            next("0-30/10 * * * *", "2023-06-10 10:30"),
+            Some(utc("2023-06-10 11:00"))
+        );
+        assert_eq!(
+            next("5/20 9-17 * * *", "2023-06-10 17:45"),
+            Some(utc("2023-06-11 09:05"))
+        );
+        assert_eq!(
+            next("0 3,15 * * *", "2023-06-10 03:00"),
+            Some(utc("2023-06-10 15:00"))
+        );
+    }
+
+    #[test]
+    fn rolls_over_months_and_years() {
+        assert_eq!(
+            next("0 3 * * *", "2023-06-30 03:00"),
+            Some(utc("2023-07-01 03:00"))
+        );
+        assert_eq!(
+            next("@monthly", "2023-12-15 08:00"),
+            Some(utc("2024-01-01 00:00"))
+        );
+        assert_eq!(
+            next("0 0 29 2 *", "2023-03-01 00:00"),
+            Some(utc("2024-02-29 00:00"))
+        );
+    }
+
+    #[test]
+    fn treats_seven_as_sunday() {
+        assert_eq!(
+            "0 0 * * 7".parse::<CronSchedule>(),
+            "0 0 * * 0".parse::<CronSchedule>()
+        );
+        // 2023-06-11 is a Sunday
+        assert_eq!(
+            next("0 0 * * 7", "2023-06-10 12:00"),
+            Some(utc("2023-06-11 00:00"))
+        );
+    }
+
+    #[test]
+    fn runs_on_either_restricted_day() {
+        // The 13th is a Tuesday, before the next Friday
+        assert_eq!(
+            next("0 0 13 * 5", "2023-06-10 00:00"),
+            Some(utc("2023-06-13 00:00"))
+        );
+        // The next Friday is before the 13th of July
+        assert_eq!(
+            next("0 0 13 * 5", "2023-06-13 00:00"),
+            Some(utc("2023-06-16 00:00"))
+        );
+    }
+
+    #[test]
+    fn needs_both_days_when_one_starts_with_a_star() {
+        // Odd days that are Mondays, 2023-06-05 is the first one
+        assert_eq!(
+            next("0 0 */2 * 1", "2023-06-01 12:00"),
+            Some(utc("2023-06-05 00:00"))
+        );
+    }
+
+    #[test]
+    fn stops_when_the_schedule_never_runs() {
+        assert_eq!(next("0 0 31 2 *", "2023-06-10 00:00"), None);
+    }
+}
@@ -0,0 +1,31 @@
+use rocket::http::Header;
+use rocket::response::{self, Responder};
+use rocket::Request;
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::okapi::openapi3::Responses;
+use rocket_okapi::response::OpenApiResponderInner;
+
+/// Response of a deprecated route, sent with a `Deprecation` header (RFC 9745)
+pub struct Deprecated<R> {
+    /// The response of the route
+    pub inner: R,
+    /// Path of the route replacing the deprecated one, sent in a `Link` header
+    pub successor: String,
+}
+
+impl<'r, R: Responder<'r, 'static>> Responder<'r, 'static> for Deprecated<R> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let link = format!("<{}>; rel=\"successor-version\"", self.successor);
+
+        response::Response::build_from(self.inner.respond_to(request)?)
+            .header(Header::new("Deprecation", "true"))
+            .header(Header::new("Link", link))
+            .ok()
+    }
+}
+
+impl<R: OpenApiResponderInner> OpenApiResponderInner for Deprecated<R> {
+    fn responses(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        R::responses(gen)
+    }
+}
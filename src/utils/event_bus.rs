@@ -0,0 +1,425 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use diesel::pg::Pg;
+use diesel::{prelude::*, PgConnection};
+use postgres::fallible_iterator::FallibleIterator;
+use postgres::{Client, NoTls};
+use rocket::futures::stream::Stream;
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::stream::{stream, Event as SseEvent, EventStream};
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast::{self, error::RecvError, Receiver, Sender};
+use rocket::tokio::task::spawn_blocking;
+use rocket::tokio::time::sleep;
+use rocket::{Request, Shutdown};
+use rocket_okapi::{
+    gen::OpenApiGenerator,
+    request::{OpenApiFromRequest, RequestHeaderInput},
+};
+
+use crate::config::db::{get_connection, PoolConnection};
+use crate::models::event::{Event, EventCursor};
+use crate::models::organization::Membership;
+use crate::schema::todo_events;
+use crate::utils::precondition::header_parameter;
+
+/// Channel the database announces recorded events on, see the `todo_events_notify` migration
+static CHANNEL: &str = "todo_events";
+
+/// Number of events kept for the subscribers that fall behind, older ones are read from the database again
+static CAPACITY: usize = 1024;
+
+/// Time to wait before connecting again once the connection listening to the database is lost
+static RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Time to wait for an announcement before checking the connection
+static POLL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Number of events read from the database at once
+static PAGE_SIZE: i64 = 200;
+
+/// Time to wait before reading again an announced event held back by a transaction still running
+static HELD_BACK_DELAY: Duration = Duration::from_millis(500);
+
+/// Server-Sent Events stream of the changes made to todos
+pub type EventFeed = EventStream<Pin<Box<dyn Stream<Item = SseEvent> + Send>>>;
+
+/// Fans the events recorded by every server instance out to the streams of this instance
+///
+/// A thread listens to the announcements Postgres makes with NOTIFY once an event is committed,
+/// loads the event and sends it to every subscriber. Streams read the events they send from the
+/// database, the published events only tell them when to read
+#[derive(Clone)]
+pub struct EventBus {
+    /// Sender the events are published with, subscribers get a receiver from it
+    sender: Sender<Arc<Event>>,
+}
+
+impl EventBus {
+    /// Starts listening to the events announced by the database
+    ///
+    /// # Arguments
+    ///
+    /// * `db_url` - The connection string of the database, the listener keeps a connection of its own
+    /// * `pool` - A pool of database connections the announced events are loaded with
+    ///
+    /// # Returns
+    ///
+    /// * The event bus
+    pub fn listen(db_url: &str, pool: PoolConnection) -> EventBus {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        let url = db_url.to_string();
+        let publisher = sender.clone();
+
+        thread::Builder::new()
+            .name("event-bus".to_string())
+            .spawn(move || EventBus::run(url, pool, publisher))
+            .expect("Failed to start event listener.");
+
+        EventBus { sender }
+    }
+
+    /// Subscribes to the events recorded from now on
+    ///
+    /// # Returns
+    ///
+    /// * A receiver of the events, in the order they were committed
+    pub fn subscribe(&self) -> Receiver<Arc<Event>> {
+        self.sender.subscribe()
+    }
+
+    /// Streams the events a user is told about as Server-Sent Events
+    ///
+    /// Events are read from the database in the order of their cursor, see `EventCursor`, starting
+    /// after `after` or from now on. The events published on the bus wake the stream up to read the
+    /// next ones, so an event committed after a later one is still sent. The organizations of the
+    /// user are read again before each read, so leaving an organization stops its events
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - A pool of database connections
+    /// * `user` - Id of the user
+    /// * `after` - Cursor of the last event the client received, if it is reconnecting
+    /// * `shutdown` - Ends the stream when the server stops
+    ///
+    /// # Returns
+    ///
+    /// * The stream, each event is named after its action and carries its cursor as id
+    pub fn stream(
+        &self,
+        pool: &PoolConnection,
+        user: i32,
+        after: Option<EventCursor>,
+        mut shutdown: Shutdown,
+    ) -> EventFeed {
+        let pool = pool.clone();
+        // Subscribed before the first read, so no announcement falls in between
+        let mut receiver = self.subscribe();
+
+        let events = stream! {
+            let mut cursor = match after {
+                Some(after) => after,
+                None => match EventBus::query(&pool, |conn| {
+                    EventCursor::horizon(conn).map_err(|_| "Failed to get events".to_string())
+                })
+                .await
+                {
+                    Ok(horizon) => horizon,
+                    Err(message) => {
+                        yield SseEvent::data(message).event("error");
+                        return;
+                    }
+                },
+            };
+
+            // Cursor of the latest announced event, read once no running transaction holds it back
+            let mut announced: Option<EventCursor> = None;
+
+            loop {
+                let since = cursor;
+
+                let page = EventBus::query(&pool, move |conn| {
+                    let organizations = Membership::organization_ids(user, conn)?;
+
+                    // Read first, every event before it is read by the query below
+                    let horizon = EventCursor::horizon(conn);
+                    let page =
+                        Event::get_committed_since(user, &organizations, since, PAGE_SIZE, conn);
+
+                    match (horizon, page) {
+                        (Ok(horizon), Ok(page)) => Ok((organizations, horizon, page)),
+                        _ => Err("Failed to get events".to_string()),
+                    }
+                })
+                .await;
+
+                let (organizations, horizon, page) = match page {
+                    Ok(page) => page,
+                    Err(message) => {
+                        yield SseEvent::data(message).event("error");
+                        break;
+                    }
+                };
+
+                let full = page.len() as i64 == PAGE_SIZE;
+
+                for event in page {
+                    cursor = EventCursor::of(&event);
+
+                    if event.is_visible_to(user, &organizations) {
+                        yield EventBus::to_sse(&event);
+                    }
+                }
+
+                if full {
+                    continue;
+                }
+
+                // Waits for an announcement that may concern the user before reading again
+                loop {
+                    let held_back = announced
+                        .is_some_and(|announced| announced.mutation_id >= horizon.mutation_id);
+
+                    let event = select! {
+                        received = receiver.recv() => match received {
+                            Ok(event) => event,
+                            // Missed announcements only delay the events, they are read from the database
+                            Err(RecvError::Lagged(_)) => break,
+                            Err(RecvError::Closed) => return,
+                        },
+                        _ = sleep(HELD_BACK_DELAY), if held_back => break,
+                        _ = &mut shutdown => return,
+                    };
+
+                    if event.owner_id == user
+                        || event.organization_id.is_some()
+                        || event.changes.get("organizationId").is_some()
+                    {
+                        announced = announced.max(Some(EventCursor::of(&event)));
+                        break;
+                    }
+                }
+            }
+        };
+
+        EventStream::from(Box::pin(events) as Pin<Box<dyn Stream<Item = SseEvent> + Send>>)
+    }
+
+    /// Runs a database query on a blocking thread, away from the tasks serving the streams
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - A pool of database connections
+    /// * `query` - Function running the query
+    ///
+    /// # Returns
+    ///
+    /// * The result of the query or an error message
+    pub async fn query<T, F>(pool: &PoolConnection, query: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut PgConnection) -> Result<T, String> + Send + 'static,
+    {
+        let pool = pool.clone();
+
+        let result = spawn_blocking(move || {
+            let mut conn = get_connection(&pool)?;
+            query(&mut conn)
+        })
+        .await;
+
+        match result {
+            Ok(result) => result,
+            Err(_) => Err("Failed to query database".to_string()),
+        }
+    }
+
+    /// Internal function to turn an event into a Server-Sent Event
+    fn to_sse(event: &Event) -> SseEvent {
+        SseEvent::json(event)
+            .id(EventCursor::of(event).to_string())
+            .event(event.action.as_str())
+    }
+
+    /// Internal function listening to the database until the server stops, connecting again whenever the connection is lost
+    fn run(url: String, pool: PoolConnection, publisher: Sender<Arc<Event>>) {
+        let mut last_seen: Option<i32> = None;
+
+        loop {
+            match Listener::connect(&url) {
+                Ok(mut listener) => {
+                    // Events recorded while the connection was down are published once it is back
+                    let missed = match last_seen {
+                        Some(after) => EventBus::load(
+                            &pool,
+                            todo_events::table
+                                .filter(todo_events::id.gt(after))
+                                .into_boxed(),
+                        ),
+                        None => EventBus::latest(&pool).map(|latest| {
+                            last_seen = Some(latest.unwrap_or(0));
+                            vec![]
+                        }),
+                    };
+
+                    match missed {
+                        Ok(events) => EventBus::publish(events, &publisher, &mut last_seen),
+                        Err(message) => log::warn!("{}", message),
+                    }
+
+                    loop {
+                        let announced = match listener.wait(POLL_TIMEOUT) {
+                            Ok(announced) => announced,
+                            Err(message) => {
+                                log::warn!("Lost connection listening to events: {}", message);
+                                break;
+                            }
+                        };
+
+                        if announced.is_empty() {
+                            continue;
+                        }
+
+                        match EventBus::load(
+                            &pool,
+                            todo_events::table
+                                .filter(todo_events::id.eq_any(announced))
+                                .into_boxed(),
+                        ) {
+                            Ok(events) => EventBus::publish(events, &publisher, &mut last_seen),
+                            Err(message) => log::warn!("{}", message),
+                        }
+                    }
+                }
+                Err(message) => log::warn!("Failed to listen to events: {}", message),
+            }
+
+            thread::sleep(RECONNECT_DELAY);
+        }
+    }
+
+    /// Internal function to load the events selected by a query, oldest first
+    fn load(
+        pool: &PoolConnection,
+        query: todo_events::BoxedQuery<'static, Pg>,
+    ) -> Result<Vec<Event>, String> {
+        let mut conn = get_connection(pool)?;
+
+        let result = query.order(todo_events::id.asc()).load::<Event>(&mut conn);
+
+        match result {
+            Ok(events) => Ok(events),
+            Err(_) => Err("Failed to load events".to_string()),
+        }
+    }
+
+    /// Internal function to get the id of the latest event
+    fn latest(pool: &PoolConnection) -> Result<Option<i32>, String> {
+        let mut conn = get_connection(pool)?;
+
+        let result = todo_events::table
+            .select(diesel::dsl::max(todo_events::id))
+            .first::<Option<i32>>(&mut conn);
+
+        match result {
+            Ok(latest) => Ok(latest),
+            Err(_) => Err("Failed to load events".to_string()),
+        }
+    }
+
+    /// Internal function to send events to the subscribers, remembering the latest one
+    fn publish(events: Vec<Event>, publisher: &Sender<Arc<Event>>, last_seen: &mut Option<i32>) {
+        for event in events {
+            *last_seen = Some(last_seen.map_or(event.id, |seen| seen.max(event.id)));
+
+            // Sending only fails without subscribers, the event is not needed then
+            let _ = publisher.send(Arc::new(event));
+        }
+    }
+}
+
+/// Connection of its own listening to the announcements of the database
+///
+/// The pooled connections cannot wait for announcements, so a client of its own is kept for it
+struct Listener {
+    client: Client,
+}
+
+impl Listener {
+    /// Internal function to connect to the database and listen to the event channel
+    fn connect(url: &str) -> Result<Listener, String> {
+        let mut client = match Client::connect(url, NoTls) {
+            Ok(client) => client,
+            Err(error) => return Err(error.to_string()),
+        };
+
+        if let Err(error) = client.batch_execute(&format!("LISTEN {}", CHANNEL)) {
+            return Err(error.to_string());
+        }
+
+        Ok(Listener { client })
+    }
+
+    /// Internal function to wait for announcements, returning the ids of the announced events
+    /// Returns no id if nothing was announced before the timeout
+    fn wait(&mut self, timeout: Duration) -> Result<Vec<i32>, String> {
+        let mut notifications = self.client.notifications();
+
+        let first = match notifications.timeout_iter(timeout).next() {
+            Ok(first) => first,
+            Err(error) => return Err(error.to_string()),
+        };
+
+        // Takes the announcements received along with the first one without waiting for more
+        let received = match first {
+            Some(first) => match notifications.iter().collect::<Vec<_>>() {
+                Ok(rest) => [vec![first], rest].concat(),
+                Err(error) => return Err(error.to_string()),
+            },
+            None => vec![],
+        };
+        drop(notifications);
+
+        // No announcement is also returned once the connection is closed
+        if received.is_empty() && self.client.is_closed() {
+            return Err("Connection is closed".to_string());
+        }
+
+        Ok(received
+            .iter()
+            .filter_map(|notification| notification.payload().parse().ok())
+            .collect())
+    }
+}
+
+/// Last-Event-ID header of a request, sent by a client reconnecting to a stream
+#[derive(Debug)]
+pub struct LastEventId(pub Option<EventCursor>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LastEventId {
+    type Error = String;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let header = request.headers().get_one("Last-Event-ID");
+
+        Outcome::Success(LastEventId(header.and_then(EventCursor::parse)))
+    }
+}
+
+impl<'a> OpenApiFromRequest<'a> for LastEventId {
+    fn from_request_input(
+        gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        header_parameter(
+            gen,
+            "Last-Event-ID",
+            "Id of the last event the client received, the events committed since are sent first",
+        )
+    }
+}
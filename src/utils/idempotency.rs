@@ -0,0 +1,330 @@
+use rocket::http::{Header, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::{self, Responder};
+use rocket::serde::json::Json;
+use rocket::Request;
+use rocket_okapi::okapi::openapi3::Responses;
+use rocket_okapi::response::OpenApiResponderInner;
+use rocket_okapi::{
+    gen::OpenApiGenerator,
+    request::{OpenApiFromRequest, RequestHeaderInput},
+};
+use serde::Serialize;
+
+use crate::config::db::{get_connection, PoolConnection};
+use crate::config::idempotency::{establish_idempotency, IdempotencyConfig};
+use crate::consts::Response;
+use crate::models::idempotency::{Claim, IdempotencyRecord, StoredResponse};
+use crate::models::job::Job;
+use crate::utils::jobs::JobHandler;
+use crate::utils::precondition::header_parameter;
+use crate::utils::signature;
+
+/// Maximum length of an idempotency key
+static MAX_KEY_LENGTH: usize = 255;
+
+/// Response a route can store, so it is replayed when the request is retried
+pub trait Replayable {
+    /// Gets the status code, the headers to send again and the body of the response
+    fn snapshot(&self) -> Option<StoredResponse>;
+}
+
+impl<T: Serialize> Replayable for Json<T> {
+    fn snapshot(&self) -> Option<StoredResponse> {
+        serde_json::to_value(&self.0)
+            .ok()
+            .map(|body| StoredResponse {
+                code: Status::Ok.code,
+                headers: vec![],
+                body,
+            })
+    }
+}
+
+impl<T: Serialize> Replayable for (Status, Json<T>) {
+    fn snapshot(&self) -> Option<StoredResponse> {
+        serde_json::to_value(&self.1 .0)
+            .ok()
+            .map(|body| StoredResponse {
+                code: self.0.code,
+                headers: vec![],
+                body,
+            })
+    }
+}
+
+/// Idempotency-Key header of a request, along with a fingerprint of the request
+#[derive(Debug)]
+pub struct IdempotencyKey {
+    /// The key, None if the header is missing
+    key: Option<String>,
+    /// Keyed hash of the method, path and body of the request
+    fingerprint: String,
+    /// Method and path of the request
+    target: String,
+    /// Number of seconds the response is replayed for
+    ttl: i64,
+}
+
+impl IdempotencyKey {
+    /// Adds the body of the request to its fingerprint, so a key reused with another body is rejected
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - The body of the request
+    ///
+    /// # Returns
+    ///
+    /// * The key with the updated fingerprint
+    pub fn with_payload<P: Serialize>(mut self, payload: &P) -> IdempotencyKey {
+        let body = serde_json::to_vec(payload).unwrap_or_default();
+        self.fingerprint = signature::fingerprint(&self.target, &body);
+
+        self
+    }
+
+    /// Handles a request at most once per key
+    ///
+    /// Without a key the request is handled as usual. The response of the first request sent with a key
+    /// that succeeds is stored and replayed to the requests sent with the same key until it expires.
+    /// The key is released when the request fails or the handler panics, so a retry is handled again
+    ///
+    /// # Arguments
+    ///
+    /// * `pool` - A pool of database connections
+    /// * `user` - Id of the user sending the request, None for requests sent without a token
+    /// * `handler` - Function handling the request, returning the response of a success or of a failure
+    ///
+    /// # Returns
+    ///
+    /// * The response of the handler, the stored response, or 409 Conflict if the first request sent
+    ///   with the key is still being handled and 422 Unprocessable Entity if the key was used for
+    ///   another request
+    pub fn run<R, E, F>(
+        self,
+        pool: &PoolConnection,
+        user: Option<i32>,
+        handler: F,
+    ) -> Idempotent<R, E>
+    where
+        R: Replayable,
+        F: FnOnce() -> Result<R, E>,
+    {
+        let key = match self.key {
+            Some(key) => key,
+            None => return Idempotent::Handled(handler()),
+        };
+
+        if key.is_empty()
+            || key.len() > MAX_KEY_LENGTH
+            || !key.bytes().all(|byte| byte.is_ascii_graphic())
+        {
+            return Idempotent::Rejected(
+                Status::BadRequest,
+                format!(
+                    "Idempotency-Key must be 1 to {} visible ASCII characters",
+                    MAX_KEY_LENGTH
+                ),
+            );
+        }
+
+        // The connection is released while the request is handled, the handler gets its own
+        let claim = match get_connection(pool) {
+            Ok(mut conn) => {
+                IdempotencyRecord::claim(user, &key, &self.fingerprint, self.ttl, &mut conn)
+            }
+            Err(message) => Err(message),
+        };
+
+        match claim {
+            Ok(Claim::New) => {}
+            Ok(Claim::Replay(stored)) => return Idempotent::Replayed(stored),
+            Ok(Claim::Mismatch) => {
+                return Idempotent::Rejected(
+                    Status::UnprocessableEntity,
+                    "Idempotency-Key was already used for a different request".to_string(),
+                )
+            }
+            Ok(Claim::InProgress) => {
+                return Idempotent::Rejected(
+                    Status::Conflict,
+                    "A request with this Idempotency-Key is still being handled".to_string(),
+                )
+            }
+            Err(message) => return Idempotent::Rejected(Status::ServiceUnavailable, message),
+        }
+
+        // Released unless the response is stored, including when the handler panics
+        let mut claimed = ClaimedKey {
+            pool,
+            user,
+            key: &key,
+            stored: false,
+        };
+
+        let response = handler();
+
+        let snapshot = match response {
+            Ok(ref response) => response.snapshot(),
+            Err(_) => return Idempotent::Handled(response),
+        };
+
+        let stored = match (get_connection(pool), snapshot) {
+            (Ok(mut conn), Some(snapshot)) => {
+                IdempotencyRecord::store(user, &key, snapshot, &mut conn)
+            }
+            (Ok(_), None) => Err("Failed to store response".to_string()),
+            (Err(message), _) => Err(message),
+        };
+
+        match stored {
+            Ok(_) => claimed.stored = true,
+            Err(message) => log::warn!("{} for Idempotency-Key {}", message, key),
+        }
+
+        Idempotent::Handled(response)
+    }
+}
+
+/// Idempotency key claimed for a request, released when dropped before its response is stored
+struct ClaimedKey<'a> {
+    /// A pool of database connections
+    pool: &'a PoolConnection,
+    /// Id of the user who sent the request
+    user: Option<i32>,
+    /// The key
+    key: &'a str,
+    /// Whether the response of the request was stored
+    stored: bool,
+}
+
+impl Drop for ClaimedKey<'_> {
+    fn drop(&mut self) {
+        if self.stored {
+            return;
+        }
+
+        let released = match get_connection(self.pool) {
+            Ok(mut conn) => IdempotencyRecord::release(self.user, self.key, &mut conn)
+                .map_err(|_| "Failed to release key".to_string()),
+            Err(message) => Err(message),
+        };
+
+        // Left to expire, retries get 409 Conflict until then
+        if let Err(message) = released {
+            log::warn!("{} for Idempotency-Key {}", message, self.key);
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IdempotencyKey {
+    type Error = String;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let key = request.headers().get_one("Idempotency-Key");
+        let target = format!("{} {}", request.method(), request.uri());
+        let config = match request.rocket().state::<IdempotencyConfig>() {
+            Some(config) => *config,
+            None => establish_idempotency(),
+        };
+
+        Outcome::Success(IdempotencyKey {
+            key: key.map(|key| key.trim().to_string()),
+            fingerprint: signature::fingerprint(&target, b""),
+            target,
+            ttl: config.ttl,
+        })
+    }
+}
+
+impl<'a> OpenApiFromRequest<'a> for IdempotencyKey {
+    fn from_request_input(
+        gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        header_parameter(
+            gen,
+            "Idempotency-Key",
+            "Unique key of the request, a retry sent with the same key gets the first response back",
+        )
+    }
+}
+
+/// Response to a request that may have been sent with an idempotency key
+pub enum Idempotent<R, E = R> {
+    /// Response of the request, handled now, either a success or a failure
+    Handled(Result<R, E>),
+    /// Stored response of the first request sent with the key, sent with an `Idempotent-Replayed` header
+    Replayed(StoredResponse),
+    /// The request cannot be handled with the key it was sent with
+    Rejected(Status, String),
+}
+
+impl<'r, R, E> Responder<'r, 'static> for Idempotent<R, E>
+where
+    R: Responder<'r, 'static>,
+    E: Responder<'r, 'static>,
+{
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        match self {
+            Idempotent::Handled(Ok(response)) => response.respond_to(request),
+            Idempotent::Handled(Err(response)) => response.respond_to(request),
+            Idempotent::Replayed(stored) => {
+                let status = Status::from_code(stored.code).unwrap_or(Status::Ok);
+                let mut response = response::Response::build_from(
+                    (status, Json(stored.body)).respond_to(request)?,
+                );
+
+                for (name, value) in stored.headers {
+                    response.header(Header::new(name, value));
+                }
+
+                response
+                    .header(Header::new("Idempotent-Replayed", "true"))
+                    .ok()
+            }
+            Idempotent::Rejected(status, message) => (
+                status,
+                Json(Response::<i8> {
+                    message,
+                    data: vec![],
+                }),
+            )
+                .respond_to(request),
+        }
+    }
+}
+
+impl<R, E> OpenApiResponderInner for Idempotent<R, E>
+where
+    R: OpenApiResponderInner,
+    E: OpenApiResponderInner,
+{
+    fn responses(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        let mut responses = Result::<R, E>::responses(gen)?;
+        rocket_okapi::util::ensure_status_code_exists(&mut responses, 409);
+        rocket_okapi::util::ensure_status_code_exists(&mut responses, 422);
+
+        Ok(responses)
+    }
+}
+
+/// Deletes the expired idempotency keys
+pub struct PurgeIdempotencyKeys;
+
+impl JobHandler for PurgeIdempotencyKeys {
+    fn kind(&self) -> &'static str {
+        "idempotency.purge"
+    }
+
+    fn run(&self, _job: &Job, pool: &PoolConnection) -> Result<(), String> {
+        let mut conn = get_connection(pool)?;
+
+        match IdempotencyRecord::purge_expired(&mut conn) {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Failed to purge idempotency keys".to_string()),
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use serde_json::Value;
+
+use crate::config::db::{get_connection, PoolConnection};
+use crate::models::job::Job;
+use crate::models::organization::{Invitation, INVITE};
+use crate::utils::email::Mailer;
+use crate::utils::jobs::JobHandler;
+
+/// Emails invitations to the invited users, queued by `Organization::invite`
+///
+/// The email carries the token of the invitation, which is never returned by the API, so only
+/// the owner of the invited address can accept it
+pub struct SendInvitations {
+    /// Mailer sending the emails, None if emails are not set up
+    mailer: Option<Mailer>,
+}
+
+impl SendInvitations {
+    /// Creates the handler
+    ///
+    /// # Arguments
+    ///
+    /// * `mailer` - Mailer sending the emails, the jobs fail without one so they can be retried once it is set up
+    ///
+    /// # Returns
+    ///
+    /// * The handler
+    pub fn new(mailer: Option<Mailer>) -> SendInvitations {
+        SendInvitations { mailer }
+    }
+}
+
+impl JobHandler for SendInvitations {
+    fn kind(&self) -> &'static str {
+        INVITE
+    }
+
+    fn run(&self, job: &Job, pool: &PoolConnection) -> Result<(), String> {
+        let mailer = match self.mailer {
+            Some(ref mailer) => mailer,
+            None => return Err("Invitations cannot be sent, SMTP_URL is not set".to_string()),
+        };
+
+        let invitation_id = match job.payload.get("invitationId").and_then(Value::as_i64) {
+            Some(invitation_id) => invitation_id as i32,
+            None => return Err(format!("Invalid payload {}", job.payload)),
+        };
+
+        let found = {
+            let mut conn = get_connection(pool)?;
+            Invitation::find_pending(invitation_id, &mut conn)?
+        };
+
+        // The invitation was accepted, expired or deleted since
+        let (invitation, organization) = match found {
+            Some(found) => found,
+            None => return Ok(()),
+        };
+
+        let body = format!(
+            "You were invited to join {} as {}.\n\n\
+             Sign in with this email address and accept the invitation with its token:\n\n\
+             POST /invitations/{}/accept\n\n\
+             The invitation expires on {} UTC.",
+            organization,
+            invitation.role.as_str(),
+            invitation.token,
+            invitation.expires_at.format("%Y-%m-%d %H:%M"),
+        );
+
+        // No connection is held while waiting for the SMTP server
+        mailer.send(
+            &invitation.email,
+            &format!("Invitation to {}", organization),
+            &body,
+        )
+    }
+}
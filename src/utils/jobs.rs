@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use rocket::fairing::AdHoc;
+
+use crate::config::db::{get_connection, PoolConnection};
+use crate::models::job::{Job, JobSchedule};
+
+/// Time to wait before looking for due jobs again when there were none
+static POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Runs the jobs of one kind
+///
+/// A job can be attempted more than once, when it fails or when its worker stops while running it,
+/// so handlers must cope with running the same job again
+pub trait JobHandler: Send + Sync {
+    /// Kind of the jobs the handler runs, e.g. `webhook.deliver`
+    fn kind(&self) -> &'static str;
+
+    /// Runs a job
+    ///
+    /// # Arguments
+    ///
+    /// * `job` - The job, its arguments are in its payload
+    /// * `pool` - A pool of database connections, a connection should not be held while waiting on something else
+    ///
+    /// # Returns
+    ///
+    /// * Nothing if the job succeeded, why it failed otherwise, it is retried later
+    fn run(&self, job: &Job, pool: &PoolConnection) -> Result<(), String>;
+}
+
+/// Job queued on a cron schedule when the workers start
+pub struct Schedule {
+    /// Unique name of the schedule
+    pub name: &'static str,
+    /// Kind of the jobs queued
+    pub kind: &'static str,
+    /// Cron expression of the schedule, evaluated in UTC
+    pub cron: &'static str,
+}
+
+/// Runs the queued jobs
+///
+/// Jobs are stored in the database and claimed with `FOR UPDATE SKIP LOCKED`, so every server
+/// instance can run workers without running a job twice at once
+pub struct JobWorker {
+    /// A pool of database connections
+    pool: PoolConnection,
+    /// Handlers of the jobs by kind
+    handlers: Arc<HashMap<&'static str, Box<dyn JobHandler>>>,
+}
+
+impl JobWorker {
+    /// Fairing registering the schedules and starting the workers once Rocket is up, the database pool must be managed
+    ///
+    /// # Arguments
+    ///
+    /// * `workers` - Number of threads running jobs, none are started for 0
+    /// * `handlers` - Handlers of the jobs
+    /// * `schedules` - Jobs queued on a cron schedule
+    ///
+    /// # Returns
+    ///
+    /// * The fairing
+    pub fn fairing(
+        workers: usize,
+        handlers: Vec<Box<dyn JobHandler>>,
+        schedules: Vec<Schedule>,
+    ) -> AdHoc {
+        let handlers = Arc::new(
+            handlers
+                .into_iter()
+                .map(|handler| (handler.kind(), handler))
+                .collect::<HashMap<_, _>>(),
+        );
+
+        AdHoc::on_liftoff("Jobs", move |rocket| {
+            Box::pin(async move {
+                if workers == 0 {
+                    return;
+                }
+
+                let pool = match rocket.state::<PoolConnection>() {
+                    Some(pool) => pool.clone(),
+                    None => {
+                        log::error!("Jobs need the database pool");
+                        return;
+                    }
+                };
+
+                match get_connection(&pool) {
+                    Ok(mut conn) => {
+                        for schedule in schedules.iter() {
+                            let registered = JobSchedule::register(
+                                schedule.name,
+                                schedule.kind,
+                                schedule.cron,
+                                &mut conn,
+                            );
+
+                            if let Err(message) = registered {
+                                log::error!(
+                                    "Failed to register schedule {}: {}",
+                                    schedule.name,
+                                    message
+                                );
+                            }
+                        }
+                    }
+                    Err(message) => log::error!("Failed to register schedules: {}", message),
+                }
+
+                for index in 0..workers {
+                    let worker = JobWorker {
+                        pool: pool.clone(),
+                        handlers: handlers.clone(),
+                    };
+
+                    let started = thread::Builder::new()
+                        .name(format!("jobs-{}", index))
+                        .spawn(move || worker.run());
+
+                    if let Err(error) = started {
+                        log::error!("Failed to start job worker: {}", error);
+                    }
+                }
+            })
+        })
+    }
+
+    /// Internal function running the due jobs until the server stops
+    fn run(self) {
+        loop {
+            match self.run_next() {
+                Ok(true) => {}
+                Ok(false) => thread::sleep(POLL_INTERVAL),
+                Err(message) => {
+                    log::warn!("{}", message);
+                    thread::sleep(POLL_INTERVAL);
+                }
+            }
+        }
+    }
+
+    /// Internal function to queue the scheduled jobs then claim and run the next job, returning whether one ran
+    fn run_next(&self) -> Result<bool, String> {
+        let claimed = {
+            let mut conn = get_connection(&self.pool)?;
+            JobSchedule::enqueue_due(&mut conn)?;
+            Job::claim(&mut conn)?
+        };
+
+        let job = match claimed {
+            Some(job) => job,
+            None => return Ok(false),
+        };
+
+        // No connection is held while the job runs
+        let outcome = if job.attempts > job.max_attempts {
+            // Claimed again after its last worker stopped while running it
+            Err("The worker stopped while running the job".to_string())
+        } else {
+            match self.handlers.get(job.kind.as_str()) {
+                Some(handler) => {
+                    panic::catch_unwind(AssertUnwindSafe(|| handler.run(&job, &self.pool)))
+                        .unwrap_or_else(|_| Err("The job panicked".to_string()))
+                }
+                None => Err(format!("No handler for jobs of kind {}", job.kind)),
+            }
+        };
+
+        let mut conn = get_connection(&self.pool)?;
+
+        match outcome {
+            Ok(()) => job.succeed(&mut conn)?,
+            Err(message) => {
+                log::warn!("Job {} of kind {} failed: {}", job.id, job.kind, message);
+                job.fail(&message, &mut conn)?;
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Deletes the jobs that succeeded a while ago
+pub struct PurgeJobs {
+    /// Number of seconds a succeeded job is kept for
+    pub age: i64,
+}
+
+impl JobHandler for PurgeJobs {
+    fn kind(&self) -> &'static str {
+        "jobs.purge"
+    }
+
+    fn run(&self, _job: &Job, pool: &PoolConnection) -> Result<(), String> {
+        let mut conn = get_connection(pool)?;
+
+        match Job::purge(self.age, &mut conn) {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Failed to purge jobs".to_string()),
+        }
+    }
+}
@@ -54,39 +54,11 @@ impl<'r> FromRequest<'r> for TokenValidation {
                 // Remove the Bearer prefix from the token
                 let token = token.replace("Bearer ", "");
 
-                // Decode the token
-                let decode_token = jsonwebtoken::decode::<Claims>(
-                    &token,
-                    &DecodingKey::from_secret(&KEY),
-                    &jsonwebtoken::Validation::default(),
-                );
-
-                // Check if the token is valid
-                let result = match decode_token {
-                    Ok(token_data) => token_data,
-                    Err(_) => {
-                        return Outcome::Failure((
-                            rocket::http::Status::Unauthorized,
-                            "Invalid token".to_string(),
-                        ));
-                    }
-                };
-
-                // Check if the token is expired
-                let now = Utc::now().timestamp_nanos() / 1_000_000_000;
-
-                // Return an error if the token is expired
-                if result.claims.exp < now {
-                    return Outcome::Failure((
-                        rocket::http::Status::Unauthorized,
-                        "Token expired".to_string(),
-                    ));
-                }
-
                 // Returns TokenValidation struct if the token is valid
-                return Outcome::Success(TokenValidation {
-                    claims: result.claims,
-                });
+                match TokenValidation::from_token(&token) {
+                    Ok(validation) => Outcome::Success(validation),
+                    Err(message) => Outcome::Failure((rocket::http::Status::Unauthorized, message)),
+                }
             }
             None => {
                 // Return an error if the token is not found
@@ -99,6 +71,45 @@ impl<'r> FromRequest<'r> for TokenValidation {
     }
 }
 
+/// Token Validation Implementation
+impl TokenValidation {
+    /// Validates a token sent outside of the Authorization header, e.g. when opening a WebSocket
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - The token, without the Bearer prefix
+    ///
+    /// # Returns
+    ///
+    /// * The validation result, or an error message if the token is invalid or expired
+    pub fn from_token(token: &str) -> Result<TokenValidation, String> {
+        // Decode the token
+        let decode_token = jsonwebtoken::decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(&KEY),
+            &jsonwebtoken::Validation::default(),
+        );
+
+        // Check if the token is valid
+        let result = match decode_token {
+            Ok(token_data) => token_data,
+            Err(_) => return Err("Invalid token".to_string()),
+        };
+
+        // Check if the token is expired
+        let now = Utc::now().timestamp_nanos() / 1_000_000_000;
+
+        // Return an error if the token is expired
+        if result.claims.exp < now {
+            return Err("Token expired".to_string());
+        }
+
+        Ok(TokenValidation {
+            claims: result.claims,
+        })
+    }
+}
+
 impl<'a> OpenApiFromRequest<'a> for TokenValidation {
     fn from_request_input(
         _gen: &mut OpenApiGenerator,
@@ -0,0 +1,155 @@
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+
+/// URL schemes links and images are allowed to use, relative URLs are always allowed
+static SAFE_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+/// Renders a Markdown body to HTML
+///
+/// Raw HTML in the body is escaped instead of being rendered, and links using schemes such as
+/// `javascript:` are dropped, so the output can be embedded in a page as is
+///
+/// # Arguments
+///
+/// * `body` - The Markdown body
+///
+/// # Returns
+///
+/// * The rendered HTML
+pub fn to_html(body: &str) -> String {
+    let options =
+        Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS;
+
+    let events = Parser::new_ext(body, options).map(|event| match event {
+        Event::Html(raw) => Event::Text(raw),
+        Event::Start(Tag::Link(kind, url, title)) => {
+            Event::Start(Tag::Link(kind, safe_url(url), title))
+        }
+        Event::Start(Tag::Image(kind, url, title)) => {
+            Event::Start(Tag::Image(kind, safe_url(url), title))
+        }
+        event => event,
+    });
+
+    let mut output = String::new();
+    html::push_html(&mut output, events);
+    output
+}
+
+/// Finds the users mentioned in a Markdown body
+///
+/// A mention is an `@` followed by a user name or an email, e.g. `@alice` or `@alice@example.com`
+/// Mentions inside inline code, fenced code blocks and indented code blocks are ignored
+///
+/// # Arguments
+///
+/// * `body` - The Markdown body
+///
+/// # Returns
+///
+/// * The mentioned names, lowercased and without duplicates, in order of appearance
+pub fn mentions(body: &str) -> Vec<String> {
+    let mut found: Vec<String> = vec![];
+    let mut text = String::new();
+    let mut in_code_block = false;
+
+    // Text can be split into several events, so it is scanned once a block of text ends
+    for event in Parser::new(body) {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+            Event::Text(part) if !in_code_block => {
+                text.push_str(&part);
+                continue;
+            }
+            _ => {}
+        }
+
+        if !text.is_empty() {
+            scan_mentions(&text, &mut found);
+            text.clear();
+        }
+    }
+
+    scan_mentions(&text, &mut found);
+    found
+}
+
+/// Internal function to collect the mentions of a piece of plain text
+fn scan_mentions(text: &str, found: &mut Vec<String>) {
+    let chars: Vec<char> = text.chars().collect();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let starts_mention =
+            chars[index] == '@' && (index == 0 || !chars[index - 1].is_alphanumeric());
+
+        if !starts_mention {
+            index += 1;
+            continue;
+        }
+
+        let end = chars[index + 1..]
+            .iter()
+            .position(|c| !(c.is_alphanumeric() || "._-+@".contains(*c)))
+            .map_or(chars.len(), |length| index + 1 + length);
+
+        let mention: String = chars[index + 1..end].iter().collect();
+        let mention = mention
+            .trim_matches(|c: char| !c.is_alphanumeric())
+            .to_lowercase();
+
+        if !mention.is_empty() && !found.contains(&mention) {
+            found.push(mention);
+        }
+
+        index = end;
+    }
+}
+
+/// Internal function to replace URLs using an unsafe scheme with an empty URL
+fn safe_url(url: CowStr) -> CowStr {
+    let scheme = match url.split_once(':') {
+        Some((scheme, _)) if !scheme.contains(['/', '?', '#']) => scheme.trim().to_lowercase(),
+        _ => return url,
+    };
+
+    if SAFE_SCHEMES.contains(&scheme.as_str()) {
+        url
+    } else {
+        CowStr::Borrowed("")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_names_and_emails() {
+        assert_eq!(
+            mentions("Hi @Alice and @bob@example.com, also @alice."),
+            vec!["alice".to_string(), "bob@example.com".to_string()]
+        );
+    }
+
+    #[test]
+    fn ignores_addresses_and_code() {
+        let body = "Mail carol@example.com about `@dave`\n\
+                    \n\
+                    ```\n\
+                    @erin\n\
+                    ```\n\
+                    \n\
+                    \x20   @frank\n\
+                    \n\
+                    Thanks @grace";
+
+        assert_eq!(mentions(body), vec!["grace".to_string()]);
+    }
+
+    #[test]
+    fn joins_text_split_by_the_parser() {
+        assert_eq!(mentions("@bob&amp;"), vec!["bob".to_string()]);
+        assert_eq!(mentions("**@carol** @dave"), vec!["carol", "dave"]);
+    }
+}
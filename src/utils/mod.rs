@@ -1 +1,20 @@
+pub mod collab;
+pub mod created;
+pub mod cron;
+pub mod deprecation;
+pub mod email;
+pub mod event_bus;
+pub mod idempotency;
+pub mod invitations;
+pub mod jobs;
 pub mod jwt;
+pub mod markdown;
+pub mod notifications;
+pub mod precondition;
+pub mod rank;
+pub mod rrule;
+pub mod s3;
+pub mod signature;
+pub mod storage;
+pub mod webhooks;
+pub mod websocket;
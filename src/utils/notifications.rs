@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use diesel::result::{DatabaseErrorKind, Error};
+
+use crate::config::db::{get_connection, PoolConnection};
+use crate::models::job::Job;
+use crate::models::notification::{
+    Channel, NewNotification, Notification, NotificationKind, DELIVER,
+};
+use crate::models::reminder::Reminder;
+use crate::models::user::User;
+use crate::models::webhook::Webhook;
+use crate::utils::email::Mailer;
+use crate::utils::jobs::JobHandler;
+
+/// Way of sending a notification to its user
+///
+/// Notifications are sent by `notification.deliver` jobs, one for every channel the user turned on,
+/// so a channel failing is retried without sending the notification again through the others
+pub trait NotificationChannel: Send + Sync {
+    /// Channel implemented, the user settings name it
+    fn channel(&self) -> Channel;
+
+    /// Sends a notification
+    ///
+    /// # Arguments
+    ///
+    /// * `notification` - The notification
+    /// * `pool` - A pool of database connections
+    ///
+    /// # Returns
+    ///
+    /// * Nothing, or why the notification could not be sent, it is retried later
+    fn send(&self, notification: &NewNotification, pool: &PoolConnection) -> Result<(), String>;
+}
+
+/// Adds notifications to the inbox of their user
+pub struct InAppChannel;
+
+impl NotificationChannel for InAppChannel {
+    fn channel(&self) -> Channel {
+        Channel::InApp
+    }
+
+    fn send(&self, notification: &NewNotification, pool: &PoolConnection) -> Result<(), String> {
+        let mut conn = get_connection(pool)?;
+
+        match Notification::create(std::slice::from_ref(notification), &mut conn) {
+            Ok(_) => Ok(()),
+            // The user or the todo was deleted since
+            Err(Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _)) => Ok(()),
+            Err(_) => Err("Failed to create notification".to_string()),
+        }
+    }
+}
+
+/// Emails notifications to the address of their user
+pub struct EmailChannel {
+    /// Mailer sending the emails, None if emails are not set up
+    mailer: Option<Mailer>,
+}
+
+impl EmailChannel {
+    /// Creates the channel
+    ///
+    /// # Arguments
+    ///
+    /// * `mailer` - Mailer sending the emails, notifications are dropped without one
+    ///
+    /// # Returns
+    ///
+    /// * The channel
+    pub fn new(mailer: Option<Mailer>) -> EmailChannel {
+        EmailChannel { mailer }
+    }
+}
+
+impl NotificationChannel for EmailChannel {
+    fn channel(&self) -> Channel {
+        Channel::Email
+    }
+
+    fn send(&self, notification: &NewNotification, pool: &PoolConnection) -> Result<(), String> {
+        let mailer = match self.mailer {
+            Some(ref mailer) => mailer,
+            None => {
+                log::warn!("Email notifications are on for a user but SMTP_URL is not set");
+                return Ok(());
+            }
+        };
+
+        let user = {
+            let mut conn = get_connection(pool)?;
+
+            match User::find_by_id(notification.user_id, &mut conn) {
+                Ok(user) => user,
+                // The user was deleted since
+                Err(_) => return Ok(()),
+            }
+        };
+
+        let subject = match notification.kind {
+            NotificationKind::Mention => "You were mentioned in a comment",
+            NotificationKind::Reminder => "Reminder",
+        };
+
+        // No connection is held while waiting for the SMTP server
+        mailer.send(&user.email, subject, &notification.message)
+    }
+}
+
+/// Sends notifications to the webhooks of their user subscribed to `notification.created`
+pub struct WebhookChannel;
+
+impl NotificationChannel for WebhookChannel {
+    fn channel(&self) -> Channel {
+        Channel::Webhook
+    }
+
+    fn send(&self, notification: &NewNotification, pool: &PoolConnection) -> Result<(), String> {
+        let mut conn = get_connection(pool)?;
+
+        match Webhook::notify(notification, &mut conn) {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Failed to queue deliveries".to_string()),
+        }
+    }
+}
+
+/// Sends the notifications queued by `Notification::notify` through their channel
+pub struct NotificationSender {
+    /// Channels by name
+    channels: HashMap<Channel, Box<dyn NotificationChannel>>,
+}
+
+impl NotificationSender {
+    /// Creates the sender
+    ///
+    /// # Arguments
+    ///
+    /// * `channels` - Channels notifications can be sent through
+    ///
+    /// # Returns
+    ///
+    /// * The sender
+    pub fn new(channels: Vec<Box<dyn NotificationChannel>>) -> NotificationSender {
+        let channels = channels
+            .into_iter()
+            .map(|channel| (channel.channel(), channel))
+            .collect();
+
+        NotificationSender { channels }
+    }
+}
+
+impl JobHandler for NotificationSender {
+    fn kind(&self) -> &'static str {
+        DELIVER
+    }
+
+    fn run(&self, job: &Job, pool: &PoolConnection) -> Result<(), String> {
+        let channel = job
+            .payload
+            .get("channel")
+            .cloned()
+            .and_then(|channel| serde_json::from_value::<Channel>(channel).ok());
+
+        let notification = job
+            .payload
+            .get("notification")
+            .cloned()
+            .and_then(|notification| serde_json::from_value::<NewNotification>(notification).ok());
+
+        let (channel, notification) = match (channel, notification) {
+            (Some(channel), Some(notification)) => (channel, notification),
+            _ => return Err(format!("Invalid payload {}", job.payload)),
+        };
+
+        match self.channels.get(&channel) {
+            Some(sender) => sender.send(&notification, pool),
+            None => Err(format!("No sender for channel {}", channel.as_str())),
+        }
+    }
+}
+
+/// Sends the due reminders, queued every minute
+pub struct SendReminders;
+
+impl JobHandler for SendReminders {
+    fn kind(&self) -> &'static str {
+        "reminders.send"
+    }
+
+    fn run(&self, _job: &Job, pool: &PoolConnection) -> Result<(), String> {
+        let mut conn = get_connection(pool)?;
+
+        Reminder::send_due(&mut conn).map(|_| ())
+    }
+}
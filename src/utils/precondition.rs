@@ -0,0 +1,233 @@
+use rocket::http::{Header, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::response::{self, Responder};
+use rocket::Request;
+use rocket_okapi::okapi::openapi3::{Object, Parameter, ParameterValue, Responses};
+use rocket_okapi::response::OpenApiResponderInner;
+use rocket_okapi::{
+    gen::OpenApiGenerator,
+    request::{OpenApiFromRequest, RequestHeaderInput},
+};
+
+/// Formats the version of a resource as an ETag, e.g. `"3"`
+pub fn etag(version: i32) -> String {
+    format!("\"{}\"", version)
+}
+
+/// Internal function to read the version from a strong ETag, None for weak ETags such as `W/"3"`
+fn parse_strong_etag(tag: &str) -> Option<i32> {
+    tag.trim()
+        .strip_prefix('"')?
+        .strip_suffix('"')?
+        .parse()
+        .ok()
+}
+
+/// Internal function to read the version from an ETag, weak ETags such as `W/"3"` included
+fn parse_etag(tag: &str) -> Option<i32> {
+    let tag = tag.trim();
+
+    parse_strong_etag(tag.strip_prefix("W/").unwrap_or(tag))
+}
+
+/// Documents an optional request header read by a request guard
+pub fn header_parameter(
+    gen: &mut OpenApiGenerator,
+    name: &str,
+    description: &str,
+) -> rocket_okapi::Result<RequestHeaderInput> {
+    Ok(RequestHeaderInput::Parameter(Parameter {
+        name: name.to_owned(),
+        location: "header".to_owned(),
+        description: Some(description.to_owned()),
+        required: false,
+        deprecated: false,
+        allow_empty_value: false,
+        value: ParameterValue::Schema {
+            style: None,
+            explode: None,
+            allow_reserved: false,
+            schema: gen.json_schema::<String>(),
+            example: None,
+            examples: None,
+        },
+        extensions: Object::default(),
+    }))
+}
+
+/// If-Match header of a request, holding the ETags the client based its changes on
+#[derive(Debug)]
+pub struct IfMatch(Option<String>);
+
+impl IfMatch {
+    /// Gets the versions the resource must still be at one of for the request to apply
+    ///
+    /// ETags are compared with the strong comparison, so weak ETags such as `W/"3"` never match
+    ///
+    /// # Returns
+    ///
+    /// * The versions of the strong ETags of the header, None if the header is `*` and any version matches
+    /// * 428 Precondition Required if the header is missing
+    /// * 412 Precondition Failed if the header holds no strong ETag, as it cannot match
+    pub fn versions(&self) -> Result<Option<Vec<i32>>, Status> {
+        let header = match self.0.as_deref().map(str::trim) {
+            None => return Err(Status::PreconditionRequired),
+            Some("*") => return Ok(None),
+            Some(header) => header,
+        };
+
+        let versions: Vec<i32> = header.split(',').filter_map(parse_strong_etag).collect();
+
+        if versions.is_empty() {
+            return Err(Status::PreconditionFailed);
+        }
+
+        Ok(Some(versions))
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfMatch {
+    type Error = String;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let header = request.headers().get_one("If-Match");
+
+        Outcome::Success(IfMatch(header.map(str::to_string)))
+    }
+}
+
+impl<'a> OpenApiFromRequest<'a> for IfMatch {
+    fn from_request_input(
+        gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        header_parameter(
+            gen,
+            "If-Match",
+            "ETag of the version the changes are based on, as returned by a read, or `*`",
+        )
+    }
+}
+
+/// If-None-Match header of a request, holding the ETags of the versions the client already has
+#[derive(Debug)]
+pub struct IfNoneMatch(Option<String>);
+
+impl IfNoneMatch {
+    /// Checks whether the client already has a version of the resource
+    ///
+    /// # Arguments
+    ///
+    /// * `version` - The current version of the resource
+    ///
+    /// # Returns
+    ///
+    /// * Whether one of the ETags of the header, or `*`, matches the version
+    pub fn matches(&self, version: i32) -> bool {
+        match self.0.as_deref() {
+            Some(header) => header
+                .split(',')
+                .any(|tag| tag.trim() == "*" || parse_etag(tag) == Some(version)),
+            None => false,
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfNoneMatch {
+    type Error = String;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let header = request.headers().get_one("If-None-Match");
+
+        Outcome::Success(IfNoneMatch(header.map(str::to_string)))
+    }
+}
+
+impl<'a> OpenApiFromRequest<'a> for IfNoneMatch {
+    fn from_request_input(
+        gen: &mut OpenApiGenerator,
+        _name: String,
+        _required: bool,
+    ) -> rocket_okapi::Result<RequestHeaderInput> {
+        header_parameter(
+            gen,
+            "If-None-Match",
+            "ETags of the versions the client already has, the body is omitted if one is current",
+        )
+    }
+}
+
+/// Response to a conditional read
+pub enum Conditional<R> {
+    /// The resource, sent with its ETag when its version is known
+    Modified(Option<i32>, R),
+    /// The client already has the current version of the resource, sent as 304 Not Modified
+    NotModified(i32),
+}
+
+impl<'r, R: Responder<'r, 'static>> Responder<'r, 'static> for Conditional<R> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        match self {
+            Conditional::Modified(Some(version), body) => {
+                response::Response::build_from(body.respond_to(request)?)
+                    .header(Header::new("ETag", etag(version)))
+                    .ok()
+            }
+            Conditional::Modified(None, body) => body.respond_to(request),
+            Conditional::NotModified(version) => response::Response::build()
+                .status(Status::NotModified)
+                .header(Header::new("ETag", etag(version)))
+                .ok(),
+        }
+    }
+}
+
+impl<R: OpenApiResponderInner> OpenApiResponderInner for Conditional<R> {
+    fn responses(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        let mut responses = R::responses(gen)?;
+        rocket_okapi::util::ensure_status_code_exists(&mut responses, 304);
+
+        Ok(responses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn if_match(header: &str) -> Result<Option<Vec<i32>>, Status> {
+        IfMatch(Some(header.to_string())).versions()
+    }
+
+    fn if_none_match(header: &str, version: i32) -> bool {
+        IfNoneMatch(Some(header.to_string())).matches(version)
+    }
+
+    #[test]
+    fn if_match_reads_every_strong_etag() {
+        assert_eq!(if_match("\"3\""), Ok(Some(vec![3])));
+        assert_eq!(if_match(" \"3\", \"5\" "), Ok(Some(vec![3, 5])));
+        assert_eq!(if_match("*"), Ok(None));
+        assert_eq!(IfMatch(None).versions(), Err(Status::PreconditionRequired));
+    }
+
+    #[test]
+    fn if_match_ignores_weak_and_invalid_etags() {
+        assert_eq!(if_match("W/\"3\""), Err(Status::PreconditionFailed));
+        assert_eq!(if_match("W/\"3\", \"4\""), Ok(Some(vec![4])));
+        assert_eq!(if_match("3"), Err(Status::PreconditionFailed));
+        assert_eq!(if_match(""), Err(Status::PreconditionFailed));
+    }
+
+    #[test]
+    fn if_none_match_accepts_weak_etags() {
+        assert!(if_none_match("W/\"3\"", 3));
+        assert!(if_none_match("\"2\", \"3\"", 3));
+        assert!(if_none_match("*", 3));
+        assert!(!if_none_match("\"2\"", 3));
+        assert!(!IfNoneMatch(None).matches(3));
+    }
+}
@@ -0,0 +1,282 @@
+/// Digits ranks are written with, in ascending order
+static DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// Number of digits available
+static BASE: u32 = 36;
+
+/// Ranks longer than this should be rebalanced
+pub static MAX_LENGTH: usize = 32;
+
+/// Minimum number of ranks left between two neighbours after spreading them
+static GAP: u64 = 1024;
+
+/// Computes a rank that sorts strictly between two ranks
+///
+/// Ranks are compared lexicographically and never end with `0`, so there is always room for
+/// another rank between two different ranks
+///
+/// # Arguments
+///
+/// * `lower` - Rank the result must sort after, if empty the result sorts before `upper`
+/// * `upper` - Rank the result must sort before, if empty the result sorts after `lower`
+///
+/// # Returns
+///
+/// * The new rank, or None if a rank is invalid or `lower` does not sort before `upper`
+pub fn between(lower: Option<&str>, upper: Option<&str>) -> Option<String> {
+    let lower = match lower {
+        Some(lower) => Some(decode(lower)?),
+        None => None,
+    };
+    let upper = match upper {
+        Some(upper) => Some(decode(upper)?),
+        None => None,
+    };
+
+    let digits = match (lower, upper) {
+        (None, None) => vec![BASE / 2],
+        (Some(lower), None) => increment(&lower),
+        (None, Some(upper)) => decrement(&upper).unwrap_or_else(|| midpoint(&[], Some(&upper))),
+        (Some(lower), Some(upper)) if lower < upper => midpoint(&lower, Some(&upper)),
+        _ => return None,
+    };
+
+    Some(encode(&digits))
+}
+
+/// Computes evenly spaced ranks, used to rebalance ranks that grew too long
+///
+/// # Arguments
+///
+/// * `count` - Number of ranks to compute
+///
+/// # Returns
+///
+/// * The ranks in ascending order
+pub fn spread(count: usize) -> Vec<String> {
+    let slots = count as u64 + 1;
+    let mut width = 1;
+    let mut capacity = BASE as u64;
+
+    while capacity < slots * GAP {
+        width += 1;
+        capacity *= BASE as u64;
+    }
+
+    let step = capacity / slots;
+
+    (1..slots)
+        .map(|slot| {
+            let mut value = slot * step;
+            let mut digits = vec![0; width];
+
+            for digit in digits.iter_mut().rev() {
+                *digit = (value % BASE as u64) as u32;
+                value /= BASE as u64;
+            }
+
+            while digits.last() == Some(&0) {
+                digits.pop();
+            }
+
+            encode(&digits)
+        })
+        .collect()
+}
+
+/// Internal function to compute a rank after another one, keeping its length when possible
+fn increment(lower: &[u32]) -> Vec<u32> {
+    let mut digits = lower.to_vec();
+
+    for index in (0..digits.len()).rev() {
+        if digits[index] + 1 < BASE {
+            digits[index] += 1;
+
+            if let Some(last) = digits.last_mut() {
+                *last = (*last).max(1);
+            }
+
+            return digits;
+        }
+
+        digits[index] = 0;
+    }
+
+    let mut digits = lower.to_vec();
+    digits.push(BASE / 2);
+    digits
+}
+
+/// Internal function to compute a rank right before another one, keeping it as short as possible
+/// Returns None when no digit can be lowered without ending the rank with `0`
+fn decrement(upper: &[u32]) -> Option<Vec<u32>> {
+    let index = upper.iter().rposition(|digit| *digit > 1)?;
+    let mut digits = upper[..index].to_vec();
+    digits.push(upper[index] - 1);
+    Some(digits)
+}
+
+/// Internal function to compute a rank between two ranks
+/// `lower` must sort before `upper`, an empty `upper` stands for the end of the ranks
+fn midpoint(lower: &[u32], upper: Option<&[u32]>) -> Vec<u32> {
+    if let Some(upper) = upper {
+        let common = upper
+            .iter()
+            .enumerate()
+            .take_while(|(index, digit)| lower.get(*index).copied().unwrap_or(0) == **digit)
+            .count();
+
+        if common > 0 {
+            let mut digits = upper[..common].to_vec();
+            digits.extend(midpoint(
+                lower.get(common..).unwrap_or(&[]),
+                Some(&upper[common..]),
+            ));
+            return digits;
+        }
+    }
+
+    let lower_digit = lower.first().copied().unwrap_or(0);
+    let upper_digit = upper
+        .and_then(|upper| upper.first().copied())
+        .unwrap_or(BASE);
+
+    if upper_digit - lower_digit > 1 {
+        return vec![(lower_digit + upper_digit) / 2];
+    }
+
+    match upper {
+        Some(upper) if upper.len() > 1 => vec![upper[0]],
+        _ => {
+            let mut digits = vec![lower_digit];
+            digits.extend(midpoint(lower.get(1..).unwrap_or(&[]), None));
+            digits
+        }
+    }
+}
+
+/// Internal function to read the digits of a rank, rejecting empty ranks and ranks ending with `0`
+fn decode(rank: &str) -> Option<Vec<u32>> {
+    if rank.is_empty() || rank.ends_with('0') {
+        return None;
+    }
+
+    rank.bytes()
+        .map(|byte| {
+            DIGITS
+                .iter()
+                .position(|digit| *digit == byte)
+                .map(|digit| digit as u32)
+        })
+        .collect()
+}
+
+/// Internal function to write the digits of a rank
+fn encode(digits: &[u32]) -> String {
+    digits
+        .iter()
+        .map(|digit| DIGITS[*digit as usize] as char)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn valid(rank: &str) -> bool {
+        decode(rank).is_some()
+    }
+
+    #[test]
+    fn first_rank_sits_in_the_middle() {
+        assert_eq!(between(None, None), Some("i".to_string()));
+    }
+
+    #[test]
+    fn ranks_after_and_before_a_rank() {
+        let after = between(Some("i"), None).unwrap();
+        let before = between(None, Some("i")).unwrap();
+
+        assert!(after.as_str() > "i" && valid(&after));
+        assert!(before.as_str() < "i" && valid(&before));
+        assert_eq!(between(Some("z"), None), Some("zi".to_string()));
+        assert_eq!(between(None, Some("1")), Some("0i".to_string()));
+    }
+
+    #[test]
+    fn ranks_between_two_ranks() {
+        for (lower, upper) in [
+            ("a", "c"),
+            ("a", "b"),
+            ("a1", "a2"),
+            ("az", "b"),
+            ("1", "11"),
+        ] {
+            let rank = between(Some(lower), Some(upper)).unwrap();
+
+            assert!(
+                lower < rank.as_str() && rank.as_str() < upper,
+                "{} {} {}",
+                lower,
+                rank,
+                upper
+            );
+            assert!(valid(&rank));
+        }
+    }
+
+    #[test]
+    fn repeated_inserts_keep_the_order() {
+        let mut lower = "a".to_string();
+        let upper = "b".to_string();
+
+        for _ in 0..100 {
+            let rank = between(Some(&lower), Some(&upper)).unwrap();
+
+            assert!(lower < rank && rank < upper);
+            lower = rank;
+        }
+
+        let mut upper = "b".to_string();
+
+        for _ in 0..100 {
+            let rank = between(None, Some(&upper)).unwrap();
+
+            assert!(rank < upper && valid(&rank));
+            upper = rank;
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_ranks_and_order() {
+        assert_eq!(between(Some("b"), Some("a")), None);
+        assert_eq!(between(Some("a"), Some("a")), None);
+        assert_eq!(between(Some("a0"), None), None);
+        assert_eq!(between(None, Some("")), None);
+        assert_eq!(between(Some("A"), None), None);
+    }
+
+    #[test]
+    fn spread_ranks_are_ordered_and_valid() {
+        for count in [0, 1, 2, 35, 36, 1000, 5000] {
+            let ranks = spread(count);
+
+            assert_eq!(ranks.len(), count);
+            assert!(ranks.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(ranks
+                .iter()
+                .all(|rank| valid(rank) && rank.len() < MAX_LENGTH));
+        }
+    }
+
+    #[test]
+    fn spread_ranks_leave_room_between_them() {
+        let ranks = spread(100);
+
+        for pair in ranks.windows(2) {
+            let rank = between(Some(&pair[0]), Some(&pair[1])).unwrap();
+
+            assert!(rank.len() <= pair[0].len().max(pair[1].len()));
+        }
+    }
+}
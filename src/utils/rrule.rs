@@ -0,0 +1,504 @@
+use std::str::FromStr;
+
+use chrono::{
+    Datelike, Duration, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Weekday,
+};
+use chrono_tz::Tz;
+
+/// Maximum number of periods walked through while looking for occurrences
+/// This protects against rules that rarely or never produce an occurrence
+static MAX_PERIODS: i64 = 2000;
+
+/// Frequency of a recurrence rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// End of a recurrence rule given by `UNTIL`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Until {
+    /// `UNTIL` given in UTC, e.g. `20230601T000000Z`
+    Utc(NaiveDateTime),
+    /// `UNTIL` given in the local time of the rule, e.g. `20230601` or `20230601T000000`
+    Local(NaiveDateTime),
+}
+
+/// Recurrence rule following RFC 5545
+/// Supports the `FREQ`, `INTERVAL`, `COUNT`, `UNTIL`, `BYDAY` (without ordinals) and `BYMONTHDAY` parts
+/// Weeks start on Monday
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecurrenceRule {
+    /// Frequency of the rule
+    pub frequency: Frequency,
+    /// Number of periods between two occurrences, defaults to 1
+    pub interval: u32,
+    /// Total number of occurrences of the series
+    pub count: Option<u32>,
+    /// Last time an occurrence can happen
+    pub until: Option<Until>,
+    /// Days of the week occurrences happen on
+    pub by_day: Vec<Weekday>,
+    /// Days of the month occurrences happen on, negative values count from the end of the month
+    pub by_month_day: Vec<i32>,
+}
+
+impl FromStr for RecurrenceRule {
+    type Err = String;
+
+    /// Parses a rule such as `FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE`, with or without the `RRULE:` prefix
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let rule = rule.trim();
+        let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut count = None;
+        let mut until = None;
+        let mut by_day = vec![];
+        let mut by_month_day = vec![];
+
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = match part.split_once('=') {
+                Some(pair) => pair,
+                None => return Err(format!("Invalid rule part {}", part)),
+            };
+
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(format!("Unsupported frequency {}", value)),
+                    })
+                }
+                "INTERVAL" => {
+                    interval = match value.parse::<u32>() {
+                        Ok(interval) if interval > 0 => interval,
+                        _ => return Err(format!("Invalid interval {}", value)),
+                    }
+                }
+                "COUNT" => {
+                    count = match value.parse::<u32>() {
+                        Ok(count) if count > 0 => Some(count),
+                        _ => return Err(format!("Invalid count {}", value)),
+                    }
+                }
+                "UNTIL" => until = Some(parse_until(value)?),
+                "BYDAY" => {
+                    for day in value.split(',') {
+                        by_day.push(parse_weekday(day)?);
+                    }
+                }
+                "BYMONTHDAY" => {
+                    for day in value.split(',') {
+                        match day.parse::<i32>() {
+                            Ok(day) if day != 0 && (-31..=31).contains(&day) => {
+                                by_month_day.push(day)
+                            }
+                            _ => return Err(format!("Invalid month day {}", day)),
+                        }
+                    }
+                }
+                "WKST" if value.eq_ignore_ascii_case("MO") => {}
+                _ => return Err(format!("Unsupported rule part {}", part)),
+            }
+        }
+
+        let frequency = match frequency {
+            Some(frequency) => frequency,
+            None => return Err("FREQ is required".to_string()),
+        };
+
+        if count.is_some() && until.is_some() {
+            return Err("COUNT and UNTIL cannot be used together".to_string());
+        }
+
+        Ok(RecurrenceRule {
+            frequency,
+            interval,
+            count,
+            until,
+            by_day,
+            by_month_day,
+        })
+    }
+}
+
+impl RecurrenceRule {
+    /// Computes the occurrences following a given occurrence of the series
+    ///
+    /// Occurrences are computed on the wall clock of the time zone, so a todo due at 9:00 stays due at 9:00
+    /// across daylight saving time changes. The wall clock time is read from the start of the series rather
+    /// than from `after`, which may have been moved forward by an hour when it fell in a daylight saving gap
+    ///
+    /// # Arguments
+    ///
+    /// * `after` - An occurrence of the series in UTC, the following occurrences are computed from it
+    /// * `position` - Position of `after` in the series, starting at 1, used to honor `COUNT`
+    /// * `start` - First occurrence of the series in UTC, every occurrence keeps its wall clock time
+    /// * `tz` - Time zone the rule is evaluated in
+    /// * `limit` - Maximum number of occurrences returned
+    ///
+    /// # Returns
+    ///
+    /// * The following occurrences in UTC, in chronological order
+    pub fn occurrences_after(
+        &self,
+        after: NaiveDateTime,
+        position: u32,
+        start: NaiveDateTime,
+        tz: Tz,
+        limit: usize,
+    ) -> Vec<NaiveDateTime> {
+        let mut limit = limit;
+
+        if let Some(count) = self.count {
+            limit = limit.min(count.saturating_sub(position) as usize);
+        }
+
+        let time = tz.from_utc_datetime(&start).naive_local().time();
+        let anchor = tz
+            .from_utc_datetime(&after)
+            .naive_local()
+            .date()
+            .and_time(time);
+        let mut occurrences = vec![];
+
+        for period in 0..MAX_PERIODS {
+            if occurrences.len() >= limit {
+                break;
+            }
+
+            let step = period * self.interval as i64;
+
+            for candidate in self.period_candidates(anchor, step) {
+                if candidate <= anchor {
+                    continue;
+                }
+
+                let utc = match to_utc(candidate, tz) {
+                    Some(utc) => utc,
+                    None => continue,
+                };
+
+                let past_until = match self.until {
+                    Some(Until::Utc(until)) => utc > until,
+                    Some(Until::Local(until)) => candidate > until,
+                    None => false,
+                };
+
+                if past_until || occurrences.len() >= limit {
+                    return occurrences;
+                }
+
+                occurrences.push(utc);
+            }
+        }
+
+        occurrences
+    }
+
+    /// Internal function to list the candidates of a period, sorted, in local time
+    /// # Arguments
+    /// * `anchor` - Occurrence the series is computed from, in local time
+    /// * `step` - Number of periods between the anchor period and this period
+    /// # Returns
+    /// * The candidates of the period
+    fn period_candidates(&self, anchor: NaiveDateTime, step: i64) -> Vec<NaiveDateTime> {
+        let time = anchor.time();
+        let date = anchor.date();
+
+        let mut dates = match self.frequency {
+            Frequency::Daily => {
+                let day = date + Duration::days(step);
+
+                if self.by_day.is_empty() || self.by_day.contains(&day.weekday()) {
+                    vec![day]
+                } else {
+                    vec![]
+                }
+            }
+            Frequency::Weekly => {
+                let monday = date - Duration::days(date.weekday().num_days_from_monday() as i64)
+                    + Duration::weeks(step);
+                let days = if self.by_day.is_empty() {
+                    vec![date.weekday()]
+                } else {
+                    self.by_day.clone()
+                };
+
+                days.iter()
+                    .map(|day| monday + Duration::days(day.num_days_from_monday() as i64))
+                    .collect()
+            }
+            Frequency::Monthly => {
+                let months = date.year() as i64 * 12 + date.month0() as i64 + step;
+                let (year, month) = ((months / 12) as i32, (months % 12) as u32 + 1);
+
+                self.month_dates(year, month, date.day())
+            }
+            Frequency::Yearly => {
+                match NaiveDate::from_ymd_opt(date.year() + step as i32, date.month(), date.day())
+                {
+                    Some(day) => vec![day],
+                    None => vec![],
+                }
+            }
+        };
+
+        dates.sort();
+        dates.dedup();
+        dates.into_iter().map(|day| day.and_time(time)).collect()
+    }
+
+    /// Internal function to list the dates of a month matching `BYMONTHDAY` and `BYDAY`
+    /// When neither is given, the day of the month of the anchor is used
+    /// # Arguments
+    /// * `year` - Year of the month
+    /// * `month` - Month, starting at 1
+    /// * `anchor_day` - Day of the month of the anchor
+    /// # Returns
+    /// * The matching dates, days that do not exist in the month are skipped
+    fn month_dates(&self, year: i32, month: u32, anchor_day: u32) -> Vec<NaiveDate> {
+        let days_in_month = match NaiveDate::from_ymd_opt(year, month, 1) {
+            Some(first) => {
+                let next = if month == 12 {
+                    NaiveDate::from_ymd_opt(year + 1, 1, 1)
+                } else {
+                    NaiveDate::from_ymd_opt(year, month + 1, 1)
+                };
+
+                match next {
+                    Some(next) => (next - first).num_days() as i32,
+                    None => return vec![],
+                }
+            }
+            None => return vec![],
+        };
+
+        let mut days: Vec<i32> = if !self.by_month_day.is_empty() {
+            self.by_month_day
+                .iter()
+                .map(|day| if *day < 0 { days_in_month + day + 1 } else { *day })
+                .collect()
+        } else if !self.by_day.is_empty() {
+            (1..=days_in_month).collect()
+        } else {
+            vec![anchor_day as i32]
+        };
+
+        days.retain(|day| *day >= 1 && *day <= days_in_month);
+
+        days.into_iter()
+            .filter_map(|day| NaiveDate::from_ymd_opt(year, month, day as u32))
+            .filter(|day| self.by_day.is_empty() || self.by_day.contains(&day.weekday()))
+            .collect()
+    }
+}
+
+/// Converts a local time to UTC
+/// Ambiguous times, when clocks go back, resolve to the earliest one
+/// Times skipped when clocks go forward are moved forward by an hour
+pub fn to_utc(local: NaiveDateTime, tz: Tz) -> Option<NaiveDateTime> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(time) => Some(time.naive_utc()),
+        LocalResult::Ambiguous(earliest, _) => Some(earliest.naive_utc()),
+        LocalResult::None => match tz.from_local_datetime(&(local + Duration::hours(1))) {
+            LocalResult::Single(time) => Some(time.naive_utc()),
+            LocalResult::Ambiguous(earliest, _) => Some(earliest.naive_utc()),
+            LocalResult::None => None,
+        },
+    }
+}
+
+/// Internal function to parse a two letter weekday such as `MO`
+fn parse_weekday(day: &str) -> Result<Weekday, String> {
+    match day.trim().to_ascii_uppercase().as_str() {
+        "MO" => Ok(Weekday::Mon),
+        "TU" => Ok(Weekday::Tue),
+        "WE" => Ok(Weekday::Wed),
+        "TH" => Ok(Weekday::Thu),
+        "FR" => Ok(Weekday::Fri),
+        "SA" => Ok(Weekday::Sat),
+        "SU" => Ok(Weekday::Sun),
+        _ => Err(format!("Unsupported day {}", day)),
+    }
+}
+
+/// Internal function to parse an `UNTIL` value, either a date or a date-time
+/// A date alone includes the whole day
+fn parse_until(value: &str) -> Result<Until, String> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        return match NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S") {
+            Ok(until) => Ok(Until::Utc(until)),
+            Err(_) => Err(format!("Invalid until {}", value)),
+        };
+    }
+
+    if let Ok(until) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        return Ok(Until::Local(until));
+    }
+
+    match NaiveDate::parse_from_str(value, "%Y%m%d") {
+        Ok(until) => match NaiveTime::from_hms_opt(23, 59, 59) {
+            Some(end_of_day) => Ok(Until::Local(until.and_time(end_of_day))),
+            None => Err(format!("Invalid until {}", value)),
+        },
+        Err(_) => Err(format!("Invalid until {}", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn rule(value: &str) -> RecurrenceRule {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_rule_parts() {
+        let parsed = rule("RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,we;WKST=MO");
+
+        assert_eq!(parsed.frequency, Frequency::Weekly);
+        assert_eq!(parsed.interval, 2);
+        assert_eq!(parsed.by_day, vec![Weekday::Mon, Weekday::Wed]);
+        assert_eq!(parsed.count, None);
+        assert_eq!(parsed.until, None);
+    }
+
+    #[test]
+    fn parses_until_forms() {
+        assert_eq!(
+            rule("FREQ=DAILY;UNTIL=20230601T120000Z").until,
+            Some(Until::Utc(utc("2023-06-01 12:00")))
+        );
+        assert_eq!(
+            rule("FREQ=DAILY;UNTIL=20230601T120000").until,
+            Some(Until::Local(utc("2023-06-01 12:00")))
+        );
+        assert_eq!(
+            rule("FREQ=DAILY;UNTIL=20230601").until,
+            Some(Until::Local(
+                NaiveDateTime::parse_from_str("2023-06-01 23:59:59", "%Y-%m-%d %H:%M:%S").unwrap()
+            ))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        for invalid in [
+            "INTERVAL=2",
+            "FREQ=HOURLY",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=DAILY;COUNT=3;UNTIL=20230601",
+            "FREQ=WEEKLY;BYDAY=1MO",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+            "FREQ=DAILY;BYSETPOS=1",
+            "FREQ=DAILY;UNTIL=tomorrow",
+        ] {
+            assert!(invalid.parse::<RecurrenceRule>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn count_ends_the_series() {
+        let start = utc("2023-05-01 09:00");
+        let daily = rule("FREQ=DAILY;COUNT=3");
+
+        assert_eq!(
+            daily.occurrences_after(start, 1, start, Tz::UTC, 10),
+            vec![utc("2023-05-02 09:00"), utc("2023-05-03 09:00")]
+        );
+        assert!(daily
+            .occurrences_after(utc("2023-05-03 09:00"), 3, start, Tz::UTC, 10)
+            .is_empty());
+    }
+
+    #[test]
+    fn until_date_includes_the_whole_day() {
+        let start = utc("2023-05-01 09:00");
+
+        assert_eq!(
+            rule("FREQ=DAILY;UNTIL=20230503").occurrences_after(start, 1, start, Tz::UTC, 10),
+            vec![utc("2023-05-02 09:00"), utc("2023-05-03 09:00")]
+        );
+        assert_eq!(
+            rule("FREQ=DAILY;UNTIL=20230503T080000Z").occurrences_after(
+                start,
+                1,
+                start,
+                Tz::UTC,
+                10
+            ),
+            vec![utc("2023-05-02 09:00")]
+        );
+    }
+
+    #[test]
+    fn weekly_by_day() {
+        // 2023-05-01 is a Monday
+        let start = utc("2023-05-01 09:00");
+
+        assert_eq!(
+            rule("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR").occurrences_after(
+                start,
+                1,
+                start,
+                Tz::UTC,
+                3
+            ),
+            vec![
+                utc("2023-05-05 09:00"),
+                utc("2023-05-15 09:00"),
+                utc("2023-05-19 09:00")
+            ]
+        );
+    }
+
+    #[test]
+    fn monthly_last_day_skips_nothing() {
+        let start = utc("2023-01-31 09:00");
+
+        assert_eq!(
+            rule("FREQ=MONTHLY;BYMONTHDAY=-1").occurrences_after(start, 1, start, Tz::UTC, 2),
+            vec![utc("2023-02-28 09:00"), utc("2023-03-31 09:00")]
+        );
+    }
+
+    #[test]
+    fn keeps_wall_clock_time_across_daylight_saving() {
+        // 9:00 in Lisbon is 9:00 UTC in winter and 8:00 UTC in summer, clocks change on 2023-03-26
+        let start = utc("2023-03-25 09:00");
+
+        assert_eq!(
+            rule("FREQ=DAILY").occurrences_after(start, 1, start, Tz::Europe__Lisbon, 2),
+            vec![utc("2023-03-26 08:00"), utc("2023-03-27 08:00")]
+        );
+    }
+
+    #[test]
+    fn gap_shift_does_not_move_the_series() {
+        // 2:30 does not exist in New York on 2023-03-12, that occurrence is moved to 3:30
+        let start = utc("2023-03-11 07:30");
+        let daily = rule("FREQ=DAILY");
+        let shifted = daily.occurrences_after(start, 1, start, Tz::America__New_York, 1)[0];
+
+        assert_eq!(shifted, utc("2023-03-12 07:30"));
+        // The next one is back at 2:30, 6:30 UTC in summer time, not at 3:30
+        assert_eq!(
+            daily.occurrences_after(shifted, 2, start, Tz::America__New_York, 1),
+            vec![utc("2023-03-13 06:30")]
+        );
+    }
+}
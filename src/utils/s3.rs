@@ -0,0 +1,182 @@
+use std::io::Read;
+use std::time::Duration;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use crate::utils::storage::BlobStore;
+
+/// Characters of a path segment that are sent as is, everything else is percent-encoded
+static UNRESERVED: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-._~";
+
+/// Headers covered by the signature of a request, in the order they are signed
+static SIGNED_HEADERS: &str = "host;x-amz-content-sha256;x-amz-date";
+
+/// Blob store keeping blobs in a bucket of an S3 compatible service such as AWS S3 or MinIO
+///
+/// Requests are signed with AWS Signature Version 4 and use path-style URLs
+/// (`<endpoint>/<bucket>/<key>`), which every S3 compatible service supports
+pub struct S3Store {
+    /// Base URL of the service, e.g. `http://localhost:9000`
+    endpoint: String,
+    /// Host header of the service, taken from the endpoint
+    host: String,
+    /// Name of the bucket
+    bucket: String,
+    /// Region of the bucket, MinIO accepts any region
+    region: String,
+    /// Access key id
+    access_key: String,
+    /// Secret access key
+    secret_key: String,
+    /// HTTP client shared by all requests
+    agent: ureq::Agent,
+}
+
+impl S3Store {
+    /// Creates a store keeping blobs in a bucket, the bucket must already exist
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - Base URL of the service, e.g. `https://s3.eu-west-1.amazonaws.com`
+    /// * `bucket` - Name of the bucket
+    /// * `region` - Region of the bucket
+    /// * `access_key` - Access key id
+    /// * `secret_key` - Secret access key
+    ///
+    /// # Returns
+    ///
+    /// * The store or an error message if the endpoint is not an http or https URL
+    pub fn new(
+        endpoint: &str,
+        bucket: &str,
+        region: &str,
+        access_key: &str,
+        secret_key: &str,
+    ) -> Result<S3Store, String> {
+        let endpoint = endpoint.trim_end_matches('/');
+
+        let host = match endpoint
+            .strip_prefix("https://")
+            .or_else(|| endpoint.strip_prefix("http://"))
+        {
+            Some(host) if !host.is_empty() && !host.contains('/') => host.to_string(),
+            _ => return Err("S3 endpoint must be an http or https URL without a path".to_string()),
+        };
+
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(Duration::from_secs(5))
+            .timeout(Duration::from_secs(60))
+            .build();
+
+        Ok(S3Store {
+            endpoint: endpoint.to_string(),
+            host,
+            bucket: bucket.to_string(),
+            region: region.to_string(),
+            access_key: access_key.to_string(),
+            secret_key: secret_key.to_string(),
+            agent,
+        })
+    }
+
+    /// Internal function to build a signed request for a blob
+    fn request(&self, method: &str, key: &str, payload: &[u8]) -> ureq::Request {
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+
+        let path = format!("/{}/{}", encode_path(&self.bucket), encode_path(key));
+        let payload_hash = hex::encode(Sha256::digest(payload));
+
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method, path, self.host, payload_hash, amz_date, SIGNED_HEADERS, payload_hash
+        );
+
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let signing_key = [self.region.as_str(), "s3", "aws4_request"].iter().fold(
+            hmac_sha256(
+                format!("AWS4{}", self.secret_key).as_bytes(),
+                date.as_bytes(),
+            ),
+            |key, part| hmac_sha256(&key, part.as_bytes()),
+        );
+        let signature = hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key, scope, SIGNED_HEADERS, signature
+        );
+
+        self.agent
+            .request(method, &format!("{}{}", self.endpoint, path))
+            .set("x-amz-content-sha256", &payload_hash)
+            .set("x-amz-date", &amz_date)
+            .set("Authorization", &authorization)
+    }
+}
+
+impl BlobStore for S3Store {
+    fn put(&self, key: &str, bytes: &[u8], content_type: &str) -> Result<(), String> {
+        let result = self
+            .request("PUT", key, bytes)
+            .set("Content-Type", content_type)
+            .send_bytes(bytes);
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(_) => Err("Failed to store file".to_string()),
+        }
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, String> {
+        let response = match self.request("GET", key, &[]).call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(404, _)) => return Err("File not found".to_string()),
+            Err(_) => return Err("Failed to read file".to_string()),
+        };
+
+        let mut bytes = vec![];
+
+        match response.into_reader().read_to_end(&mut bytes) {
+            Ok(_) => Ok(bytes),
+            Err(_) => Err("Failed to read file".to_string()),
+        }
+    }
+
+    fn delete(&self, key: &str) -> Result<(), String> {
+        match self.request("DELETE", key, &[]).call() {
+            Ok(_) | Err(ureq::Error::Status(404, _)) => Ok(()),
+            Err(_) => Err("Failed to delete file".to_string()),
+        }
+    }
+}
+
+/// Internal function to compute an HMAC-SHA256
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Internal function to percent-encode a path the way S3 expects it, keeping the slashes
+fn encode_path(path: &str) -> String {
+    path.bytes()
+        .map(|byte| {
+            if byte == b'/' || UNRESERVED.contains(&byte) {
+                (byte as char).to_string()
+            } else {
+                format!("%{:02X}", byte)
+            }
+        })
+        .collect()
+}
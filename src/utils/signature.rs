@@ -0,0 +1,122 @@
+use std::env;
+use std::sync::OnceLock;
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+
+/// Key paths and request fingerprints are signed with, read from the `SIGNING_KEY` environment variable
+///
+/// Without it a random key is used, so signed links stop working when the server restarts and are
+/// only accepted by the instance that signed them, and idempotency keys are only matched by that instance
+static KEY: OnceLock<Vec<u8>> = OnceLock::new();
+
+/// Signs a path so it can be requested without a token until it expires
+///
+/// # Arguments
+///
+/// * `path` - The path to sign, e.g. `/attachments/1/download`
+/// * `expires` - Unix timestamp after which the signature is no longer accepted
+///
+/// # Returns
+///
+/// * The signature, hex encoded
+pub fn sign(path: &str, expires: i64) -> String {
+    hex::encode(mac(path, expires).finalize().into_bytes())
+}
+
+/// Checks the signature of a path
+///
+/// # Arguments
+///
+/// * `path` - The signed path
+/// * `expires` - Unix timestamp the path was signed with
+/// * `signature` - The signature, hex encoded
+///
+/// # Returns
+///
+/// * Nothing, or an error message if the signature is invalid or expired
+pub fn verify(path: &str, expires: i64, signature: &str) -> Result<(), String> {
+    let signature = match hex::decode(signature) {
+        Ok(signature) => signature,
+        Err(_) => return Err("Invalid signature".to_string()),
+    };
+
+    // Compared in constant time, so the signature cannot be guessed byte by byte
+    if mac(path, expires).verify_slice(&signature).is_err() {
+        return Err("Invalid signature".to_string());
+    }
+
+    if expires < Utc::now().timestamp() {
+        return Err("Link has expired".to_string());
+    }
+
+    Ok(())
+}
+
+/// Signs the payload of a webhook delivery with the secret of the webhook
+///
+/// The timestamp is signed along with the payload, so receivers can reject deliveries replayed later
+///
+/// # Arguments
+///
+/// * `secret` - The secret of the webhook
+/// * `timestamp` - Unix timestamp sent in the `X-Webhook-Timestamp` header
+/// * `payload` - The body of the delivery
+///
+/// # Returns
+///
+/// * The HMAC-SHA256 of `<timestamp>.<payload>`, hex encoded
+pub fn sign_payload(secret: &str, timestamp: i64, payload: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}.{}", timestamp, payload).as_bytes());
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Fingerprints a request, keyed so the fingerprint cannot be reversed into the request
+///
+/// Request bodies can hold secrets, e.g. the password of a signup, a plain hash of them could be
+/// brute-forced from the stored fingerprint
+///
+/// # Arguments
+///
+/// * `target` - The method and path of the request
+/// * `body` - The body of the request
+///
+/// # Returns
+///
+/// * The HMAC-SHA256 of `<target>\n<body>` under the signing key, hex encoded
+pub fn fingerprint(target: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key()).expect("HMAC accepts keys of any length");
+    mac.update(target.as_bytes());
+    mac.update(b"\n");
+    mac.update(body);
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Internal function to read the key paths are signed with
+fn key() -> &'static [u8] {
+    KEY.get_or_init(|| match env::var("SIGNING_KEY") {
+        Ok(key) if !key.is_empty() => key.into_bytes(),
+        _ => {
+            log::warn!(
+                "SIGNING_KEY is not set, signed links and idempotency keys will not survive a restart"
+            );
+
+            let mut key = vec![0; 32];
+            rand::thread_rng().fill_bytes(&mut key);
+            key
+        }
+    })
+}
+
+/// Internal function to compute the MAC of a path and its expiry
+fn mac(path: &str, expires: i64) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}\n{}", path, expires).as_bytes());
+    mac
+}
@@ -0,0 +1,166 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+
+use crate::config::db::PoolConnection;
+use crate::config::storage::Storage;
+use crate::models::attachment::{Attachment, PURGE};
+use crate::models::job::Job;
+use crate::utils::jobs::JobHandler;
+
+/// Storage for the content of uploaded files, addressed by key
+///
+/// Keys are relative paths such as `todos/1/3f2a...`, implementations must not assume anything
+/// else about them
+pub trait BlobStore: Send + Sync {
+    /// Stores a blob, replacing any blob stored under the same key
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the blob
+    /// * `bytes` - The content of the blob
+    /// * `content_type` - The MIME type of the content
+    ///
+    /// # Returns
+    ///
+    /// * Nothing or an error message
+    fn put(&self, key: &str, bytes: &[u8], content_type: &str) -> Result<(), String>;
+
+    /// Reads a blob
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the blob
+    ///
+    /// # Returns
+    ///
+    /// * The content of the blob or an error message
+    fn get(&self, key: &str) -> Result<Vec<u8>, String>;
+
+    /// Deletes a blob, deleting a blob that does not exist is not an error
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key of the blob
+    ///
+    /// # Returns
+    ///
+    /// * Nothing or an error message
+    fn delete(&self, key: &str) -> Result<(), String>;
+}
+
+/// Blob store keeping blobs as files under a directory of the local filesystem
+pub struct LocalStore {
+    /// Directory the blobs are stored in
+    root: PathBuf,
+}
+
+impl LocalStore {
+    /// Creates a store keeping blobs under a directory, the directory is created when needed
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The directory the blobs are stored in
+    ///
+    /// # Returns
+    ///
+    /// * The store
+    pub fn new<P: Into<PathBuf>>(root: P) -> LocalStore {
+        LocalStore { root: root.into() }
+    }
+
+    /// Internal function to get the path of a blob, rejecting keys that would escape the root
+    fn path(&self, key: &str) -> Result<PathBuf, String> {
+        let relative = Path::new(key);
+        let is_plain = relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+
+        if key.is_empty() || !is_plain {
+            return Err("Invalid storage key".to_string());
+        }
+
+        Ok(self.root.join(relative))
+    }
+}
+
+impl BlobStore for LocalStore {
+    fn put(&self, key: &str, bytes: &[u8], _content_type: &str) -> Result<(), String> {
+        let path = self.path(key)?;
+
+        if let Some(parent) = path.parent() {
+            if fs::create_dir_all(parent).is_err() {
+                return Err("Failed to store file".to_string());
+            }
+        }
+
+        // Written next to the blob first, so a blob is never read while half written
+        let staging = path.with_extension("partial");
+
+        let result = fs::write(&staging, bytes).and_then(|_| fs::rename(&staging, &path));
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(_) => {
+                let _ = fs::remove_file(&staging);
+                Err("Failed to store file".to_string())
+            }
+        }
+    }
+
+    fn get(&self, key: &str) -> Result<Vec<u8>, String> {
+        match fs::read(self.path(key)?) {
+            Ok(bytes) => Ok(bytes),
+            Err(error) if error.kind() == ErrorKind::NotFound => Err("File not found".to_string()),
+            Err(_) => Err("Failed to read file".to_string()),
+        }
+    }
+
+    fn delete(&self, key: &str) -> Result<(), String> {
+        match fs::remove_file(self.path(key)?) {
+            Ok(_) => Ok(()),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(()),
+            Err(_) => Err("Failed to delete file".to_string()),
+        }
+    }
+}
+
+/// Removes the files of deleted attachments from the blob store, queued by `Attachment::queue_purge`
+pub struct PurgeBlobs {
+    /// Blob store the files are kept in
+    store: Storage,
+}
+
+impl PurgeBlobs {
+    /// Creates the handler
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - The blob store the files are kept in
+    ///
+    /// # Returns
+    ///
+    /// * The handler
+    pub fn new(store: Storage) -> PurgeBlobs {
+        PurgeBlobs { store }
+    }
+}
+
+impl JobHandler for PurgeBlobs {
+    fn kind(&self) -> &'static str {
+        PURGE
+    }
+
+    fn run(&self, job: &Job, _pool: &PoolConnection) -> Result<(), String> {
+        let keys = job
+            .payload
+            .get("keys")
+            .cloned()
+            .and_then(|keys| serde_json::from_value::<Vec<String>>(keys).ok());
+
+        match keys {
+            Some(keys) => Attachment::purge_blobs(&keys, self.store.as_ref()),
+            None => Err(format!("Invalid payload {}", job.payload)),
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use std::io;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::Value;
+
+use crate::config::db::{get_connection, PoolConnection};
+use crate::models::job::Job;
+use crate::models::webhook::{DeliveryAttempt, Webhook, WebhookDelivery, DELIVER};
+use crate::utils::jobs::JobHandler;
+use crate::utils::signature::sign_payload;
+
+/// Time an endpoint has to answer a delivery
+static TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Sends the queued webhook deliveries
+///
+/// Deliveries are queued in the database along with the events they are about, every attempt is
+/// made by a `webhook.deliver` job. The host of a webhook is resolved on every attempt, and the
+/// delivery is refused when it resolves to a loopback, private, link-local or otherwise internal
+/// address, e.g. the metadata service of a cloud provider, unless the host is allowed explicitly
+pub struct WebhookSender {
+    /// HTTP client shared by the deliveries of the sender
+    agent: ureq::Agent,
+}
+
+impl WebhookSender {
+    /// Creates a sender
+    ///
+    /// # Arguments
+    ///
+    /// * `allowed_hosts` - Hosts reached whatever address they resolve to, e.g. `localhost` for a stub endpoint
+    ///
+    /// # Returns
+    ///
+    /// * The sender
+    pub fn new(allowed_hosts: Vec<String>) -> WebhookSender {
+        let resolver = move |netloc: &str| resolve_public(netloc, &allowed_hosts);
+
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(Duration::from_secs(5))
+            .timeout(TIMEOUT)
+            .redirects(0)
+            .resolver(resolver)
+            .build();
+
+        WebhookSender { agent }
+    }
+
+    /// Posts a delivery to its webhook
+    ///
+    /// The body is the payload of the delivery, sent along with the headers:
+    ///
+    /// * `X-Webhook-Id` - Id of the delivery, the same for every attempt
+    /// * `X-Webhook-Event` - Event type, e.g. `todo.completed`
+    /// * `X-Webhook-Timestamp` - Unix timestamp of the attempt
+    /// * `X-Webhook-Signature` - `sha256=` followed by the HMAC-SHA256 of `<timestamp>.<body>`, keyed with the secret of the webhook
+    ///
+    /// # Arguments
+    ///
+    /// * `webhook` - The webhook
+    /// * `delivery` - The delivery
+    ///
+    /// # Returns
+    ///
+    /// * The outcome of the attempt, only the status of the response is kept
+    pub fn send(&self, webhook: &Webhook, delivery: &WebhookDelivery) -> DeliveryAttempt {
+        let body = delivery.payload.to_string();
+        let timestamp = Utc::now().timestamp();
+        let signature = sign_payload(&webhook.secret, timestamp, &body);
+
+        let result = self
+            .agent
+            .post(&webhook.url)
+            .set("Content-Type", "application/json")
+            .set("User-Agent", "todo-webhooks")
+            .set("X-Webhook-Id", &delivery.id.to_string())
+            .set("X-Webhook-Event", &delivery.event_type)
+            .set("X-Webhook-Timestamp", &timestamp.to_string())
+            .set("X-Webhook-Signature", &format!("sha256={}", signature))
+            .send_string(&body);
+
+        match result {
+            Ok(response) => DeliveryAttempt {
+                response_status: Some(response.status() as i32),
+                error: None,
+            },
+            Err(ureq::Error::Status(code, _)) => DeliveryAttempt {
+                response_status: Some(code as i32),
+                error: Some(format!("Endpoint answered with status {}", code)),
+            },
+            Err(ureq::Error::Transport(transport)) => DeliveryAttempt {
+                response_status: None,
+                error: Some(transport.to_string()),
+            },
+        }
+    }
+}
+
+impl JobHandler for WebhookSender {
+    fn kind(&self) -> &'static str {
+        DELIVER
+    }
+
+    fn run(&self, job: &Job, pool: &PoolConnection) -> Result<(), String> {
+        let argument = |name: &str| {
+            job.payload
+                .get(name)
+                .and_then(Value::as_i64)
+                .map(|value| value as i32)
+                .ok_or_else(|| format!("Job is missing {}", name))
+        };
+
+        let found = {
+            let mut conn = get_connection(pool)?;
+            WebhookDelivery::find_due(argument("deliveryId")?, argument("attempt")?, &mut conn)?
+        };
+
+        // The delivery was sent already, or its webhook was disabled or deleted
+        let (delivery, webhook) = match found {
+            Some(found) => found,
+            None => return Ok(()),
+        };
+
+        // No connection is held while waiting for the endpoint
+        let attempt = self.send(&webhook, &delivery);
+
+        let mut conn = get_connection(pool)?;
+        delivery.finish(attempt, &mut conn)
+    }
+}
+
+/// Internal function resolving the `host:port` of a webhook, refusing internal addresses unless the host is allowed
+fn resolve_public(netloc: &str, allowed_hosts: &[String]) -> io::Result<Vec<SocketAddr>> {
+    let addresses = netloc.to_socket_addrs()?.collect::<Vec<_>>();
+
+    let host = netloc
+        .rsplit_once(':')
+        .map_or(netloc, |(host, _)| host)
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+
+    if allowed_hosts
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(host))
+    {
+        return Ok(addresses);
+    }
+
+    // Every address is checked, the connection may be made to any of them
+    match addresses.iter().find(|address| !is_public(address.ip())) {
+        Some(address) => Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "{} resolves to {}, which is not a public address",
+                host,
+                address.ip()
+            ),
+        )),
+        None => Ok(addresses),
+    }
+}
+
+/// Internal function checking that an address is reachable on the internet
+fn is_public(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => {
+            let [first, second, ..] = address.octets();
+
+            !(address.is_loopback()
+                || address.is_private()
+                || address.is_link_local()
+                || address.is_unspecified()
+                || address.is_broadcast()
+                || address.is_multicast()
+                || address.is_documentation()
+                // 0.0.0.0/8, this network
+                || first == 0
+                // 100.64.0.0/10, shared address space, some metadata services live there
+                || (first == 100 && (second & 0xc0) == 64)
+                // 198.18.0.0/15, benchmarking
+                || (first == 198 && (second & 0xfe) == 18)
+                // 240.0.0.0/4, reserved
+                || first >= 240)
+        }
+        IpAddr::V6(address) => match address.to_ipv4_mapped() {
+            Some(mapped) => is_public(IpAddr::V4(mapped)),
+            None => {
+                !(address.is_loopback()
+                    || address.is_unspecified()
+                    || address.is_multicast()
+                    || address.is_unique_local()
+                    || address.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn public(address: &str) -> bool {
+        is_public(address.parse().unwrap())
+    }
+
+    #[test]
+    fn internal_addresses_are_not_public() {
+        assert!(!public("127.0.0.1"));
+        assert!(!public("10.1.2.3"));
+        assert!(!public("172.16.0.1"));
+        assert!(!public("192.168.1.1"));
+        assert!(!public("169.254.169.254"));
+        assert!(!public("100.100.100.200"));
+        assert!(!public("0.0.0.0"));
+        assert!(!public("::1"));
+        assert!(!public("fd00:ec2::254"));
+        assert!(!public("fe80::1"));
+        assert!(!public("::ffff:127.0.0.1"));
+    }
+
+    #[test]
+    fn internet_addresses_are_public() {
+        assert!(public("93.184.216.34"));
+        assert!(public("172.32.0.1"));
+        assert!(public("2606:2800:220:1:248:1893:25c8:1946"));
+    }
+
+    #[test]
+    fn allowed_hosts_resolve_to_anything() {
+        let allowed = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+
+        assert!(resolve_public("127.0.0.1:8080", &allowed).is_ok());
+        assert!(resolve_public("127.0.0.1:8080", &[]).is_err());
+        assert!(resolve_public("[::1]:8080", &allowed).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use rocket::futures::stream::{SplitSink, SplitStream};
+use rocket::futures::{SinkExt, StreamExt};
+use rocket::tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Response};
+use tokio_tungstenite::tungstenite::http::header::CONTENT_TYPE;
+use tokio_tungstenite::tungstenite::http::{HeaderValue, StatusCode};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::{CloseFrame, WebSocketConfig};
+use tokio_tungstenite::tungstenite::{self, Error};
+use tokio_tungstenite::WebSocketStream;
+
+pub use tokio_tungstenite::tungstenite::handshake::server::Request;
+
+/// Largest message accepted from a client, in bytes, fragments included
+pub static MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+/// Close code sent when the client breaks the protocol
+pub static CLOSE_PROTOCOL_ERROR: u16 = 1002;
+
+/// Close code sent when the client sends a binary message
+pub static CLOSE_UNSUPPORTED: u16 = 1003;
+
+/// Close code sent when the connection is refused once open, e.g. its user does not exist anymore
+pub static CLOSE_POLICY_VIOLATION: u16 = 1008;
+
+/// Close code sent when a message is larger than `MAX_MESSAGE_SIZE`
+pub static CLOSE_TOO_BIG: u16 = 1009;
+
+/// Largest reason sent in a close frame, the payload of a control frame is capped at 125 bytes
+static MAX_CLOSE_REASON: usize = 123;
+
+/// Receiving half of a WebSocket connection
+pub type Reader = SplitStream<WebSocketStream<TcpStream>>;
+
+/// Sending half of a WebSocket connection
+pub type Writer = SplitSink<WebSocketStream<TcpStream>, tungstenite::Message>;
+
+/// Message read from a client
+#[derive(Debug)]
+pub enum Message {
+    /// A text message, fragments joined
+    Text(String),
+    /// A ping, answered with a pong carrying the same payload
+    Ping(Vec<u8>),
+    /// The client closes the connection
+    Close,
+}
+
+/// Message sent to a client
+#[derive(Debug)]
+pub enum Frame {
+    /// A text message
+    Text(String),
+    /// A pong answering a ping
+    Pong(Vec<u8>),
+    /// Closes the connection with a close code and a reason
+    Close(u16, String),
+}
+
+/// Error closing a connection, along with the close code sent to the client
+#[derive(Debug)]
+pub struct ProtocolError {
+    /// The close code
+    pub code: u16,
+    /// The reason
+    pub message: String,
+}
+
+/// Performs the opening handshake of a connection
+///
+/// The framing, the masking and the limits of RFC 6455 are left to tungstenite. Frames are capped
+/// at `MAX_MESSAGE_SIZE`, and control frames larger than 125 bytes close the connection
+///
+/// # Arguments
+///
+/// * `stream` - The connection
+/// * `check` - Checks the handshake request, returns the status and the reason to refuse it with
+///
+/// # Returns
+///
+/// * What `check` returned along with both halves of the connection, or an error message if the handshake failed or was refused
+pub async fn accept<T, F>(stream: TcpStream, check: F) -> Result<(T, Reader, Writer), String>
+where
+    F: FnOnce(&Request) -> Result<T, (StatusCode, String)> + Unpin,
+{
+    let mut checked = None;
+
+    // tungstenite expects the refusal as an HTTP response in the `Err` of the callback
+    #[allow(clippy::result_large_err)]
+    let callback = |request: &Request, response: Response| match check(request) {
+        Ok(value) => {
+            checked = Some(value);
+            Ok(response)
+        }
+        Err((status, message)) => Err(reject(status, message)),
+    };
+
+    let config = WebSocketConfig {
+        max_message_size: Some(MAX_MESSAGE_SIZE),
+        max_frame_size: Some(MAX_MESSAGE_SIZE),
+        ..WebSocketConfig::default()
+    };
+
+    let stream =
+        match tokio_tungstenite::accept_hdr_async_with_config(stream, callback, Some(config)).await
+        {
+            Ok(stream) => stream,
+            Err(error) => return Err(error.to_string()),
+        };
+
+    match checked {
+        Some(value) => {
+            let (writer, reader) = stream.split();
+            Ok((value, reader, writer))
+        }
+        None => Err("Handshake was not checked".to_string()),
+    }
+}
+
+/// Reads the next message sent by a client
+/// Pongs are skipped, and the fragments of a text message are joined
+///
+/// # Arguments
+///
+/// * `reader` - The receiving half of the connection
+///
+/// # Returns
+///
+/// * The message, or the error the connection is closed with
+pub async fn read_message(reader: &mut Reader) -> Result<Message, ProtocolError> {
+    loop {
+        return match reader.next().await {
+            Some(Ok(tungstenite::Message::Text(text))) => Ok(Message::Text(text)),
+            Some(Ok(tungstenite::Message::Ping(payload))) => Ok(Message::Ping(payload)),
+            Some(Ok(tungstenite::Message::Pong(_))) | Some(Ok(tungstenite::Message::Frame(_))) => {
+                continue
+            }
+            Some(Ok(tungstenite::Message::Binary(_))) => Err(protocol_error(
+                CLOSE_UNSUPPORTED,
+                "Only text messages are supported",
+            )),
+            Some(Ok(tungstenite::Message::Close(_))) | None => Ok(Message::Close),
+            Some(Err(Error::Capacity(_))) => {
+                Err(protocol_error(CLOSE_TOO_BIG, "Message is too large"))
+            }
+            Some(Err(Error::Utf8)) => Err(protocol_error(
+                CLOSE_PROTOCOL_ERROR,
+                "Text messages must be valid UTF-8",
+            )),
+            Some(Err(error)) => Err(protocol_error(CLOSE_PROTOCOL_ERROR, &error.to_string())),
+        };
+    }
+}
+
+/// Sends a frame to a client
+///
+/// # Arguments
+///
+/// * `writer` - The sending half of the connection
+/// * `frame` - The frame
+///
+/// # Returns
+///
+/// * Nothing, or an error message if the frame could not be sent
+pub async fn write_frame(writer: &mut Writer, frame: Frame) -> Result<(), String> {
+    let message = match frame {
+        Frame::Text(text) => tungstenite::Message::Text(text),
+        Frame::Pong(payload) => tungstenite::Message::Pong(payload),
+        Frame::Close(code, mut reason) => {
+            while reason.len() > MAX_CLOSE_REASON {
+                reason.pop();
+            }
+
+            tungstenite::Message::Close(Some(CloseFrame {
+                code: CloseCode::from(code),
+                reason: reason.into(),
+            }))
+        }
+    };
+
+    writer
+        .send(message)
+        .await
+        .map_err(|error| error.to_string())
+}
+
+/// Internal function to build the plain HTTP response refusing a handshake
+fn reject(status: StatusCode, message: String) -> ErrorResponse {
+    let mut response = ErrorResponse::new(Some(message));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+
+    response
+}
+
+/// Internal function to build a protocol error
+fn protocol_error(code: u16, message: &str) -> ProtocolError {
+    ProtocolError {
+        code,
+        message: message.to_string(),
+    }
+}